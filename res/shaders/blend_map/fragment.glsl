@@ -1,3 +1,5 @@
+#version 330 core
+
 uniform sampler2D backgroundTexture;
 uniform sampler2D rTexture;
 uniform sampler2D gTexture;
@@ -6,6 +8,8 @@ uniform sampler2D blendMap;
 
 in vec2 textureCoords;
 
+out vec4 FragColor;
+
 void main() {
     vec4 blendMapColor = texture(blendMap, textureCoords);
 
@@ -18,4 +22,5 @@ void main() {
     vec4 bTextureColor = texture(bTexture, tiledCoords) * blendMapColor.b;
 
     vec4 totalColor = backgroundTextureColor + rTextureColor + gTextureColor + bTextureColor;
+    FragColor = totalColor;
 }
\ No newline at end of file
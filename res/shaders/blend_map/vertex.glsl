@@ -6,7 +6,7 @@ uniform mat4 model;
 
 out vec2 textureCoords;
 
-layout(location = 1) in vec3 Position;
+layout(location = 0) in vec3 Position;
 layout(location = 2) in vec2 Uv;
 
 void main() {
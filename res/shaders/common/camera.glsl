@@ -0,0 +1,4 @@
+//Matrices set by Camera::prepare_render and the object being drawn.
+uniform mat4 projection;
+uniform mat4 view;
+uniform mat4 model;
\ No newline at end of file
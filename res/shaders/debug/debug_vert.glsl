@@ -2,9 +2,7 @@
 
 layout(location = 0) in vec3 pos;
 
-uniform mat4 projection;
-uniform mat4 view;
-uniform mat4 model;
+#include "common/camera.glsl"
 
 void main() {
     gl_Position = projection * view * model * vec4(pos, 1.0);
@@ -0,0 +1,54 @@
+//#include "lighting/lights.glsl". MAX_LIGHTS and MAX_OBJECT_LIGHTS come from Lighting::defines.
+
+#define LIGHT_DIRECTIONAL 0
+#define LIGHT_POINT 1
+#define LIGHT_SPOT 2
+
+struct Light {
+    //xyz: world position, w: range
+    vec4 positionRange;
+    //xyz: direction the light points to, w: type
+    vec4 directionType;
+    //rgb: color, a: intensity
+    vec4 colorIntensity;
+    //x: cos(inner angle), y: cos(outer angle)
+    vec4 cone;
+};
+
+layout (std140) uniform Lights {
+    ivec4 lightCount;
+    Light lights[MAX_LIGHTS];
+};
+
+//Indices into `lights` affecting the current object, see Lighting::bind_object.
+uniform int objectLights[MAX_OBJECT_LIGHTS];
+uniform int objectLightCount;
+
+float lightAttenuation(float distance, float range) {
+    float ratio = distance / range;
+    float window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+    return window * window / (distance * distance + 1.0);
+}
+
+//Radiance arriving at `worldPosition` from light `index`. L is the direction towards the light.
+vec3 lightRadiance(int index, vec3 worldPosition, out vec3 L) {
+    Light light = lights[index];
+    int type = int(light.directionType.w);
+    vec3 radiance = light.colorIntensity.rgb * light.colorIntensity.a;
+
+    if (type == LIGHT_DIRECTIONAL) {
+        L = normalize(-light.directionType.xyz);
+        return radiance;
+    }
+
+    vec3 toLight = light.positionRange.xyz - worldPosition;
+    float distance = length(toLight);
+    L = toLight / max(distance, 0.0001);
+    radiance *= lightAttenuation(distance, light.positionRange.w);
+
+    if (type == LIGHT_SPOT) {
+        float theta = dot(-L, normalize(light.directionType.xyz));
+        radiance *= smoothstep(light.cone.y, light.cone.x, theta);
+    }
+    return radiance;
+}
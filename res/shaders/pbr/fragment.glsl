@@ -0,0 +1,150 @@
+#version 330 core
+
+struct PbrMaterial {
+    vec4 baseColorFactor;
+    float metallicFactor;
+    float roughnessFactor;
+    float occlusionStrength;
+    float normalScale;
+    float heightScale;
+    //DirectX style normal maps store +Y pointing down.
+    bool flipNormalY;
+
+    bool hasBaseColorMap;
+    bool hasNormalMap;
+    bool hasMetallicMap;
+    bool hasRoughnessMap;
+    bool hasOcclusionMap;
+    bool hasHeightMap;
+
+    sampler2D baseColorMap;
+    sampler2D normalMap;
+    sampler2D metallicMap;
+    sampler2D roughnessMap;
+    sampler2D occlusionMap;
+    sampler2D heightMap;
+};
+
+#include "shadows/shadows.glsl"
+
+uniform PbrMaterial pbr;
+uniform vec3 cameraPosition;
+//Whether the vertices carry colors at location 4, which then multiply the base color.
+uniform bool hasVertexColors;
+
+uniform vec3 ambientColor;
+
+in vec3 worldPosition;
+in vec2 uv;
+in mat3 TBN;
+in vec4 color;
+
+out vec4 FragColor;
+
+const float PI = 3.14159265359;
+
+vec2 parallax(vec2 coords, vec3 viewTangent) {
+    if (!pbr.hasHeightMap || pbr.heightScale == 0.0) {
+        return coords;
+    }
+    const float layers = 16.0;
+    float layerDepth = 1.0 / layers;
+    vec2 delta = viewTangent.xy / max(viewTangent.z, 0.05) * pbr.heightScale / layers;
+
+    float currentDepth = 0.0;
+    vec2 current = coords;
+    float sampled = 1.0 - texture(pbr.heightMap, current).r;
+    while (currentDepth < sampled) {
+        current -= delta;
+        sampled = 1.0 - texture(pbr.heightMap, current).r;
+        currentDepth += layerDepth;
+    }
+    vec2 previous = current + delta;
+    float after = sampled - currentDepth;
+    float before = 1.0 - texture(pbr.heightMap, previous).r - currentDepth + layerDepth;
+    float weight = after / (after - before);
+    return mix(current, previous, weight);
+}
+
+float distributionGGX(float NdotH, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denominator = NdotH * NdotH * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denominator * denominator);
+}
+
+float geometrySchlickGGX(float NdotV, float roughness) {
+    float r = roughness + 1.0;
+    float k = (r * r) / 8.0;
+    return NdotV / (NdotV * (1.0 - k) + k);
+}
+
+float geometrySmith(float NdotV, float NdotL, float roughness) {
+    return geometrySchlickGGX(NdotV, roughness) * geometrySchlickGGX(NdotL, roughness);
+}
+
+vec3 fresnelSchlick(float cosTheta, vec3 F0) {
+    return F0 + (1.0 - F0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
+}
+
+void main() {
+    vec3 V = normalize(cameraPosition - worldPosition);
+    vec2 coords = parallax(uv, normalize(transpose(TBN) * V));
+
+    vec4 baseColor = pbr.baseColorFactor;
+    if (pbr.hasBaseColorMap) {
+        baseColor *= texture(pbr.baseColorMap, coords);
+    }
+    if (hasVertexColors) {
+        baseColor *= color;
+    }
+    float metallic = pbr.metallicFactor;
+    if (pbr.hasMetallicMap) {
+        metallic *= texture(pbr.metallicMap, coords).r;
+    }
+    float roughness = pbr.roughnessFactor;
+    if (pbr.hasRoughnessMap) {
+        roughness *= texture(pbr.roughnessMap, coords).r;
+    }
+    roughness = clamp(roughness, 0.04, 1.0);
+    float occlusion = 1.0;
+    if (pbr.hasOcclusionMap) {
+        occlusion = mix(1.0, texture(pbr.occlusionMap, coords).r, pbr.occlusionStrength);
+    }
+
+    vec3 N = TBN[2];
+    if (pbr.hasNormalMap) {
+        vec3 tangentNormal = texture(pbr.normalMap, coords).xyz * 2.0 - 1.0;
+        if (pbr.flipNormalY) {
+            tangentNormal.y = -tangentNormal.y;
+        }
+        tangentNormal.xy *= pbr.normalScale;
+        N = TBN * tangentNormal;
+    }
+    N = normalize(N);
+
+    vec3 F0 = mix(vec3(0.04), baseColor.rgb, metallic);
+    float NdotV = max(dot(N, V), 0.0001);
+    vec3 kDiffuse = baseColor.rgb * (1.0 - metallic) / PI;
+
+    vec3 radiance = vec3(0.0);
+    for (int i = 0; i < objectLightCount; i++) {
+        vec3 L;
+        vec3 incoming = lightRadiance(objectLights[i], worldPosition, L);
+        incoming *= lightShadow(objectLights[i], worldPosition, N, L);
+        vec3 H = normalize(V + L);
+        float NdotL = max(dot(N, L), 0.0);
+        float NdotH = max(dot(N, H), 0.0);
+
+        vec3 F = fresnelSchlick(max(dot(H, V), 0.0), F0);
+        float D = distributionGGX(NdotH, roughness);
+        float G = geometrySmith(NdotV, NdotL, roughness);
+        vec3 specular = (D * G * F) / (4.0 * NdotV * max(NdotL, 0.0001));
+        vec3 kD = (vec3(1.0) - F) * kDiffuse;
+
+        radiance += (kD + specular) * incoming * NdotL;
+    }
+    vec3 ambient = ambientColor * baseColor.rgb * occlusion;
+
+    FragColor = vec4(ambient + radiance, baseColor.a);
+}
\ No newline at end of file
@@ -0,0 +1,21 @@
+#version 330 core
+
+uniform sampler2D screenTexture;
+uniform bool horizontal;
+
+in vec2 uv;
+out vec4 FragColor;
+
+const float weight[5] = float[] (0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main() {
+    vec2 texel = 1.0 / textureSize(screenTexture, 0);
+    vec2 direction = horizontal ? vec2(texel.x, 0.0) : vec2(0.0, texel.y);
+
+    vec3 result = texture(screenTexture, uv).rgb * weight[0];
+    for (int i = 1; i < 5; ++i) {
+        result += texture(screenTexture, uv + direction * i).rgb * weight[i];
+        result += texture(screenTexture, uv - direction * i).rgb * weight[i];
+    }
+    FragColor = vec4(result, 1.0);
+}
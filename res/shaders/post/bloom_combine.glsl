@@ -0,0 +1,14 @@
+#version 330 core
+
+uniform sampler2D screenTexture;
+uniform sampler2D bloomTexture;
+uniform float intensity;
+
+in vec2 uv;
+out vec4 FragColor;
+
+void main() {
+    vec4 color = texture(screenTexture, uv);
+    vec3 bloom = texture(bloomTexture, uv).rgb;
+    FragColor = vec4(color.rgb + bloom * intensity, color.a);
+}
@@ -0,0 +1,13 @@
+#version 330 core
+
+uniform sampler2D screenTexture;
+uniform float threshold;
+
+in vec2 uv;
+out vec4 FragColor;
+
+void main() {
+    vec3 color = texture(screenTexture, uv).rgb;
+    float brightness = dot(color, vec3(0.2126, 0.7152, 0.0722));
+    FragColor = vec4(color * step(threshold, brightness), 1.0);
+}
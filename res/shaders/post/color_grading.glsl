@@ -0,0 +1,29 @@
+#version 330 core
+
+uniform sampler2D screenTexture;
+//Unwrapped 3D LUT laid out as a horizontal strip of `lutSize` slices.
+uniform sampler2D lutTexture;
+uniform float lutSize;
+uniform float contribution;
+
+in vec2 uv;
+out vec4 FragColor;
+
+vec3 lookup(vec3 color) {
+    float slice = color.b * (lutSize - 1.0);
+    float sliceLow = floor(slice);
+    float sliceHigh = min(sliceLow + 1.0, lutSize - 1.0);
+
+    float x = (color.r * (lutSize - 1.0) + 0.5) / (lutSize * lutSize);
+    float y = (color.g * (lutSize - 1.0) + 0.5) / lutSize;
+
+    vec3 low = texture(lutTexture, vec2(x + sliceLow / lutSize, y)).rgb;
+    vec3 high = texture(lutTexture, vec2(x + sliceHigh / lutSize, y)).rgb;
+    return mix(low, high, slice - sliceLow);
+}
+
+void main() {
+    vec4 color = texture(screenTexture, uv);
+    vec3 graded = lookup(clamp(color.rgb, 0.0, 1.0));
+    FragColor = vec4(mix(color.rgb, graded, contribution), color.a);
+}
@@ -0,0 +1,51 @@
+#version 330 core
+
+uniform sampler2D screenTexture;
+uniform vec2 resolution;
+
+in vec2 uv;
+out vec4 FragColor;
+
+const float FXAA_SPAN_MAX = 8.0;
+const float FXAA_REDUCE_MUL = 1.0 / 8.0;
+const float FXAA_REDUCE_MIN = 1.0 / 128.0;
+
+float luma(vec3 color) {
+    return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+void main() {
+    vec2 texel = 1.0 / resolution;
+
+    float lumaNW = luma(texture(screenTexture, uv + vec2(-1.0, -1.0) * texel).rgb);
+    float lumaNE = luma(texture(screenTexture, uv + vec2(1.0, -1.0) * texel).rgb);
+    float lumaSW = luma(texture(screenTexture, uv + vec2(-1.0, 1.0) * texel).rgb);
+    float lumaSE = luma(texture(screenTexture, uv + vec2(1.0, 1.0) * texel).rgb);
+    vec4 center = texture(screenTexture, uv);
+    float lumaM = luma(center.rgb);
+
+    float lumaMin = min(lumaM, min(min(lumaNW, lumaNE), min(lumaSW, lumaSE)));
+    float lumaMax = max(lumaM, max(max(lumaNW, lumaNE), max(lumaSW, lumaSE)));
+
+    vec2 dir;
+    dir.x = -((lumaNW + lumaNE) - (lumaSW + lumaSE));
+    dir.y = ((lumaNW + lumaSW) - (lumaNE + lumaSE));
+
+    float dirReduce = max((lumaNW + lumaNE + lumaSW + lumaSE) * (0.25 * FXAA_REDUCE_MUL), FXAA_REDUCE_MIN);
+    float rcpDirMin = 1.0 / (min(abs(dir.x), abs(dir.y)) + dirReduce);
+    dir = clamp(dir * rcpDirMin, vec2(-FXAA_SPAN_MAX), vec2(FXAA_SPAN_MAX)) * texel;
+
+    vec3 rgbA = 0.5 * (
+        texture(screenTexture, uv + dir * (1.0 / 3.0 - 0.5)).rgb +
+        texture(screenTexture, uv + dir * (2.0 / 3.0 - 0.5)).rgb);
+    vec3 rgbB = rgbA * 0.5 + 0.25 * (
+        texture(screenTexture, uv + dir * -0.5).rgb +
+        texture(screenTexture, uv + dir * 0.5).rgb);
+
+    float lumaB = luma(rgbB);
+    if (lumaB < lumaMin || lumaB > lumaMax) {
+        FragColor = vec4(rgbA, center.a);
+    } else {
+        FragColor = vec4(rgbB, center.a);
+    }
+}
@@ -0,0 +1,12 @@
+#version 330 core
+
+uniform sampler2D screenTexture;
+uniform float gamma;
+
+in vec2 uv;
+out vec4 FragColor;
+
+void main() {
+    vec4 color = texture(screenTexture, uv);
+    FragColor = vec4(pow(color.rgb, vec3(1.0 / gamma)), color.a);
+}
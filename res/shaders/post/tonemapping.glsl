@@ -0,0 +1,22 @@
+#version 330 core
+
+uniform sampler2D screenTexture;
+uniform float exposure;
+
+in vec2 uv;
+out vec4 FragColor;
+
+//ACES filmic approximation by Krzysztof Narkowicz.
+vec3 aces(vec3 x) {
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), 0.0, 1.0);
+}
+
+void main() {
+    vec4 color = texture(screenTexture, uv);
+    FragColor = vec4(aces(color.rgb * exposure), color.a);
+}
@@ -0,0 +1,15 @@
+#version 330 core
+
+uniform sampler2D screenTexture;
+uniform float intensity;
+uniform float radius;
+
+in vec2 uv;
+out vec4 FragColor;
+
+void main() {
+    vec4 color = texture(screenTexture, uv);
+    float distance = length(uv - vec2(0.5));
+    float vignette = smoothstep(radius, radius - 0.25, distance);
+    FragColor = vec4(mix(color.rgb, color.rgb * vignette, intensity), color.a);
+}
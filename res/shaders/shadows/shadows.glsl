@@ -0,0 +1,66 @@
+//#include "shadows/shadows.glsl". MAX_SHADOW_TILES and the light sizes come from Shadows::defines.
+#include "lighting/lights.glsl"
+
+//Every shadow map is a tile of this depth atlas.
+uniform sampler2D shadowAtlas;
+uniform mat4 shadowMatrices[MAX_SHADOW_TILES];
+//xy: offset, zw: size of each tile in atlas uv space.
+uniform vec4 shadowRects[MAX_SHADOW_TILES];
+//View space depth at which each cascade of the directional light ends.
+uniform vec4 cascadeSplits;
+uniform int cascadeCount;
+uniform mat4 shadowCameraView;
+uniform int pcfRadius;
+uniform float shadowDepthBias;
+uniform float shadowNormalBias;
+//Set per object, see Shadows::bind_object.
+uniform bool receiveShadows;
+
+float shadowTile(int tile, vec3 worldPosition, vec3 N, vec3 L) {
+    float NdotL = clamp(dot(N, L), 0.0, 1.0);
+    vec3 offsetPosition = worldPosition + N * shadowNormalBias * (1.0 - NdotL);
+    vec4 lightSpace = shadowMatrices[tile] * vec4(offsetPosition, 1.0);
+    vec3 projected = lightSpace.xyz / lightSpace.w * 0.5 + 0.5;
+    if (projected.z > 1.0 || any(lessThan(projected.xy, vec2(0.0))) || any(greaterThan(projected.xy, vec2(1.0)))) {
+        return 1.0;
+    }
+    float bias = max(shadowDepthBias * (1.0 - NdotL), shadowDepthBias * 0.1);
+
+    vec4 rect = shadowRects[tile];
+    vec2 texel = 1.0 / vec2(textureSize(shadowAtlas, 0));
+    //Keep the filter from sampling neighbouring tiles.
+    vec2 minimum = rect.xy + texel * 0.5;
+    vec2 maximum = rect.xy + rect.zw - texel * 0.5;
+
+    float lit = 0.0;
+    float samples = 0.0;
+    for (int x = -pcfRadius; x <= pcfRadius; x++) {
+        for (int y = -pcfRadius; y <= pcfRadius; y++) {
+            vec2 coords = clamp(rect.xy + projected.xy * rect.zw + vec2(x, y) * texel, minimum, maximum);
+            float closest = texture(shadowAtlas, coords).r;
+            lit += projected.z - bias > closest ? 0.0 : 1.0;
+            samples += 1.0;
+        }
+    }
+    return lit / samples;
+}
+
+//1 when `worldPosition` is fully lit by light `index`, 0 when fully shadowed.
+float lightShadow(int index, vec3 worldPosition, vec3 N, vec3 L) {
+    float tile = lights[index].cone.z;
+    if (!receiveShadows || tile < 0.5) {
+        return 1.0;
+    }
+    int first = int(tile + 0.5) - 1;
+    if (int(lights[index].directionType.w) != LIGHT_DIRECTIONAL) {
+        return shadowTile(first, worldPosition, N, L);
+    }
+
+    float depth = -(shadowCameraView * vec4(worldPosition, 1.0)).z;
+    for (int cascade = 0; cascade < cascadeCount; cascade++) {
+        if (depth < cascadeSplits[cascade]) {
+            return shadowTile(first + cascade, worldPosition, N, L);
+        }
+    }
+    return 1.0;
+}
@@ -0,0 +1,15 @@
+//Linear blend skinning. Joint matrices are uploaded by SkinningBuffer.
+#ifndef MAX_JOINTS
+#define MAX_JOINTS 128
+#endif
+
+layout (std140) uniform Joints {
+    mat4 joints[MAX_JOINTS];
+};
+
+mat4 skinMatrix(vec4 jointIndices, vec4 jointWeights) {
+    return jointWeights.x * joints[int(jointIndices.x)]
+         + jointWeights.y * joints[int(jointIndices.y)]
+         + jointWeights.z * joints[int(jointIndices.z)]
+         + jointWeights.w * joints[int(jointIndices.w)];
+}
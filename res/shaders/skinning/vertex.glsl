@@ -0,0 +1,34 @@
+#version 330 core
+
+#include "common/camera.glsl"
+#include "skinning/skinning.glsl"
+
+layout (location = 0) in vec3 Position;
+layout (location = 1) in vec3 Normal;
+layout (location = 2) in vec2 Uv;
+layout (location = 3) in vec4 Tangent;
+layout (location = 4) in vec4 Color;
+layout (location = 5) in vec4 Joints;
+layout (location = 6) in vec4 Weights;
+
+out vec3 worldPosition;
+out vec2 uv;
+out mat3 TBN;
+out vec4 color;
+
+void main() {
+    mat4 skinned = model * skinMatrix(Joints, Weights);
+    vec4 world = skinned * vec4(Position, 1.0);
+    mat3 normalMatrix = transpose(inverse(mat3(skinned)));
+
+    vec3 N = normalize(normalMatrix * Normal);
+    vec3 T = normalize(normalMatrix * Tangent.xyz);
+    T = normalize(T - dot(T, N) * N);
+    vec3 B = cross(N, T) * Tangent.w;
+
+    worldPosition = world.xyz;
+    uv = Uv;
+    color = Color;
+    TBN = mat3(T, B, N);
+    gl_Position = projection * view * world;
+}
@@ -0,0 +1,16 @@
+#version 330 core
+
+uniform mat4 projection;
+uniform mat4 model;
+
+out vec2 currentPos;
+out vec2 uv;
+
+layout (location = 0) in vec2 aPos;
+layout (location = 1) in vec2 aUV;
+
+void main() {
+    gl_Position = projection * model * vec4(aPos, 0, 1);
+    currentPos = aPos;
+    uv = aUV;
+}
\ No newline at end of file
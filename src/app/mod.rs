@@ -3,6 +3,7 @@ use imgui_glfw_rs::{imgui, ImguiGLFW};
 
 
 use std::error::Error;
+use std::mem::ManuallyDrop;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::sync::mpsc::Receiver;
@@ -14,6 +15,17 @@ use imgui_glfw_rs::imgui::Ui;
 use rlua::{Lua, UserData, UserDataMethods};
 use crate::GLConsts::{CullFace, DepthTest};
 use crate::gfx::consts::color::Color;
+use crate::gfx::lighting::{Lighting, LightSettings};
+use crate::gfx::post_processing::PostProcessStack;
+use crate::gfx::shadows::{Shadows, ShadowSettings};
+use crate::loader::assets::{AssetLoader, AssetSettings};
+use crate::gfx::resource::{GlObject, Owned, report_live_objects};
+use crate::gfx::texture::texture_library::TextureLibrary;
+use crate::gfx::post_processing::release_fullscreen_quad;
+use crate::ecs::clear_components;
+use crate::scene::Scene;
+use crate::ecs::animator::update_animators;
+use crate::ecs::lod_group::update_lod_groups;
 use crate::math::linear_algebra::types::Vec4;
 
 pub type EventHandler = Receiver<(f64, WindowEvent)>;
@@ -25,6 +37,8 @@ pub static mut WINDOW_SIZE: [u32; 2] = [512; 2];
 
 static mut IMGUI: Option<Arc<Mutex<imgui::Context>>> = None;
 static mut IMGUI_GLFW: Option<Arc<Mutex<ImguiGLFW>>> = None;
+///The flags the window was created with.
+static mut CONFIG_FLAGS: u8 = 0;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Sequence)]
 #[repr(u8)]
@@ -39,6 +53,7 @@ pub enum AppConfigFlags {
 
 impl AppConfigFlags {
     fn configure(init: &mut Glfw, flags: u8) {
+        unsafe { CONFIG_FLAGS = flags; }
         let config_flags = all::<AppConfigFlags>();
 
         let mut index = 0;
@@ -61,14 +76,20 @@ impl AppConfigFlags {
             index += 1;
         }
     }
+
+    ///Whether the window was created with this flag.
+    pub fn enabled(&self) -> bool {
+        unsafe { (CONFIG_FLAGS & *self as u8) != 0 }
+    }
 }
 
 pub struct App<A: Application> {
     glfw: Glfw,
     window: Window,
     events: EventHandler,
-    app: A,
-    context: GLContext,
+    ///Dropped by hand, before the window, see `Drop for App`.
+    app: ManuallyDrop<A>,
+    context: ManuallyDrop<GLContext>,
     imgui: Arc<Mutex<imgui::Context>>,
     imgui_glfw: Arc<Mutex<ImguiGLFW>>,
 }
@@ -105,6 +126,8 @@ pub struct GLContext {
     pub depth_testing: Depth,
     pub cull_face: FaceCulling,
     pub depth_mask: bool,
+    ///Shared by the clones handed to Lua, so the stack's targets and programs exist once.
+    pub post_processing: Arc<Mutex<PostProcessStack>>,
 }
 
 impl UserData for GLContext {
@@ -168,6 +191,26 @@ impl UserData for GLContext {
             m.depth_func(depth_func);
             Ok(())
         });
+
+        _methods.add_method("post_passes", |_, m, _: ()| {
+            Ok(m.post_processing.lock().unwrap().names())
+        });
+
+        _methods.add_method_mut("post_enable", |_, m, (name, enabled): (String, bool)| {
+            m.post_processing.lock().unwrap().set_enabled(&name, enabled).map_err(|e| rlua::Error::RuntimeError(e.to_string()))
+        });
+
+        _methods.add_method_mut("post_toggle", |_, m, name: String| {
+            m.post_processing.lock().unwrap().toggle(&name).map_err(|e| rlua::Error::RuntimeError(e.to_string()))
+        });
+
+        _methods.add_method_mut("post_move", |_, m, (name, index): (String, usize)| {
+            m.post_processing.lock().unwrap().move_pass(&name, index).map_err(|e| rlua::Error::RuntimeError(e.to_string()))
+        });
+
+        _methods.add_method_mut("post_set", |_, m, (name, parameter, value): (String, String, f32)| {
+            m.post_processing.lock().unwrap().set_parameter(&name, &parameter, value).map_err(|e| rlua::Error::RuntimeError(e.to_string()))
+        });
     }
 }
 
@@ -214,6 +257,30 @@ impl GLContext {
         self.depth_func(self.depth_testing.depth_func);
     }
 
+    ///Redirects the scene into the post processing targets, if any pass is enabled.
+    pub fn begin_post_processing(&mut self) {
+        if let Err(error) = self.post_processing.lock().unwrap().begin(self.viewport) {
+            eprintln!("Post processing disabled for this frame: {}", error);
+        }
+    }
+
+    ///Runs the post processing chain into the window and restores the context state.
+    pub fn end_post_processing(&self) {
+        let post_processing = self.post_processing.lock().unwrap();
+        if !post_processing.is_active() {
+            return;
+        }
+        post_processing.end();
+        match self.cull_face.enabled {
+            true => self.enable(CullFace),
+            false => self.disable(CullFace)
+        }
+        match self.depth_testing.enabled {
+            true => self.enable(DepthTest),
+            false => self.disable(DepthTest)
+        }
+    }
+
     pub fn enable(&self, constant: GLConsts) {
         enable(constant);
     }
@@ -267,10 +334,27 @@ pub trait Application {
         self.super_late_update(delta);
     }
     fn render(&mut self, context: &mut GLContext, delta: f32) {
+        Lighting::update();
+        if let Some(camera) = self.camera() {
+            update_lod_groups(camera);
+        }
+        self.render_shadows(context);
+        context.begin_post_processing();
         context.prepare_render();
         self.super_render(context, delta);
+        context.end_post_processing();
         self.render_ui(context, delta);
     }
+    ///Renders the shadow maps of every shadow casting light, calling render_shadow_casters once per map.
+    fn render_shadows(&mut self, context: &mut GLContext) {
+        let camera = match self.camera() {
+            Some(camera) => camera.clone(),
+            None => return
+        };
+        Shadows::render(&camera, |program| self.render_shadow_casters(context, program));
+    }
+    ///Draw every shadow casting mesh with `program`, setting its `model` uniform. Components draw themselves.
+    fn render_shadow_casters(&mut self, _context: &mut GLContext, _program: &Program) {}
     fn super_update(&mut self, delta: f32) {
         UI::update(delta);
     }
@@ -300,13 +384,30 @@ pub trait Application {
     fn on_file_drop(&self, _window: &mut Window, _file_drop: &Vec<PathBuf>) {}
     fn on_char(&self, _window: &mut Window, _c: &char) {}
     fn on_char_modifiers(&self, _window: &mut Window, _c: &char, _m: &Modifiers) {}
-    ///Initializes the Input and UI systems. Without these calls, the Input and UI systems will not work. Will cause errors and crash.
+    ///Initializes the Input, UI, asset loading, Lighting and Shadow systems. Without these calls, the Input and UI systems will not work. Will cause errors and crash.
     fn super_init(&self, _context: &mut GLContext) {
         unsafe {
             Input::init();
             UI::init();
+            AssetLoader::init(self.asset_settings());
+            Lighting::init(self.light_settings());
+            if let Err(error) = Shadows::init(self.shadow_settings()) {
+                eprintln!("Shadows disabled: {}", error);
+            }
         }
     }
+    ///Size of the light buffer and the per object light limit. Read once by super_init.
+    fn light_settings(&self) -> LightSettings {
+        LightSettings::default()
+    }
+    ///Asset worker count and the per frame upload budget. Read once by super_init.
+    fn asset_settings(&self) -> AssetSettings {
+        AssetSettings::default()
+    }
+    ///Shadow map resolution, cascades and filtering. Read once by super_init.
+    fn shadow_settings(&self) -> ShadowSettings {
+        ShadowSettings::default()
+    }
     ///
     fn super_handle_event(&mut self, window: &mut Window, context: &mut GLContext, event: &WindowEvent) {
         unsafe {
@@ -359,8 +460,14 @@ pub trait Application {
     fn camera(&self) -> Option<&Camera>;
     fn camera_mut(&mut self) -> Option<&mut Camera>;
 
-    fn create_program(&self, fragment_shader: FragmentShader, vertex_shader: VertexShader) -> Result<Program, Box<dyn Error>> {
-        Program::new(fragment_shader, vertex_shader)
+    ///Called after a watched program was rebuilt from disk. Relinking resets uniform values and
+    ///uniform block bindings, by default the light block is bound again.
+    fn on_shaders_reloaded(&mut self, _context: &mut GLContext, program: &Program) {
+        Lighting::bind(program);
+    }
+
+    fn create_program(&self, fragment_shader: FragmentShader, vertex_shader: VertexShader) -> Result<Owned<Program>, Box<dyn Error>> {
+        Ok(Program::new(fragment_shader, vertex_shader)?.owned())
     }
 
     fn load_obj_by_path<P: AsRef<Path>>(&self, path: P) -> Result<OBJ, Box<dyn Error>> {
@@ -396,6 +503,7 @@ impl<A: Application> App<A> {
                 front_face: Ordering::ClockWise,
             },
             depth_mask: false,
+            post_processing: Arc::new(Mutex::new(PostProcessStack::new())),
         };
 
         let mut imgui = imgui::Context::create();
@@ -409,8 +517,8 @@ impl<A: Application> App<A> {
             glfw,
             window,
             events,
-            app,
-            context,
+            app: ManuallyDrop::new(app),
+            context: ManuallyDrop::new(context),
             imgui: imgui_arc,
             imgui_glfw: imgui_glfw_arc,
         })
@@ -421,6 +529,11 @@ impl<A: Application> App<A> {
             let now = self.window.glfw.get_time();
             let delta_time = (now - last_update_time) as f32;
             self.glfw.poll_events();
+            for program in Program::reload_changed() {
+                self.app.on_shaders_reloaded(&mut self.context, &program);
+            }
+            AssetLoader::update();
+            update_animators(delta_time);
             self.app.update(delta_time);
             self.app.late_update(delta_time);
             self.app.render(&mut self.context, delta_time);
@@ -462,4 +575,28 @@ impl<A: Application> App<A> {
     pub fn set_title<T: ToString>(&mut self, title: T) {
         self.window.set_title(title.to_string().as_str());
     }
+}
+
+///Drops the application, the context and the engine singletons while the window's GL context is still current,
+///then reports the GL objects nothing deleted.
+impl<A: Application> Drop for App<A> {
+    fn drop(&mut self) {
+        unsafe {
+            ManuallyDrop::drop(&mut self.app);
+            ManuallyDrop::drop(&mut self.context);
+            release_singletons();
+        }
+        report_live_objects();
+    }
+}
+
+unsafe fn release_singletons() {
+    clear_components();
+    Scene::unload_scene();
+    UI::shutdown();
+    Shadows::shutdown();
+    Lighting::shutdown();
+    TextureLibrary::shutdown();
+    AssetLoader::shutdown();
+    release_fullscreen_quad();
 }
\ No newline at end of file
@@ -0,0 +1,53 @@
+use std::path::PathBuf;
+use std::process::exit;
+use rust_gl::loader::textures::{CompressedFormat, Container, convert_folder, ConvertOptions};
+
+const USAGE: &'static str = "Usage: assets compress <folder> [--format bc1|bc3|bc4|bc5] [--container ktx2|dds] [--out <folder>]
+
+Compresses every image in <folder> into DDS or KTX2 containers with full mip chains.
+Without --format, grayscale maps become BC4, images with transparency BC3 and the rest BC1.";
+
+fn compress(args: &[String]) -> Result<(), String> {
+    let mut input = None;
+    let mut options = ConvertOptions::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                let name = args.next().ok_or("--format needs a value")?;
+                options.format = Some(CompressedFormat::from_name(name).ok_or(format!("Unknown format {}", name))?);
+            }
+            "--container" => {
+                options.container = match args.next().map(|s| s.as_str()) {
+                    Some("ktx2") => Container::Ktx2,
+                    Some("dds") => Container::Dds,
+                    other => return Err(format!("Unknown container {:?}", other))
+                };
+            }
+            "--out" => {
+                options.output = Some(PathBuf::from(args.next().ok_or("--out needs a folder")?));
+            }
+            path if input.is_none() => input = Some(PathBuf::from(path)),
+            other => return Err(format!("Unexpected argument {}", other))
+        }
+    }
+    let input = input.ok_or("Missing the input folder")?;
+    let written = convert_folder(&input, &options).map_err(|e| e.to_string())?;
+    for path in &written {
+        println!("Wrote {}", path.display());
+    }
+    println!("Compressed {} images from {}", written.len(), input.display());
+    Ok(())
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    let result = match args.first().map(|s| s.as_str()) {
+        Some("compress") => compress(&args[1..]),
+        _ => Err(USAGE.to_string())
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
@@ -0,0 +1,252 @@
+use imgui_glfw_rs::imgui::Ui;
+use crate::ecs::{Component, ComponentItems, components_mut, ECSResult};
+use crate::ecs::lua_component::Value;
+use crate::gfx::animation::{AnimationClip, blend_poses, Pose, Skeleton};
+use crate::loader::models::gltf::Gltf;
+use crate::math::linear_algebra::types::Mat4;
+use crate::math::fmod;
+
+pub const ANIMATOR_NAME: &'static str = "Animator";
+
+#[derive(Debug, Clone, Copy)]
+struct Playback {
+    clip: usize,
+    time: f32,
+}
+
+///A clip being faded out.
+#[derive(Debug, Clone, Copy)]
+struct Fade {
+    from: Playback,
+    elapsed: f32,
+    duration: f32,
+}
+
+///Plays animation clips on a model's nodes and computes the joint matrices of its skeleton.
+///Animators are advanced once per frame by the app, see `update_animators`.
+#[derive(Debug, Clone)]
+pub struct Animator {
+    pub speed: f32,
+    pub looping: bool,
+    skeleton: Option<Skeleton>,
+    clips: Vec<AnimationClip>,
+    rest_pose: Pose,
+    current: Option<Playback>,
+    fade: Option<Fade>,
+    pose: Pose,
+    joint_matrices: Vec<Mat4>,
+    name: String,
+    component_items: ComponentItems,
+}
+
+impl Default for Animator {
+    fn default() -> Self {
+        let mut d = Self {
+            speed: 1.0,
+            looping: true,
+            skeleton: None,
+            clips: vec![],
+            rest_pose: vec![],
+            current: None,
+            fade: None,
+            pose: vec![],
+            joint_matrices: vec![],
+            name: ANIMATOR_NAME.to_string(),
+            component_items: ComponentItems {
+                state: Default::default(),
+                enabled: true,
+                parent: 0,
+                id: 0,
+            },
+        };
+        d.component_items.id = d.inc_id();
+        d
+    }
+}
+
+impl Animator {
+    ///Animates the nodes of a model whose exported transforms are `rest_pose`, deforming `skeleton` if it's skinned.
+    pub fn new(rest_pose: Pose, skeleton: Option<Skeleton>, clips: Vec<AnimationClip>) -> Animator {
+        let mut animator = Self {
+            skeleton,
+            clips,
+            pose: rest_pose.clone(),
+            rest_pose,
+            ..Default::default()
+        };
+        animator.update_joint_matrices();
+        animator
+    }
+
+    ///Every animation of the model, driving the skeleton `skin` if given.
+    pub fn from_gltf(gltf: &Gltf, skin: Option<usize>) -> Animator {
+        let skeleton = skin.and_then(|skin| gltf.skins().get(skin)).cloned();
+        Self::new(gltf.rest_pose(), skeleton, gltf.animations().clone())
+    }
+
+    pub fn with_clip(mut self, clip: AnimationClip) -> Animator {
+        self.clips.push(clip);
+        self
+    }
+
+    fn clip_index(&self, name: &str) -> Option<usize> {
+        self.clips.iter().position(|clip| clip.name() == name)
+    }
+
+    ///Starts `name` from the beginning, cutting off the current clip. Returns false if there is no such clip.
+    pub fn play(&mut self, name: &str) -> bool {
+        match self.clip_index(name) {
+            Some(clip) => {
+                self.current = Some(Playback { clip, time: 0.0 });
+                self.fade = None;
+                true
+            }
+            None => false
+        }
+    }
+
+    ///Starts `name` and blends from the current clip to it over `seconds`.
+    pub fn crossfade(&mut self, name: &str, seconds: f32) -> bool {
+        let from = self.current;
+        if !self.play(name) {
+            return false;
+        }
+        if let Some(from) = from {
+            if seconds > 0.0 {
+                self.fade = Some(Fade { from, elapsed: 0.0, duration: seconds });
+            }
+        }
+        true
+    }
+
+    ///Stops playback and returns to the rest pose.
+    pub fn stop(&mut self) {
+        self.current = None;
+        self.fade = None;
+        self.pose = self.rest_pose.clone();
+        self.update_joint_matrices();
+    }
+
+    fn step(&self, playback: &mut Playback, delta: f32) {
+        let duration = self.clips[playback.clip].duration();
+        playback.time += delta * self.speed;
+        playback.time = match self.looping && duration > 0.0 {
+            true => fmod(playback.time, duration),
+            false => playback.time.clamp(0.0, duration)
+        };
+    }
+
+    fn sample(&self, playback: &Playback) -> Pose {
+        let mut pose = self.rest_pose.clone();
+        self.clips[playback.clip].sample(playback.time, &mut pose);
+        pose
+    }
+
+    ///Moves playback forward by `delta` seconds and updates the pose and joint matrices.
+    pub fn advance(&mut self, delta: f32) {
+        let mut current = match self.current {
+            Some(current) => current,
+            None => return
+        };
+        self.step(&mut current, delta);
+        self.current = Some(current);
+        let mut pose = self.sample(&current);
+
+        if let Some(mut fade) = self.fade {
+            self.step(&mut fade.from, delta);
+            fade.elapsed += delta;
+            if fade.elapsed >= fade.duration {
+                self.fade = None;
+            } else {
+                pose = blend_poses(&self.sample(&fade.from), &pose, fade.elapsed / fade.duration);
+                self.fade = Some(fade);
+            }
+        }
+        self.pose = pose;
+        self.update_joint_matrices();
+    }
+
+    fn update_joint_matrices(&mut self) {
+        self.joint_matrices = match &self.skeleton {
+            Some(skeleton) => skeleton.joint_matrices(&self.pose),
+            None => vec![]
+        };
+    }
+
+    pub fn clips(&self) -> &Vec<AnimationClip> {
+        &self.clips
+    }
+
+    pub fn skeleton(&self) -> Option<&Skeleton> {
+        self.skeleton.as_ref()
+    }
+
+    ///Name of the playing clip.
+    pub fn current_clip(&self) -> Option<&str> {
+        self.current.map(|playback| self.clips[playback.clip].name())
+    }
+
+    ///Playback position of the current clip, in seconds.
+    pub fn time(&self) -> f32 {
+        self.current.map(|playback| playback.time).unwrap_or(0.0)
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.current.is_some()
+    }
+
+    ///Local transform of every node, indexed like the model's nodes.
+    pub fn pose(&self) -> &Pose {
+        &self.pose
+    }
+
+    ///The matrices to upload with `SkinningBuffer::upload`. Empty without a skeleton.
+    pub fn joint_matrices(&self) -> &Vec<Mat4> {
+        &self.joint_matrices
+    }
+}
+
+///Advances every enabled animator, called by the app once per frame.
+pub fn update_animators(delta: f32) {
+    for component in components_mut() {
+        if !*component.enabled() {
+            continue;
+        }
+        if let Some(animator) = component.downcast_mut::<Animator>() {
+            animator.advance(delta);
+        }
+    }
+}
+
+impl Component for Animator {
+    fn name(&self) -> &String {
+        &self.name
+    }
+
+    fn items(&self) -> &ComponentItems {
+        &self.component_items
+    }
+
+    fn items_mut(&mut self) -> &mut ComponentItems {
+        &mut self.component_items
+    }
+
+    fn set(&mut self, key: &String, value: &Value) -> ECSResult {
+        match (&*key.to_lowercase(), value) {
+            ("speed", Value::Float(speed)) => self.speed = *speed,
+            ("clip", Value::String(clip)) if self.play(clip) => {}
+            _ => return Some(format!("Variable '{}' with value: '{:?}' could not be assigned to component '{}'", key, value, self.name))
+        }
+        None
+    }
+
+    fn imgui_context(&mut self, ui: &Ui) {
+        ui.group(|| {
+            ui.text(format!("Clip: {}", self.current_clip().unwrap_or("none")));
+            ui.text(format!("Time: {:.2}", self.time()));
+            ui.text("Speed:");
+            ui.input_float(format!("##speed_{}_{}", self.name, self.component_items.id), &mut self.speed).build();
+            ui.checkbox(format!("Loop##loop_{}_{}", self.name, self.component_items.id), &mut self.looping);
+        });
+    }
+}
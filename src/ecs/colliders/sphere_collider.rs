@@ -4,23 +4,25 @@ use crate::ecs::colliders::Collider;
 use crate::ecs::{Component, ComponentItems, draw_vec3, ECSResult};
 use crate::ecs::lua_component::Value;
 use crate::math::linear_algebra::types::Vec3;
-use crate::{Camera, draw_arrays, DrawMode, Face, FragmentShader, mat4, OBJ, Program, shaded_wireframe, VertexArrayObject, VertexArrayObjectType, VertexShader};
+use crate::{Camera, draw_arrays, draw_elements, DrawMode, Face, mat4, Program, shaded_wireframe, VertexArrayObject, VertexArrayObjectType};
 use crate::ecs::transform::Transform;
 use crate::math::linear_algebra::IDENTITY_MAT4;
+use crate::gfx::primitives::Primitive;
+use crate::gfx::resource::Shared;
+use crate::gfx::shader::preprocessor::Preprocessor;
+use crate::loader::asset_manager::{AssetManager, SharedAsset};
 use crate::scene::Scene;
-use crate::utils::constructor::Constructor;
 use crate::MatrixWrapper;
 use crate::math::linear_algebra::matrix_ext::MatrixExt;
 use crate::math::linear_algebra::vector_wrapper::Vec3Wrapper;
 
-pub const SPHERE_OBJ: &'static str = include_str!("../../../res/models/debug/sphere/sphere.obj");
-pub const SPHERE_VERT: &'static str = include_str!("../../../res/shaders/debug/debug_vert.glsl");
-pub const SPHERE_FRAG: &'static str = include_str!("../../../res/shaders/debug/debug_frag.glsl");
+pub const SPHERE_VERT: &'static str = "debug/debug_vert.glsl";
+pub const SPHERE_FRAG: &'static str = "debug/debug_frag.glsl";
 
 #[derive(Debug)]
 pub struct RenderBundle {
     vao: VertexArrayObject,
-    program: Program,
+    program: SharedAsset<Shared<Program>>,
     color: [f32; 4]
 }
 
@@ -60,9 +62,8 @@ impl Collider for SphereCollider {}
 
 impl Component for SphereCollider {
     fn awake(&mut self) {
-        let o = OBJ::from_raw(SPHERE_OBJ.to_string()).unwrap();
-        let vao = o.objects()[0].build_vao("None").unwrap();
-        let program = Program::new(FragmentShader::new(SPHERE_FRAG), VertexShader::new(SPHERE_VERT)).unwrap();
+        let vao = Primitive::UvSphere { radius: 1.0, segments: 24, rings: 16 }.build_vao();
+        let program = AssetManager::program(&Preprocessor::new(), SPHERE_VERT, SPHERE_FRAG).unwrap();
         self.bundle = Some(RenderBundle {
             vao,
             program,
@@ -120,6 +121,9 @@ impl Component for SphereCollider {
                         VertexArrayObjectType::Arrays(tris) => {
                             draw_arrays(DrawMode::Triangles, 0, *tris);
                         }
+                        VertexArrayObjectType::Elements(count) => {
+                            draw_elements(DrawMode::Triangles, *count);
+                        }
                         _ => {}
                     }
                 }
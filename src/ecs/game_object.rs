@@ -1,10 +1,13 @@
 use std::any::Any;
 use rlua::prelude::LuaContext;
 use serde::*;
+use vecmath::col_mat4_mul;
 use crate::ecs::{Component, get_by_id, register_component};
 use crate::ecs::lua_component::LuaComponent;
-use crate::ecs::transform::Transform;
-use crate::scene::COMPONENTS;
+use crate::ecs::transform::{Transform, TRANSFORM_NAME};
+use crate::math::linear_algebra::IDENTITY_MAT4;
+use crate::math::linear_algebra::types::Mat4;
+use crate::scene::Scene;
 
 static mut ID: usize = 0;
 
@@ -14,6 +17,11 @@ pub struct GameObject {
     pub(crate) id: usize,
     pub(crate) tag: String,
     pub(crate) components: Vec<usize>,
+    ///Id of the parent object, None for root objects.
+    #[serde(default)]
+    pub(crate) parent: Option<usize>,
+    #[serde(default)]
+    pub(crate) children: Vec<usize>,
 }
 
 impl GameObject {
@@ -25,6 +33,22 @@ impl GameObject {
         register_component(comp);
     }
 
+    ///Attaches an already configured component, e.g. a `Transform` with its position set.
+    pub fn attach_component<T: Component + 'static>(&mut self, mut component: T) {
+        component.items_mut().parent = self.id;
+        component.items_mut().id = component.inc_id();
+        self.components.push(component.items().id);
+        register_component(component);
+    }
+
+    ///Makes `child` a child of this object. The child's transform is relative to its parent, see `model_matrix`.
+    pub fn add_child(&mut self, child: &mut GameObject) {
+        child.parent = Some(self.id);
+        if !self.children.contains(&child.id) {
+            self.children.push(child.id);
+        }
+    }
+
     pub fn remove<T: Component + Default + 'static>(&mut self) -> Option<&'static mut Box<dyn Component>> {
         let _t = T::default();
         for comp in &self.components {
@@ -52,29 +76,25 @@ impl GameObject {
             tag: "".to_string(),
             components: vec![
 
-            ]
+            ],
+            parent: None,
+            children: vec![],
         }
     }
 
+    pub fn named<T: ToString>(name: T) -> Self {
+        let mut object = Self::empty();
+        object.name = name.to_string();
+        object
+    }
+
+    ///The first attached component named `name`, ignoring case.
     pub fn get_component<T: ToString>(&self, name: T) -> Option<&'static Box<dyn Component>> {
-        unsafe {
-            let components = &COMPONENTS;
-            match components {
-                Some(components) => {
-                    if let Some(components) = components.get(&self.id) {
-                        for component in components {
-                            if component.name().eq_ignore_ascii_case(name.to_string().as_str()) {
-                                return Some(component);
-                            }
-                        }
-                    }
-                    return None;
-                }
-                None => {
-                    panic!("Unable to get component. Components not initialized.");
-                }
-            }
-        }
+        let name = name.to_string();
+        self.components.iter()
+            .filter_map(get_by_id)
+            .find(|component| component.name().eq_ignore_ascii_case(&name))
+            .map(|component| &*component)
     }
 
     pub fn name(&self) -> &str {
@@ -101,4 +121,42 @@ impl GameObject {
     pub fn components_mut(&mut self) -> &mut Vec<usize> {
         &mut self.components
     }
+
+    ///The matrix of the object's own `Transform`, identity without one.
+    pub fn local_matrix(&self) -> Mat4 {
+        self.get_component(TRANSFORM_NAME)
+            .and_then(|component| component.downcast_ref::<Transform>())
+            .map(|transform| transform.matrix())
+            .unwrap_or(IDENTITY_MAT4)
+    }
+
+    ///The local matrix composed with those of every ancestor in the `Scene`.
+    pub fn model_matrix(&self) -> Mat4 {
+        let mut matrix = self.local_matrix();
+        let mut visited = vec![self.id];
+        let mut parent = self.parent;
+        while let Some(id) = parent {
+            //A parent loop would never reach a root.
+            if visited.contains(&id) {
+                break;
+            }
+            visited.push(id);
+            match Scene::get_object(&id) {
+                Some(object) => {
+                    matrix = col_mat4_mul(object.local_matrix(), matrix);
+                    parent = object.parent;
+                }
+                None => break
+            }
+        }
+        matrix
+    }
+
+    pub fn parent_id(&self) -> Option<usize> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &Vec<usize> {
+        &self.children
+    }
 }
\ No newline at end of file
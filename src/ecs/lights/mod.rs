@@ -0,0 +1,60 @@
+use vecmath::col_mat3_transform;
+use crate::ecs::{Component, components_of};
+use crate::ecs::lights::directional_light::DirectionalLight;
+use crate::ecs::lights::point_light::PointLight;
+use crate::ecs::lights::spot_light::SpotLight;
+use crate::gfx::lighting::LightData;
+use crate::math::linear_algebra::mat3;
+use crate::math::linear_algebra::types::Vec3;
+use crate::math::linear_algebra::vector_ext::VectorExt;
+
+pub mod directional_light;
+pub mod point_light;
+pub mod spot_light;
+
+///Used for lights whose direction is too short to normalize.
+pub(crate) const DEFAULT_DIRECTION: Vec3 = [0.0, -1.0, 0.0];
+
+pub trait Light: Component {
+    ///The light in the layout of the `Lights` uniform block, placed at `position`.
+    fn light_data(&self, position: Vec3) -> LightData;
+    fn intensity(&self) -> f32;
+
+    ///World position of the light, taken from the parent's model matrix.
+    fn world_position(&self) -> Vec3 {
+        match self.parent() {
+            None => [0.0; 3],
+            Some(parent) => {
+                let model = parent.model_matrix();
+                [model[3][0], model[3][1], model[3][2]]
+            }
+        }
+    }
+
+    ///`direction` rotated by the parent's model matrix and normalized.
+    fn world_direction(&self, direction: Vec3) -> Vec3 {
+        let direction = match self.parent() {
+            None => direction,
+            Some(parent) => col_mat3_transform(mat3(parent.model_matrix()), direction)
+        };
+        direction.normalized_or(DEFAULT_DIRECTION)
+    }
+}
+
+fn collect<L: Light>(lights: &mut Vec<LightData>) {
+    for light in components_of::<L>() {
+        if !*light.enabled() || light.intensity() <= 0.0 {
+            continue;
+        }
+        lights.push(light.light_data(light.world_position()));
+    }
+}
+
+///Every enabled light in the scene.
+pub fn collect_lights() -> Vec<LightData> {
+    let mut lights = vec![];
+    collect::<DirectionalLight>(&mut lights);
+    collect::<PointLight>(&mut lights);
+    collect::<SpotLight>(&mut lights);
+    lights
+}
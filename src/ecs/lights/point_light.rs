@@ -0,0 +1,84 @@
+use imgui_glfw_rs::imgui::Ui;
+use crate::ecs::{Component, ComponentItems, draw_vec3, ECSResult};
+use crate::ecs::lights::Light;
+use crate::ecs::lua_component::Value;
+use crate::gfx::lighting::{LightData, LightType};
+use crate::math::linear_algebra::types::Vec3;
+
+#[derive(Debug, Clone)]
+pub struct PointLight {
+    pub color: Vec3,
+    pub intensity: f32,
+    ///Distance at which the light fades out completely.
+    pub range: f32,
+    name: String,
+    component_items: ComponentItems,
+}
+
+impl Default for PointLight {
+    fn default() -> Self {
+        let mut d = Self {
+            color: [1.0; 3],
+            intensity: 1.0,
+            range: 10.0,
+            name: "PointLight".to_string(),
+            component_items: ComponentItems {
+                state: Default::default(),
+                enabled: true,
+                parent: 0,
+                id: 0,
+            },
+        };
+        d.component_items.id = d.inc_id();
+        d
+    }
+}
+
+impl Light for PointLight {
+    fn light_data(&self, position: Vec3) -> LightData {
+        LightData {
+            position_range: [position[0], position[1], position[2], self.range],
+            direction_type: [0.0, 0.0, 0.0, LightType::Point as u8 as f32],
+            color_intensity: [self.color[0], self.color[1], self.color[2], self.intensity],
+            cone: [0.0; 4],
+        }
+    }
+
+    fn intensity(&self) -> f32 {
+        self.intensity
+    }
+}
+
+impl Component for PointLight {
+    fn name(&self) -> &String {
+        &self.name
+    }
+
+    fn items(&self) -> &ComponentItems {
+        &self.component_items
+    }
+
+    fn items_mut(&mut self) -> &mut ComponentItems {
+        &mut self.component_items
+    }
+
+    fn set(&mut self, key: &String, value: &Value) -> ECSResult {
+        match (&*key.to_lowercase(), value) {
+            ("color", Value::Vec3(color)) => self.color = *color,
+            ("intensity", Value::Float(intensity)) => self.intensity = *intensity,
+            ("range", Value::Float(range)) => self.range = *range,
+            _ => return Some(format!("Variable '{}' with value: '{:?}' could not be assigned to component '{}'", key, value, self.name))
+        }
+        None
+    }
+
+    fn imgui_context(&mut self, ui: &Ui) {
+        ui.group(|| {
+            draw_vec3(&mut self.color, ui, "Color", self.component_items.id);
+            ui.text("Intensity:");
+            ui.input_float(format!("##intensity_{}_{}", self.name, self.component_items.id), &mut self.intensity).build();
+            ui.text("Range:");
+            ui.input_float(format!("##range_{}_{}", self.name, self.component_items.id), &mut self.range).build();
+        });
+    }
+}
@@ -0,0 +1,106 @@
+use imgui_glfw_rs::imgui::Ui;
+use crate::ecs::{Component, ComponentItems, draw_vec3, ECSResult};
+use crate::ecs::lights::Light;
+use crate::ecs::lua_component::Value;
+use crate::gfx::lighting::{LightData, LightType};
+use crate::math::linear_algebra::types::Vec3;
+
+#[derive(Debug, Clone)]
+pub struct SpotLight {
+    pub color: Vec3,
+    pub intensity: f32,
+    pub range: f32,
+    ///Direction the cone points to, rotated with the parent.
+    pub direction: Vec3,
+    ///Half angle in degrees of the fully lit cone.
+    pub inner_angle: f32,
+    ///Half angle in degrees where the light reaches zero.
+    pub outer_angle: f32,
+    ///Whether the light renders shadow maps.
+    pub cast_shadows: bool,
+    name: String,
+    component_items: ComponentItems,
+}
+
+impl Default for SpotLight {
+    fn default() -> Self {
+        let mut d = Self {
+            color: [1.0; 3],
+            intensity: 1.0,
+            range: 15.0,
+            direction: [0.0, -1.0, 0.0],
+            inner_angle: 20.0,
+            outer_angle: 30.0,
+            cast_shadows: false,
+            name: "SpotLight".to_string(),
+            component_items: ComponentItems {
+                state: Default::default(),
+                enabled: true,
+                parent: 0,
+                id: 0,
+            },
+        };
+        d.component_items.id = d.inc_id();
+        d
+    }
+}
+
+impl Light for SpotLight {
+    fn light_data(&self, position: Vec3) -> LightData {
+        let direction = self.world_direction(self.direction);
+        let outer = self.outer_angle.max(self.inner_angle);
+        LightData {
+            position_range: [position[0], position[1], position[2], self.range],
+            direction_type: [direction[0], direction[1], direction[2], LightType::Spot as u8 as f32],
+            color_intensity: [self.color[0], self.color[1], self.color[2], self.intensity],
+            cone: [self.inner_angle.to_radians().cos(), outer.to_radians().cos(), 0.0, self.cast_shadows as u8 as f32],
+        }
+    }
+
+    fn intensity(&self) -> f32 {
+        self.intensity
+    }
+}
+
+impl Component for SpotLight {
+    fn name(&self) -> &String {
+        &self.name
+    }
+
+    fn items(&self) -> &ComponentItems {
+        &self.component_items
+    }
+
+    fn items_mut(&mut self) -> &mut ComponentItems {
+        &mut self.component_items
+    }
+
+    fn set(&mut self, key: &String, value: &Value) -> ECSResult {
+        match (&*key.to_lowercase(), value) {
+            ("color", Value::Vec3(color)) => self.color = *color,
+            ("direction", Value::Vec3(direction)) => self.direction = *direction,
+            ("intensity", Value::Float(intensity)) => self.intensity = *intensity,
+            ("range", Value::Float(range)) => self.range = *range,
+            ("inner_angle", Value::Float(angle)) => self.inner_angle = *angle,
+            ("outer_angle", Value::Float(angle)) => self.outer_angle = *angle,
+            _ => return Some(format!("Variable '{}' with value: '{:?}' could not be assigned to component '{}'", key, value, self.name))
+        }
+        None
+    }
+
+    fn imgui_context(&mut self, ui: &Ui) {
+        ui.group(|| {
+            draw_vec3(&mut self.color, ui, "Color", self.component_items.id);
+            ui.text("Intensity:");
+            ui.input_float(format!("##intensity_{}_{}", self.name, self.component_items.id), &mut self.intensity).build();
+            ui.text("Range:");
+            ui.input_float(format!("##range_{}_{}", self.name, self.component_items.id), &mut self.range).build();
+            draw_vec3(&mut self.direction, ui, "Direction", self.component_items.id);
+            ui.text("Inner angle:");
+            ui.input_float(format!("##inner_{}_{}", self.name, self.component_items.id), &mut self.inner_angle).build();
+            ui.text("Outer angle:");
+            ui.input_float(format!("##outer_{}_{}", self.name, self.component_items.id), &mut self.outer_angle).build();
+            ui.checkbox(format!("Cast shadows##{}", self.component_items.id), &mut self.cast_shadows);
+        });
+    }
+}
@@ -0,0 +1,266 @@
+use imgui_glfw_rs::imgui::Ui;
+use crate::Camera;
+use crate::ecs::{Component, ComponentItems, components_mut, ECSResult};
+use crate::ecs::lua_component::Value;
+use crate::gfx::mesh::{Mesh, VertexAttribute};
+use crate::gfx::objects::vertex_array_object::VertexArrayObject;
+use crate::gfx::pbr::{bind_pbr_object, pbr_program, PbrMaterial};
+use crate::gfx::program::Program;
+use crate::gfx::resource::Shared;
+use crate::loader::models::gltf::GltfPrimitive;
+use crate::loader::models::obj::Object;
+use crate::math::bounds::BoundingSphere;
+use crate::math::linear_algebra::IDENTITY_MAT4;
+use crate::math::linear_algebra::types::{Mat4, Vec3};
+use crate::math::linear_algebra::vector_ext::VectorExt;
+
+pub const LOD_GROUP_NAME: &'static str = "LODGroup";
+
+///A level of detail and the smallest screen size it's used at.
+#[derive(Debug, Clone)]
+pub struct LOD {
+    pub mesh: Mesh,
+    ///Fraction of the screen height the group's bounding sphere has to cover, see `screen_size`.
+    pub screen_size: f32,
+}
+
+impl LOD {
+    pub fn new(mesh: Mesh, screen_size: f32) -> LOD {
+        Self { mesh, screen_size }
+    }
+}
+
+///Fraction of the screen height covered by a sphere seen through the camera's perspective. At least 1 from inside it.
+pub fn screen_size(camera: &Camera, center: Vec3, radius: f32) -> f32 {
+    let position = camera.position();
+    let distance = center.distance(&position);
+    if distance <= radius {
+        return f32::INFINITY;
+    }
+    radius / (distance * (camera.fov().to_radians() * 0.5).tan())
+}
+
+///Picks one of a model's levels of detail by how large it appears on screen.
+///Levels are ordered from the most detailed, and the group is culled below the last level's screen size.
+///Groups are updated once per frame with the app's camera, see `update_lod_groups`, and `render`
+///draws the current level with the PBR program.
+#[derive(Debug)]
+pub struct LODGroup {
+    ///Multiplies the measured screen size, above 1 to keep detailed levels longer.
+    pub bias: f32,
+    pub material: PbrMaterial,
+    ///Whether the current level is drawn into shadow maps.
+    pub cast_shadows: bool,
+    pub receive_shadows: bool,
+    levels: Vec<LOD>,
+    ///A vertex array per level, built in `awake`.
+    vaos: Vec<VertexArrayObject>,
+    program: Option<Shared<Program>>,
+    bounding_sphere: BoundingSphere,
+    current: Option<usize>,
+    screen_size: f32,
+    name: String,
+    component_items: ComponentItems,
+}
+
+impl Default for LODGroup {
+    fn default() -> Self {
+        let mut d = Self {
+            bias: 1.0,
+            material: PbrMaterial::default(),
+            cast_shadows: true,
+            receive_shadows: true,
+            levels: vec![],
+            vaos: vec![],
+            program: None,
+            bounding_sphere: BoundingSphere::new([0.0; 3], 0.0),
+            current: None,
+            screen_size: 0.0,
+            name: LOD_GROUP_NAME.to_string(),
+            component_items: ComponentItems {
+                state: Default::default(),
+                enabled: true,
+                parent: 0,
+                id: 0,
+            },
+        };
+        d.component_items.id = d.inc_id();
+        d
+    }
+}
+
+impl LODGroup {
+    ///Sorts the levels by descending screen size. The bounds of the first level enclose the group.
+    pub fn new(mut levels: Vec<LOD>) -> LODGroup {
+        levels.sort_by(|a, b| b.screen_size.total_cmp(&a.screen_size));
+        let bounding_sphere = levels.first()
+            .and_then(|level| level.mesh.bounding_sphere())
+            .unwrap_or(BoundingSphere::new([0.0; 3], 0.0));
+        Self {
+            levels,
+            bounding_sphere,
+            current: Some(0),
+            ..Default::default()
+        }
+    }
+
+    ///Simplifies `mesh` to every `(triangle ratio, screen size)` level, see `Mesh::generate_lods`.
+    pub fn from_mesh(mesh: &Mesh, levels: &[(f32, f32)]) -> LODGroup {
+        let ratios = levels.iter().map(|(ratio, _)| *ratio).collect::<Vec<f32>>();
+        let lods = mesh.generate_lods(&ratios).into_iter().zip(levels)
+            .map(|(mesh, (_, screen_size))| LOD::new(mesh, *screen_size))
+            .collect();
+        Self::new(lods)
+    }
+
+    ///Levels of an obj object, see `Mesh::from_object` and `from_mesh`.
+    pub fn from_object(object: &Object, levels: &[(f32, f32)]) -> LODGroup {
+        Self::from_mesh(&Mesh::from_object(object), levels)
+    }
+
+    ///Levels of a rigid glTF primitive, see `Mesh::from_gltf` and `from_mesh`.
+    pub fn from_gltf(primitive: &GltfPrimitive, levels: &[(f32, f32)]) -> LODGroup {
+        Self::from_mesh(&Mesh::from_gltf(primitive), levels)
+    }
+
+    pub fn with_material(mut self, material: PbrMaterial) -> LODGroup {
+        self.material = material;
+        self
+    }
+
+    pub fn levels(&self) -> &Vec<LOD> {
+        &self.levels
+    }
+
+    ///Local bounds of the most detailed level.
+    pub fn bounding_sphere(&self) -> &BoundingSphere {
+        &self.bounding_sphere
+    }
+
+    ///Index of the level in use, None while culled.
+    pub fn current_level(&self) -> Option<usize> {
+        self.current
+    }
+
+    pub fn current_mesh(&self) -> Option<&Mesh> {
+        self.current.map(|level| &self.levels[level].mesh)
+    }
+
+    ///Screen size measured by the last `update_level`, bias included.
+    pub fn screen_size(&self) -> f32 {
+        self.screen_size
+    }
+
+    ///The first level at most `screen_size`, None if it's smaller than every level's.
+    pub fn level_for(&self, screen_size: f32) -> Option<usize> {
+        self.levels.iter().position(|level| screen_size >= level.screen_size)
+    }
+
+    ///The bounds in the world, placed by the parent's model matrix.
+    pub fn world_bounding_sphere(&self) -> BoundingSphere {
+        self.bounding_sphere.transformed(&self.model_matrix())
+    }
+
+    ///The parent's model matrix, identity without a parent.
+    pub fn model_matrix(&self) -> Mat4 {
+        self.parent().map(|parent| parent.model_matrix()).unwrap_or(IDENTITY_MAT4)
+    }
+
+    ///Measures the group through `camera` and switches to the matching level.
+    pub fn update_level(&mut self, camera: &Camera) -> Option<usize> {
+        let sphere = self.world_bounding_sphere();
+        self.screen_size = screen_size(camera, sphere.center, sphere.radius) * self.bias;
+        self.current = self.level_for(self.screen_size);
+        self.current
+    }
+}
+
+///Selects the level of every enabled group for `camera`, called by the app once per frame.
+pub fn update_lod_groups(camera: &Camera) {
+    for component in components_mut() {
+        if !*component.enabled() {
+            continue;
+        }
+        if let Some(group) = component.downcast_mut::<LODGroup>() {
+            group.update_level(camera);
+        }
+    }
+}
+
+impl Component for LODGroup {
+    fn awake(&mut self) {
+        match pbr_program() {
+            Ok(program) => {
+                self.vaos = self.levels.iter().map(|level| {
+                    let mut mesh = level.mesh.clone();
+                    mesh.complete_streams();
+                    mesh.build_vao()
+                }).collect();
+                self.program = Some(program);
+            }
+            Err(e) => eprintln!("Unable to build the program of '{}': {}", self.name, e)
+        }
+    }
+
+    fn name(&self) -> &String {
+        &self.name
+    }
+
+    fn items(&self) -> &ComponentItems {
+        &self.component_items
+    }
+
+    fn items_mut(&mut self) -> &mut ComponentItems {
+        &mut self.component_items
+    }
+
+    fn set(&mut self, key: &String, value: &Value) -> ECSResult {
+        match (&*key.to_lowercase(), value) {
+            ("bias", Value::Float(bias)) => self.bias = *bias,
+            _ => return Some(format!("Variable '{}' with value: '{:?}' could not be assigned to component '{}'", key, value, self.name))
+        }
+        None
+    }
+
+    fn render(&mut self, camera: &Camera) {
+        let (vao, program) = match (self.current.and_then(|level| self.vaos.get(level)), &self.program) {
+            (Some(vao), Some(program)) => (vao, program),
+            _ => return
+        };
+        let vertex_colors = self.current_mesh().map(|mesh| mesh.attributes().contains(&VertexAttribute::Color)).unwrap_or(false);
+        let model = self.model_matrix();
+        program.enable();
+        bind_pbr_object(program, camera, &self.material, &model, &self.bounding_sphere.transformed(&model), self.receive_shadows, vertex_colors);
+        vao.draw();
+        program.disable();
+    }
+
+    fn render_shadow(&mut self, program: &Program) {
+        if let Some(vao) = self.current.and_then(|level| self.vaos.get(level)) {
+            program.set_uniform_mat4("model", &self.model_matrix());
+            vao.draw();
+        }
+    }
+
+    fn imgui_context(&mut self, ui: &Ui) {
+        ui.group(|| {
+            match self.current {
+                Some(level) => ui.text(format!("Level: {} of {} ({} triangles)", level, self.levels.len(), self.levels[level].mesh.triangle_count())),
+                None => ui.text("Level: culled")
+            }
+            ui.text(format!("Screen size: {:.3}", self.screen_size));
+            ui.text("Bias:");
+            ui.input_float(format!("##bias_{}_{}", self.name, self.component_items.id), &mut self.bias).build();
+            ui.checkbox(format!("Cast shadows##cast_{}_{}", self.name, self.component_items.id), &mut self.cast_shadows);
+            ui.checkbox(format!("Receive shadows##receive_{}_{}", self.name, self.component_items.id), &mut self.receive_shadows);
+        });
+    }
+
+    fn cast_shadows(&self) -> bool {
+        self.cast_shadows
+    }
+
+    fn receive_shadows(&self) -> bool {
+        self.receive_shadows
+    }
+}
@@ -4,7 +4,7 @@ use downcast_rs::{Downcast, impl_downcast};
 use imgui_glfw_rs::imgui::{TreeNodeFlags, Ui};
 use maplit::hashmap;
 use serde::*;
-use crate::Camera;
+use crate::{Camera, Program};
 use crate::ecs::game_object::GameObject;
 use crate::ecs::lua_component::Value;
 use crate::math::linear_algebra::types::Vec3;
@@ -15,6 +15,9 @@ pub mod transform;
 
 pub mod colliders;
 pub mod lua_component;
+pub mod lights;
+pub mod animator;
+pub mod lod_group;
 
 static mut ID: usize = 0;
 static mut COMPONENTS: Option<HashMap<usize, Box<dyn Component + 'static>>> = None;
@@ -81,11 +84,39 @@ pub fn init_components() {
     unsafe { COMPONENTS = Some(hashmap! {}); }
 }
 
+///Drops every registered component along with the GL objects it owns.
+pub fn clear_components() {
+    unsafe { COMPONENTS = None; }
+}
+
 pub fn register_component<C: Component + 'static>(component: C) {
     let component = Box::new(component);
     unsafe {
-        if let Some(components) = COMPONENTS.as_mut() {
-            components.insert(component.items().id, component);
+        COMPONENTS.get_or_insert_with(HashMap::new).insert(component.items().id, component);
+    }
+}
+
+///Every registered component of type `T`.
+pub fn components_of<T: Component>() -> Vec<&'static T> {
+    let mut out = vec![];
+    unsafe {
+        if let Some(components) = &COMPONENTS {
+            for (_, component) in components {
+                if let Some(component) = component.downcast_ref::<T>() {
+                    out.push(component);
+                }
+            }
+        }
+    }
+    out
+}
+
+///Every registered component, mutably.
+pub fn components_mut() -> Vec<&'static mut Box<dyn Component>> {
+    unsafe {
+        match &mut COMPONENTS {
+            Some(components) => components.values_mut().collect(),
+            None => vec![]
         }
     }
 }
@@ -120,6 +151,14 @@ pub trait Component: Debug + Downcast {
     fn set_enabled(&mut self, flag: bool) {
         self.items_mut().enabled = flag;
     }
+    ///Whether `render_shadow` draws the component into shadow maps, only renderers cast shadows.
+    fn cast_shadows(&self) -> bool {
+        false
+    }
+    ///Whether the component's surfaces are darkened by shadow maps, only renderers receive shadows.
+    fn receive_shadows(&self) -> bool {
+        false
+    }
     fn parent(&self) -> Option<&'static mut GameObject> {
         Scene::get_object(self.parent_id())
     }
@@ -147,6 +186,8 @@ pub trait Component: Debug + Downcast {
     fn imgui_context(&mut self, ui: &Ui) {}
     fn set(&mut self, key: &String, value: &Value)  -> ECSResult;
     fn render(&mut self, camera: &Camera) {}
+    ///Draws the component's geometry into a shadow map. `program` only needs the `model` uniform.
+    fn render_shadow(&mut self, program: &Program) {}
 }
 
 pub type ECSResult = Option<String>;
\ No newline at end of file
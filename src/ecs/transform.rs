@@ -1,6 +1,7 @@
 use imgui_glfw_rs::imgui::{InputFloat, InputTextFlags, Ui};
 use crate::ecs::{Component, ComponentItems, ComponentState, draw_vec3, ECSResult};
-use crate::math::linear_algebra::types::Vec3;
+use crate::math::linear_algebra::types::{Mat4, Vec3};
+use crate::math::quaternion::{compose, from_rotation_vector};
 use serde::*;
 use crate::ecs::lua_component::Value;
 
@@ -32,6 +33,13 @@ impl Default for Transform {
     }
 }
 
+impl Transform {
+    ///Column major model matrix, scaling first, then rotating and translating.
+    pub fn matrix(&self) -> Mat4 {
+        compose(self.position, from_rotation_vector(self.rotation), self.scale)
+    }
+}
+
 impl Component for Transform {
     fn name(&self) -> &String {
         &self.name
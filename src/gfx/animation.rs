@@ -0,0 +1,332 @@
+use std::error::Error;
+use vecmath::col_mat4_mul;
+use crate::gfx::lighting::Lighting;
+use crate::gfx::pbr::PBR_FRAGMENT;
+use crate::gfx::program::Program;
+use crate::gfx::resource::Shared;
+use crate::gfx::shader::preprocessor::Preprocessor;
+use crate::gfx::shadows::Shadows;
+use crate::gfx::uniform_block::UniformBuffer;
+use crate::math::linear_algebra::IDENTITY_MAT4;
+use crate::math::linear_algebra::types::{Mat4, Vec3, Vec4};
+use crate::math::quaternion::{compose, IDENTITY_QUAT, normalize, Quat, slerp};
+use crate::uniform_block;
+
+pub const SKINNING_VERTEX: &'static str = "skinning/vertex.glsl";
+///Size of the joint array of the `Joints` block, defined as `MAX_JOINTS` for `skinning/skinning.glsl`.
+pub const MAX_JOINTS: usize = 128;
+///Uniform buffer binding of the `Joints` block.
+pub const JOINTS_BINDING: u32 = 3;
+
+///Whether truncating to `MAX_JOINTS` was already reported, so it's logged once rather than every frame.
+static mut TRUNCATION_REPORTED: bool = false;
+
+uniform_block! {
+    ///Skinning matrices, for shaders including `skinning/skinning.glsl`.
+    #[derive(Debug, Clone)]
+    pub struct JointMatrices("Joints") {
+        pub joints: Vec<Mat4>,
+    }
+}
+
+impl JointMatrices {
+    ///Pads or truncates to `MAX_JOINTS` so the block matches its GLSL size. Truncation is logged,
+    ///vertices bound to the dropped joints won't follow them.
+    pub fn new(matrices: &[Mat4]) -> JointMatrices {
+        unsafe {
+            if matrices.len() > MAX_JOINTS && !TRUNCATION_REPORTED {
+                TRUNCATION_REPORTED = true;
+                eprintln!("Skinning supports {} joints, the last {} of {} are ignored", MAX_JOINTS, matrices.len() - MAX_JOINTS, matrices.len());
+            }
+        }
+        let mut joints = matrices.iter().take(MAX_JOINTS).copied().collect::<Vec<Mat4>>();
+        joints.resize(MAX_JOINTS, IDENTITY_MAT4);
+        Self { joints }
+    }
+}
+
+///The PBR program with skinning, for meshes built by `GltfPrimitive::build_vao` from skinned primitives.
+///Call `bind_pbr_object` and bind a `SkinningBuffer` before drawing.
+pub fn skinned_pbr_program() -> Result<Shared<Program>, Box<dyn Error>> {
+    let program = Preprocessor::new()
+        .with_defines(&Shadows::defines())
+        .define("MAX_JOINTS", MAX_JOINTS)
+        .program(SKINNING_VERTEX, PBR_FRAGMENT)?;
+    Lighting::bind(&program);
+    Ok(program)
+}
+
+///Owns the `Joints` uniform buffer skinned programs read their matrices from.
+#[derive(Debug)]
+pub struct SkinningBuffer {
+    buffer: UniformBuffer<JointMatrices>,
+}
+
+impl SkinningBuffer {
+    pub fn new() -> SkinningBuffer {
+        Self { buffer: UniformBuffer::new(JOINTS_BINDING) }
+    }
+
+    pub fn upload(&mut self, matrices: &[Mat4]) {
+        self.buffer.upload(&JointMatrices::new(matrices));
+    }
+
+    ///Connects the program's `Joints` block to this buffer. Returns false if the program isn't skinned.
+    pub fn bind(&self, program: &Program) -> bool {
+        self.buffer.bind(program)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    Linear,
+    ///Holds each keyframe until the next one.
+    Step,
+    ///Hermite spline, every keyframe stores an in-tangent, the value and an out-tangent.
+    CubicSpline,
+}
+
+impl Interpolation {
+    pub fn from_name(name: &str) -> Option<Interpolation> {
+        match name {
+            "LINEAR" => Some(Interpolation::Linear),
+            "STEP" => Some(Interpolation::Step),
+            "CUBICSPLINE" => Some(Interpolation::CubicSpline),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChannelPath {
+    Translation,
+    Rotation,
+    Scale,
+}
+
+///Local translation, rotation and scale of a node.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NodeTransform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for NodeTransform {
+    fn default() -> Self {
+        Self {
+            translation: [0.0; 3],
+            rotation: IDENTITY_QUAT,
+            scale: [1.0; 3],
+        }
+    }
+}
+
+impl NodeTransform {
+    pub fn matrix(&self) -> Mat4 {
+        compose(self.translation, self.rotation, self.scale)
+    }
+
+    ///Interpolates towards `other`, linearly for translation and scale and spherically for the rotation.
+    pub fn blend(&self, other: &NodeTransform, weight: f32) -> NodeTransform {
+        Self {
+            translation: lerp3(self.translation, other.translation, weight),
+            rotation: slerp(self.rotation, other.rotation, weight),
+            scale: lerp3(self.scale, other.scale, weight),
+        }
+    }
+}
+
+fn lerp3(a: Vec3, b: Vec3, t: f32) -> Vec3 {
+    [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, a[2] + (b[2] - a[2]) * t]
+}
+
+///The local transform of every node of a model, indexed like the model's nodes.
+pub type Pose = Vec<NodeTransform>;
+
+///Blends two poses of the same model, `weight` 0 is `a` and 1 is `b`.
+pub fn blend_poses(a: &Pose, b: &Pose, weight: f32) -> Pose {
+    a.iter().zip(b).map(|(a, b)| a.blend(b, weight)).collect()
+}
+
+///Keyframes animating one property of one node.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    pub node: usize,
+    pub path: ChannelPath,
+    pub interpolation: Interpolation,
+    ///Keyframe times in seconds, increasing.
+    pub times: Vec<f32>,
+    ///One value per keyframe, three for cubic splines. Translations and scales leave w unused.
+    pub values: Vec<Vec4>,
+}
+
+impl Channel {
+    fn key(&self, index: usize) -> Vec4 {
+        match self.interpolation {
+            Interpolation::CubicSpline => self.values[index * 3 + 1],
+            _ => self.values[index]
+        }
+    }
+
+    ///The channel's value at `time`, clamped to the first and last keyframe.
+    pub fn sample(&self, time: f32) -> Option<Vec4> {
+        let last = self.times.len().checked_sub(1)?;
+        let stride = match self.interpolation {
+            Interpolation::CubicSpline => 3,
+            _ => 1
+        };
+        if self.values.len() < self.times.len() * stride {
+            return None;
+        }
+        if time <= self.times[0] {
+            return Some(self.key(0));
+        }
+        if time >= self.times[last] {
+            return Some(self.key(last));
+        }
+        let next = self.times.partition_point(|t| *t <= time);
+        let previous = next - 1;
+        let delta = self.times[next] - self.times[previous];
+        let t = (time - self.times[previous]) / delta;
+        let (a, b) = (self.key(previous), self.key(next));
+        let value = match self.interpolation {
+            Interpolation::Step => a,
+            Interpolation::Linear => match self.path {
+                ChannelPath::Rotation => slerp(a, b, t),
+                _ => lerp4(a, b, t)
+            },
+            Interpolation::CubicSpline => {
+                let out_tangent = self.values[previous * 3 + 2];
+                let in_tangent = self.values[next * 3];
+                let (t2, t3) = (t * t, t * t * t);
+                let mut value = [0.0; 4];
+                for i in 0..4 {
+                    value[i] = (2.0 * t3 - 3.0 * t2 + 1.0) * a[i]
+                        + (t3 - 2.0 * t2 + t) * delta * out_tangent[i]
+                        + (-2.0 * t3 + 3.0 * t2) * b[i]
+                        + (t3 - t2) * delta * in_tangent[i];
+                }
+                value
+            }
+        };
+        Some(match self.path {
+            ChannelPath::Rotation => normalize(value),
+            _ => value
+        })
+    }
+}
+
+fn lerp4(a: Vec4, b: Vec4, t: f32) -> Vec4 {
+    [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, a[2] + (b[2] - a[2]) * t, a[3] + (b[3] - a[3]) * t]
+}
+
+///A named set of channels, e.g. a walk cycle.
+#[derive(Debug, Clone)]
+pub struct AnimationClip {
+    name: String,
+    channels: Vec<Channel>,
+    duration: f32,
+}
+
+impl AnimationClip {
+    pub fn new<T: ToString>(name: T, channels: Vec<Channel>) -> AnimationClip {
+        let duration = channels.iter()
+            .filter_map(|channel| channel.times.last().copied())
+            .fold(0.0, f32::max);
+        Self { name: name.to_string(), channels, duration }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn channels(&self) -> &Vec<Channel> {
+        &self.channels
+    }
+
+    ///Time of the last keyframe, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    ///Overwrites the animated properties of `pose` with their values at `time`. Other properties keep their values.
+    pub fn sample(&self, time: f32, pose: &mut Pose) {
+        for channel in &self.channels {
+            let (transform, value) = match (pose.get_mut(channel.node), channel.sample(time)) {
+                (Some(transform), Some(value)) => (transform, value),
+                _ => continue
+            };
+            match channel.path {
+                ChannelPath::Translation => transform.translation = [value[0], value[1], value[2]],
+                ChannelPath::Rotation => transform.rotation = value,
+                ChannelPath::Scale => transform.scale = [value[0], value[1], value[2]],
+            }
+        }
+    }
+}
+
+///The joints of a skinned mesh and the node hierarchy they live in.
+#[derive(Debug, Clone)]
+pub struct Skeleton {
+    ///Node index per joint, in the order of the mesh's joint indices.
+    joints: Vec<usize>,
+    ///Per joint, transforms from the mesh's bind space into the joint's space.
+    inverse_bind_matrices: Vec<Mat4>,
+    ///Parent node per node.
+    parents: Vec<Option<usize>>,
+    rest_pose: Pose,
+}
+
+impl Skeleton {
+    ///`parents` and `rest_pose` cover every node of the model, not only the joints.
+    ///Missing inverse bind matrices are identities.
+    pub fn new(joints: Vec<usize>, mut inverse_bind_matrices: Vec<Mat4>, parents: Vec<Option<usize>>, rest_pose: Pose) -> Skeleton {
+        inverse_bind_matrices.resize(joints.len(), IDENTITY_MAT4);
+        Self { joints, inverse_bind_matrices, parents, rest_pose }
+    }
+
+    pub fn joints(&self) -> &Vec<usize> {
+        &self.joints
+    }
+
+    pub fn inverse_bind_matrices(&self) -> &Vec<Mat4> {
+        &self.inverse_bind_matrices
+    }
+
+    ///The pose of the model when it was exported.
+    pub fn rest_pose(&self) -> &Pose {
+        &self.rest_pose
+    }
+
+    ///Model space matrix of every node in `pose`.
+    pub fn world_matrices(&self, pose: &Pose) -> Vec<Mat4> {
+        let mut world: Vec<Option<Mat4>> = vec![None; pose.len()];
+        for node in 0..pose.len() {
+            self.world_matrix(node, pose, &mut world);
+        }
+        world.into_iter().map(|m| m.unwrap_or(IDENTITY_MAT4)).collect()
+    }
+
+    fn world_matrix(&self, node: usize, pose: &Pose, world: &mut Vec<Option<Mat4>>) -> Mat4 {
+        if let Some(matrix) = world[node] {
+            return matrix;
+        }
+        let local = pose[node].matrix();
+        let matrix = match self.parents.get(node).copied().flatten() {
+            Some(parent) if parent < pose.len() => col_mat4_mul(self.world_matrix(parent, pose, world), local),
+            _ => local
+        };
+        world[node] = Some(matrix);
+        matrix
+    }
+
+    ///The matrices uploaded to `JointMatrices`: each joint's model space matrix times its inverse bind matrix.
+    ///They're in model space, so skinned meshes are drawn with the model's matrix rather than their node's.
+    pub fn joint_matrices(&self, pose: &Pose) -> Vec<Mat4> {
+        let world = self.world_matrices(pose);
+        self.joints.iter().zip(&self.inverse_bind_matrices)
+            .map(|(joint, inverse_bind)| col_mat4_mul(world.get(*joint).copied().unwrap_or(IDENTITY_MAT4), *inverse_bind))
+            .collect()
+    }
+}
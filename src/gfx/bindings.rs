@@ -4,6 +4,7 @@ use gl::*;
 use std::ptr::{null_mut};
 use rlua::{Context, MetaMethod, UserData, UserDataMethods};
 use crate::gfx::shader::Shader;
+use crate::app::AppConfigFlags;
 
 #[derive(Debug, Clone, Copy)]
 #[repr(u32)]
@@ -30,7 +31,41 @@ pub enum DrawType {
 #[derive(Debug, Clone, Copy)]
 #[repr(u32)]
 pub enum BufferType {
-    ArrayBuffer = gl::ARRAY_BUFFER
+    ArrayBuffer = gl::ARRAY_BUFFER,
+    ElementArrayBuffer = gl::ELEMENT_ARRAY_BUFFER,
+    UniformBuffer = gl::UNIFORM_BUFFER,
+    ShaderStorageBuffer = gl::SHADER_STORAGE_BUFFER,
+    DispatchIndirectBuffer = gl::DISPATCH_INDIRECT_BUFFER,
+    DrawIndirectBuffer = gl::DRAW_INDIRECT_BUFFER,
+}
+
+///Bits for `buffers::map_buffer_range`, combine them with `|`.
+#[derive(Debug, Clone, Copy)]
+#[repr(u32)]
+pub enum MapAccess {
+    Read = gl::MAP_READ_BIT,
+    Write = gl::MAP_WRITE_BIT,
+    ///The previous contents of the range may be discarded.
+    InvalidateRange = gl::MAP_INVALIDATE_RANGE_BIT,
+    InvalidateBuffer = gl::MAP_INVALIDATE_BUFFER_BIT,
+    ///Don't wait for the GPU to finish with the buffer, the caller avoids overwriting data in use.
+    Unsynchronized = gl::MAP_UNSYNCHRONIZED_BIT,
+}
+
+impl std::ops::BitOr for MapAccess {
+    type Output = u32;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self as u32 | rhs as u32
+    }
+}
+
+impl std::ops::BitOr<MapAccess> for u32 {
+    type Output = u32;
+
+    fn bitor(self, rhs: MapAccess) -> Self::Output {
+        self | rhs as u32
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -48,11 +83,66 @@ impl std::ops::BitOr for ColorFlags {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(u32)]
 pub enum ShaderType {
     VertexShader = gl::VERTEX_SHADER,
     FragmentShader = gl::FRAGMENT_SHADER,
+    GeometryShader = gl::GEOMETRY_SHADER,
+    TessControlShader = gl::TESS_CONTROL_SHADER,
+    TessEvaluationShader = gl::TESS_EVALUATION_SHADER,
+    ComputeShader = gl::COMPUTE_SHADER,
+}
+
+impl ShaderType {
+    ///The context a stage needs beyond OpenGL 3.3. Tessellation is core since 4.0, compute since 4.3.
+    pub fn required_flag(&self) -> Option<AppConfigFlags> {
+        match self {
+            ShaderType::TessControlShader | ShaderType::TessEvaluationShader | ShaderType::ComputeShader => Some(AppConfigFlags::OpenGLAdvanced),
+            _ => None
+        }
+    }
+}
+
+///Bits for `compute::memory_barrier`, combine them with `|`.
+#[derive(Debug, Clone, Copy)]
+#[repr(u32)]
+pub enum MemoryBarrier {
+    VertexAttribArray = gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT,
+    ElementArray = gl::ELEMENT_ARRAY_BARRIER_BIT,
+    Uniform = gl::UNIFORM_BARRIER_BIT,
+    TextureFetch = gl::TEXTURE_FETCH_BARRIER_BIT,
+    ShaderImageAccess = gl::SHADER_IMAGE_ACCESS_BARRIER_BIT,
+    Command = gl::COMMAND_BARRIER_BIT,
+    BufferUpdate = gl::BUFFER_UPDATE_BARRIER_BIT,
+    TextureUpdate = gl::TEXTURE_UPDATE_BARRIER_BIT,
+    Framebuffer = gl::FRAMEBUFFER_BARRIER_BIT,
+    ShaderStorage = gl::SHADER_STORAGE_BARRIER_BIT,
+    All = gl::ALL_BARRIER_BITS,
+}
+
+impl std::ops::BitOr for MemoryBarrier {
+    type Output = u32;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self as u32 | rhs as u32
+    }
+}
+
+impl std::ops::BitOr<MemoryBarrier> for u32 {
+    type Output = u32;
+
+    fn bitor(self, rhs: MemoryBarrier) -> Self::Output {
+        self | rhs as u32
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(u32)]
+pub enum ImageAccess {
+    ReadOnly = gl::READ_ONLY,
+    WriteOnly = gl::WRITE_ONLY,
+    ReadWrite = gl::READ_WRITE,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -63,6 +153,14 @@ pub enum IV {
     LinkStatus = gl::LINK_STATUS,
 
     InfoLogLength = gl::INFO_LOG_LENGTH,
+
+    AttachedShaders = gl::ATTACHED_SHADERS,
+
+    ActiveUniforms = gl::ACTIVE_UNIFORMS,
+
+    ActiveAttributes = gl::ACTIVE_ATTRIBUTES,
+
+    ActiveUniformBlocks = gl::ACTIVE_UNIFORM_BLOCKS,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -70,6 +168,8 @@ pub enum IV {
 pub enum DrawMode {
     Triangles = gl::TRIANGLES,
     TriangleStrip = gl::TRIANGLE_STRIP,
+    ///Input of tessellation programs, see `graphics::patch_vertices`.
+    Patches = gl::PATCHES,
 }
 
 use enum_iterator::{all, Sequence};
@@ -204,6 +304,7 @@ pub enum MipMaps {
     NearestMipmapLinear = GLConsts::NearestMipmapLinear as u32,
     LinearMipmapLinear = GLConsts::LinearMipmapLinear as u32,
     Linear = GLConsts::Linear as u32,
+    Nearest = GLConsts::Nearest as u32,
 }
 
 impl Into<GLConsts> for MipMaps {
@@ -213,7 +314,8 @@ impl Into<GLConsts> for MipMaps {
             MipMaps::LinearMipmapNearest => GLConsts::LinearMipmapNearest,
             MipMaps::NearestMipmapLinear => GLConsts::NearestMipmapLinear,
             MipMaps::LinearMipmapLinear => GLConsts::LinearMipmapLinear,
-            MipMaps::Linear => GLConsts::Linear
+            MipMaps::Linear => GLConsts::Linear,
+            MipMaps::Nearest => GLConsts::Nearest
         }
     }
 }
@@ -265,6 +367,7 @@ pub enum TextureParamName {
     TextureWrapS = GLConsts::TextureWrapS as u32,
     TextureWrapT = GLConsts::TextureWrapT as u32,
     TextureWrapR = GLConsts::TextureWrapR as u32,
+    TextureMaxAnisotropy = gl::TEXTURE_MAX_ANISOTROPY,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -303,13 +406,113 @@ pub enum TextureTarget {
 #[derive(Debug, Clone, Copy)]
 #[repr(u32)]
 pub enum ColorFormat {
-    RGB = GLConsts::RGB as u32
+    Red = gl::RED,
+    RG = gl::RG,
+    RGB = GLConsts::RGB as u32,
+    RGBA = gl::RGBA,
+    R8 = gl::R8,
+    RG8 = gl::RG8,
+    RGB8 = gl::RGB8,
+    RGBA8 = gl::RGBA8,
+    SRGB8 = gl::SRGB8,
+    SRGB8Alpha8 = gl::SRGB8_ALPHA8,
+    R16 = gl::R16,
+    RG16 = gl::RG16,
+    RGB16 = gl::RGB16,
+    RGBA16 = gl::RGBA16,
+    R16F = gl::R16F,
+    RG16F = gl::RG16F,
+    RGB16F = gl::RGB16F,
+    RGBA16F = gl::RGBA16F,
+    R32F = gl::R32F,
+    RG32F = gl::RG32F,
+    RGB32F = gl::RGB32F,
+    RGBA32F = gl::RGBA32F,
+    DepthComponent = gl::DEPTH_COMPONENT,
+    DepthComponent16 = gl::DEPTH_COMPONENT16,
+    DepthComponent24 = gl::DEPTH_COMPONENT24,
+    DepthComponent32F = gl::DEPTH_COMPONENT32F,
+    DepthStencil = gl::DEPTH_STENCIL,
+    Depth24Stencil8 = gl::DEPTH24_STENCIL8,
+    ///S3TC formats come from EXT_texture_compression_s3tc and EXT_texture_sRGB, which every desktop driver exposes.
+    CompressedRgbaS3tcDxt1 = 0x83F1,
+    CompressedRgbaS3tcDxt5 = 0x83F3,
+    CompressedSrgbAlphaS3tcDxt1 = 0x8C4D,
+    CompressedSrgbAlphaS3tcDxt5 = 0x8C4F,
+    CompressedRedRgtc1 = gl::COMPRESSED_RED_RGTC1,
+    CompressedRgRgtc2 = gl::COMPRESSED_RG_RGTC2,
+    CompressedRgbaBptcUnorm = gl::COMPRESSED_RGBA_BPTC_UNORM,
+    CompressedSrgbAlphaBptcUnorm = gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM,
+    CompressedRgbBptcUnsignedFloat = gl::COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT,
+    CompressedRgb8Etc2 = gl::COMPRESSED_RGB8_ETC2,
+    CompressedSrgb8Etc2 = gl::COMPRESSED_SRGB8_ETC2,
+    CompressedRgba8Etc2Eac = gl::COMPRESSED_RGBA8_ETC2_EAC,
+    CompressedSrgb8Alpha8Etc2Eac = gl::COMPRESSED_SRGB8_ALPHA8_ETC2_EAC,
 }
 
-#[derive(Debug, Clone, Copy)]
+impl ColorFormat {
+    ///Number of channels of an unsized source format.
+    pub fn channels(&self) -> usize {
+        match self {
+            ColorFormat::Red | ColorFormat::R8 | ColorFormat::R16 | ColorFormat::R16F | ColorFormat::R32F => 1,
+            ColorFormat::RG | ColorFormat::RG8 | ColorFormat::RG16 | ColorFormat::RG16F | ColorFormat::RG32F => 2,
+            ColorFormat::RGB | ColorFormat::RGB8 | ColorFormat::SRGB8 | ColorFormat::RGB16 | ColorFormat::RGB16F | ColorFormat::RGB32F => 3,
+            ColorFormat::RGBA | ColorFormat::RGBA8 | ColorFormat::SRGB8Alpha8 | ColorFormat::RGBA16 | ColorFormat::RGBA16F | ColorFormat::RGBA32F => 4,
+            ColorFormat::DepthStencil | ColorFormat::Depth24Stencil8 => 2,
+            _ => 1
+        }
+    }
+
+    pub fn is_depth(&self) -> bool {
+        match self {
+            ColorFormat::DepthComponent | ColorFormat::DepthComponent16 | ColorFormat::DepthComponent24
+            | ColorFormat::DepthComponent32F | ColorFormat::DepthStencil | ColorFormat::Depth24Stencil8 => true,
+            _ => false
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(u32)]
 pub enum DataType {
-    UnsignedByte = GLConsts::UnsignedByte as u32
+    UnsignedByte = GLConsts::UnsignedByte as u32,
+    UnsignedShort = gl::UNSIGNED_SHORT,
+    Float = gl::FLOAT,
+    UnsignedInt248 = gl::UNSIGNED_INT_24_8,
+}
+
+impl DataType {
+    ///Bytes per channel.
+    pub fn size(&self) -> usize {
+        match self {
+            DataType::UnsignedByte => 1,
+            DataType::UnsignedShort => 2,
+            DataType::Float | DataType::UnsignedInt248 => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(u32)]
+pub enum FramebufferTarget {
+    Framebuffer = gl::FRAMEBUFFER,
+    ReadFramebuffer = gl::READ_FRAMEBUFFER,
+    DrawFramebuffer = gl::DRAW_FRAMEBUFFER,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(u32)]
+pub enum FramebufferAttachment {
+    ColorAttachment0 = gl::COLOR_ATTACHMENT0,
+    DepthAttachment = gl::DEPTH_ATTACHMENT,
+    DepthStencilAttachment = gl::DEPTH_STENCIL_ATTACHMENT,
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(u32)]
+pub enum RenderbufferFormat {
+    Depth24Stencil8 = gl::DEPTH24_STENCIL8,
+    DepthComponent24 = gl::DEPTH_COMPONENT24,
 }
 
 #[derive(Debug, Clone, Copy, Sequence)]
@@ -341,10 +544,12 @@ impl UserData for Ordering {}
 impl UserData for Face {}
 
 pub mod program {
+    use std::ffi::CString;
     use std::ptr::null_mut;
     use crate::gfx::bindings::{create_whitespace_cstring_with_len, IV};
     use crate::gfx::program::Program;
     use crate::gfx::shader::Shader;
+    use crate::gfx::resource::{GlObjectKind, track_created, track_deleted};
 
     pub fn use_program(program: &Program) {
         unsafe {
@@ -359,12 +564,14 @@ pub mod program {
     }
 
     pub fn create_program() -> u32 {
-        unsafe {
+        let program = unsafe {
             gl::CreateProgram()
-        }
+        };
+        track_created(GlObjectKind::Program, program);
+        program
     }
 
-    pub fn attach_shader<S: Shader>(program: &Program, shader: &S) {
+    pub fn attach_shader<S: Shader + ?Sized>(program: &Program, shader: &S) {
         unsafe {
             gl::AttachShader(program.id(), shader.id());
         }
@@ -376,6 +583,30 @@ pub mod program {
         }
     }
 
+    pub fn detach_shader_raw(program: &Program, shader: u32) {
+        unsafe {
+            gl::DetachShader(program.id(), shader);
+        }
+    }
+
+    pub fn attached_shaders(program: &Program) -> Vec<u32> {
+        let count = get_program_iv(program, IV::AttachedShaders);
+        let mut shaders = vec![0u32; count.max(0) as usize];
+        let mut written = 0;
+        unsafe {
+            gl::GetAttachedShaders(program.id(), count, &mut written, shaders.as_mut_ptr());
+        }
+        shaders.truncate(written.max(0) as usize);
+        shaders
+    }
+
+    pub fn delete_program(program: &Program) {
+        track_deleted(GlObjectKind::Program, program.id());
+        unsafe {
+            gl::DeleteProgram(program.id());
+        }
+    }
+
     pub fn get_program_iv(program: &Program, iv: IV) -> i32 {
         unsafe {
             let mut success: i32 = 0;
@@ -388,6 +619,84 @@ pub mod program {
         get_program_iv(program, iv) == 1
     }
 
+    ///Name, array size and GL type of the active uniform at `index`.
+    pub fn active_uniform(program: &Program, index: u32) -> (String, i32, u32) {
+        let mut name = vec![0u8; 256];
+        let mut length = 0;
+        let mut size = 0;
+        let mut gl_type = 0;
+        unsafe {
+            gl::GetActiveUniform(program.id(), index, name.len() as i32, &mut length, &mut size, &mut gl_type, name.as_mut_ptr() as *mut gl::types::GLchar);
+        }
+        name.truncate(length.max(0) as usize);
+        (String::from_utf8_lossy(&name).to_string(), size, gl_type)
+    }
+
+    ///Name, array size and GL type of the active vertex attribute at `index`.
+    pub fn active_attrib(program: &Program, index: u32) -> (String, i32, u32) {
+        let mut name = vec![0u8; 256];
+        let mut length = 0;
+        let mut size = 0;
+        let mut gl_type = 0;
+        unsafe {
+            gl::GetActiveAttrib(program.id(), index, name.len() as i32, &mut length, &mut size, &mut gl_type, name.as_mut_ptr() as *mut gl::types::GLchar);
+        }
+        name.truncate(length.max(0) as usize);
+        (String::from_utf8_lossy(&name).to_string(), size, gl_type)
+    }
+
+    pub fn attrib_location(program: &Program, name: &str) -> i32 {
+        let name = CString::new(name).unwrap();
+        unsafe {
+            gl::GetAttribLocation(program.id(), name.as_ptr())
+        }
+    }
+
+    ///Index of the uniform block the active uniform at `index` belongs to, -1 for default block uniforms.
+    pub fn active_uniform_block_index(program: &Program, index: u32) -> i32 {
+        let mut block = -1;
+        unsafe {
+            gl::GetActiveUniformsiv(program.id(), 1, &index, gl::UNIFORM_BLOCK_INDEX, &mut block);
+        }
+        block
+    }
+
+    ///Offset in bytes of a uniform block member inside its block.
+    pub fn active_uniform_offset(program: &Program, index: u32) -> i32 {
+        let mut offset = -1;
+        unsafe {
+            gl::GetActiveUniformsiv(program.id(), 1, &index, gl::UNIFORM_OFFSET, &mut offset);
+        }
+        offset
+    }
+
+    pub fn active_uniform_block_name(program: &Program, index: u32) -> String {
+        let mut name = vec![0u8; 256];
+        let mut length = 0;
+        unsafe {
+            gl::GetActiveUniformBlockName(program.id(), index, name.len() as i32, &mut length, name.as_mut_ptr() as *mut gl::types::GLchar);
+        }
+        name.truncate(length.max(0) as usize);
+        String::from_utf8_lossy(&name).to_string()
+    }
+
+    ///Size in bytes the block's buffer must have.
+    pub fn active_uniform_block_size(program: &Program, index: u32) -> i32 {
+        let mut size = 0;
+        unsafe {
+            gl::GetActiveUniformBlockiv(program.id(), index, gl::UNIFORM_BLOCK_DATA_SIZE, &mut size);
+        }
+        size
+    }
+
+    pub fn active_uniform_block_binding(program: &Program, index: u32) -> i32 {
+        let mut binding = 0;
+        unsafe {
+            gl::GetActiveUniformBlockiv(program.id(), index, gl::UNIFORM_BLOCK_BINDING, &mut binding);
+        }
+        binding
+    }
+
     pub fn program_log_len(program: &Program) -> i32 {
         get_program_iv(program, IV::InfoLogLength)
     }
@@ -412,11 +721,14 @@ pub mod shader {
     use std::ffi::CString;
     use std::ptr::null;
     use super::*;
+    use crate::gfx::resource::{GlObjectKind, track_created, track_deleted};
 
     pub fn gl_create_shader(shader_type: ShaderType) -> u32 {
-        unsafe {
+        let shader = unsafe {
             CreateShader(shader_type as u32)
-        }
+        };
+        track_created(GlObjectKind::Shader, shader);
+        shader
     }
 
     pub fn shader_source<T: ToString>(source: T, shader: &dyn Shader) {
@@ -462,6 +774,7 @@ pub mod shader {
     }
 
     pub fn delete_shader<S: Shader>(shader: &S) {
+        track_deleted(GlObjectKind::Shader, shader.id());
         unsafe {
             gl::DeleteShader(shader.id());
         }
@@ -474,11 +787,13 @@ pub mod buffers {
     use crate::gfx::bindings::BufferType;
     use crate::gfx::objects::Buffer;
     use crate::gfx::objects::vertex_array_object::VertexArrayObject;
+    use crate::gfx::resource::{GlObjectKind, track_created, track_deleted};
 
     pub fn gen_buffers(count: i32) -> u32 {
         unsafe {
             let mut id: u32 = 0;
             gl::GenBuffers(count, &mut id);
+            track_created(GlObjectKind::Buffer, id);
             id
         }
     }
@@ -493,17 +808,64 @@ pub mod buffers {
         unsafe {
             gl::BufferData(buffer_type as u32, size, data, draw_type as u32);
         }
-        println!("glBufferData({:?}, {}, {:?}, {:?})", buffer_type, size, data, draw_type);
+    }
+
+    pub fn bind_buffer_raw(buffer_type: BufferType, buffer: u32) {
+        unsafe {
+            gl::BindBuffer(buffer_type as u32, buffer);
+        }
+    }
+
+    pub fn buffer_sub_data(buffer_type: BufferType, offset: isize, size: isize, data: *const c_void) {
+        unsafe {
+            gl::BufferSubData(buffer_type as u32, offset, size, data);
+        }
+    }
+
+    ///Maps `length` bytes of the bound buffer at `offset`. Returns null if mapping fails.
+    pub fn map_buffer_range(buffer_type: BufferType, offset: isize, length: isize, access: u32) -> *mut c_void {
+        unsafe {
+            gl::MapBufferRange(buffer_type as u32, offset, length, access)
+        }
+    }
+
+    ///Returns false if the contents became corrupt while mapped and must be uploaded again.
+    pub fn unmap_buffer(buffer_type: BufferType) -> bool {
+        unsafe {
+            gl::UnmapBuffer(buffer_type as u32) == gl::TRUE
+        }
+    }
+
+    pub fn delete_buffers(buffer: u32) {
+        track_deleted(GlObjectKind::Buffer, buffer);
+        unsafe {
+            gl::DeleteBuffers(1, &buffer);
+        }
+    }
+
+    ///Binds a buffer to an indexed binding point, e.g. of a uniform block.
+    pub fn bind_buffer_base(buffer_type: BufferType, index: u32, buffer: u32) {
+        unsafe {
+            gl::BindBufferBase(buffer_type as u32, index, buffer);
+        }
     }
 
     pub fn gen_vertex_arrays(size: i32) -> u32 {
         unsafe {
             let mut vao: u32 = 0;
             gl::GenVertexArrays(size, &mut vao);
+            track_created(GlObjectKind::VertexArray, vao);
             vao
         }
     }
 
+    pub fn delete_vertex_arrays(vao: u32) {
+        track_deleted(GlObjectKind::VertexArray, vao);
+        unsafe {
+            gl::DeleteVertexArrays(1, &vao);
+        }
+    }
+
     pub fn bind_vertex_array(vao: &VertexArrayObject) {
         unsafe {
             gl::BindVertexArray(vao.id());
@@ -539,12 +901,35 @@ pub mod graphics {
         }
     }
 
+    ///Draws `count` `u32` indices of the element buffer bound to the current vertex array.
+    pub fn draw_elements(mode: DrawMode, count: i32) {
+        unsafe {
+            gl::DrawElements(mode as u32, count, gl::UNSIGNED_INT, std::ptr::null());
+        }
+    }
+
     pub fn viewport(position: [i32; 2], size: [i32; 2]) {
         unsafe {
             gl::Viewport(position[0], position[1], size[0], size[1])
         }
     }
 
+    ///Vertices per patch for tessellation programs.
+    pub fn patch_vertices(count: i32) {
+        unsafe {
+            gl::PatchParameteri(gl::PATCH_VERTICES, count);
+        }
+    }
+
+    ///The current viewport as x, y, width, height.
+    pub fn get_viewport() -> [i32; 4] {
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+        viewport
+    }
+
     pub fn enable(constant: GLConsts) {
         unsafe {
             gl::Enable(constant as u32);
@@ -649,6 +1034,22 @@ pub mod uniforms {
         }
     }
 
+    ///Returns gl::INVALID_INDEX if the program has no active block named `name`.
+    pub fn uniform_block_index<T: ToString>(program: &Program, name: T) -> u32 {
+        unsafe {
+            let name = name.to_string();
+            let name = CString::new(name).unwrap();
+
+            gl::GetUniformBlockIndex(program.id(), name.as_ptr())
+        }
+    }
+
+    pub fn uniform_block_binding(program: &Program, block_index: u32, binding: u32) {
+        unsafe {
+            gl::UniformBlockBinding(program.id(), block_index, binding);
+        }
+    }
+
     pub fn uniform_1ui(location: i32, value: &u32) {
         unsafe {
             gl::Uniform1ui(location, *value);
@@ -715,15 +1116,18 @@ pub mod uniforms {
 }
 
 pub mod texturing {
+    use std::ffi::c_void;
     use crate::gfx::bindings::{ColorFormat, DataType, TextureParamName, TextureTarget};
     use crate::gfx::texture::{Texture};
     use crate::GLConsts;
+    use crate::gfx::resource::{GlObjectKind, track_created, track_deleted};
 
     pub fn gen_textures(count: i32) -> u32 {
         let mut texture = 0;
         unsafe {
             gl::GenTextures(count, &mut texture);
         }
+        track_created(GlObjectKind::Texture, texture);
         texture
     }
 
@@ -752,6 +1156,19 @@ pub mod texturing {
         }
     }
 
+    pub fn tex_image_2d_empty(texture_target: TextureTarget, level: i32, color_format: ColorFormat, width: i32, height: i32, source_color_format: ColorFormat, data_type: DataType) {
+        unsafe {
+            gl::TexImage2D(texture_target as u32, level, (color_format as u32) as i32, width, height, 0, source_color_format as u32, data_type as u32, std::ptr::null());
+        }
+    }
+
+    pub fn delete_texture(texture: &Texture) {
+        track_deleted(GlObjectKind::Texture, texture.id());
+        unsafe {
+            gl::DeleteTextures(1, &texture.id());
+        }
+    }
+
     pub fn tex_image_2d_u_bytes_raw_target(texture_target: u32, level: i32, color_format: ColorFormat, width: i32, height: i32, source_color_format: ColorFormat, data: &[u8]) {
         unsafe {
             gl::TexImage2D(texture_target, level, (color_format as u32) as i32, width, height, 0, source_color_format as u32, DataType::UnsignedByte as u32, data.as_ptr() as *const _);
@@ -759,6 +1176,60 @@ pub mod texturing {
     }
 
 
+    pub fn tex_image_2d(texture_target: u32, level: i32, color_format: ColorFormat, width: i32, height: i32, source_color_format: ColorFormat, data_type: DataType, data: *const c_void) {
+        unsafe {
+            gl::TexImage2D(texture_target, level, (color_format as u32) as i32, width, height, 0, source_color_format as u32, data_type as u32, data);
+        }
+    }
+
+    ///Uploads one mip level of block compressed data in `color_format`.
+    pub fn compressed_tex_image_2d(texture_target: TextureTarget, level: i32, color_format: ColorFormat, width: i32, height: i32, data: &[u8]) {
+        unsafe {
+            gl::CompressedTexImage2D(texture_target as u32, level, color_format as u32, width, height, 0, data.len() as i32, data.as_ptr() as *const _);
+        }
+    }
+
+    ///Allocates a 3D texture or 2D texture array, `depth` being the layer count for arrays.
+    pub fn tex_image_3d(texture_target: TextureTarget, level: i32, color_format: ColorFormat, width: i32, height: i32, depth: i32, source_color_format: ColorFormat, data_type: DataType, data: *const c_void) {
+        unsafe {
+            gl::TexImage3D(texture_target as u32, level, (color_format as u32) as i32, width, height, depth, 0, source_color_format as u32, data_type as u32, data);
+        }
+    }
+
+    pub fn tex_sub_image_3d(texture_target: TextureTarget, level: i32, offset: [i32; 3], size: [i32; 3], source_color_format: ColorFormat, data_type: DataType, data: &[u8]) {
+        unsafe {
+            gl::TexSubImage3D(texture_target as u32, level, offset[0], offset[1], offset[2], size[0], size[1], size[2], source_color_format as u32, data_type as u32, data.as_ptr() as *const _);
+        }
+    }
+
+    ///Row alignment of client pixel data, 1 for tightly packed rows.
+    pub fn unpack_alignment(alignment: i32) {
+        unsafe {
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, alignment);
+        }
+    }
+
+    pub fn tex_parameteri_raw(target: TextureTarget, param_name: TextureParamName, param: i32) {
+        unsafe {
+            gl::TexParameteri(target as u32, param_name as u32, param);
+        }
+    }
+
+    pub fn tex_parameterf(target: TextureTarget, param_name: TextureParamName, param: f32) {
+        unsafe {
+            gl::TexParameterf(target as u32, param_name as u32, param);
+        }
+    }
+
+    ///Highest anisotropy the driver supports, 1.0 when anisotropic filtering is unavailable.
+    pub fn max_anisotropy() -> f32 {
+        let mut max = 1.0;
+        unsafe {
+            gl::GetFloatv(gl::MAX_TEXTURE_MAX_ANISOTROPY, &mut max);
+        }
+        max
+    }
+
     pub fn generate_mipmap(texture_target: TextureTarget) {
         unsafe {
             gl::GenerateMipmap(texture_target as u32);
@@ -772,6 +1243,135 @@ pub mod texturing {
     }
 }
 
+pub mod framebuffers {
+    use crate::gfx::bindings::{FramebufferAttachment, FramebufferTarget, RenderbufferFormat, TextureTarget};
+    use crate::gfx::resource::{GlObjectKind, track_created, track_deleted};
+
+    pub fn gen_framebuffers(count: i32) -> u32 {
+        let mut fbo = 0;
+        unsafe {
+            gl::GenFramebuffers(count, &mut fbo);
+        }
+        track_created(GlObjectKind::Framebuffer, fbo);
+        fbo
+    }
+
+    pub fn bind_framebuffer(target: FramebufferTarget, fbo: u32) {
+        unsafe {
+            gl::BindFramebuffer(target as u32, fbo);
+        }
+    }
+
+    ///Binds the window's default framebuffer.
+    pub fn unbind_framebuffer(target: FramebufferTarget) {
+        bind_framebuffer(target, 0);
+    }
+
+    pub fn framebuffer_texture_2d(target: FramebufferTarget, attachment: FramebufferAttachment, texture_target: TextureTarget, texture: u32, level: i32) {
+        unsafe {
+            gl::FramebufferTexture2D(target as u32, attachment as u32, texture_target as u32, texture, level);
+        }
+    }
+
+    pub fn check_framebuffer_status(target: FramebufferTarget) -> bool {
+        unsafe {
+            gl::CheckFramebufferStatus(target as u32) == gl::FRAMEBUFFER_COMPLETE
+        }
+    }
+
+    ///For depth only framebuffers, which have no color attachment to draw to or read from.
+    pub fn disable_color_buffers() {
+        unsafe {
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+        }
+    }
+
+    pub fn delete_framebuffer(fbo: u32) {
+        track_deleted(GlObjectKind::Framebuffer, fbo);
+        unsafe {
+            gl::DeleteFramebuffers(1, &fbo);
+        }
+    }
+
+    pub fn gen_renderbuffers(count: i32) -> u32 {
+        let mut rbo = 0;
+        unsafe {
+            gl::GenRenderbuffers(count, &mut rbo);
+        }
+        track_created(GlObjectKind::Renderbuffer, rbo);
+        rbo
+    }
+
+    pub fn bind_renderbuffer(rbo: u32) {
+        unsafe {
+            gl::BindRenderbuffer(gl::RENDERBUFFER, rbo);
+        }
+    }
+
+    pub fn renderbuffer_storage(format: RenderbufferFormat, width: i32, height: i32) {
+        unsafe {
+            gl::RenderbufferStorage(gl::RENDERBUFFER, format as u32, width, height);
+        }
+    }
+
+    pub fn framebuffer_renderbuffer(target: FramebufferTarget, attachment: FramebufferAttachment, rbo: u32) {
+        unsafe {
+            gl::FramebufferRenderbuffer(target as u32, attachment as u32, gl::RENDERBUFFER, rbo);
+        }
+    }
+
+    pub fn delete_renderbuffer(rbo: u32) {
+        track_deleted(GlObjectKind::Renderbuffer, rbo);
+        unsafe {
+            gl::DeleteRenderbuffers(1, &rbo);
+        }
+    }
+}
+
+pub mod compute {
+    use crate::gfx::bindings::{ColorFormat, ImageAccess};
+    use crate::gfx::texture::Texture;
+
+    ///Runs the enabled compute program over `groups` work groups.
+    pub fn dispatch_compute(groups: [u32; 3]) {
+        unsafe {
+            gl::DispatchCompute(groups[0], groups[1], groups[2]);
+        }
+    }
+
+    ///Runs the enabled compute program with the group counts read from the bound `DispatchIndirectBuffer` at `offset`.
+    pub fn dispatch_compute_indirect(offset: isize) {
+        unsafe {
+            gl::DispatchComputeIndirect(offset);
+        }
+    }
+
+    ///Orders writes of earlier shaders before the reads selected by `barriers`, see `MemoryBarrier`.
+    pub fn memory_barrier(barriers: u32) {
+        unsafe {
+            gl::MemoryBarrier(barriers);
+        }
+    }
+
+    ///Binds a texture level to an image unit for `image2D` access in shaders.
+    pub fn bind_image_texture(unit: u32, texture: &Texture, level: i32, access: ImageAccess, format: ColorFormat) {
+        unsafe {
+            gl::BindImageTexture(unit, texture.id(), level, gl::FALSE, 0, access as u32, format as u32);
+        }
+    }
+
+    pub fn max_work_group_count() -> [i32; 3] {
+        let mut count = [0; 3];
+        unsafe {
+            for i in 0..3 {
+                gl::GetIntegeri_v(gl::MAX_COMPUTE_WORK_GROUP_COUNT, i as u32, &mut count[i]);
+            }
+        }
+        count
+    }
+}
+
 fn create_whitespace_cstring_with_len(len: usize) -> CString {
     // allocate buffer of correct size
     let mut buffer: Vec<u8> = Vec::with_capacity(len + 1);
@@ -1,27 +1,28 @@
-use std::collections::HashMap;
-use maplit::hashmap;
-use crate::{active_texture, BufferType, Camera, Constructor, DrawType, FragmentShader, LocalAttribPointer, Program, Texture, VertexArrayObject, VertexArrayObjectType, VertexBufferObject, VertexShader};
+use crate::{active_texture, BufferType, Camera, Constructor, draw_arrays, draw_elements, DrawMode, DrawType, LocalAttribPointer, Program, Texture, uniform_1i, VertexArrayObject, VertexArrayObjectType, VertexBufferObject};
 use crate::BufferDataType::Float;
 use crate::gfx::render::{RenderObject, RenderProgramObject};
+use crate::gfx::resource::Shared;
+use crate::gfx::shader::preprocessor::Preprocessor;
 use crate::math::linear_algebra::types::Mat4;
 
-const BLEND_VERTEX: &'static str = include_str!("../../res/shaders/blend_map/vertex.glsl");
-const BLEND_FRAGMENT: &'static str = include_str!("../../res/shaders/blend_map/fragment.glsl");
+const BLEND_VERTEX: &'static str = "blend_map/vertex.glsl";
+const BLEND_FRAGMENT: &'static str = "blend_map/fragment.glsl";
+///Texture units in order: the background, the textures selected by the red, green and blue channels, and the blend map.
+const SAMPLERS: [&'static str; 5] = ["backgroundTexture", "rTexture", "gTexture", "bTexture", "blendMap"];
 
 #[derive(Debug)]
 pub struct BlendMap {
     vao: VertexArrayObject,
-    locations: HashMap<String, i32>,
-    program: Program,
+    program: Shared<Program>,
 }
 
 impl BlendMap {
     pub fn new(
-        background: Texture,
-        r: Texture,
-        g: Texture,
-        b: Texture,
-        blend: Texture,
+        background: Shared<Texture>,
+        r: Shared<Texture>,
+        g: Shared<Texture>,
+        b: Shared<Texture>,
+        blend: Shared<Texture>,
         buffer: &[f32],
         triangles: i32,
         draw_type: DrawType,
@@ -33,25 +34,49 @@ impl BlendMap {
                 LocalAttribPointer::new(3, Float, false),
                 LocalAttribPointer::new(2, Float, false),
             ])
-            .with_texture(Some("backgroundTexture"), background)
-            .with_texture(Some("rTexture"), r)
-            .with_texture(Some("gTexture"), g)
-            .with_texture(Some("bTexture"), b)
-            .with_texture(Some("blendMap"), blend);
-        let program = Program::new(FragmentShader::new(BLEND_FRAGMENT), VertexShader::new(BLEND_VERTEX)).unwrap();
-        let locations = hashmap! {
-            "backgroundTexture".to_string() => program.uniform("backgroundTexture"),
-            "rTexture".to_string() => program.uniform("rTexture"),
-            "gTexture".to_string() => program.uniform("gTexture"),
-            "bTexture".to_string() => program.uniform("bTexture"),
-            "blendMap".to_string() => program.uniform("blendMap")
-        };
+            .build();
+        Self::with_vao(vao, background, r, g, b, blend)
+    }
+
+    ///Only the textures and program, for geometry drawn through `draw_with` such as terrain chunks.
+    pub fn from_textures(background: Shared<Texture>, r: Shared<Texture>, g: Shared<Texture>, b: Shared<Texture>, blend: Shared<Texture>) -> BlendMap {
+        Self::with_vao(VertexArrayObject::new(None), background, r, g, b, blend)
+    }
+
+    fn with_vao(vao: VertexArrayObject, background: Shared<Texture>, r: Shared<Texture>, g: Shared<Texture>, b: Shared<Texture>, blend: Shared<Texture>) -> BlendMap {
+        let vao = vao
+            .with_texture(Some(SAMPLERS[0]), background)
+            .with_texture(Some(SAMPLERS[1]), r)
+            .with_texture(Some(SAMPLERS[2]), g)
+            .with_texture(Some(SAMPLERS[3]), b)
+            .with_texture(Some(SAMPLERS[4]), blend);
+        let program = Preprocessor::new().program(BLEND_VERTEX, BLEND_FRAGMENT).unwrap();
         Self {
             vao,
-            locations,
             program,
         }
     }
+
+    ///Draws vertex arrays laid out as position, normal, uv with this map's textures.
+    pub fn draw_with<'a, I: IntoIterator<Item=&'a VertexArrayObject>>(&self, camera: &Camera, model: &Mat4, vaos: I) {
+        self.program.enable();
+        camera.prepare_render(&self.program);
+        self.program.set_uniform_mat4("model", model);
+        for (i, texture) in self.vao.textures().iter().enumerate() {
+            active_texture(i as u32);
+            self.bind_texture2d(texture);
+            uniform_1i(self.location(SAMPLERS[i]), &(i as i32));
+        }
+        for vao in vaos {
+            vao.bind();
+            match vao.vao_type() {
+                Some(VertexArrayObjectType::Arrays(count)) => draw_arrays(DrawMode::Triangles, 0, *count),
+                Some(VertexArrayObjectType::Elements(count)) => draw_elements(DrawMode::Triangles, *count),
+                _ => {}
+            }
+        }
+        self.program.disable();
+    }
 }
 
 impl RenderObject for BlendMap {
@@ -60,17 +85,7 @@ impl RenderObject for BlendMap {
     }
 
     fn draw(&self, camera: &Camera, model: &Mat4) {
-        self.program().enable();
-        self.vao.bind();
-        camera.prepare_render(&self.program);
-        self.program.set_uniform_mat4("model", model);
-        for i in 0..self.vao.textures().len() {
-            active_texture(i as u32);
-            self.bind_texture2d(&self.vao.textures()[i]);
-        }
-
-
-        self.program.disable();
+        self.draw_with(camera, model, [&self.vao]);
     }
 }
 
@@ -78,8 +93,4 @@ impl RenderProgramObject for BlendMap {
     fn program(&self) -> &Program {
         &self.program
     }
-
-    fn locations(&self) -> &HashMap<String, i32> {
-        &self.locations
-    }
 }
\ No newline at end of file
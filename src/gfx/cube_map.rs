@@ -1,7 +1,8 @@
 use crate::Texture;
+use crate::gfx::resource::Owned;
 
 pub struct CubeMap {
-    texture: Texture
+    texture: Owned<Texture>
 }
 
 impl CubeMap {
@@ -0,0 +1,244 @@
+use std::mem::size_of;
+use crate::ecs::lights::collect_lights;
+use crate::gfx::bindings::BufferType::UniformBuffer;
+use crate::gfx::bindings::DrawType::DynamicDraw;
+use crate::gfx::bindings::buffers::{bind_buffer_base, bind_buffer_raw, buffer_data_array, buffer_sub_data, delete_buffers, gen_buffers};
+use crate::gfx::bindings::uniforms::{uniform_block_binding, uniform_block_index};
+use crate::gfx::program::Program;
+use crate::gfx::shader::preprocessor::Defines;
+use crate::math::linear_algebra::types::{Vec3, Vec4};
+use crate::math::linear_algebra::vector_ext::VectorExt;
+
+pub const LIGHTS_BLOCK: &'static str = "Lights";
+///Uniform buffer binding point the light block is bound to.
+pub const LIGHTS_BINDING: u32 = 0;
+
+pub const DEFAULT_MAX_LIGHTS: usize = 32;
+pub const DEFAULT_MAX_OBJECT_LIGHTS: usize = 8;
+
+static mut LIGHTING: Option<Lighting> = None;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum LightType {
+    Directional = 0,
+    Point = 1,
+    Spot = 2,
+}
+
+///A light as laid out in the std140 `Lights` block. See `res/shaders/lighting/lights.glsl`.
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct LightData {
+    ///xyz: world position, w: range
+    pub position_range: Vec4,
+    ///xyz: direction, w: type
+    pub direction_type: Vec4,
+    ///rgb: color, a: intensity
+    pub color_intensity: Vec4,
+    ///x: cos(inner angle), y: cos(outer angle), z: first shadow map tile + 1 (0 without shadows), w: casts shadows
+    pub cone: Vec4,
+}
+
+impl LightData {
+    pub fn light_type(&self) -> LightType {
+        match self.direction_type[3] as u8 {
+            0 => LightType::Directional,
+            1 => LightType::Point,
+            _ => LightType::Spot
+        }
+    }
+
+    pub fn position(&self) -> Vec3 {
+        [self.position_range[0], self.position_range[1], self.position_range[2]]
+    }
+
+    pub fn range(&self) -> f32 {
+        self.position_range[3]
+    }
+
+    pub fn direction(&self) -> Vec3 {
+        [self.direction_type[0], self.direction_type[1], self.direction_type[2]]
+    }
+
+    pub fn casts_shadows(&self) -> bool {
+        self.cone[3] > 0.5
+    }
+
+    ///Whether the light can reach a sphere at `position` with `radius`.
+    pub fn affects(&self, position: Vec3, radius: f32) -> bool {
+        match self.light_type() {
+            LightType::Directional => true,
+            _ => self.position().distance(&position) - radius <= self.range()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LightSettings {
+    ///Size of the light array in the uniform buffer. Extra lights are dropped, directional lights first kept.
+    pub max_lights: usize,
+    ///How many of the closest lights a single object is shaded with.
+    pub max_object_lights: usize,
+}
+
+impl Default for LightSettings {
+    fn default() -> Self {
+        Self {
+            max_lights: DEFAULT_MAX_LIGHTS,
+            max_object_lights: DEFAULT_MAX_OBJECT_LIGHTS,
+        }
+    }
+}
+
+///Collects the light components each frame and uploads them into a uniform buffer
+///shared by every lit program.
+#[derive(Debug)]
+pub struct Lighting {
+    settings: LightSettings,
+    ubo: u32,
+    lights: Vec<LightData>,
+}
+
+impl Drop for Lighting {
+    fn drop(&mut self) {
+        delete_buffers(self.ubo);
+    }
+}
+
+impl Lighting {
+    pub unsafe fn init(settings: LightSettings) {
+        let ubo = gen_buffers(1);
+        bind_buffer_raw(UniformBuffer, ubo);
+        buffer_data_array(UniformBuffer, Self::buffer_size(&settings), std::ptr::null(), DynamicDraw);
+        bind_buffer_raw(UniformBuffer, 0);
+        bind_buffer_base(UniformBuffer, LIGHTS_BINDING, ubo);
+        LIGHTING = Some(Lighting {
+            settings,
+            ubo,
+            lights: vec![],
+        });
+    }
+
+    ///Deletes the light buffer. Called by the app on shutdown.
+    pub unsafe fn shutdown() {
+        LIGHTING = None;
+    }
+
+    pub fn is_initialized() -> bool {
+        unsafe { LIGHTING.is_some() }
+    }
+
+    pub fn settings() -> LightSettings {
+        unsafe {
+            match &LIGHTING {
+                None => LightSettings::default(),
+                Some(lighting) => lighting.settings
+            }
+        }
+    }
+
+    fn buffer_size(settings: &LightSettings) -> isize {
+        (size_of::<[i32; 4]>() + size_of::<LightData>() * settings.max_lights) as isize
+    }
+
+    ///The array sizes `lighting/lights.glsl` needs, for the shader preprocessor.
+    pub fn defines() -> Defines {
+        let settings = Self::settings();
+        Defines::new()
+            .with("MAX_LIGHTS", settings.max_lights)
+            .with("MAX_OBJECT_LIGHTS", settings.max_object_lights)
+    }
+
+    ///Gathers every enabled light component and uploads them. Called once per frame by the application.
+    pub fn update() {
+        unsafe {
+            if let Some(lighting) = &mut LIGHTING {
+                let mut lights = collect_lights();
+                //Directional lights always affect everything, keep them if the buffer overflows.
+                lights.sort_by_key(|light| light.light_type() != LightType::Directional);
+                lights.truncate(lighting.settings.max_lights);
+                lighting.upload(&lights);
+                lighting.lights = lights;
+            }
+        }
+    }
+
+    fn upload(&self, lights: &Vec<LightData>) {
+        let count = [lights.len() as i32, 0, 0, 0];
+        bind_buffer_raw(UniformBuffer, self.ubo);
+        buffer_sub_data(UniformBuffer, 0, size_of::<[i32; 4]>() as isize, count.as_ptr() as *const _);
+        if !lights.is_empty() {
+            buffer_sub_data(UniformBuffer, size_of::<[i32; 4]>() as isize, (size_of::<LightData>() * lights.len()) as isize, lights.as_ptr() as *const _);
+        }
+        bind_buffer_raw(UniformBuffer, 0);
+    }
+
+    ///Connects the program's `Lights` block to the shared buffer. Returns false if the program has no such block.
+    pub fn bind(program: &Program) -> bool {
+        let index = uniform_block_index(program, LIGHTS_BLOCK);
+        if index == gl::INVALID_INDEX {
+            return false;
+        }
+        uniform_block_binding(program, index, LIGHTS_BINDING);
+        true
+    }
+
+    ///Indices of the lights affecting a sphere at `position` with `radius`, closest first.
+    pub fn lights_for(position: Vec3, radius: f32) -> Vec<i32> {
+        unsafe {
+            match &LIGHTING {
+                None => vec![],
+                Some(lighting) => {
+                    let mut affecting = lighting.lights.iter()
+                        .enumerate()
+                        .filter(|(_, light)| light.affects(position, radius))
+                        .map(|(index, light)| {
+                            let d = match light.light_type() {
+                                LightType::Directional => 0.0,
+                                _ => light.position().distance(&position)
+                            };
+                            (index as i32, d)
+                        })
+                        .collect::<Vec<(i32, f32)>>();
+                    affecting.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+                    affecting.truncate(lighting.settings.max_object_lights);
+                    affecting.into_iter().map(|(index, _)| index).collect()
+                }
+            }
+        }
+    }
+
+    ///Uploads the per-object light list for an object bounded by a sphere. The program must be enabled.
+    pub fn bind_object(program: &Program, position: Vec3, radius: f32) {
+        let indices = Self::lights_for(position, radius);
+        for (i, index) in indices.iter().enumerate() {
+            program.set_uniform_int(format!("objectLights[{}]", i), index);
+        }
+        program.set_uniform_int("objectLightCount", &(indices.len() as i32));
+    }
+
+    ///Records which shadow map tiles the lights at the given indices use and uploads the lights again.
+    ///Tiles are reset by the next `update`.
+    pub fn set_shadow_tiles(tiles: &[(usize, usize)]) {
+        unsafe {
+            if let Some(lighting) = &mut LIGHTING {
+                for (index, tile) in tiles {
+                    if let Some(light) = lighting.lights.get_mut(*index) {
+                        light.cone[2] = (*tile + 1) as f32;
+                    }
+                }
+                lighting.upload(&lighting.lights);
+            }
+        }
+    }
+
+    pub fn lights() -> Vec<LightData> {
+        unsafe {
+            match &LIGHTING {
+                None => vec![],
+                Some(lighting) => lighting.lights.clone()
+            }
+        }
+    }
+}
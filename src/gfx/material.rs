@@ -0,0 +1,260 @@
+use std::cell::OnceCell;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use enum_iterator::{all, Sequence};
+use crate::gfx::bindings::texturing::active_texture;
+use crate::gfx::program::Program;
+use crate::gfx::resource::{GlObject, Shared};
+use crate::gfx::texture::Texture;
+use crate::math::linear_algebra::types::Vec3;
+
+///The texture slots a wavefront material can reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Sequence)]
+pub enum TextureMapType {
+    ///map_Ka
+    Ambient,
+    ///map_Kd
+    Diffuse,
+    ///map_Ks
+    Specular,
+    ///map_Ns
+    SpecularExponent,
+    ///map_d
+    Dissolve,
+    ///map_Bump, bump
+    Bump,
+    ///norm
+    Normal,
+    ///disp
+    Displacement,
+    ///map_Ke
+    Emissive,
+}
+
+impl TextureMapType {
+    ///The sampler uniform this map is bound to, inside the `material` struct.
+    pub fn uniform(&self) -> &'static str {
+        match self {
+            TextureMapType::Ambient => "ambientMap",
+            TextureMapType::Diffuse => "diffuseMap",
+            TextureMapType::Specular => "specularMap",
+            TextureMapType::SpecularExponent => "shininessMap",
+            TextureMapType::Dissolve => "opacityMap",
+            TextureMapType::Bump => "bumpMap",
+            TextureMapType::Normal => "normalMap",
+            TextureMapType::Displacement => "displacementMap",
+            TextureMapType::Emissive => "emissiveMap",
+        }
+    }
+
+    ///The mtl statement of this map.
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            TextureMapType::Ambient => "map_Ka",
+            TextureMapType::Diffuse => "map_Kd",
+            TextureMapType::Specular => "map_Ks",
+            TextureMapType::SpecularExponent => "map_Ns",
+            TextureMapType::Dissolve => "map_d",
+            TextureMapType::Bump => "map_Bump",
+            TextureMapType::Normal => "norm",
+            TextureMapType::Displacement => "disp",
+            TextureMapType::Emissive => "map_Ke",
+        }
+    }
+
+    ///The boolean uniform telling the shader whether the map is bound.
+    pub fn flag_uniform(&self) -> String {
+        let uniform = self.uniform();
+        format!("has{}{}", uniform[..1].to_uppercase(), &uniform[1..])
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TextureMap {
+    map_type: TextureMapType,
+    ///Path as written in the mtl file.
+    source: String,
+    ///`source` resolved against the directory of the mtl file.
+    path: PathBuf,
+    ///-s u v w
+    scale: Vec3,
+    ///-o u v w
+    offset: Vec3,
+    ///-bm, only meaningful for bump maps.
+    bump_multiplier: f32,
+    ///Set by the first `load`, so every vertex array built from the material shares one texture.
+    texture: OnceCell<Shared<Texture>>,
+}
+
+impl TextureMap {
+    pub fn new<T: ToString>(map_type: TextureMapType, source: T, path: PathBuf) -> TextureMap {
+        Self {
+            map_type,
+            source: source.to_string(),
+            path,
+            scale: [1.0; 3],
+            offset: [0.0; 3],
+            bump_multiplier: 1.0,
+            texture: OnceCell::new(),
+        }
+    }
+
+    ///Loads the texture from disk if it has not been loaded yet. Requires a current GL context.
+    pub fn load(&self) -> Result<Shared<Texture>, Box<dyn Error>> {
+        if let Some(texture) = self.texture.get() {
+            return Ok(texture.clone());
+        }
+        let texture = Texture::new_from_file(&self.path)?.shared();
+        Ok(self.texture.get_or_init(|| texture).clone())
+    }
+
+    pub fn map_type(&self) -> TextureMapType {
+        self.map_type
+    }
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+    pub fn source_mut(&mut self) -> &mut String {
+        &mut self.source
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub fn scale(&self) -> Vec3 {
+        self.scale
+    }
+    pub fn scale_mut(&mut self) -> &mut Vec3 {
+        &mut self.scale
+    }
+    pub fn offset(&self) -> Vec3 {
+        self.offset
+    }
+    pub fn offset_mut(&mut self) -> &mut Vec3 {
+        &mut self.offset
+    }
+    pub fn bump_multiplier(&self) -> f32 {
+        self.bump_multiplier
+    }
+    pub fn bump_multiplier_mut(&mut self) -> &mut f32 {
+        &mut self.bump_multiplier
+    }
+    pub fn texture(&self) -> Option<&Shared<Texture>> {
+        self.texture.get()
+    }
+    ///Sets an already uploaded texture, e.g. one decoded by the asset loader, so `load` won't read the file again.
+    pub fn set_texture(&mut self, texture: Shared<Texture>) {
+        self.texture = OnceCell::from(texture);
+    }
+}
+
+///A wavefront material. See: <a href="https://paulbourke.net/dataformats/mtl/">MTL</a>
+///
+///Bound to a program as a `material` uniform struct:
+///`ambient`, `diffuse`, `specular`, `emissive` (vec3), `shininess`, `opacity`, `opticalDensity` (float),
+///`illum` (int), and for every texture map a sampler (e.g. `diffuseMap`) plus a flag (e.g. `hasDiffuseMap`).
+#[derive(Debug, Clone)]
+pub struct Material {
+    pub name: String,
+    ///Ka
+    pub ambient: Vec3,
+    ///Kd
+    pub diffuse: Vec3,
+    ///Ks
+    pub specular: Vec3,
+    ///Ke
+    pub emissive: Vec3,
+    ///Ns
+    pub shininess: f32,
+    ///d, or 1 - Tr
+    pub opacity: f32,
+    ///Ni
+    pub optical_density: f32,
+    ///illum
+    pub illumination: u8,
+    maps: Vec<TextureMap>,
+}
+
+impl Material {
+    pub fn new<T: ToString>(name: T) -> Material {
+        Self {
+            name: name.to_string(),
+            ambient: [1.0; 3],
+            diffuse: [0.8; 3],
+            specular: [0.0; 3],
+            emissive: [0.0; 3],
+            shininess: 0.0,
+            opacity: 1.0,
+            optical_density: 1.0,
+            illumination: 2,
+            maps: vec![],
+        }
+    }
+
+    ///Sets a map, replacing any existing map of the same type.
+    pub fn put_map(&mut self, map: TextureMap) {
+        self.maps.retain(|m| m.map_type != map.map_type);
+        self.maps.push(map);
+    }
+
+    pub fn map(&self, map_type: TextureMapType) -> Option<&TextureMap> {
+        self.maps.iter().find(|m| m.map_type == map_type)
+    }
+
+    pub fn map_mut(&mut self, map_type: TextureMapType) -> Option<&mut TextureMap> {
+        self.maps.iter_mut().find(|m| m.map_type == map_type)
+    }
+
+    pub fn maps(&self) -> &Vec<TextureMap> {
+        &self.maps
+    }
+
+    pub fn maps_mut(&mut self) -> &mut Vec<TextureMap> {
+        &mut self.maps
+    }
+
+    ///Loads every referenced texture. Maps that fail to load are reported and skipped.
+    pub fn load_textures(&self) -> Vec<(TextureMapType, Shared<Texture>)> {
+        let mut textures = vec![];
+        for map in &self.maps {
+            match map.load() {
+                Ok(texture) => textures.push((map.map_type, texture)),
+                Err(e) => eprintln!("Unable to load {:?} map '{}' of material '{}': {}", map.map_type, map.source, self.name, e)
+            }
+        }
+        textures
+    }
+
+    ///Uploads the material to `program`. Textures are bound starting at texture unit `first_unit`.
+    ///Returns the next free texture unit.
+    pub fn bind(&self, program: &Program, first_unit: u32) -> u32 {
+        program.set_uniform_vec3("material.ambient", &self.ambient);
+        program.set_uniform_vec3("material.diffuse", &self.diffuse);
+        program.set_uniform_vec3("material.specular", &self.specular);
+        program.set_uniform_vec3("material.emissive", &self.emissive);
+        program.set_uniform_float("material.shininess", &self.shininess);
+        program.set_uniform_float("material.opacity", &self.opacity);
+        program.set_uniform_float("material.opticalDensity", &self.optical_density);
+        program.set_uniform_int("material.illum", &(self.illumination as i32));
+
+        let mut unit = first_unit;
+        for map_type in all::<TextureMapType>() {
+            let texture = self.map(map_type).and_then(|map| map.texture.get());
+            let flag = format!("material.{}", map_type.flag_uniform());
+            program.set_uniform_bool(flag, &texture.is_some());
+            if let Some(texture) = texture {
+                active_texture(unit);
+                texture.bind();
+                program.set_uniform_int(format!("material.{}", map_type.uniform()), &(unit as i32));
+                unit += 1;
+            }
+        }
+        active_texture(0);
+        unit
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self::new("Default")
+    }
+}
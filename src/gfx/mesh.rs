@@ -0,0 +1,551 @@
+use std::collections::HashMap;
+use vecmath::{col_mat3_transform, col_mat4_transform, mat3_det, mat3_inv, mat3_transposed, vec3_cross, vec3_sub};
+use crate::{BufferDataType, BufferType, Constructor, DrawType, LocalAttribPointer, VertexArrayObject, VertexArrayObjectType, VertexBufferObject};
+use crate::gfx::objects::gpu_buffer::GpuBuffer;
+use crate::loader::models::gltf::GltfPrimitive;
+use crate::loader::models::obj::{DEFAULT_MATERIAL, DEFAULT_NAME, Object, Vertex};
+use crate::math::bounds::{Aabb, BoundingSphere};
+use crate::math::linear_algebra::mat3;
+use crate::math::linear_algebra::types::{Mat4, Vec2, Vec3, Vec4};
+use crate::math::linear_algebra::vector_ext::VectorExt;
+use crate::math::tangent::triangle_normal;
+
+///Values given to the vertices of a stream that only some merged meshes have.
+const DEFAULT_NORMAL: Vec3 = [0.0, 1.0, 0.0];
+const DEFAULT_UV: Vec2 = [0.0, 0.0];
+const DEFAULT_TANGENT: Vec4 = [1.0, 0.0, 0.0, 1.0];
+const DEFAULT_COLOR: Vec4 = [1.0; 4];
+
+///The vertex streams of a `Mesh`, in the order `build_vao` lays them out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VertexAttribute {
+    Position,
+    Normal,
+    Uv,
+    Tangent,
+    Color,
+}
+
+impl VertexAttribute {
+    ///Number of floats per vertex.
+    pub fn size(&self) -> u32 {
+        match self {
+            VertexAttribute::Position | VertexAttribute::Normal => 3,
+            VertexAttribute::Uv => 2,
+            VertexAttribute::Tangent | VertexAttribute::Color => 4,
+        }
+    }
+}
+
+///A range of a mesh's triangles sharing a material, e.g. a group of an obj object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Submesh {
+    pub name: String,
+    pub material: String,
+    ///First index and number of indices, both multiples of 3.
+    pub start: usize,
+    pub count: usize,
+    ///Whether generated normals are shared between adjacent triangles, see `Group::smooth`.
+    pub smooth: bool,
+}
+
+impl Submesh {
+    pub fn new<T: ToString>(name: T, material: T, start: usize, count: usize, smooth: bool) -> Submesh {
+        Self { name: name.to_string(), material: material.to_string(), start, count, smooth }
+    }
+}
+
+///Indexed triangles with typed vertex streams, independent of the file format they came from.
+///Every stream other than positions is either empty or holds one value per position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mesh {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+    tangents: Vec<Vec4>,
+    colors: Vec<Vec4>,
+    indices: Vec<u32>,
+    submeshes: Vec<Submesh>,
+}
+
+impl Mesh {
+    ///A mesh with a single smooth submesh covering every index.
+    pub fn new(positions: Vec<Vec3>, indices: Vec<u32>) -> Mesh {
+        let submeshes = vec![Submesh::new(DEFAULT_NAME, DEFAULT_MATERIAL, 0, indices.len(), true)];
+        Self { positions, normals: vec![], uvs: vec![], tangents: vec![], colors: vec![], indices, submeshes }
+    }
+
+    pub fn with_normals(mut self, normals: Vec<Vec3>) -> Mesh {
+        self.normals = normals;
+        self
+    }
+
+    pub fn with_uvs(mut self, uvs: Vec<Vec2>) -> Mesh {
+        self.uvs = uvs;
+        self
+    }
+
+    pub fn with_tangents(mut self, tangents: Vec<Vec4>) -> Mesh {
+        self.tangents = tangents;
+        self
+    }
+
+    pub fn with_colors(mut self, colors: Vec<Vec4>) -> Mesh {
+        self.colors = colors;
+        self
+    }
+
+    ///Replaces the default submesh. The submeshes should cover every index.
+    pub fn with_submeshes(mut self, submeshes: Vec<Submesh>) -> Mesh {
+        self.submeshes = submeshes;
+        self
+    }
+
+    ///One submesh per group. Corners sharing a position, uv and normal become one vertex.
+    pub fn from_object(object: &Object) -> Mesh {
+        let mut mesh = Self::new(vec![], vec![]);
+        mesh.submeshes.clear();
+        let mut vertices: HashMap<Vertex, u32> = HashMap::new();
+        for group in object.groups() {
+            let start = mesh.indices.len();
+            let has_uvs = group.has_uvs();
+            let has_normals = group.has_normals();
+            for face in group.faces() {
+                for vertex in face {
+                    let index = *vertices.entry(*vertex).or_insert_with(|| {
+                        mesh.positions.push(object.vertices()[vertex.0]);
+                        if has_uvs || !mesh.uvs.is_empty() {
+                            mesh.uvs.resize(mesh.positions.len() - 1, DEFAULT_UV);
+                            mesh.uvs.push(vertex.1.map(|uv| object.uvs()[uv]).unwrap_or(DEFAULT_UV));
+                        }
+                        if has_normals || !mesh.normals.is_empty() {
+                            mesh.normals.resize(mesh.positions.len() - 1, DEFAULT_NORMAL);
+                            mesh.normals.push(vertex.2.map(|normal| object.normals()[normal]).unwrap_or(DEFAULT_NORMAL));
+                        }
+                        if object.has_colors() {
+                            let [r, g, b] = object.colors()[vertex.0];
+                            mesh.colors.push([r, g, b, 1.0]);
+                        }
+                        (mesh.positions.len() - 1) as u32
+                    });
+                    mesh.indices.push(index);
+                }
+            }
+            mesh.submeshes.push(Submesh::new(group.name(), group.material(), start, mesh.indices.len() - start, group.smooth()));
+        }
+        mesh
+    }
+
+    ///Unindexed triangles laid out as position, normal, uv, as built by `MeshBuilder` and drawn by `BlendMap`.
+    pub fn from_interleaved(buffer: &[f32]) -> Mesh {
+        let mut positions = vec![];
+        let mut normals = vec![];
+        let mut uvs = vec![];
+        for vertex in buffer.chunks_exact(8) {
+            positions.push([vertex[0], vertex[1], vertex[2]]);
+            normals.push([vertex[3], vertex[4], vertex[5]]);
+            uvs.push([vertex[6], vertex[7]]);
+        }
+        let indices = (0..(positions.len() - positions.len() % 3) as u32).collect();
+        Self::new(positions, indices).with_normals(normals).with_uvs(uvs)
+    }
+
+    ///The primitive's streams, with its material index as the submesh's material name.
+    pub fn from_gltf(primitive: &GltfPrimitive) -> Mesh {
+        let material = primitive.material().map(|material| material.to_string()).unwrap_or(DEFAULT_MATERIAL.to_string());
+        let count = primitive.indices().len();
+        Self::new(primitive.positions().clone(), primitive.indices().clone())
+            .with_normals(primitive.normals().clone())
+            .with_uvs(primitive.uvs().clone())
+            .with_tangents(primitive.tangents().clone())
+            .with_colors(primitive.colors().clone())
+            .with_submeshes(vec![Submesh::new(DEFAULT_NAME.to_string(), material, 0, count, true)])
+    }
+
+    pub fn positions(&self) -> &Vec<Vec3> {
+        &self.positions
+    }
+
+    pub fn normals(&self) -> &Vec<Vec3> {
+        &self.normals
+    }
+
+    pub fn uvs(&self) -> &Vec<Vec2> {
+        &self.uvs
+    }
+
+    pub fn tangents(&self) -> &Vec<Vec4> {
+        &self.tangents
+    }
+
+    pub fn colors(&self) -> &Vec<Vec4> {
+        &self.colors
+    }
+
+    pub fn indices(&self) -> &Vec<u32> {
+        &self.indices
+    }
+
+    pub fn submeshes(&self) -> &Vec<Submesh> {
+        &self.submeshes
+    }
+
+    pub fn submeshes_mut(&mut self) -> &mut Vec<Submesh> {
+        &mut self.submeshes
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item=[usize; 3]> + '_ {
+        self.indices.chunks_exact(3).map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+    }
+
+    fn triangle_positions(&self, [a, b, c]: [usize; 3]) -> [Vec3; 3] {
+        [self.positions[a], self.positions[b], self.positions[c]]
+    }
+
+    ///The streams this mesh has, which are the attributes of its VAO in order.
+    pub fn attributes(&self) -> Vec<VertexAttribute> {
+        let mut attributes = vec![VertexAttribute::Position];
+        let n = self.positions.len();
+        if n > 0 && self.normals.len() == n {
+            attributes.push(VertexAttribute::Normal);
+        }
+        if n > 0 && self.uvs.len() == n {
+            attributes.push(VertexAttribute::Uv);
+        }
+        if n > 0 && self.tangents.len() == n {
+            attributes.push(VertexAttribute::Tangent);
+        }
+        if n > 0 && self.colors.len() == n {
+            attributes.push(VertexAttribute::Color);
+        }
+        attributes
+    }
+
+    ///Appends a copy of vertex `index` to every stream and returns the copy's index.
+    fn duplicate_vertex(&mut self, index: usize) -> usize {
+        fn copy<T: Copy>(stream: &mut Vec<T>, index: usize) {
+            if let Some(value) = stream.get(index).copied() {
+                stream.push(value);
+            }
+        }
+        copy(&mut self.positions, index);
+        copy(&mut self.normals, index);
+        copy(&mut self.uvs, index);
+        copy(&mut self.tangents, index);
+        copy(&mut self.colors, index);
+        self.positions.len() - 1
+    }
+
+    ///Area weighted normals shared by the triangles around a position in smooth submeshes, face normals
+    ///in flat ones. Flat triangles get their own vertices, splitting vertices they shared.
+    pub fn generate_normals(&mut self) {
+        let submeshes = self.submeshes.clone();
+        let flat_corners = submeshes.iter().filter(|s| !s.smooth).flat_map(|s| s.start..(s.start + s.count).min(self.indices.len())).collect::<Vec<usize>>();
+        let smooth_corners = submeshes.iter().filter(|s| s.smooth).flat_map(|s| s.start..(s.start + s.count).min(self.indices.len())).collect::<Vec<usize>>();
+
+        let mut flat = vec![false; self.positions.len()];
+        let mut used = vec![false; self.positions.len()];
+        for corner in &flat_corners {
+            let vertex = self.indices[*corner] as usize;
+            if used[vertex] {
+                let copy = self.duplicate_vertex(vertex);
+                self.indices[*corner] = copy as u32;
+                flat.push(true);
+            } else {
+                used[vertex] = true;
+                flat[vertex] = true;
+            }
+        }
+        let mut split: HashMap<usize, usize> = HashMap::new();
+        for corner in &smooth_corners {
+            let vertex = self.indices[*corner] as usize;
+            if flat[vertex] {
+                let copy = match split.get(&vertex) {
+                    Some(copy) => *copy,
+                    None => {
+                        let copy = self.duplicate_vertex(vertex);
+                        flat.push(false);
+                        split.insert(vertex, copy);
+                        copy
+                    }
+                };
+                self.indices[*corner] = copy as u32;
+            }
+        }
+
+        self.normals = vec![DEFAULT_NORMAL; self.positions.len()];
+        for corners in flat_corners.chunks_exact(3) {
+            let triangle = [self.indices[corners[0]] as usize, self.indices[corners[1]] as usize, self.indices[corners[2]] as usize];
+            let normal = triangle_normal(self.triangle_positions(triangle)).normalized_or(DEFAULT_NORMAL);
+            for vertex in triangle {
+                self.normals[vertex] = normal;
+            }
+        }
+        //Keyed by position rather than vertex so uv seams don't show as hard edges.
+        let key = |p: Vec3| p.map(|v| (v + 0.0).to_bits());
+        let mut accumulated: HashMap<[u32; 3], Vec3> = HashMap::new();
+        for corners in smooth_corners.chunks_exact(3) {
+            let [a, b, c] = self.triangle_positions([self.indices[corners[0]] as usize, self.indices[corners[1]] as usize, self.indices[corners[2]] as usize]);
+            //The cross product's length is twice the area, weighting larger triangles more.
+            let normal = vec3_cross(vec3_sub(b, a), vec3_sub(c, a));
+            for position in [a, b, c] {
+                let sum = accumulated.entry(key(position)).or_insert([0.0; 3]);
+                for i in 0..3 {
+                    sum[i] += normal[i];
+                }
+            }
+        }
+        for corner in &smooth_corners {
+            let vertex = self.indices[*corner] as usize;
+            self.normals[vertex] = accumulated[&key(self.positions[vertex])].normalized_or(DEFAULT_NORMAL);
+        }
+    }
+
+    ///Marks every submesh smooth and generates normals.
+    pub fn generate_smooth_normals(&mut self) {
+        self.submeshes.iter_mut().for_each(|submesh| submesh.smooth = true);
+        self.generate_normals();
+    }
+
+    ///Marks every submesh flat and generates normals.
+    pub fn generate_flat_normals(&mut self) {
+        self.submeshes.iter_mut().for_each(|submesh| submesh.smooth = false);
+        self.generate_normals();
+    }
+
+    ///MikkTSpace tangents with `bitangent = cross(normal, tangent) * w`, the layout glTF uses and normal map
+    ///bakers expect. Vertices whose triangles get different tangents, like at uv mirrors, are split.
+    ///Requires normals and uvs, returns false without them.
+    pub fn generate_tangents(&mut self) -> bool {
+        let n = self.positions.len();
+        if self.normals.len() != n || self.uvs.len() != n {
+            return false;
+        }
+        let mut geometry = MikkTSpace { mesh: self, tangents: vec![DEFAULT_TANGENT; self.indices.len()] };
+        if !bevy_mikktspace::generate_tangents(&mut geometry) {
+            return false;
+        }
+        let corner_tangents = geometry.tangents;
+        self.tangents = vec![DEFAULT_TANGENT; n];
+        let mut assigned = vec![false; n];
+        let mut copies: HashMap<(usize, [u32; 4]), usize> = HashMap::new();
+        for (corner, tangent) in corner_tangents.into_iter().enumerate() {
+            let vertex = self.indices[corner] as usize;
+            if !assigned[vertex] {
+                assigned[vertex] = true;
+                self.tangents[vertex] = tangent;
+                continue;
+            }
+            if self.tangents[vertex] == tangent {
+                continue;
+            }
+            let copy = *copies.entry((vertex, tangent.map(f32::to_bits))).or_insert_with(|| self.duplicate_vertex(vertex));
+            self.tangents[copy] = tangent;
+            self.indices[corner] = copy as u32;
+        }
+        true
+    }
+
+    ///Generates missing normals and tangents and zeroes missing uvs, so `build_vao` matches the
+    ///position, normal, uv, tangent layout `pbr_program` expects.
+    pub fn complete_streams(&mut self) {
+        let count = self.positions.len();
+        if self.normals.len() != count {
+            self.generate_normals();
+        }
+        if self.uvs.len() != count {
+            self.uvs = vec![DEFAULT_UV; count];
+        }
+        if self.tangents.len() != count {
+            self.generate_tangents();
+        }
+    }
+
+    ///Merges vertices whose streams are equal after snapping to a grid of `epsilon`, 0 merges exact duplicates only.
+    ///Returns the number of vertices removed.
+    pub fn weld(&mut self, epsilon: f32) -> usize {
+        let quantize = |v: f32| match epsilon > 0.0 {
+            true => (v / epsilon).round() as i64,
+            false => (v + 0.0).to_bits() as i64
+        };
+        let mut keys: HashMap<Vec<i64>, u32> = HashMap::new();
+        let mut remap = Vec::with_capacity(self.positions.len());
+        let mut kept = vec![];
+        for vertex in 0..self.positions.len() {
+            let mut key = self.positions[vertex].iter().map(|v| quantize(*v)).collect::<Vec<i64>>();
+            for stream in [
+                self.normals.get(vertex).map(|v| v.to_vec()),
+                self.uvs.get(vertex).map(|v| v.to_vec()),
+                self.tangents.get(vertex).map(|v| v.to_vec()),
+                self.colors.get(vertex).map(|v| v.to_vec()),
+            ].into_iter().flatten() {
+                key.extend(stream.into_iter().map(quantize));
+            }
+            let index = *keys.entry(key).or_insert_with(|| {
+                kept.push(vertex);
+                (kept.len() - 1) as u32
+            });
+            remap.push(index);
+        }
+        let removed = self.positions.len() - kept.len();
+        fn retain<T: Copy>(stream: &mut Vec<T>, kept: &[usize]) {
+            if !stream.is_empty() {
+                *stream = kept.iter().map(|i| stream[*i]).collect();
+            }
+        }
+        retain(&mut self.positions, &kept);
+        retain(&mut self.normals, &kept);
+        retain(&mut self.uvs, &kept);
+        retain(&mut self.tangents, &kept);
+        retain(&mut self.colors, &kept);
+        self.indices.iter_mut().for_each(|index| *index = remap[*index as usize]);
+        removed
+    }
+
+    ///None for a mesh without vertices.
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(&self.positions)
+    }
+
+    pub fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        BoundingSphere::from_points(&self.positions)
+    }
+
+    ///Bakes a column major transform into positions, normals and tangents. Mirroring transforms
+    ///reverse the winding and tangent handedness so triangles keep facing outwards.
+    pub fn transform(&mut self, matrix: &Mat4) {
+        for position in &mut self.positions {
+            let [x, y, z, _] = col_mat4_transform(*matrix, [position[0], position[1], position[2], 1.0]);
+            *position = [x, y, z];
+        }
+        let linear = mat3(*matrix);
+        let determinant = mat3_det(linear);
+        let normal_matrix = match determinant.abs() > f32::EPSILON {
+            true => mat3_transposed(mat3_inv(linear)),
+            false => linear
+        };
+        for normal in &mut self.normals {
+            *normal = col_mat3_transform(normal_matrix, *normal).normalized_or(DEFAULT_NORMAL);
+        }
+        for tangent in &mut self.tangents {
+            let [x, y, z] = col_mat3_transform(linear, [tangent[0], tangent[1], tangent[2]]).normalized_or(DEFAULT_NORMAL);
+            let w = if determinant < 0.0 { -tangent[3] } else { tangent[3] };
+            *tangent = [x, y, z, w];
+        }
+        if determinant < 0.0 {
+            self.indices.chunks_exact_mut(3).for_each(|triangle| triangle.swap(1, 2));
+        }
+    }
+
+    ///Appends `other`'s vertices, triangles and submeshes. A stream only one of the meshes has is
+    ///filled with defaults for the other's vertices.
+    pub fn merge(&mut self, other: &Mesh) {
+        fn append<T: Copy>(stream: &mut Vec<T>, count: usize, other: &[T], other_count: usize, default: T) {
+            if stream.is_empty() && other.is_empty() {
+                return;
+            }
+            stream.resize(count, default);
+            match other.is_empty() {
+                true => stream.extend(std::iter::repeat(default).take(other_count)),
+                false => stream.extend_from_slice(other)
+            }
+        }
+        let (count, other_count) = (self.positions.len(), other.positions.len());
+        append(&mut self.normals, count, &other.normals, other_count, DEFAULT_NORMAL);
+        append(&mut self.uvs, count, &other.uvs, other_count, DEFAULT_UV);
+        append(&mut self.tangents, count, &other.tangents, other_count, DEFAULT_TANGENT);
+        append(&mut self.colors, count, &other.colors, other_count, DEFAULT_COLOR);
+        self.positions.extend_from_slice(&other.positions);
+
+        let start = self.indices.len();
+        self.indices.extend(other.indices.iter().map(|index| index + count as u32));
+        self.submeshes.extend(other.submeshes.iter().map(|submesh| Submesh { start: submesh.start + start, ..submesh.clone() }));
+    }
+
+    ///Every vertex's streams one after another, in the order of `attributes`.
+    pub fn interleaved(&self) -> Vec<f32> {
+        let attributes = self.attributes();
+        let stride = attributes.iter().map(|attribute| attribute.size() as usize).sum::<usize>();
+        let mut vertices = Vec::with_capacity(self.positions.len() * stride);
+        for i in 0..self.positions.len() {
+            for attribute in &attributes {
+                match attribute {
+                    VertexAttribute::Position => vertices.extend_from_slice(&self.positions[i]),
+                    VertexAttribute::Normal => vertices.extend_from_slice(&self.normals[i]),
+                    VertexAttribute::Uv => vertices.extend_from_slice(&self.uvs[i]),
+                    VertexAttribute::Tangent => vertices.extend_from_slice(&self.tangents[i]),
+                    VertexAttribute::Color => vertices.extend_from_slice(&self.colors[i]),
+                }
+            }
+        }
+        vertices
+    }
+
+    fn build_indexed_vao(&self, indices: &[u32]) -> VertexArrayObject {
+        let pointers = self.attributes().iter()
+            .map(|attribute| LocalAttribPointer::new(attribute.size(), BufferDataType::Float, false))
+            .collect();
+        VertexArrayObject::new(Some(VertexArrayObjectType::Elements(indices.len() as i32)))
+            .with_buffer(VertexBufferObject::array(BufferType::ArrayBuffer, DrawType::StaticDraw, &self.interleaved()))
+            .with_buffer(GpuBuffer::from_slice(BufferType::ElementArrayBuffer, DrawType::StaticDraw, indices))
+            .with_local_attrib_pointers(pointers)
+            .build()
+    }
+
+    ///Every triangle in one VAO. Attributes take consecutive locations in the order of `attributes`,
+    ///so a mesh with all streams matches the position, normal, uv, tangent, color layout of `GltfPrimitive::build_vao`.
+    pub fn build_vao(&self) -> VertexArrayObject {
+        self.build_indexed_vao(&self.indices)
+    }
+
+    ///A VAO per material, drawing the triangles of every submesh using it.
+    pub fn build_vaos(&self) -> HashMap<String, VertexArrayObject> {
+        let mut indices: HashMap<String, Vec<u32>> = HashMap::new();
+        for submesh in &self.submeshes {
+            let end = (submesh.start + submesh.count).min(self.indices.len());
+            indices.entry(submesh.material.clone()).or_default().extend_from_slice(&self.indices[submesh.start.min(end)..end]);
+        }
+        indices.into_iter().map(|(material, indices)| (material, self.build_indexed_vao(&indices))).collect()
+    }
+}
+
+///A mesh's triangles as MikkTSpace faces, collecting the tangent of every corner.
+struct MikkTSpace<'a> {
+    mesh: &'a Mesh,
+    tangents: Vec<Vec4>,
+}
+
+impl MikkTSpace<'_> {
+    fn vertex(&self, face: usize, vert: usize) -> usize {
+        self.mesh.indices[face * 3 + vert] as usize
+    }
+}
+
+impl bevy_mikktspace::Geometry for MikkTSpace<'_> {
+    fn num_faces(&self) -> usize {
+        self.mesh.triangle_count()
+    }
+    fn num_vertices_of_face(&self, _face: usize) -> usize {
+        3
+    }
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.mesh.positions[self.vertex(face, vert)]
+    }
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.mesh.normals[self.vertex(face, vert)]
+    }
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.mesh.uvs[self.vertex(face, vert)]
+    }
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        self.tangents[face * 3 + vert] = tangent;
+    }
+}
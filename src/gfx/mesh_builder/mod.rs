@@ -1,10 +1,11 @@
 
-use std::mem::{size_of};
 use vecmath::Matrix4;
 use crate::{BufferDataType, Constructor, draw_arrays, DrawMode, Face, LocalAttribPointer, MatrixExt, MatrixWrapper, shaded_wireframe, VertexArrayObject, VertexArrayObjectType, VertexBufferObject};
 use crate::BufferType::ArrayBuffer;
 use crate::DrawType::{DynamicDraw};
 use crate::math::linear_algebra::types::Vec3;
+use crate::gfx::mesh::Mesh;
+use crate::loader::models::obj::Object;
 use crate::utils::data_structure::DataStructure;
 
 pub const FACE: &'static [f32] = &[
@@ -66,15 +67,39 @@ impl MeshBuilder {
                 VertexArrayObjectType::Arrays(tris) => {
                     *tris += vertices.len() as i32 / 8;
                 }
-                VertexArrayObjectType::ArrayStrips(_) => {}
+                VertexArrayObjectType::ArrayStrips(_) | VertexArrayObjectType::Elements(_) => {}
             }
         }
-        let add_size = size_of::<f32>() * vertices.len();
         self.faces.push_vec(vertices);
-        let buffer = &mut self.vao.buffers_mut()[0];
-        *buffer.size_mut() += add_size as isize;
-        buffer.set_data(self.faces.as_ptr() as *const _);
-        buffer.buffer_data();
+        if let Some(buffer) = self.vao.buffers_mut()[0].downcast_mut::<VertexBufferObject>() {
+            buffer.set_data(&self.faces);
+        }
+    }
+
+    ///Interleaved position, normal and uv of every triangle corner.
+    pub fn vertices(&self) -> &Vec<f32> {
+        &self.faces
+    }
+
+    ///The built triangles as an object, e.g. to write them with `PLY::from_object` or `STL::from_object`.
+    pub fn to_object<T: ToString>(&self, name: T) -> Object {
+        let mut positions = vec![];
+        let mut normals = vec![];
+        let mut uvs = vec![];
+        for vertex in self.faces.chunks_exact(8) {
+            positions.push([vertex[0], vertex[1], vertex[2]]);
+            normals.push([vertex[3], vertex[4], vertex[5]]);
+            uvs.push([vertex[6], vertex[7]]);
+        }
+        let faces = (0..positions.len() / 3)
+            .map(|t| [0, 1, 2].map(|corner| (t * 3 + corner, Some(t * 3 + corner), Some(t * 3 + corner))))
+            .collect();
+        Object::from_faces(name, positions, vec![], normals, uvs, faces)
+    }
+
+    ///The built triangles as an unindexed mesh, `Mesh::weld` shares their corners.
+    pub fn to_mesh(&self) -> Mesh {
+        Mesh::from_interleaved(&self.faces)
     }
 
     pub fn bind(&self) {
@@ -100,7 +125,7 @@ impl MeshBuilder {
                     VertexArrayObjectType::Arrays(tris) => {
                         draw_arrays(DrawMode::Triangles, 0, *tris);
                     }
-                    VertexArrayObjectType::ArrayStrips(_) => {}
+                    VertexArrayObjectType::ArrayStrips(_) | VertexArrayObjectType::Elements(_) => {}
                 }
             }
         });
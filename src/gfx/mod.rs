@@ -10,4 +10,16 @@ pub mod mesh_builder;
 pub mod blend_map;
 pub mod consts;
 pub mod cube_map;
-pub mod skybox;
\ No newline at end of file
+pub mod skybox;
+pub mod post_processing;
+pub mod material;
+pub mod pbr;
+pub mod lighting;
+pub mod shadows;
+pub mod reflection;
+pub mod uniform_block;
+pub mod resource;
+pub mod animation;
+pub mod mesh;
+pub mod simplify;
+pub mod terrain;
\ No newline at end of file
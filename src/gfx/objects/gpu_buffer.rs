@@ -0,0 +1,185 @@
+use std::marker::PhantomData;
+use std::mem::size_of;
+use std::ops::{Deref, DerefMut};
+use bytemuck::{cast_slice, Pod};
+use crate::gfx::bindings::{BufferType, DrawType};
+use crate::gfx::bindings::buffers::*;
+use crate::gfx::objects::Buffer;
+
+///A GL buffer holding elements of `T`. `len` elements are in use out of `capacity` allocated on the GPU.
+///Works for any target: vertices, indices, uniform or shader storage blocks and indirect commands.
+#[derive(Debug)]
+pub struct GpuBuffer<T: Pod> {
+    id: u32,
+    buffer_type: BufferType,
+    draw_type: DrawType,
+    len: usize,
+    capacity: usize,
+    element: PhantomData<T>,
+}
+
+impl<T: Pod> GpuBuffer<T> {
+    ///Creates the buffer without allocating storage.
+    pub fn new(buffer_type: BufferType, draw_type: DrawType) -> GpuBuffer<T> {
+        Self {
+            id: gen_buffers(1),
+            buffer_type,
+            draw_type,
+            len: 0,
+            capacity: 0,
+            element: PhantomData,
+        }
+    }
+
+    pub fn from_slice(buffer_type: BufferType, draw_type: DrawType, data: &[T]) -> GpuBuffer<T> {
+        let mut buffer = Self::new(buffer_type, draw_type);
+        buffer.allocate(data.len(), Some(data));
+        buffer
+    }
+
+    ///Allocates uninitialized storage for `capacity` elements.
+    pub fn with_capacity(buffer_type: BufferType, draw_type: DrawType, capacity: usize) -> GpuBuffer<T> {
+        let mut buffer = Self::new(buffer_type, draw_type);
+        buffer.allocate(capacity, None);
+        buffer
+    }
+
+    fn allocate(&mut self, capacity: usize, data: Option<&[T]>) {
+        let pointer = match data {
+            None => std::ptr::null(),
+            Some(data) => cast_slice::<T, u8>(data).as_ptr() as *const _
+        };
+        bind_buffer_raw(self.buffer_type, self.id);
+        buffer_data_array(self.buffer_type, (capacity * size_of::<T>()) as isize, pointer, self.draw_type);
+        self.capacity = capacity;
+        self.len = data.map(|data| data.len()).unwrap_or(0);
+    }
+
+    ///Replaces the contents, reallocating only when `data` doesn't fit the current storage.
+    pub fn set_data(&mut self, data: &[T]) {
+        if data.len() > self.capacity {
+            self.allocate(data.len(), Some(data));
+        } else {
+            self.sub_data(0, data);
+            self.len = data.len();
+        }
+    }
+
+    ///Overwrites elements starting at element `offset`, growing `len` if the range ends past it.
+    ///Panics if the range doesn't fit the allocated storage.
+    pub fn sub_data(&mut self, offset: usize, data: &[T]) {
+        assert!(offset + data.len() <= self.capacity, "sub_data range {}..{} exceeds the buffer capacity {}", offset, offset + data.len(), self.capacity);
+        if data.is_empty() {
+            return;
+        }
+        let bytes = cast_slice::<T, u8>(data);
+        bind_buffer_raw(self.buffer_type, self.id);
+        buffer_sub_data(self.buffer_type, (offset * size_of::<T>()) as isize, bytes.len() as isize, bytes.as_ptr() as *const _);
+        self.len = self.len.max(offset + data.len());
+    }
+
+    ///Detaches the current storage and allocates fresh storage of the same size, so writes don't wait for
+    ///draws still reading the old contents.
+    pub fn orphan(&mut self) {
+        self.allocate(self.capacity, None);
+    }
+
+    ///Uploads per-frame data: orphans the storage, growing it when needed, then writes `data`.
+    pub fn stream(&mut self, data: &[T]) {
+        self.allocate(self.capacity.max(data.len()), None);
+        self.sub_data(0, data);
+    }
+
+    ///Maps `len` elements at `offset` into client memory. `access` combines `MapAccess` bits.
+    ///The range is unmapped when the returned guard drops.
+    pub fn map_range(&mut self, offset: usize, len: usize, access: u32) -> Option<MappedRange<'_, T>> {
+        if len == 0 || offset + len > self.capacity {
+            return None;
+        }
+        bind_buffer_raw(self.buffer_type, self.id);
+        let pointer = map_buffer_range(self.buffer_type, (offset * size_of::<T>()) as isize, (len * size_of::<T>()) as isize, access);
+        if pointer.is_null() {
+            return None;
+        }
+        self.len = self.len.max(offset + len);
+        Some(MappedRange {
+            buffer: self,
+            pointer: pointer as *mut T,
+            len,
+        })
+    }
+
+    ///Binds the buffer to an indexed binding point of its target, for uniform and shader storage blocks.
+    pub fn bind_base(&self, index: u32) {
+        bind_buffer_base(self.buffer_type, index, self.id);
+    }
+
+    pub fn unbind(&self) {
+        bind_buffer_raw(self.buffer_type, 0);
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl<T: Pod> Drop for GpuBuffer<T> {
+    fn drop(&mut self) {
+        delete_buffers(self.id);
+    }
+}
+
+impl<T: Pod + std::fmt::Debug> Buffer for GpuBuffer<T> {
+    fn size(&self) -> isize {
+        (self.capacity * size_of::<T>()) as isize
+    }
+
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn buffer_type(&self) -> &BufferType {
+        &self.buffer_type
+    }
+
+    fn draw_type(&self) -> &DrawType {
+        &self.draw_type
+    }
+}
+
+///A mapped range of a `GpuBuffer`, usable as a slice until dropped.
+#[derive(Debug)]
+pub struct MappedRange<'a, T: Pod> {
+    buffer: &'a GpuBuffer<T>,
+    pointer: *mut T,
+    len: usize,
+}
+
+impl<'a, T: Pod> Deref for MappedRange<'a, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { std::slice::from_raw_parts(self.pointer, self.len) }
+    }
+}
+
+impl<'a, T: Pod> DerefMut for MappedRange<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { std::slice::from_raw_parts_mut(self.pointer, self.len) }
+    }
+}
+
+impl<'a, T: Pod> Drop for MappedRange<'a, T> {
+    fn drop(&mut self) {
+        bind_buffer_raw(self.buffer.buffer_type, self.buffer.id);
+        if !unmap_buffer(self.buffer.buffer_type) {
+            eprintln!("Buffer {} was corrupted while mapped", self.buffer.id);
+        }
+    }
+}
@@ -1,13 +1,16 @@
 use crate::Constructor;
 use crate::gfx::bindings::attrib_pointer::*;
-use crate::gfx::bindings::buffers::{bind_vertex_array, gen_vertex_arrays, unbind_vertex_array};
+use crate::gfx::bindings::DrawMode;
+use crate::gfx::bindings::buffers::{bind_vertex_array, delete_vertex_arrays, gen_vertex_arrays, unbind_vertex_array};
+use crate::gfx::bindings::graphics::{draw_arrays, draw_elements};
 use crate::gfx::objects::Buffer;
 use crate::gfx::objects::vertex_array_object_type::VertexArrayObjectType;
 use crate::gfx::objects::vertex_attrib_pointer::{LocalAttribPointer, VertexAttribPointer};
+use crate::gfx::resource::Shared;
 use crate::gfx::texture::Texture;
 
 #[derive(Debug)]
-pub struct VertexArrayObject(u32, Vec<Box<dyn Buffer>>, Vec<VertexAttribPointer>, isize, Option<VertexArrayObjectType>, Vec<Texture>, Vec<Option<String>>);
+pub struct VertexArrayObject(u32, Vec<Box<dyn Buffer>>, Vec<VertexAttribPointer>, isize, Option<VertexArrayObjectType>, Vec<Shared<Texture>>, Vec<Option<String>>);
 
 impl VertexArrayObject {
     pub fn bind(&self) {
@@ -20,7 +23,6 @@ impl VertexArrayObject {
     pub fn bind_buffer<B: Buffer + 'static>(&mut self, buffer: B) {
         self.bind();
         buffer.bind();
-        buffer.buffer_data();
         self.1.push(Box::new(buffer));
         self.unbind();
     }
@@ -97,12 +99,12 @@ impl VertexArrayObject {
         &mut self.4
     }
 
-    pub fn with_texture<T: ToString>(mut self, name: Option<T>, texture: Texture) -> VertexArrayObject {
+    pub fn with_texture<T: ToString>(mut self, name: Option<T>, texture: Shared<Texture>) -> VertexArrayObject {
         self.put_texture(name, texture);
         self
     }
 
-    pub fn put_texture<T: ToString>(&mut self, name: Option<T>, texture: Texture) {
+    pub fn put_texture<T: ToString>(&mut self, name: Option<T>, texture: Shared<Texture>) {
         self.5.push(texture);
         let name = match name {
             None => {
@@ -115,9 +117,27 @@ impl VertexArrayObject {
         self.6.push(name);
     }
 
-    pub fn textures(&self) -> &Vec<Texture> {
+    pub fn textures(&self) -> &Vec<Shared<Texture>> {
         &self.5
     }
+
+    ///Binds the array and draws all of it as triangles. Arrays without a type are not drawn.
+    pub fn draw(&self) {
+        self.bind();
+        match self.4 {
+            Some(VertexArrayObjectType::Arrays(count)) => draw_arrays(DrawMode::Triangles, 0, count),
+            Some(VertexArrayObjectType::ArrayStrips(count)) => draw_arrays(DrawMode::TriangleStrip, 0, count),
+            Some(VertexArrayObjectType::Elements(count)) => draw_elements(DrawMode::Triangles, count),
+            None => {}
+        }
+    }
+}
+
+///Deletes the vertex array and, through their own drops, the buffers it owns and its references to shared textures.
+impl Drop for VertexArrayObject {
+    fn drop(&mut self) {
+        delete_vertex_arrays(self.0);
+    }
 }
 
 impl Constructor<Option<VertexArrayObjectType>> for VertexArrayObject {
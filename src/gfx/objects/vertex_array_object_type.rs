@@ -1,5 +1,7 @@
 #[derive(Debug, Clone)]
 pub enum VertexArrayObjectType {
     Arrays(i32),
-    ArrayStrips(i32)
+    ArrayStrips(i32),
+    ///Indexed triangles, the count of `u32` indices in the vertex array's element buffer.
+    Elements(i32)
 }
\ No newline at end of file
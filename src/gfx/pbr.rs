@@ -0,0 +1,144 @@
+use std::error::Error;
+use crate::gfx::bindings::texturing::active_texture;
+use crate::gfx::lighting::Lighting;
+use crate::gfx::program::Program;
+use crate::gfx::resource::Shared;
+use crate::gfx::shadows::Shadows;
+use crate::gfx::shader::preprocessor::Preprocessor;
+use crate::gfx::texture::Texture;
+use crate::gfx::texture::texture_library::{TextureLibrary, TextureLibraryFlag, TextureRole, TextureSet};
+use crate::math::bounds::BoundingSphere;
+use crate::math::camera::Camera;
+use crate::math::linear_algebra::types::{Mat4, Vec4};
+
+pub const PBR_VERTEX: &'static str = "pbr/vertex.glsl";
+pub const PBR_FRAGMENT: &'static str = "pbr/fragment.glsl";
+
+///Metallic-roughness program. Expects meshes laid out as position, normal, uv, tangent and optionally color,
+///see `Object::build_tangent_vao`. Shaded by the scene lights and their shadows,
+///call `bind_pbr_object` before drawing.
+pub fn pbr_program() -> Result<Shared<Program>, Box<dyn Error>> {
+    let program = Preprocessor::new()
+        .with_defines(&Shadows::defines())
+        .program(PBR_VERTEX, PBR_FRAGMENT)?;
+    Lighting::bind(&program);
+    Ok(program)
+}
+
+///Sets what one object drawn with a PBR program needs: the camera, its model matrix, the lights and
+///shadow maps reaching `bounds` in world space and its material. `vertex_colors` tells whether the
+///vertex array has colors at location 4 to tint the base color. The program must be enabled.
+pub fn bind_pbr_object(program: &Program, camera: &Camera, material: &PbrMaterial, model: &Mat4, bounds: &BoundingSphere, receive_shadows: bool, vertex_colors: bool) {
+    camera.prepare_render(program);
+    program.set_uniform_vec3("cameraPosition", &camera.position());
+    program.set_uniform_mat4("model", model);
+    program.set_uniform_bool("hasVertexColors", &vertex_colors);
+    Lighting::bind_object(program, bounds.center, bounds.radius);
+    let unit = material.bind(program, 0);
+    Shadows::bind(program, unit);
+    Shadows::bind_object(program, receive_shadows);
+}
+
+///The sampler and flag uniforms of a role inside the `pbr` struct.
+fn role_uniforms(role: TextureRole) -> (&'static str, &'static str) {
+    match role {
+        TextureRole::AmbientOcclusion => ("pbr.occlusionMap", "pbr.hasOcclusionMap"),
+        TextureRole::BaseColor => ("pbr.baseColorMap", "pbr.hasBaseColorMap"),
+        TextureRole::Height => ("pbr.heightMap", "pbr.hasHeightMap"),
+        TextureRole::Metallic => ("pbr.metallicMap", "pbr.hasMetallicMap"),
+        TextureRole::Normal => ("pbr.normalMap", "pbr.hasNormalMap"),
+        TextureRole::Roughness => ("pbr.roughnessMap", "pbr.hasRoughnessMap"),
+    }
+}
+
+///A metallic-roughness material. Each factor multiplies its map, or is used alone when the map is missing.
+#[derive(Debug, Clone)]
+pub struct PbrMaterial {
+    pub base_color_factor: Vec4,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub occlusion_strength: f32,
+    pub normal_scale: f32,
+    ///Parallax depth in uv units. 0 disables parallax mapping.
+    pub height_scale: f32,
+    pub flip_normal_y: bool,
+    textures: TextureSet,
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        Self {
+            base_color_factor: [1.0; 4],
+            metallic_factor: 1.0,
+            roughness_factor: 1.0,
+            occlusion_strength: 1.0,
+            normal_scale: 1.0,
+            height_scale: 0.05,
+            flip_normal_y: false,
+            textures: TextureSet::new(),
+        }
+    }
+}
+
+impl PbrMaterial {
+    pub fn new(textures: TextureSet) -> PbrMaterial {
+        Self {
+            textures,
+            ..Default::default()
+        }
+    }
+
+    ///Builds a material from one of the bundled sets. The texture library must be loaded.
+    pub fn from_library(flag: TextureLibraryFlag) -> PbrMaterial {
+        let mut material = Self::new(TextureLibrary::get_texture_set(flag));
+        if let TextureLibraryFlag::Granite = flag {
+            material.base_color_factor = [0.55, 0.55, 0.57, 1.0];
+            material.flip_normal_y = true;
+        }
+        material
+    }
+
+    pub fn with_texture(mut self, role: TextureRole, texture: Shared<Texture>) -> PbrMaterial {
+        self.textures.put(role, texture);
+        self
+    }
+
+    pub fn texture(&self, role: TextureRole) -> Option<&Shared<Texture>> {
+        self.textures.get(role)
+    }
+
+    pub fn textures(&self) -> &TextureSet {
+        &self.textures
+    }
+
+    pub fn textures_mut(&mut self) -> &mut TextureSet {
+        &mut self.textures
+    }
+
+    ///Uploads the factors and binds the maps starting at texture unit `first_unit`.
+    ///Returns the next free texture unit.
+    pub fn bind(&self, program: &Program, first_unit: u32) -> u32 {
+        program.set_uniform_vec4("pbr.baseColorFactor", &self.base_color_factor);
+        program.set_uniform_float("pbr.metallicFactor", &self.metallic_factor);
+        program.set_uniform_float("pbr.roughnessFactor", &self.roughness_factor);
+        program.set_uniform_float("pbr.occlusionStrength", &self.occlusion_strength);
+        program.set_uniform_float("pbr.normalScale", &self.normal_scale);
+        program.set_uniform_float("pbr.heightScale", &self.height_scale);
+        program.set_uniform_bool("pbr.flipNormalY", &self.flip_normal_y);
+
+        let mut unit = first_unit;
+        for role in enum_iterator::all::<TextureRole>() {
+            let (sampler, flag) = role_uniforms(role);
+            let texture = self.textures.get(role);
+            program.set_uniform_bool(flag, &texture.is_some());
+            if let Some(texture) = texture {
+                active_texture(unit);
+                texture.bind();
+                program.set_uniform_int(sampler, &(unit as i32));
+                unit += 1;
+            }
+        }
+        active_texture(0);
+        unit
+    }
+}
@@ -0,0 +1,473 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use crate::gfx::bindings::{ColorFormat, DrawMode, FramebufferTarget, GLConsts};
+use crate::gfx::bindings::BufferDataType::Float;
+use crate::gfx::bindings::BufferType::ArrayBuffer;
+use crate::gfx::bindings::DrawType::StaticDraw;
+use crate::gfx::bindings::framebuffers::unbind_framebuffer;
+use crate::gfx::bindings::graphics::{disable, draw_arrays, viewport};
+use crate::gfx::bindings::texturing::active_texture;
+use crate::gfx::objects::vertex_array_object::VertexArrayObject;
+use crate::gfx::objects::vertex_array_object_type::VertexArrayObjectType;
+use crate::gfx::objects::vertex_attrib_pointer::LocalAttribPointer;
+use crate::gfx::objects::vertex_buffer_object::VertexBufferObject;
+use crate::gfx::post_processing::render_target::RenderTarget;
+use crate::gfx::program::Program;
+use crate::gfx::resource::{GlObject, Owned, Shared};
+use crate::gfx::shader::fragment_shader::FragmentShader;
+use crate::gfx::shader::vertex_shader::VertexShader;
+use crate::gfx::texture::Texture;
+use crate::utils::constructor::Constructor;
+
+pub mod render_target;
+
+const POST_VERTEX: &'static str = include_str!("../../../res/shaders/post/vertex.glsl");
+const TONEMAPPING_FRAGMENT: &'static str = include_str!("../../../res/shaders/post/tonemapping.glsl");
+const GAMMA_FRAGMENT: &'static str = include_str!("../../../res/shaders/post/gamma.glsl");
+const FXAA_FRAGMENT: &'static str = include_str!("../../../res/shaders/post/fxaa.glsl");
+const BLOOM_EXTRACT_FRAGMENT: &'static str = include_str!("../../../res/shaders/post/bloom_extract.glsl");
+const BLOOM_BLUR_FRAGMENT: &'static str = include_str!("../../../res/shaders/post/bloom_blur.glsl");
+const BLOOM_COMBINE_FRAGMENT: &'static str = include_str!("../../../res/shaders/post/bloom_combine.glsl");
+const VIGNETTE_FRAGMENT: &'static str = include_str!("../../../res/shaders/post/vignette.glsl");
+const COLOR_GRADING_FRAGMENT: &'static str = include_str!("../../../res/shaders/post/color_grading.glsl");
+
+///Fullscreen quad as a triangle strip: position (xy), uv.
+pub const QUAD: &'static [f32] = &[
+    -1.0, -1.0, 0.0, 0.0,
+    1.0, -1.0, 1.0, 0.0,
+    -1.0, 1.0, 0.0, 1.0,
+    1.0, 1.0, 1.0, 1.0,
+];
+
+///Color format used for the scene and the intermediate targets, so tonemapping has HDR input.
+pub const POST_COLOR_FORMAT: ColorFormat = ColorFormat::RGBA16F;
+
+static mut FULLSCREEN_QUAD: Option<VertexArrayObject> = None;
+
+#[derive(Debug, Clone)]
+pub enum PostProcessError {
+    IncompleteFramebuffer(i32, i32),
+    DuplicatePass(String),
+    UnknownPass(String),
+    UnknownParameter(String, String),
+}
+
+impl Display for PostProcessError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PostProcessError::IncompleteFramebuffer(width, height) => {
+                f.write_fmt(format_args!("Framebuffer of size {}x{} is incomplete", width, height))
+            }
+            PostProcessError::DuplicatePass(name) => {
+                f.write_fmt(format_args!("A post processing pass named '{}' already exists", name))
+            }
+            PostProcessError::UnknownPass(name) => {
+                f.write_fmt(format_args!("No post processing pass named '{}'", name))
+            }
+            PostProcessError::UnknownParameter(name, parameter) => {
+                f.write_fmt(format_args!("Post processing pass '{}' has no parameter '{}'", name, parameter))
+            }
+        }
+    }
+}
+
+impl Error for PostProcessError {}
+
+///A single effect in the post processing chain, along with its tweakable parameters.
+#[derive(Debug, Clone)]
+pub enum PostProcessEffect {
+    ///ACES filmic tonemapping of the HDR scene color.
+    Tonemapping { exposure: f32 },
+    GammaCorrection { gamma: f32 },
+    Fxaa,
+    ///Bright-pass, separable gaussian blur at half resolution, then additive combine.
+    Bloom { threshold: f32, intensity: f32, iterations: u32 },
+    Vignette { intensity: f32, radius: f32 },
+    ///`lut` is an unwrapped 3D LUT: `lut_size` slices of `lut_size`x`lut_size` laid out horizontally.
+    ColorGrading { lut: Shared<Texture>, lut_size: f32, contribution: f32 },
+    ///User supplied fragment shader. Receives `screenTexture`, `resolution` and `uv`.
+    Custom,
+}
+
+impl PostProcessEffect {
+    pub fn tonemapping() -> PostProcessEffect {
+        PostProcessEffect::Tonemapping { exposure: 1.0 }
+    }
+
+    pub fn gamma_correction() -> PostProcessEffect {
+        PostProcessEffect::GammaCorrection { gamma: 2.2 }
+    }
+
+    pub fn bloom() -> PostProcessEffect {
+        PostProcessEffect::Bloom { threshold: 1.0, intensity: 1.0, iterations: 5 }
+    }
+
+    pub fn vignette() -> PostProcessEffect {
+        PostProcessEffect::Vignette { intensity: 0.5, radius: 0.75 }
+    }
+
+    pub fn color_grading(lut: Shared<Texture>) -> PostProcessEffect {
+        let lut_size = lut.height() as f32;
+        PostProcessEffect::ColorGrading { lut, lut_size, contribution: 1.0 }
+    }
+
+    fn programs(&self) -> Result<Vec<Owned<Program>>, Box<dyn Error>> {
+        let fragments = match self {
+            PostProcessEffect::Tonemapping { .. } => vec![TONEMAPPING_FRAGMENT],
+            PostProcessEffect::GammaCorrection { .. } => vec![GAMMA_FRAGMENT],
+            PostProcessEffect::Fxaa => vec![FXAA_FRAGMENT],
+            PostProcessEffect::Bloom { .. } => vec![BLOOM_EXTRACT_FRAGMENT, BLOOM_BLUR_FRAGMENT, BLOOM_COMBINE_FRAGMENT],
+            PostProcessEffect::Vignette { .. } => vec![VIGNETTE_FRAGMENT],
+            PostProcessEffect::ColorGrading { .. } => vec![COLOR_GRADING_FRAGMENT],
+            PostProcessEffect::Custom => vec![]
+        };
+        let mut programs = vec![];
+        for fragment in fragments {
+            programs.push(Program::new(FragmentShader::new(fragment), VertexShader::new(POST_VERTEX))?.owned());
+        }
+        Ok(programs)
+    }
+
+    pub fn set_parameter(&mut self, parameter: &str, value: f32) -> bool {
+        match (self, parameter) {
+            (PostProcessEffect::Tonemapping { exposure }, "exposure") => *exposure = value,
+            (PostProcessEffect::GammaCorrection { gamma }, "gamma") => *gamma = value,
+            (PostProcessEffect::Bloom { threshold, .. }, "threshold") => *threshold = value,
+            (PostProcessEffect::Bloom { intensity, .. }, "intensity") => *intensity = value,
+            (PostProcessEffect::Bloom { iterations, .. }, "iterations") => *iterations = value.max(0.0) as u32,
+            (PostProcessEffect::Vignette { intensity, .. }, "intensity") => *intensity = value,
+            (PostProcessEffect::Vignette { radius, .. }, "radius") => *radius = value,
+            (PostProcessEffect::ColorGrading { contribution, .. }, "contribution") => *contribution = value,
+            _ => return false
+        }
+        true
+    }
+}
+
+///A named effect with the programs and intermediate targets it owns, deleted when the pass is dropped.
+#[derive(Debug)]
+pub struct PostProcessPass {
+    name: String,
+    enabled: bool,
+    effect: PostProcessEffect,
+    programs: Vec<Owned<Program>>,
+    ///Intermediate targets owned by the pass, e.g. the half resolution bloom buffers.
+    targets: Vec<RenderTarget>,
+}
+
+impl PostProcessPass {
+    pub fn new<T: ToString>(name: T, effect: PostProcessEffect) -> Result<PostProcessPass, Box<dyn Error>> {
+        let programs = effect.programs()?;
+        Ok(Self {
+            name: name.to_string(),
+            enabled: true,
+            effect,
+            programs,
+            targets: vec![],
+        })
+    }
+
+    ///Creates a pass from a fragment shader, linked against the fullscreen vertex shader.
+    pub fn custom<T: ToString>(name: T, fragment_shader: FragmentShader) -> Result<PostProcessPass, Box<dyn Error>> {
+        let program = Program::new(fragment_shader, VertexShader::new(POST_VERTEX))?.owned();
+        Ok(Self {
+            name: name.to_string(),
+            enabled: true,
+            effect: PostProcessEffect::Custom,
+            programs: vec![program],
+            targets: vec![],
+        })
+    }
+
+    fn resize(&mut self, size: [i32; 2]) -> Result<(), PostProcessError> {
+        self.targets.clear();
+        if let PostProcessEffect::Bloom { .. } = self.effect {
+            let half = [size[0] / 2, size[1] / 2];
+            for _ in 0..2 {
+                self.targets.push(RenderTarget::new(half[0], half[1], POST_COLOR_FORMAT, false)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn apply(&self, source: &Texture, destination: Option<&RenderTarget>, window_viewport: [i32; 4]) {
+        let resolution = [source.width() as f32, source.height() as f32];
+        match &self.effect {
+            PostProcessEffect::Bloom { threshold, intensity, iterations } => {
+                let (extract, blur, combine) = (&self.programs[0], &self.programs[1], &self.programs[2]);
+
+                self.targets[0].bind();
+                extract.enable();
+                bind_screen_texture(extract, source);
+                extract.set_uniform_float("threshold", threshold);
+                draw_fullscreen_quad();
+
+                blur.enable();
+                let mut horizontal = true;
+                for _ in 0..iterations * 2 {
+                    let (from, to) = match horizontal {
+                        true => (&self.targets[0], &self.targets[1]),
+                        false => (&self.targets[1], &self.targets[0])
+                    };
+                    to.bind();
+                    bind_screen_texture(blur, from.texture());
+                    blur.set_uniform_bool("horizontal", &horizontal);
+                    draw_fullscreen_quad();
+                    horizontal = !horizontal;
+                }
+
+                bind_destination(destination, window_viewport);
+                combine.enable();
+                bind_screen_texture(combine, source);
+                bind_texture_unit(combine, "bloomTexture", 1, self.targets[0].texture());
+                combine.set_uniform_float("intensity", intensity);
+                draw_fullscreen_quad();
+            }
+            effect => {
+                let program = match self.programs.first() {
+                    Some(program) => program,
+                    None => return
+                };
+                bind_destination(destination, window_viewport);
+                program.enable();
+                bind_screen_texture(program, source);
+                program.set_uniform_vec2("resolution", &resolution);
+                match effect {
+                    PostProcessEffect::Tonemapping { exposure } => {
+                        program.set_uniform_float("exposure", exposure);
+                    }
+                    PostProcessEffect::GammaCorrection { gamma } => {
+                        program.set_uniform_float("gamma", gamma);
+                    }
+                    PostProcessEffect::Vignette { intensity, radius } => {
+                        program.set_uniform_float("intensity", intensity);
+                        program.set_uniform_float("radius", radius);
+                    }
+                    PostProcessEffect::ColorGrading { lut, lut_size, contribution } => {
+                        bind_texture_unit(program, "lutTexture", 1, lut);
+                        program.set_uniform_float("lutSize", lut_size);
+                        program.set_uniform_float("contribution", contribution);
+                    }
+                    _ => {}
+                }
+                draw_fullscreen_quad();
+            }
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+    pub fn effect(&self) -> &PostProcessEffect {
+        &self.effect
+    }
+    pub fn effect_mut(&mut self) -> &mut PostProcessEffect {
+        &mut self.effect
+    }
+    pub fn programs(&self) -> &Vec<Owned<Program>> {
+        &self.programs
+    }
+}
+
+///An ordered chain of post processing passes, run between the scene and the UI.
+///
+///The scene is rendered into an offscreen target, then each enabled pass reads the previous result
+///and writes into the other of two ping-pong targets. The last enabled pass writes to the window.
+#[derive(Debug, Default)]
+pub struct PostProcessStack {
+    passes: Vec<PostProcessPass>,
+    targets: Vec<RenderTarget>,
+    size: [i32; 2],
+    window_viewport: [i32; 4],
+}
+
+impl PostProcessStack {
+    pub fn new() -> PostProcessStack {
+        Self::default()
+    }
+
+    pub fn push(&mut self, pass: PostProcessPass) -> Result<(), PostProcessError> {
+        if self.index_of(pass.name()).is_some() {
+            return Err(PostProcessError::DuplicatePass(pass.name().to_string()));
+        }
+        self.passes.push(pass);
+        //Force the pass targets to be created on the next frame.
+        self.size = [0; 2];
+        Ok(())
+    }
+
+    pub fn with_pass(mut self, pass: PostProcessPass) -> Result<PostProcessStack, PostProcessError> {
+        self.push(pass)?;
+        Ok(self)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<PostProcessPass, PostProcessError> {
+        let index = self.find(name)?;
+        Ok(self.passes.remove(index))
+    }
+
+    ///Moves the pass to `index` in the chain, clamped to the number of passes.
+    pub fn move_pass(&mut self, name: &str, index: usize) -> Result<(), PostProcessError> {
+        let from = self.find(name)?;
+        let pass = self.passes.remove(from);
+        let index = index.min(self.passes.len());
+        self.passes.insert(index, pass);
+        Ok(())
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<(), PostProcessError> {
+        self.pass_mut(name)?.enabled = enabled;
+        Ok(())
+    }
+
+    pub fn toggle(&mut self, name: &str) -> Result<bool, PostProcessError> {
+        let pass = self.pass_mut(name)?;
+        pass.enabled = !pass.enabled;
+        Ok(pass.enabled)
+    }
+
+    pub fn set_parameter(&mut self, name: &str, parameter: &str, value: f32) -> Result<(), PostProcessError> {
+        let pass = self.pass_mut(name)?;
+        match pass.effect.set_parameter(parameter, value) {
+            true => Ok(()),
+            false => Err(PostProcessError::UnknownParameter(name.to_string(), parameter.to_string()))
+        }
+    }
+
+    pub fn pass(&self, name: &str) -> Result<&PostProcessPass, PostProcessError> {
+        let index = self.find(name)?;
+        Ok(&self.passes[index])
+    }
+
+    pub fn pass_mut(&mut self, name: &str) -> Result<&mut PostProcessPass, PostProcessError> {
+        let index = self.find(name)?;
+        Ok(&mut self.passes[index])
+    }
+
+    pub fn passes(&self) -> &Vec<PostProcessPass> {
+        &self.passes
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.passes.iter().map(|pass| pass.name.clone()).collect()
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.passes.iter().position(|pass| pass.name == name)
+    }
+
+    fn find(&self, name: &str) -> Result<usize, PostProcessError> {
+        self.index_of(name).ok_or_else(|| PostProcessError::UnknownPass(name.to_string()))
+    }
+
+    ///True if at least one pass is enabled, i.e. the scene must be rendered offscreen.
+    pub fn is_active(&self) -> bool {
+        self.passes.iter().any(|pass| pass.enabled)
+    }
+
+    fn resize(&mut self, size: [i32; 2]) -> Result<(), PostProcessError> {
+        self.targets.clear();
+        for _ in 0..2 {
+            self.targets.push(RenderTarget::new(size[0], size[1], POST_COLOR_FORMAT, true)?);
+        }
+        for pass in &mut self.passes {
+            pass.resize(size)?;
+        }
+        self.size = size;
+        Ok(())
+    }
+
+    ///Redirects rendering into the offscreen scene target. Call before the scene is cleared and drawn.
+    pub fn begin(&mut self, window_viewport: [i32; 4]) -> Result<(), PostProcessError> {
+        if !self.is_active() {
+            return Ok(());
+        }
+        self.window_viewport = window_viewport;
+        let size = [window_viewport[2], window_viewport[3]];
+        if size != self.size || self.targets.is_empty() {
+            self.resize(size)?;
+        }
+        self.targets[0].bind();
+        Ok(())
+    }
+
+    ///Runs every enabled pass in order. The final pass is drawn into the window's framebuffer.
+    pub fn end(&self) {
+        if !self.is_active() || self.targets.is_empty() {
+            return;
+        }
+        disable(GLConsts::DepthTest);
+        disable(GLConsts::CullFace);
+
+        let enabled = self.passes.iter().filter(|pass| pass.enabled).collect::<Vec<&PostProcessPass>>();
+        let mut current = 0;
+        for (index, pass) in enabled.iter().enumerate() {
+            let source = self.targets[current].texture();
+            let destination = match index + 1 == enabled.len() {
+                true => None,
+                false => Some(&self.targets[1 - current])
+            };
+            pass.apply(source, destination, self.window_viewport);
+            current = 1 - current;
+        }
+        active_texture(0);
+        Program::current_program(|program| program.disable());
+        bind_destination(None, self.window_viewport);
+    }
+
+    pub fn targets(&self) -> &Vec<RenderTarget> {
+        &self.targets
+    }
+}
+
+fn bind_destination(destination: Option<&RenderTarget>, window_viewport: [i32; 4]) {
+    match destination {
+        Some(target) => target.bind(),
+        None => {
+            unbind_framebuffer(FramebufferTarget::Framebuffer);
+            viewport([window_viewport[0], window_viewport[1]], [window_viewport[2], window_viewport[3]]);
+        }
+    }
+}
+
+fn bind_screen_texture(program: &Program, texture: &Texture) {
+    bind_texture_unit(program, "screenTexture", 0, texture);
+}
+
+fn bind_texture_unit(program: &Program, name: &str, unit: u32, texture: &Texture) {
+    active_texture(unit);
+    texture.bind();
+    program.set_uniform_int(name, &(unit as i32));
+}
+
+pub fn draw_fullscreen_quad() {
+    unsafe {
+        if FULLSCREEN_QUAD.is_none() {
+            FULLSCREEN_QUAD = Some(
+                VertexArrayObject::new(Some(VertexArrayObjectType::ArrayStrips(4)))
+                    .with_buffer(VertexBufferObject::array(ArrayBuffer, StaticDraw, QUAD))
+                    .with_local_attrib_pointers(vec![
+                        LocalAttribPointer::new(2, Float, false),
+                        LocalAttribPointer::new(2, Float, false),
+                    ])
+                    .build()
+            );
+        }
+        if let Some(quad) = &FULLSCREEN_QUAD {
+            quad.bind();
+            draw_arrays(DrawMode::TriangleStrip, 0, 4);
+            quad.unbind();
+        }
+    }
+}
+
+///Deletes the quad `draw_fullscreen_quad` creates on first use. Called by the app on shutdown.
+pub unsafe fn release_fullscreen_quad() {
+    FULLSCREEN_QUAD = None;
+}
@@ -0,0 +1,86 @@
+use crate::gfx::bindings::{ColorFormat, FramebufferAttachment, FramebufferTarget, RenderbufferFormat, TextureTarget};
+use crate::gfx::bindings::framebuffers::{bind_framebuffer, bind_renderbuffer, check_framebuffer_status, delete_framebuffer, delete_renderbuffer, framebuffer_renderbuffer, framebuffer_texture_2d, gen_framebuffers, gen_renderbuffers, renderbuffer_storage, unbind_framebuffer};
+use crate::gfx::bindings::graphics::viewport;
+use crate::gfx::post_processing::PostProcessError;
+use crate::gfx::resource::{GlObject, Owned};
+use crate::gfx::texture::Texture;
+
+///An offscreen framebuffer with a single color texture and an optional depth/stencil renderbuffer.
+///Everything is deleted when the target is dropped.
+#[derive(Debug)]
+pub struct RenderTarget {
+    fbo: u32,
+    rbo: Option<u32>,
+    texture: Owned<Texture>,
+    width: i32,
+    height: i32,
+}
+
+impl RenderTarget {
+    pub fn new(width: i32, height: i32, color_format: ColorFormat, depth: bool) -> Result<RenderTarget, PostProcessError> {
+        let width = width.max(1);
+        let height = height.max(1);
+        let fbo = gen_framebuffers(1);
+        bind_framebuffer(FramebufferTarget::Framebuffer, fbo);
+
+        let texture = Texture::render_target(width, height, color_format).owned();
+        framebuffer_texture_2d(FramebufferTarget::Framebuffer, FramebufferAttachment::ColorAttachment0, TextureTarget::Texture2D, texture.id(), 0);
+
+        let rbo = match depth {
+            true => {
+                let rbo = gen_renderbuffers(1);
+                bind_renderbuffer(rbo);
+                renderbuffer_storage(RenderbufferFormat::Depth24Stencil8, width, height);
+                framebuffer_renderbuffer(FramebufferTarget::Framebuffer, FramebufferAttachment::DepthStencilAttachment, rbo);
+                bind_renderbuffer(0);
+                Some(rbo)
+            }
+            false => None
+        };
+
+        let complete = check_framebuffer_status(FramebufferTarget::Framebuffer);
+        unbind_framebuffer(FramebufferTarget::Framebuffer);
+
+        let target = Self { fbo, rbo, texture, width, height };
+        if !complete {
+            //Dropping the unfinished target deletes what was created.
+            return Err(PostProcessError::IncompleteFramebuffer(width, height));
+        }
+        Ok(target)
+    }
+
+    ///Binds the framebuffer and sets the viewport to cover it.
+    pub fn bind(&self) {
+        bind_framebuffer(FramebufferTarget::Framebuffer, self.fbo);
+        viewport([0, 0], [self.width, self.height]);
+    }
+
+    pub fn unbind(&self) {
+        unbind_framebuffer(FramebufferTarget::Framebuffer);
+    }
+
+    pub fn id(&self) -> u32 {
+        self.fbo
+    }
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+    pub fn size(&self) -> [i32; 2] {
+        [self.width, self.height]
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        delete_framebuffer(self.fbo);
+        if let Some(rbo) = self.rbo {
+            delete_renderbuffer(rbo);
+        }
+    }
+}
@@ -1,14 +1,316 @@
-use crate::{OBJ, VertexArrayObject};
-
-const CUBE_OBJ: &'static str = include_str!("cube/cube.obj");
-const CUBE_MAT: &'static str = include_str!("cube/cube.mtl");
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+use std::collections::HashMap;
+use crate::VertexArrayObject;
+use crate::gfx::mesh::Mesh;
+use crate::math::linear_algebra::types::{Vec2, Vec3};
 
+///Shapes generated in code, centered on the origin with y up. Front faces wind counter-clockwise,
+///normals point outwards and uvs run along the surface from 0 to 1, wrapping once around round shapes.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Primitive {
-    Cube
+    ///A box with a separate vertex per face so every face is flat and mapped to the full texture.
+    Cube { size: Vec3 },
+    ///Latitude and longitude lines, with `rings` bands between the poles.
+    UvSphere { radius: f32, segments: u32, rings: u32 },
+    ///An icosahedron split `subdivisions` times, spreading the triangles evenly over the sphere.
+    Icosphere { radius: f32, subdivisions: u32 },
+    ///A grid in the xz plane facing up, split into `subdivisions` quads along x and z.
+    Plane { width: f32, depth: f32, subdivisions: [u32; 2] },
+    Cylinder { radius: f32, height: f32, segments: u32 },
+    ///A cone with its base at the bottom and its tip at the top.
+    Cone { radius: f32, height: f32, segments: u32 },
+    ///A cylinder capped by hemispheres of `rings` bands each. `height` includes the caps.
+    Capsule { radius: f32, height: f32, segments: u32, rings: u32 },
+    ///A ring of `radius` around y swept by a tube of `tube_radius`.
+    Torus { radius: f32, tube_radius: f32, segments: u32, tube_segments: u32 },
+    ///A single rectangle in the xy plane facing +z.
+    Quad { width: f32, height: f32 },
+}
+
+///A ring of vertices around the y axis, part of a surface of revolution. A ring of radius zero is a pole.
+#[derive(Debug, Clone, Copy)]
+struct Ring {
+    radius: f32,
+    y: f32,
+    ///Normal as its distance from and height along the y axis.
+    normal: Vec2,
+    v: f32,
+}
+
+///Accumulates the streams of a primitive.
+#[derive(Debug, Default)]
+struct Geometry {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+    indices: Vec<u32>,
+}
+
+impl Geometry {
+    fn vertex(&mut self, position: Vec3, normal: Vec3, uv: Vec2) -> u32 {
+        self.positions.push(position);
+        self.normals.push(normal);
+        self.uvs.push(uv);
+        (self.positions.len() - 1) as u32
+    }
+
+    fn triangle(&mut self, a: u32, b: u32, c: u32) {
+        self.indices.extend_from_slice(&[a, b, c]);
+    }
+
+    ///A flat rectangle from `corner` spanned by `u` and `v`, facing `cross(u, v)`.
+    fn quad(&mut self, corner: Vec3, u: Vec3, v: Vec3, normal: Vec3, subdivisions: [u32; 2]) {
+        let [columns, rows] = subdivisions.map(|s| s.max(1));
+        let start = self.positions.len() as u32;
+        for j in 0..=rows {
+            for i in 0..=columns {
+                let (s, t) = (i as f32 / columns as f32, j as f32 / rows as f32);
+                let position = [0, 1, 2].map(|k| corner[k] + u[k] * s + v[k] * t);
+                self.vertex(position, normal, [s, t]);
+            }
+        }
+        let index = |i: u32, j: u32| start + j * (columns + 1) + i;
+        for j in 0..rows {
+            for i in 0..columns {
+                self.triangle(index(i, j), index(i + 1, j), index(i + 1, j + 1));
+                self.triangle(index(i, j), index(i + 1, j + 1), index(i, j + 1));
+            }
+        }
+    }
+
+    ///Sweeps `rings`, ordered from top to bottom, around the y axis. Longitude runs counter-clockwise seen from above,
+    ///starting at +x. Poles get a vertex per segment, turned half a segment so their uvs sit between the ring's.
+    fn lathe(&mut self, rings: &[Ring], segments: u32) {
+        let step = TAU / segments as f32;
+        let mut starts = Vec::with_capacity(rings.len());
+        for ring in rings {
+            let pole = ring.radius == 0.0;
+            starts.push(self.positions.len() as u32);
+            let count = if pole { segments } else { segments + 1 };
+            for s in 0..count {
+                let angle = if pole { (s as f32 + 0.5) * step } else { s as f32 * step };
+                let (sin, cos) = angle.sin_cos();
+                self.vertex(
+                    [ring.radius * cos, ring.y, -ring.radius * sin],
+                    [ring.normal[0] * cos, ring.normal[1], -ring.normal[0] * sin],
+                    [angle / TAU, ring.v],
+                );
+            }
+        }
+        for (band, pair) in rings.windows(2).enumerate() {
+            let (top, bottom) = (starts[band], starts[band + 1]);
+            for s in 0..segments {
+                match (pair[0].radius == 0.0, pair[1].radius == 0.0) {
+                    (true, true) => {}
+                    (true, false) => self.triangle(top + s, bottom + s, bottom + s + 1),
+                    (false, true) => self.triangle(top + s, bottom + s, top + s + 1),
+                    (false, false) => {
+                        self.triangle(top + s, bottom + s, top + s + 1);
+                        self.triangle(top + s + 1, bottom + s, bottom + s + 1);
+                    }
+                }
+            }
+        }
+    }
+
+    ///A flat disc at height `y` facing up or down, mapped onto the texture as seen from its front.
+    fn disc(&mut self, radius: f32, y: f32, up: bool, segments: u32) {
+        let normal = if up { [0.0, 1.0, 0.0] } else { [0.0, -1.0, 0.0] };
+        //Seen from below, +x is on the left.
+        let side = if up { 1.0 } else { -1.0 };
+        let center = self.vertex([0.0, y, 0.0], normal, [0.5, 0.5]);
+        for s in 0..segments {
+            let (sin, cos) = (s as f32 * TAU / segments as f32).sin_cos();
+            self.vertex([radius * cos, y, -radius * sin], normal, [0.5 + side * cos * 0.5, 0.5 + sin * 0.5]);
+        }
+        for s in 0..segments {
+            let (a, b) = (center + 1 + s, center + 1 + (s + 1) % segments);
+            match up {
+                true => self.triangle(center, a, b),
+                false => self.triangle(center, b, a)
+            }
+        }
+    }
+
+    fn build(self) -> Mesh {
+        Mesh::new(self.positions, self.indices).with_normals(self.normals).with_uvs(self.uvs)
+    }
 }
 
 impl Primitive {
+    ///The 2x2x2 cube drawn by the debug views.
     pub fn cube() -> VertexArrayObject {
-        OBJ::from_raw(CUBE_OBJ.to_string()).unwrap().objects()[0].build_vao("Material").unwrap()
+        Primitive::Cube { size: [2.0; 3] }.build_vao()
+    }
+
+    pub fn mesh(&self) -> Mesh {
+        let mut geometry = Geometry::default();
+        match *self {
+            Primitive::Cube { size } => {
+                let half = size.map(|s| s * 0.5);
+                //Normal, then the axes along u and v of each face.
+                let faces: [[Vec3; 3]; 6] = [
+                    [[1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]],
+                    [[-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]],
+                    [[0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]],
+                    [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+                    [[0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+                    [[0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+                ];
+                for [normal, u, v] in faces {
+                    let corner = [0, 1, 2].map(|k| (normal[k] - u[k] - v[k]) * half[k]);
+                    let (u, v) = ([0, 1, 2].map(|k| u[k] * size[k]), [0, 1, 2].map(|k| v[k] * size[k]));
+                    geometry.quad(corner, u, v, normal, [1, 1]);
+                }
+            }
+            Primitive::UvSphere { radius, segments, rings } => {
+                let rings = rings.max(2);
+                let sphere = (0..=rings).map(|r| {
+                    let (sin, cos) = (PI * r as f32 / rings as f32).sin_cos();
+                    let pole = r == 0 || r == rings;
+                    Ring { radius: if pole { 0.0 } else { radius * sin }, y: radius * cos, normal: [sin, cos], v: 1.0 - r as f32 / rings as f32 }
+                }).collect::<Vec<Ring>>();
+                geometry.lathe(&sphere, segments.max(3));
+            }
+            Primitive::Icosphere { radius, subdivisions } => {
+                geometry = Self::icosphere(radius, subdivisions);
+            }
+            Primitive::Plane { width, depth, subdivisions } => {
+                geometry.quad([-width * 0.5, 0.0, depth * 0.5], [width, 0.0, 0.0], [0.0, 0.0, -depth], [0.0, 1.0, 0.0], subdivisions);
+            }
+            Primitive::Cylinder { radius, height, segments } => {
+                let segments = segments.max(3);
+                let half = height * 0.5;
+                geometry.lathe(&[
+                    Ring { radius, y: half, normal: [1.0, 0.0], v: 1.0 },
+                    Ring { radius, y: -half, normal: [1.0, 0.0], v: 0.0 },
+                ], segments);
+                geometry.disc(radius, half, true, segments);
+                geometry.disc(radius, -half, false, segments);
+            }
+            Primitive::Cone { radius, height, segments } => {
+                let segments = segments.max(3);
+                let half = height * 0.5;
+                let slope = (height * height + radius * radius).sqrt().max(f32::EPSILON);
+                let normal = [height / slope, radius / slope];
+                geometry.lathe(&[
+                    Ring { radius: 0.0, y: half, normal, v: 1.0 },
+                    Ring { radius, y: -half, normal, v: 0.0 },
+                ], segments);
+                geometry.disc(radius, -half, false, segments);
+            }
+            Primitive::Capsule { radius, height, segments, rings } => {
+                let rings = rings.max(1);
+                let height = height.max(radius * 2.0);
+                let offset = height * 0.5 - radius;
+                //Hemisphere bands with a cylinder between the two rings at the equator.
+                let capsule = (0..=rings * 2 + 1).map(|r| {
+                    let (hemisphere, r) = if r <= rings { (offset, r) } else { (-offset, r - 1) };
+                    let (sin, cos) = (FRAC_PI_2 * r as f32 / rings as f32).sin_cos();
+                    let pole = r == 0 || r == rings * 2;
+                    let y = hemisphere + radius * cos;
+                    Ring { radius: if pole { 0.0 } else { radius * sin }, y, normal: [sin, cos], v: 0.5 + y / height }
+                }).collect::<Vec<Ring>>();
+                geometry.lathe(&capsule, segments.max(3));
+            }
+            Primitive::Torus { radius, tube_radius, segments, tube_segments } => {
+                let (segments, tube_segments) = (segments.max(3), tube_segments.max(3));
+                for i in 0..=segments {
+                    let (sin, cos) = (TAU * i as f32 / segments as f32).sin_cos();
+                    for j in 0..=tube_segments {
+                        let (tube_sin, tube_cos) = (TAU * j as f32 / tube_segments as f32).sin_cos();
+                        let normal = [tube_cos * cos, tube_sin, -tube_cos * sin];
+                        let position = [
+                            radius * cos + tube_radius * normal[0],
+                            tube_radius * normal[1],
+                            -radius * sin + tube_radius * normal[2],
+                        ];
+                        geometry.vertex(position, normal, [i as f32 / segments as f32, j as f32 / tube_segments as f32]);
+                    }
+                }
+                let index = |i: u32, j: u32| i * (tube_segments + 1) + j;
+                for i in 0..segments {
+                    for j in 0..tube_segments {
+                        geometry.triangle(index(i, j), index(i + 1, j), index(i + 1, j + 1));
+                        geometry.triangle(index(i, j), index(i + 1, j + 1), index(i, j + 1));
+                    }
+                }
+            }
+            Primitive::Quad { width, height } => {
+                geometry.quad([-width * 0.5, -height * 0.5, 0.0], [width, 0.0, 0.0], [0.0, height, 0.0], [0.0, 0.0, 1.0], [1, 1]);
+            }
+        }
+        geometry.build()
+    }
+
+    fn icosphere(radius: f32, subdivisions: u32) -> Geometry {
+        let t = (1.0 + 5.0f32.sqrt()) * 0.5;
+        let mut points: Vec<Vec3> = vec![
+            [-1.0, t, 0.0], [1.0, t, 0.0], [-1.0, -t, 0.0], [1.0, -t, 0.0],
+            [0.0, -1.0, t], [0.0, 1.0, t], [0.0, -1.0, -t], [0.0, 1.0, -t],
+            [t, 0.0, -1.0], [t, 0.0, 1.0], [-t, 0.0, -1.0], [-t, 0.0, 1.0],
+        ].into_iter().map(unit).collect();
+        let mut faces: Vec<[usize; 3]> = vec![
+            [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+            [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+            [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+            [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+        ];
+        for _ in 0..subdivisions {
+            let mut middles: HashMap<(usize, usize), usize> = HashMap::new();
+            let mut middle = |a: usize, b: usize, points: &mut Vec<Vec3>| *middles.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                let (pa, pb) = (points[a], points[b]);
+                points.push(unit([pa[0] + pb[0], pa[1] + pb[1], pa[2] + pb[2]]));
+                points.len() - 1
+            });
+            faces = faces.into_iter().flat_map(|[a, b, c]| {
+                let (ab, bc, ca) = (middle(a, b, &mut points), middle(b, c, &mut points), middle(c, a, &mut points));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            }).collect();
+        }
+
+        //Spherical uvs, matching the longitude of `UvSphere`. Triangles across the seam wrap their u past 1
+        //and corners on a pole take the u of the triangle, so both get their own vertices.
+        let longitude = |p: Vec3| {
+            let u = (-p[2]).atan2(p[0]) / TAU;
+            if u < 0.0 { u + 1.0 } else { u }
+        };
+        let mut geometry = Geometry::default();
+        let mut vertices: HashMap<(usize, u32), u32> = HashMap::new();
+        for face in faces {
+            let on_pole = face.map(|i| points[i][0].abs() < 1e-6 && points[i][2].abs() < 1e-6);
+            let mut u = face.map(|i| longitude(points[i]));
+            let spanned = (0..3).filter(|k| !on_pole[*k]).map(|k| u[k]).collect::<Vec<f32>>();
+            let (min, max) = spanned.iter().fold((f32::MAX, f32::MIN), |(min, max), u| (min.min(*u), max.max(*u)));
+            for k in 0..3 {
+                if max - min > 0.5 && !on_pole[k] && u[k] < 0.5 {
+                    u[k] += 1.0;
+                }
+            }
+            for k in 0..3 {
+                if on_pole[k] {
+                    let others = (0..3).filter(|o| !on_pole[*o]).map(|o| u[o]).collect::<Vec<f32>>();
+                    u[k] = others.iter().sum::<f32>() / others.len().max(1) as f32;
+                }
+            }
+            let corners = [0, 1, 2].map(|k| {
+                let point = points[face[k]];
+                *vertices.entry((face[k], u[k].to_bits())).or_insert_with(|| {
+                    let v = 0.5 + point[1].clamp(-1.0, 1.0).asin() / PI;
+                    geometry.vertex(point.map(|x| x * radius), point, [u[k], v])
+                })
+            });
+            geometry.triangle(corners[0], corners[1], corners[2]);
+        }
+        geometry
+    }
+
+    pub fn build_vao(&self) -> VertexArrayObject {
+        self.mesh().build_vao()
     }
-}
\ No newline at end of file
+}
+
+fn unit(v: Vec3) -> Vec3 {
+    let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    [v[0] / length, v[1] / length, v[2] / length]
+}
@@ -1,20 +1,37 @@
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
-use vecmath::{Matrix4, Vector2, Vector4};
-use crate::gfx::bindings::IV;
-use crate::gfx::bindings::program::{attach_shader, create_program, disable_program, link_program, program_iv, use_program};
-use crate::gfx::bindings::uniforms::{uniform_1f, uniform_1i, uniform_1ui, uniform_2fv, uniform_4fv, uniform_location, uniform_matrix4fv};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+use vecmath::{Matrix4, Vector2, Vector3, Vector4};
+use crate::app::AppConfigFlags;
+use crate::gfx::bindings::{IV, ShaderType};
+use crate::gfx::bindings::compute::dispatch_compute;
+use crate::gfx::bindings::program::{attach_shader, attached_shaders, create_program, delete_program, detach_shader_raw, disable_program, link_program, program_iv, use_program};
+use crate::gfx::bindings::uniforms::{uniform_1f, uniform_1i, uniform_1ui, uniform_2fv, uniform_3fv, uniform_4fv, uniform_matrix4fv};
+use crate::gfx::reflection::{ProgramReflection, UniformType};
+use crate::gfx::shader::preprocessor::{Preprocessor, ProcessedShader};
 use crate::gfx::shader::Shader;
-use super::shader::{fragment_shader::FragmentShader, vertex_shader::VertexShader};
+use super::shader::{compute_shader::ComputeShader, fragment_shader::FragmentShader, geometry_shader::GeometryShader, tess_control_shader::TessControlShader, tess_evaluation_shader::TessEvaluationShader, vertex_shader::VertexShader};
 
+///A view of the enabled program's name, never deleted as it isn't `Owned`.
 static mut CURRENT_PROGRAM: Option<Program> = None;
+static mut SOURCES: Option<HashMap<u32, ProgramSources>> = None;
+static mut LAST_RELOAD_CHECK: Option<Instant> = None;
+static mut REFLECTIONS: Option<HashMap<u32, ProgramReflection>> = None;
 
-#[derive(Debug, Clone, Copy)]
+const RELOAD_INTERVAL: Duration = Duration::from_millis(500);
+
+///A program name. Not `Clone`, holders keep it in an `Owned` or `Shared`, which delete it on drop.
+#[derive(Debug)]
 pub struct Program(u32);
 
 #[derive(Debug, Clone)]
 pub enum ProgramError {
-    ProgramLinkError(String)
+    ProgramLinkError(String),
+    ///The stage needs a context created with the flag.
+    StageUnavailable(ShaderType, AppConfigFlags),
+    InvalidStages(String),
 }
 
 impl Display for ProgramError {
@@ -23,35 +40,235 @@ impl Display for ProgramError {
             ProgramError::ProgramLinkError(link) => {
                 f.write_fmt(format_args!("Link error: {}", link))
             }
+            ProgramError::StageUnavailable(stage, flag) => {
+                f.write_fmt(format_args!("{:?} requires the window to be created with AppConfigFlags::{:?}", stage, flag))
+            }
+            ProgramError::InvalidStages(reason) => {
+                f.write_fmt(format_args!("Invalid stage set: {}", reason))
+            }
         }
     }
 }
 
 impl Error for ProgramError {}
 
-impl Program {
-    pub fn new(fragment_shader: FragmentShader, vertex_shader: VertexShader) -> Result<Program, Box<dyn Error>> {
-        let id = create_program();
-        let program = Program(id);
+///Links any set of shader stages: a compute shader alone, or a vertex and a fragment shader with
+///optional geometry and tessellation stages.
+#[derive(Debug, Default)]
+pub struct ProgramBuilder {
+    vertex: Option<VertexShader>,
+    fragment: Option<FragmentShader>,
+    geometry: Option<GeometryShader>,
+    tess_control: Option<TessControlShader>,
+    tess_evaluation: Option<TessEvaluationShader>,
+    compute: Option<ComputeShader>,
+}
+
+impl ProgramBuilder {
+    pub fn new() -> ProgramBuilder {
+        Self::default()
+    }
+
+    pub fn with_vertex(mut self, shader: VertexShader) -> ProgramBuilder {
+        self.vertex = Some(shader);
+        self
+    }
+
+    pub fn with_fragment(mut self, shader: FragmentShader) -> ProgramBuilder {
+        self.fragment = Some(shader);
+        self
+    }
+
+    pub fn with_geometry(mut self, shader: GeometryShader) -> ProgramBuilder {
+        self.geometry = Some(shader);
+        self
+    }
+
+    ///The control stage is optional, a tessellation program only needs the evaluation stage.
+    pub fn with_tess_control(mut self, shader: TessControlShader) -> ProgramBuilder {
+        self.tess_control = Some(shader);
+        self
+    }
+
+    pub fn with_tess_evaluation(mut self, shader: TessEvaluationShader) -> ProgramBuilder {
+        self.tess_evaluation = Some(shader);
+        self
+    }
+
+    pub fn with_compute(mut self, shader: ComputeShader) -> ProgramBuilder {
+        self.compute = Some(shader);
+        self
+    }
 
-        if let Err(error) = fragment_shader.compilation_status() {
-            return Err(Box::new(error));
+    fn stages(&self) -> Vec<&dyn Shader> {
+        let mut stages: Vec<&dyn Shader> = vec![];
+        if let Some(shader) = &self.vertex {
+            stages.push(shader);
+        }
+        if let Some(shader) = &self.tess_control {
+            stages.push(shader);
+        }
+        if let Some(shader) = &self.tess_evaluation {
+            stages.push(shader);
+        }
+        if let Some(shader) = &self.geometry {
+            stages.push(shader);
         }
-        if let Err(error) = vertex_shader.compilation_status() {
-            return Err(Box::new(error));
+        if let Some(shader) = &self.fragment {
+            stages.push(shader);
         }
+        if let Some(shader) = &self.compute {
+            stages.push(shader);
+        }
+        stages
+    }
 
-        attach_shader(&program, &vertex_shader);
-        attach_shader(&program, &fragment_shader);
+    fn validate(&self) -> Result<(), ProgramError> {
+        let graphics = self.vertex.is_some() || self.fragment.is_some() || self.geometry.is_some()
+            || self.tess_control.is_some() || self.tess_evaluation.is_some();
+        if self.compute.is_some() {
+            if graphics {
+                return Err(ProgramError::InvalidStages("a compute shader can not be linked with other stages".to_string()));
+            }
+            return Ok(());
+        }
+        if self.vertex.is_none() || self.fragment.is_none() {
+            return Err(ProgramError::InvalidStages("a vertex and a fragment shader are required".to_string()));
+        }
+        if self.tess_control.is_some() && self.tess_evaluation.is_none() {
+            return Err(ProgramError::InvalidStages("a tessellation control shader needs an evaluation shader".to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn build(self) -> Result<Program, Box<dyn Error>> {
+        self.validate()?;
+        let stages = self.stages();
+        for stage in &stages {
+            if let Some(flag) = stage.shader_type().required_flag() {
+                if !flag.enabled() {
+                    return Err(Box::new(ProgramError::StageUnavailable(stage.shader_type(), flag)));
+                }
+            }
+            if let Err(error) = stage.compilation_status() {
+                return Err(Box::new(error));
+            }
+        }
+
+        let program = Program(create_program());
+        for stage in &stages {
+            attach_shader(&program, *stage);
+        }
         link_program(&program);
 
         let status = program.link_status();
         if !status {
             let status = program.status();
+            delete_program(&program);
             return Err(Box::new(ProgramError::ProgramLinkError(status.to_string())));
         }
         Ok(program)
     }
+}
+
+///The shader files a program was built from, so it can be rebuilt when they change on disk.
+#[derive(Debug, Clone)]
+pub struct ProgramSources {
+    preprocessor: Preprocessor,
+    vertex: String,
+    fragment: String,
+    ///Every file on disk the two stages were assembled from, includes too.
+    files: Vec<PathBuf>,
+    ///Latest modification of `files` when the program was last built.
+    modified: Option<SystemTime>,
+}
+
+impl ProgramSources {
+    pub fn new<V: ToString, F: ToString>(preprocessor: Preprocessor, vertex: V, fragment: F) -> ProgramSources {
+        Self {
+            preprocessor,
+            vertex: vertex.to_string(),
+            fragment: fragment.to_string(),
+            files: vec![],
+            modified: None,
+        }
+    }
+
+    ///Sources of two already processed stages, watching the files they were assembled from.
+    pub fn from_processed<V: ToString, F: ToString>(preprocessor: Preprocessor, vertex: V, fragment: F, stages: [&ProcessedShader; 2]) -> ProgramSources {
+        let mut sources = Self::new(preprocessor, vertex, fragment);
+        sources.set_files(stages);
+        sources.modified = sources.latest_modification();
+        sources
+    }
+
+    fn set_files(&mut self, stages: [&ProcessedShader; 2]) {
+        self.files.clear();
+        for stage in stages.iter() {
+            for file in stage.map().files() {
+                let path = self.preprocessor.root().join(file);
+                if path.exists() && !self.files.contains(&path) {
+                    self.files.push(path);
+                }
+            }
+        }
+    }
+
+    fn latest_modification(&self) -> Option<SystemTime> {
+        self.files.iter()
+            .filter_map(|file| std::fs::metadata(file).and_then(|metadata| metadata.modified()).ok())
+            .max()
+    }
+
+    fn changed(&self) -> bool {
+        self.latest_modification() > self.modified
+    }
+
+    ///Preprocesses and compiles both stages, picking up includes that were added or removed.
+    fn build(&mut self) -> Result<(VertexShader, FragmentShader), Box<dyn Error>> {
+        let vertex = self.preprocessor.process_file(&self.vertex)?;
+        let fragment = self.preprocessor.process_file(&self.fragment)?;
+        self.set_files([&vertex, &fragment]);
+        Ok((vertex.vertex()?, fragment.fragment()?))
+    }
+
+    pub fn preprocessor(&self) -> &Preprocessor {
+        &self.preprocessor
+    }
+    pub fn vertex(&self) -> &str {
+        &self.vertex
+    }
+    pub fn fragment(&self) -> &str {
+        &self.fragment
+    }
+    pub fn files(&self) -> &Vec<PathBuf> {
+        &self.files
+    }
+}
+
+impl Program {
+    pub fn new(fragment_shader: FragmentShader, vertex_shader: VertexShader) -> Result<Program, Box<dyn Error>> {
+        ProgramBuilder::new()
+            .with_vertex(vertex_shader)
+            .with_fragment(fragment_shader)
+            .build()
+    }
+
+    ///Enables the compute program and runs it over `groups` work groups. Follow with `memory_barrier`
+    ///before reading what it wrote.
+    pub fn dispatch(&self, groups: [u32; 3]) {
+        self.enable();
+        dispatch_compute(groups);
+    }
+
+    ///Deletes the GL program and forgets its sources, reflection and preprocessor variant.
+    ///Copies of this program are invalid afterwards.
+    pub fn delete(&self) {
+        self.forget_sources();
+        self.forget_reflection();
+        Preprocessor::forget_variant(self);
+        delete_program(self);
+    }
 
     pub fn link_status(&self) -> bool {
         program_iv(self, IV::LinkStatus)
@@ -64,15 +281,44 @@ impl Program {
     }
     pub fn enable(&self) {
         use_program(self);
-        unsafe { CURRENT_PROGRAM = Some(self.clone()); }
+        unsafe { CURRENT_PROGRAM = Some(Program(self.0)); }
     }
     pub fn disable(&self) {
         unsafe { CURRENT_PROGRAM = None };
         disable_program()
     }
 
+    ///Cached location of `uniform`. Names the program doesn't use are reported once.
     pub fn uniform<T: ToString>(&self, uniform: T) -> i32 {
-        uniform_location(self, uniform)
+        self.locate(uniform, None)
+    }
+
+    fn locate<T: ToString>(&self, uniform: T, setter: Option<UniformType>) -> i32 {
+        let name = uniform.to_string();
+        unsafe {
+            let reflections = REFLECTIONS.get_or_insert_with(HashMap::new);
+            reflections.entry(self.0)
+                .or_insert_with(|| ProgramReflection::reflect(self))
+                .location(self, &name, setter)
+        }
+    }
+
+    ///Active uniforms, attributes and uniform blocks, queried once after linking.
+    pub fn reflection(&self) -> ProgramReflection {
+        unsafe {
+            REFLECTIONS.get_or_insert_with(HashMap::new)
+                .entry(self.0)
+                .or_insert_with(|| ProgramReflection::reflect(self))
+                .clone()
+        }
+    }
+
+    fn forget_reflection(&self) {
+        unsafe {
+            if let Some(reflections) = &mut REFLECTIONS {
+                reflections.remove(&self.0);
+            }
+        }
     }
 
     pub fn set_uniform_bool<T: ToString>(&self, uniform: T, value: &bool) {
@@ -81,39 +327,148 @@ impl Program {
             false => 0
         };
 
-        self.set_uniform_int(uniform, &value);
+        let location = self.locate(uniform, Some(UniformType::Bool));
+        uniform_1i(location, &value);
     }
 
     pub fn set_uniform_u_byte<T: ToString>(&self, uniform: T, value: &u8) {
-        let location = self.uniform(uniform);
+        let location = self.locate(uniform, Some(UniformType::UInt));
         uniform_1ui(location, &(*value as u32));
     }
 
     pub fn set_uniform_int<T: ToString>(&self, uniform: T, value: &i32) {
-        let location = self.uniform(uniform);
+        let location = self.locate(uniform, Some(UniformType::Int));
         uniform_1i(location, value);
     }
 
     pub fn set_uniform_float<T: ToString>(&self, uniform: T, value: &f32) {
-        let location = self.uniform(uniform);
+        let location = self.locate(uniform, Some(UniformType::Float));
         uniform_1f(location, value);
     }
 
     pub fn set_uniform_vec2<T: ToString>(&self, uniform: T, value: &Vector2<f32>) {
-        let location = self.uniform(uniform);
+        let location = self.locate(uniform, Some(UniformType::Vec2));
         uniform_2fv(location, 1, value);
     }
 
+    pub fn set_uniform_vec3<T: ToString>(&self, uniform: T, value: &Vector3<f32>) {
+        let location = self.locate(uniform, Some(UniformType::Vec3));
+        uniform_3fv(location, 1, value);
+    }
+
     pub fn set_uniform_vec4<T: ToString>(&self, uniform: T, value: &Vector4<f32>) {
-        let location = self.uniform(uniform);
+        let location = self.locate(uniform, Some(UniformType::Vec4));
         uniform_4fv(location, 1, value);
     }
 
     pub fn set_uniform_mat4<T: ToString>(&self, uniform: T, value: &Matrix4<f32>) {
-        let location = self.uniform(uniform);
+        let location = self.locate(uniform, Some(UniformType::Mat4));
         uniform_matrix4fv(location, 1, false, value);
     }
 
+    ///Remembers the files the program was built from, enabling `reload` and `reload_changed`.
+    pub fn watch(&self, sources: ProgramSources) {
+        unsafe {
+            SOURCES.get_or_insert_with(HashMap::new).insert(self.0, sources);
+        }
+    }
+
+    pub fn forget_sources(&self) {
+        unsafe {
+            if let Some(sources) = &mut SOURCES {
+                sources.remove(&self.0);
+            }
+        }
+    }
+
+    pub fn sources(&self) -> Option<ProgramSources> {
+        unsafe {
+            match &SOURCES {
+                None => None,
+                Some(sources) => sources.get(&self.0).cloned()
+            }
+        }
+    }
+
+    ///Rebuilds the program from its remembered files. The new shaders are linked into a scratch program first,
+    ///so when compiling or linking fails the error is returned and this program keeps working unchanged.
+    ///Relinking resets uniform values and uniform block bindings.
+    pub fn reload(&self) -> Result<(), Box<dyn Error>> {
+        let mut sources = match self.sources() {
+            None => return Ok(()),
+            Some(sources) => sources
+        };
+        //Only retry a failed build once the files change again.
+        sources.modified = sources.latest_modification();
+        let result = self.relink(&mut sources);
+        self.watch(sources);
+        result
+    }
+
+    fn relink(&self, sources: &mut ProgramSources) -> Result<(), Box<dyn Error>> {
+        let (vertex_shader, fragment_shader) = sources.build()?;
+
+        let scratch = Program(create_program());
+        attach_shader(&scratch, &vertex_shader);
+        attach_shader(&scratch, &fragment_shader);
+        link_program(&scratch);
+        let linked = scratch.link_status();
+        let status = scratch.status();
+        delete_program(&scratch);
+        if !linked {
+            return Err(Box::new(ProgramError::ProgramLinkError(status)));
+        }
+
+        for shader in attached_shaders(self) {
+            detach_shader_raw(self, shader);
+        }
+        attach_shader(self, &vertex_shader);
+        attach_shader(self, &fragment_shader);
+        link_program(self);
+        self.forget_reflection();
+        if !self.link_status() {
+            return Err(Box::new(ProgramError::ProgramLinkError(self.status())));
+        }
+        Ok(())
+    }
+
+    ///Reloads every watched program whose files changed on disk, checking at most every half second.
+    ///Failures are logged and the previous program is kept. Returns the programs that were rebuilt.
+    ///Does nothing in release builds.
+    pub fn reload_changed() -> Vec<Program> {
+        if !cfg!(debug_assertions) {
+            return vec![];
+        }
+        let changed = unsafe {
+            let now = Instant::now();
+            if let Some(last) = LAST_RELOAD_CHECK {
+                if now.duration_since(last) < RELOAD_INTERVAL {
+                    return vec![];
+                }
+            }
+            LAST_RELOAD_CHECK = Some(now);
+            match &SOURCES {
+                None => vec![],
+                Some(sources) => sources.iter()
+                    .filter(|(_, sources)| sources.changed())
+                    .map(|(id, sources)| (Program(*id), format!("{} + {}", sources.vertex, sources.fragment)))
+                    .collect::<Vec<(Program, String)>>()
+            }
+        };
+
+        let mut reloaded = vec![];
+        for (program, name) in changed {
+            match program.reload() {
+                Ok(()) => {
+                    println!("Reloaded {}", name);
+                    reloaded.push(program);
+                }
+                Err(e) => eprintln!("Unable to reload {}, keeping the previous program: {}", name, e)
+            }
+        }
+        reloaded
+    }
+
     pub fn current_program<F>(f: F) where F: Fn(&'static Program) {
         unsafe {
             if let Some(program) = &CURRENT_PROGRAM {
@@ -0,0 +1,229 @@
+use std::collections::{HashMap, HashSet};
+use crate::gfx::bindings::IV;
+use crate::gfx::bindings::program::{active_attrib, active_uniform, active_uniform_block_binding, active_uniform_block_index, active_uniform_block_name, active_uniform_block_size, active_uniform_offset, attrib_location, get_program_iv};
+use crate::gfx::bindings::uniforms::uniform_location;
+use crate::Program;
+
+///GLSL type of an active uniform or attribute.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UniformType {
+    Float,
+    Vec2,
+    Vec3,
+    Vec4,
+    Int,
+    IVec2,
+    IVec3,
+    IVec4,
+    UInt,
+    Bool,
+    Mat3,
+    Mat4,
+    ///Any sampler, set through an int texture unit.
+    Sampler(u32),
+    ///Any image unit, set through an int.
+    Image(u32),
+    Other(u32),
+}
+
+impl UniformType {
+    pub fn from_gl(gl_type: u32) -> UniformType {
+        match gl_type {
+            gl::FLOAT => UniformType::Float,
+            gl::FLOAT_VEC2 => UniformType::Vec2,
+            gl::FLOAT_VEC3 => UniformType::Vec3,
+            gl::FLOAT_VEC4 => UniformType::Vec4,
+            gl::INT => UniformType::Int,
+            gl::INT_VEC2 => UniformType::IVec2,
+            gl::INT_VEC3 => UniformType::IVec3,
+            gl::INT_VEC4 => UniformType::IVec4,
+            gl::UNSIGNED_INT => UniformType::UInt,
+            gl::BOOL => UniformType::Bool,
+            gl::FLOAT_MAT3 => UniformType::Mat3,
+            gl::FLOAT_MAT4 => UniformType::Mat4,
+            gl::SAMPLER_1D..=gl::SAMPLER_2D_SHADOW
+            | gl::SAMPLER_2D_ARRAY
+            | gl::SAMPLER_2D_ARRAY_SHADOW
+            | gl::SAMPLER_CUBE_SHADOW
+            | gl::INT_SAMPLER_2D
+            | gl::UNSIGNED_INT_SAMPLER_2D => UniformType::Sampler(gl_type),
+            gl::IMAGE_1D..=gl::UNSIGNED_INT_IMAGE_2D_MULTISAMPLE_ARRAY => UniformType::Image(gl_type),
+            _ => UniformType::Other(gl_type)
+        }
+    }
+
+    ///Whether a `Program::set_uniform_*` call writing `setter` can legally set a uniform of this type.
+    ///Ints set bools, samplers and images too.
+    pub fn accepts(&self, setter: UniformType) -> bool {
+        match (self, setter) {
+            (UniformType::Other(_), _) => true,
+            (UniformType::Bool, UniformType::Int) => true,
+            (UniformType::Bool, UniformType::UInt) => true,
+            (UniformType::Int, UniformType::Bool) => true,
+            (UniformType::Sampler(_), UniformType::Int) => true,
+            (UniformType::Image(_), UniformType::Int) => true,
+            (uniform, setter) => *uniform == setter
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ActiveUniform {
+    pub name: String,
+    pub uniform_type: UniformType,
+    ///Element count, 1 for non arrays.
+    pub size: i32,
+    ///-1 for members of uniform blocks.
+    pub location: i32,
+    ///Index into `ProgramReflection::blocks` for block members.
+    pub block: Option<usize>,
+    ///Byte offset inside the block for block members.
+    pub offset: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct ActiveAttribute {
+    pub name: String,
+    pub attribute_type: UniformType,
+    pub size: i32,
+    pub location: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct ActiveBlock {
+    pub name: String,
+    pub index: u32,
+    ///Bytes the bound buffer needs.
+    pub data_size: i32,
+    pub binding: i32,
+}
+
+///The active interface of a linked program as reported by the driver, plus a cache of uniform locations
+///so `set_uniform_*` doesn't query GL by name every call.
+#[derive(Debug, Clone, Default)]
+pub struct ProgramReflection {
+    uniforms: Vec<ActiveUniform>,
+    attributes: Vec<ActiveAttribute>,
+    blocks: Vec<ActiveBlock>,
+    by_name: HashMap<String, usize>,
+    locations: HashMap<String, i32>,
+    warned: HashSet<String>,
+}
+
+///Strips the `[0]` drivers append to the names of uniform arrays.
+pub fn base_name(name: &str) -> &str {
+    name.strip_suffix("[0]").unwrap_or(name)
+}
+
+impl ProgramReflection {
+    pub fn reflect(program: &Program) -> ProgramReflection {
+        let mut reflection = ProgramReflection::default();
+
+        for index in 0..get_program_iv(program, IV::ActiveUniformBlocks).max(0) as u32 {
+            reflection.blocks.push(ActiveBlock {
+                name: active_uniform_block_name(program, index),
+                index,
+                data_size: active_uniform_block_size(program, index),
+                binding: active_uniform_block_binding(program, index),
+            });
+        }
+
+        for index in 0..get_program_iv(program, IV::ActiveUniforms).max(0) as u32 {
+            let (name, size, gl_type) = active_uniform(program, index);
+            let block = active_uniform_block_index(program, index);
+            let (location, block, offset) = if block >= 0 {
+                (-1, Some(block as usize), active_uniform_offset(program, index))
+            } else {
+                (uniform_location(program, &name), None, -1)
+            };
+            reflection.push_uniform(ActiveUniform {
+                name,
+                uniform_type: UniformType::from_gl(gl_type),
+                size,
+                location,
+                block,
+                offset,
+            });
+        }
+
+        for index in 0..get_program_iv(program, IV::ActiveAttributes).max(0) as u32 {
+            let (name, size, gl_type) = active_attrib(program, index);
+            let location = attrib_location(program, &name);
+            reflection.attributes.push(ActiveAttribute {
+                name,
+                attribute_type: UniformType::from_gl(gl_type),
+                size,
+                location,
+            });
+        }
+        reflection
+    }
+
+    fn push_uniform(&mut self, uniform: ActiveUniform) {
+        let index = self.uniforms.len();
+        self.by_name.insert(uniform.name.clone(), index);
+        self.by_name.insert(base_name(&uniform.name).to_string(), index);
+        if uniform.block.is_none() {
+            self.locations.insert(uniform.name.clone(), uniform.location);
+            self.locations.insert(base_name(&uniform.name).to_string(), uniform.location);
+        }
+        self.uniforms.push(uniform);
+    }
+
+    pub fn uniforms(&self) -> &Vec<ActiveUniform> {
+        &self.uniforms
+    }
+    pub fn attributes(&self) -> &Vec<ActiveAttribute> {
+        &self.attributes
+    }
+    pub fn blocks(&self) -> &Vec<ActiveBlock> {
+        &self.blocks
+    }
+
+    ///Looks up a uniform by name. Array elements like `lights[2]` resolve to the array.
+    pub fn uniform(&self, name: &str) -> Option<&ActiveUniform> {
+        let name = match name.find('[') {
+            Some(bracket) if !self.by_name.contains_key(name) => &name[..bracket],
+            _ => name
+        };
+        self.by_name.get(name).map(|index| &self.uniforms[*index])
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&ActiveAttribute> {
+        self.attributes.iter().find(|attribute| attribute.name == name)
+    }
+
+    pub fn block(&self, name: &str) -> Option<&ActiveBlock> {
+        self.blocks.iter().find(|block| block.name == name)
+    }
+
+    ///Location of `name`, querying GL once for names reflection didn't list (e.g. `lights[3]`).
+    ///Unknown names and setters of the wrong type are reported once per name.
+    pub fn location(&mut self, program: &Program, name: &str, setter: Option<UniformType>) -> i32 {
+        let location = match self.locations.get(name) {
+            Some(location) => *location,
+            None => {
+                let location = uniform_location(program, name);
+                self.locations.insert(name.to_string(), location);
+                location
+            }
+        };
+
+        let problem = match self.uniform(name) {
+            None if location == -1 => Some(format!("unknown uniform `{}`", name)),
+            Some(uniform) => match setter {
+                Some(setter) if !uniform.uniform_type.accepts(setter) => {
+                    Some(format!("uniform `{}` is a {:?}, set as {:?}", name, uniform.uniform_type, setter))
+                }
+                _ => None
+            },
+            None => None
+        };
+        if let Some(problem) = problem {
+            if self.warned.insert(name.to_string()) {
+                eprintln!("Program {}: {}", program.id(), problem);
+            }
+        }
+        location
+    }
+}
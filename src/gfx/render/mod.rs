@@ -1,4 +1,3 @@
-use std::collections::HashMap;
 use crate::{Camera, Program, Texture, uniform_1i, VertexArrayObject};
 use crate::gfx::bindings::TextureTarget;
 use crate::gfx::bindings::texturing::bind_texture;
@@ -12,10 +11,10 @@ pub trait RenderObject {
 
 pub trait RenderProgramObject: RenderObject {
     fn program(&self) -> &Program;
-    fn locations(&self) -> &HashMap<String, i32>;
 
-    fn location<T: ToString>(&self, location: T) -> Option<&i32> {
-        self.locations().get(&location.to_string())
+    ///Looked up through the program each time so it stays valid after a hot reload relinks it.
+    fn location<T: ToString>(&self, location: T) -> i32 {
+        self.program().uniform(location)
     }
 
     fn load_int(&self, location: i32, value: &i32) {
@@ -0,0 +1,131 @@
+use std::fmt::{Debug, Formatter};
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicIsize, Ordering as AtomicOrdering};
+use enum_iterator::{all, Sequence};
+use crate::gfx::program::Program;
+use crate::gfx::texture::Texture;
+
+///The GL objects counted by the leak tracker. Names are counted when the bindings create and delete them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Sequence)]
+pub enum GlObjectKind {
+    Buffer,
+    Framebuffer,
+    Program,
+    Renderbuffer,
+    Shader,
+    Texture,
+    VertexArray,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NONE_LIVE: AtomicIsize = AtomicIsize::new(0);
+static LIVE: [AtomicIsize; 7] = [NONE_LIVE; 7];
+
+///Counts a newly created object. Only debug builds count, name 0 means creation failed.
+pub fn track_created(kind: GlObjectKind, name: u32) {
+    if cfg!(debug_assertions) && name != 0 {
+        LIVE[kind as usize].fetch_add(1, AtomicOrdering::Relaxed);
+    }
+}
+
+///Counts a deleted object. Deleting name 0 is a no-op in GL and isn't counted.
+pub fn track_deleted(kind: GlObjectKind, name: u32) {
+    if cfg!(debug_assertions) && name != 0 {
+        LIVE[kind as usize].fetch_sub(1, AtomicOrdering::Relaxed);
+    }
+}
+
+///Objects of `kind` created but not deleted yet. Always 0 in release builds.
+pub fn live(kind: GlObjectKind) -> isize {
+    LIVE[kind as usize].load(AtomicOrdering::Relaxed)
+}
+
+///Every kind with live objects.
+pub fn live_objects() -> Vec<(GlObjectKind, isize)> {
+    all::<GlObjectKind>()
+        .map(|kind| (kind, live(kind)))
+        .filter(|(_, count)| *count != 0)
+        .collect()
+}
+
+///Prints the objects still alive, called by the app in debug builds once it and the engine singletons are dropped.
+pub fn report_live_objects() {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    for (kind, count) in live_objects() {
+        eprintln!("{} {:?} object(s) still alive on shutdown", count, kind);
+    }
+}
+
+///A GL object name that can be deleted.
+pub trait GlObject: Sized {
+    fn delete_object(&self);
+
+    ///Takes ownership, e.g. `Texture::from_image(&image)?.owned()`.
+    fn owned(self) -> Owned<Self> {
+        Owned::new(self)
+    }
+
+    fn shared(self) -> Shared<Self> {
+        Owned::new(self).shared()
+    }
+}
+
+impl GlObject for Texture {
+    fn delete_object(&self) {
+        self.delete();
+    }
+}
+
+impl GlObject for Program {
+    fn delete_object(&self) {
+        self.delete();
+    }
+}
+
+///Owns a `Texture` or `Program` and deletes it when dropped. Neither can be copied, so every engine type
+///holding one does so through `Owned` or `Shared`.
+pub struct Owned<T: GlObject>(T);
+
+///Cheap shared ownership, the object is deleted with the last clone. Atomic so loaded assets can be handed
+///between threads, but the last clone must still be dropped on the thread owning the GL context.
+pub type Shared<T> = Arc<Owned<T>>;
+
+impl<T: GlObject> Owned<T> {
+    pub fn new(object: T) -> Owned<T> {
+        Self(object)
+    }
+
+    pub fn shared(self) -> Shared<T> {
+        Arc::new(self)
+    }
+
+    ///Gives up ownership without deleting the object.
+    pub fn release(self) -> T {
+        let owned = std::mem::ManuallyDrop::new(self);
+        //Safe as `owned` is never dropped, so the object is only read once.
+        unsafe { std::ptr::read(&owned.0) }
+    }
+}
+
+impl<T: GlObject> Deref for Owned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: GlObject + Debug> Debug for Owned<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Owned").field(&self.0).finish()
+    }
+}
+
+impl<T: GlObject> Drop for Owned<T> {
+    fn drop(&mut self) {
+        self.0.delete_object();
+    }
+}
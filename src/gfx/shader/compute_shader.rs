@@ -0,0 +1,31 @@
+use super::{*, super::bindings::*, super::bindings::shader::*};
+use crate::utils::constructor::Constructor;
+
+#[derive(Debug, Clone)]
+pub struct ComputeShader(u32);
+
+impl<T: ToString> Constructor<T> for ComputeShader {
+    fn new(input: T) -> Self {
+        let id = gl_create_shader(ShaderType::ComputeShader);
+        let shader = Self(id);
+        shader_source(input, &shader);
+        compile_shader(&shader);
+        shader
+    }
+}
+
+impl Drop for ComputeShader {
+    fn drop(&mut self) {
+        delete_shader(self);
+    }
+}
+
+impl Shader for ComputeShader {
+    fn id(&self) -> u32 {
+        self.0
+    }
+
+    fn shader_type(&self) -> ShaderType {
+        ShaderType::ComputeShader
+    }
+}
\ No newline at end of file
@@ -2,7 +2,13 @@ use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Clone)]
 pub enum Error {
-    CompilationError(String)
+    CompilationError(String),
+    ///Included file, the file including it and the line of the #include.
+    IncludeNotFound(String, String, usize),
+    ///The include chain that loops back on itself.
+    CircularInclude(Vec<String>),
+    ///A malformed directive and the file and line it is on.
+    Directive(String, String, usize),
 }
 
 impl Display for Error {
@@ -11,6 +17,15 @@ impl Display for Error {
             Error::CompilationError(error) => {
                 f.write_fmt(format_args!("Formatting error: {}", error))
             }
+            Error::IncludeNotFound(file, parent, line) => {
+                f.write_fmt(format_args!("Could not find '{}' included from {}:{}", file, parent, line))
+            }
+            Error::CircularInclude(chain) => {
+                f.write_fmt(format_args!("Circular include: {}", chain.join(" -> ")))
+            }
+            Error::Directive(directive, file, line) => {
+                f.write_fmt(format_args!("Malformed directive '{}' in {}:{}", directive, file, line))
+            }
         }
     }
 }
@@ -4,7 +4,12 @@ use crate::gfx::shader::error::Error;
 
 pub mod fragment_shader;
 pub mod vertex_shader;
+pub mod geometry_shader;
+pub mod tess_control_shader;
+pub mod tess_evaluation_shader;
+pub mod compute_shader;
 pub mod error;
+pub mod preprocessor;
 
 #[macro_export]
 macro_rules! frag {
@@ -0,0 +1,441 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use crate::gfx::program::{Program, ProgramSources};
+use crate::gfx::resource::{GlObject, Owned, Shared};
+use crate::gfx::shader::error::Error;
+use crate::gfx::shader::compute_shader::ComputeShader;
+use crate::gfx::shader::fragment_shader::FragmentShader;
+use crate::gfx::shader::geometry_shader::GeometryShader;
+use crate::gfx::shader::tess_control_shader::TessControlShader;
+use crate::gfx::shader::tess_evaluation_shader::TessEvaluationShader;
+use crate::gfx::shader::Shader;
+use crate::gfx::shader::vertex_shader::VertexShader;
+use crate::utils::constructor::Constructor;
+
+///Directory `#include` paths and shader names are resolved against.
+pub const SHADER_ROOT: &'static str = "res/shaders";
+
+///Copies of the shared shaders compiled into the binary, used when a file is missing under the shader root.
+const EMBEDDED: &[(&'static str, &'static str)] = &[
+    ("blend_map/fragment.glsl", include_str!("../../../res/shaders/blend_map/fragment.glsl")),
+    ("blend_map/vertex.glsl", include_str!("../../../res/shaders/blend_map/vertex.glsl")),
+    ("common/camera.glsl", include_str!("../../../res/shaders/common/camera.glsl")),
+    ("debug/debug_frag.glsl", include_str!("../../../res/shaders/debug/debug_frag.glsl")),
+    ("debug/debug_vert.glsl", include_str!("../../../res/shaders/debug/debug_vert.glsl")),
+    ("lighting/lights.glsl", include_str!("../../../res/shaders/lighting/lights.glsl")),
+    ("pbr/fragment.glsl", include_str!("../../../res/shaders/pbr/fragment.glsl")),
+    ("pbr/vertex.glsl", include_str!("../../../res/shaders/pbr/vertex.glsl")),
+    ("shadows/shadows.glsl", include_str!("../../../res/shaders/shadows/shadows.glsl")),
+    ("skinning/skinning.glsl", include_str!("../../../res/shaders/skinning/skinning.glsl")),
+    ("skinning/vertex.glsl", include_str!("../../../res/shaders/skinning/vertex.glsl")),
+    ("ui/frag.glsl", include_str!("../../../res/shaders/ui/frag.glsl")),
+    ("ui/vert.glsl", include_str!("../../../res/shaders/ui/vert.glsl")),
+];
+
+const DEFAULT_VERSION: &'static str = "#version 330 core";
+
+///Weak, so a variant is deleted once its last holder drops it and rebuilt when asked for again.
+static mut VARIANTS: Option<HashMap<VariantKey, Weak<Owned<Program>>>> = None;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct VariantKey {
+    root: PathBuf,
+    vertex: String,
+    fragment: String,
+    defines: Defines,
+}
+
+///A set of `#define`s injected right after the `#version` line. Ordered, so equal sets give equal sources.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Defines(BTreeMap<String, String>);
+
+impl Defines {
+    pub fn new() -> Defines {
+        Self::default()
+    }
+
+    pub fn with<N: ToString, V: ToString>(mut self, name: N, value: V) -> Defines {
+        self.set(name, value);
+        self
+    }
+
+    ///A define without a value, for `#ifdef`.
+    pub fn flag<N: ToString>(self, name: N) -> Defines {
+        self.with(name, "")
+    }
+
+    pub fn set<N: ToString, V: ToString>(&mut self, name: N, value: V) {
+        self.0.insert(name.to_string(), value.to_string());
+    }
+
+    pub fn remove<N: ToString>(&mut self, name: N) -> Option<String> {
+        self.0.remove(&name.to_string())
+    }
+
+    ///Adds every define of `other`, overriding the ones already set.
+    pub fn extend(&mut self, other: &Defines) {
+        for (name, value) in &other.0 {
+            self.0.insert(name.clone(), value.clone());
+        }
+    }
+
+    pub fn get<N: ToString>(&self, name: N) -> Option<&str> {
+        self.0.get(&name.to_string()).map(|value| value.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn to_glsl(&self) -> String {
+        let mut out = String::new();
+        for (name, value) in &self.0 {
+            match value.is_empty() {
+                true => out.push_str(&format!("#define {}\n", name)),
+                false => out.push_str(&format!("#define {} {}\n", name, value))
+            }
+        }
+        out
+    }
+}
+
+///The files a preprocessed shader was assembled from, indexed by the source number of its `#line` directives.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<String>,
+}
+
+impl SourceMap {
+    fn id(&mut self, file: &str) -> usize {
+        match self.files.iter().position(|f| f == file) {
+            Some(id) => id,
+            None => {
+                self.files.push(file.to_string());
+                self.files.len() - 1
+            }
+        }
+    }
+
+    pub fn file(&self, id: usize) -> Option<&str> {
+        self.files.get(id).map(|file| file.as_str())
+    }
+
+    pub fn files(&self) -> &Vec<String> {
+        &self.files
+    }
+
+    ///Rewrites the `source(line)` (NVIDIA) and `source:line` (Mesa, AMD) locations of a driver log into `file:line`.
+    pub fn map_log(&self, log: &str) -> String {
+        log.lines()
+            .map(|line| self.map_line(line))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn map_line(&self, line: &str) -> String {
+        let prefix = ["ERROR: ", "WARNING: "].iter()
+            .find(|prefix| line.starts_with(*prefix))
+            .map(|prefix| prefix.len())
+            .unwrap_or(0);
+        let rest = &line[prefix..];
+        let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 || digits >= rest.len() {
+            return line.to_string();
+        }
+        let after = &rest[digits..];
+        let (number, remainder) = if after.starts_with("(") {
+            match after.find(")") {
+                Some(end) => (&after[1..end], &after[end + 1..]),
+                None => return line.to_string()
+            }
+        } else if after.starts_with(":") {
+            let length = after[1..].chars().take_while(|c| c.is_ascii_digit()).count();
+            (&after[1..1 + length], &after[1 + length..])
+        } else {
+            return line.to_string();
+        };
+        match (rest[..digits].parse::<usize>().ok().and_then(|id| self.file(id)), number.parse::<usize>()) {
+            (Some(file), Ok(number)) => format!("{}{}:{}{}", &line[..prefix], file, number, remainder),
+            _ => line.to_string()
+        }
+    }
+}
+
+///A shader with its includes resolved and defines injected, ready to compile.
+#[derive(Debug, Clone)]
+pub struct ProcessedShader {
+    source: String,
+    map: SourceMap,
+}
+
+impl ProcessedShader {
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn map(&self) -> &SourceMap {
+        &self.map
+    }
+
+    ///Compiles the source as a vertex shader. Errors point at the original files.
+    pub fn vertex(&self) -> Result<VertexShader, Error> {
+        let shader = VertexShader::new(&self.source);
+        self.check(&shader)?;
+        Ok(shader)
+    }
+
+    ///Compiles the source as a fragment shader. Errors point at the original files.
+    pub fn fragment(&self) -> Result<FragmentShader, Error> {
+        let shader = FragmentShader::new(&self.source);
+        self.check(&shader)?;
+        Ok(shader)
+    }
+
+    pub fn geometry(&self) -> Result<GeometryShader, Error> {
+        let shader = GeometryShader::new(&self.source);
+        self.check(&shader)?;
+        Ok(shader)
+    }
+
+    pub fn tess_control(&self) -> Result<TessControlShader, Error> {
+        let shader = TessControlShader::new(&self.source);
+        self.check(&shader)?;
+        Ok(shader)
+    }
+
+    pub fn tess_evaluation(&self) -> Result<TessEvaluationShader, Error> {
+        let shader = TessEvaluationShader::new(&self.source);
+        self.check(&shader)?;
+        Ok(shader)
+    }
+
+    pub fn compute(&self) -> Result<ComputeShader, Error> {
+        let shader = ComputeShader::new(&self.source);
+        self.check(&shader)?;
+        Ok(shader)
+    }
+
+    fn check(&self, shader: &dyn Shader) -> Result<(), Error> {
+        match shader.compilation_status() {
+            Err(Error::CompilationError(log)) => Err(Error::CompilationError(self.map.map_log(&log))),
+            result => result
+        }
+    }
+}
+
+///Resolves `#include "file.glsl"` against the shader root and injects `#define`s after the `#version` line.
+///Every file is included at most once per shader, so shared chunks may include their own dependencies.
+///`#line` directives keep the driver's line numbers pointing at the original files, see `SourceMap`.
+#[derive(Debug, Clone)]
+pub struct Preprocessor {
+    root: PathBuf,
+    defines: Defines,
+    ///Whether programs built by `program` reload when their files change, see `Program::reload_changed`.
+    watch: bool,
+}
+
+impl Default for Preprocessor {
+    fn default() -> Self {
+        Self {
+            root: PathBuf::from(SHADER_ROOT),
+            defines: Defines::new(),
+            watch: cfg!(debug_assertions),
+        }
+    }
+}
+
+impl Preprocessor {
+    pub fn new() -> Preprocessor {
+        Self::default()
+    }
+
+    pub fn with_root<P: AsRef<Path>>(root: P) -> Preprocessor {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            ..Default::default()
+        }
+    }
+
+    pub fn define<N: ToString, V: ToString>(mut self, name: N, value: V) -> Preprocessor {
+        self.defines.set(name, value);
+        self
+    }
+
+    pub fn with_defines(mut self, defines: &Defines) -> Preprocessor {
+        self.defines.extend(defines);
+        self
+    }
+
+    pub fn watch(mut self, flag: bool) -> Preprocessor {
+        self.watch = flag;
+        self
+    }
+
+    pub fn defines(&self) -> &Defines {
+        &self.defines
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    ///Reads a file relative to the shader root, falling back to the copies compiled into the binary.
+    pub fn load(&self, name: &str) -> Option<String> {
+        match std::fs::read_to_string(self.root.join(name)) {
+            Ok(source) => Some(source),
+            Err(_) => EMBEDDED.iter()
+                .find(|(file, _)| *file == name)
+                .map(|(_, source)| source.to_string())
+        }
+    }
+
+    pub fn process_file(&self, name: &str) -> Result<ProcessedShader, Error> {
+        match self.load(name) {
+            Some(source) => self.process(name, &source),
+            None => Err(Error::IncludeNotFound(name.to_string(), self.root.display().to_string(), 0))
+        }
+    }
+
+    ///Processes `source`. `name` labels it in error messages.
+    pub fn process(&self, name: &str, source: &str) -> Result<ProcessedShader, Error> {
+        let mut map = SourceMap::default();
+        let mut body = String::new();
+        let mut version = None;
+        let mut stack = vec![];
+        let mut included = HashSet::new();
+        self.expand(name, source, &mut map, &mut stack, &mut included, &mut version, &mut body)?;
+
+        let version = version.unwrap_or(DEFAULT_VERSION.to_string());
+        Ok(ProcessedShader {
+            source: format!("{}\n{}{}", version, self.defines.to_glsl(), body),
+            map,
+        })
+    }
+
+    fn expand(&self, name: &str, source: &str, map: &mut SourceMap, stack: &mut Vec<String>, included: &mut HashSet<String>, version: &mut Option<String>, out: &mut String) -> Result<(), Error> {
+        let id = map.id(name);
+        stack.push(name.to_string());
+        included.insert(name.to_string());
+        out.push_str(&format!("#line 1 {}\n", id));
+
+        for (index, line) in source.lines().enumerate() {
+            let number = index + 1;
+            let directive = line.trim();
+            if directive.starts_with("#version") {
+                if version.is_none() {
+                    *version = Some(directive.to_string());
+                }
+                out.push('\n');
+                continue;
+            }
+            if !directive.starts_with("#include") {
+                out.push_str(line);
+                out.push('\n');
+                continue;
+            }
+
+            let file = match Self::quoted(&directive["#include".len()..]) {
+                Some(file) => file,
+                None => return Err(Error::Directive(directive.to_string(), name.to_string(), number))
+            };
+            if stack.contains(&file) {
+                let mut chain = stack.clone();
+                chain.push(file);
+                return Err(Error::CircularInclude(chain));
+            }
+            if !included.contains(&file) {
+                let source = match self.load(&file) {
+                    Some(source) => source,
+                    None => return Err(Error::IncludeNotFound(file, name.to_string(), number))
+                };
+                self.expand(&file, &source, map, stack, included, version, out)?;
+            }
+            out.push_str(&format!("#line {} {}\n", number + 1, id));
+        }
+        stack.pop();
+        Ok(())
+    }
+
+    fn quoted(input: &str) -> Option<String> {
+        let input = input.trim();
+        if input.len() < 2 || !input.starts_with("\"") || !input.ends_with("\"") {
+            return None;
+        }
+        Some(input[1..input.len() - 1].to_string())
+    }
+
+    fn variant_key(&self, vertex: &str, fragment: &str) -> VariantKey {
+        VariantKey {
+            root: self.root.clone(),
+            vertex: vertex.to_string(),
+            fragment: fragment.to_string(),
+            defines: self.defines.clone(),
+        }
+    }
+
+    ///Builds a program from a vertex and a fragment file under the shader root. Each define set is compiled once,
+    ///later calls with the same files and defines share the variant while any holder keeps it alive.
+    pub fn program(&self, vertex: &str, fragment: &str) -> Result<Shared<Program>, Box<dyn std::error::Error>> {
+        if let Some(program) = Self::cached(&self.variant_key(vertex, fragment)) {
+            return Ok(program);
+        }
+        let vertex_source = self.process_file(vertex)?;
+        let fragment_source = self.process_file(fragment)?;
+        self.link(vertex, fragment, &vertex_source, &fragment_source)
+    }
+
+    ///Compiles and links sources already processed by `process_file`, e.g. on an asset worker thread,
+    ///and caches the result like `program`. Requires a current GL context.
+    pub fn link(&self, vertex: &str, fragment: &str, vertex_source: &ProcessedShader, fragment_source: &ProcessedShader) -> Result<Shared<Program>, Box<dyn std::error::Error>> {
+        let key = self.variant_key(vertex, fragment);
+        if let Some(program) = Self::cached(&key) {
+            return Ok(program);
+        }
+        let program = Program::new(fragment_source.fragment()?, vertex_source.vertex()?)?.shared();
+        if self.watch {
+            program.watch(ProgramSources::from_processed(self.clone(), vertex, fragment, [vertex_source, fragment_source]));
+        }
+        unsafe {
+            VARIANTS.get_or_insert_with(HashMap::new).insert(key, Arc::downgrade(&program));
+        }
+        Ok(program)
+    }
+
+    fn cached(key: &VariantKey) -> Option<Shared<Program>> {
+        unsafe {
+            match &VARIANTS {
+                None => None,
+                Some(variants) => variants.get(key).and_then(Weak::upgrade)
+            }
+        }
+    }
+
+    ///Forgets every cached variant, so the next `program` call compiles again.
+    pub fn clear_variants() {
+        unsafe {
+            VARIANTS = None;
+        }
+    }
+
+    ///Drops the cached variant that built `program`, e.g. because it is being deleted.
+    pub fn forget_variant(program: &Program) {
+        unsafe {
+            if let Some(variants) = &mut VARIANTS {
+                variants.retain(|_, variant| match variant.upgrade() {
+                    None => false,
+                    Some(variant) => variant.id() != program.id()
+                });
+            }
+        }
+    }
+
+    pub fn variant_count() -> usize {
+        unsafe {
+            match &VARIANTS {
+                None => 0,
+                Some(variants) => variants.values().filter(|variant| variant.strong_count() > 0).count()
+            }
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use super::{*, super::bindings::*, super::bindings::shader::*};
+use crate::utils::constructor::Constructor;
+
+#[derive(Debug, Clone)]
+pub struct TessEvaluationShader(u32);
+
+impl<T: ToString> Constructor<T> for TessEvaluationShader {
+    fn new(input: T) -> Self {
+        let id = gl_create_shader(ShaderType::TessEvaluationShader);
+        let shader = Self(id);
+        shader_source(input, &shader);
+        compile_shader(&shader);
+        shader
+    }
+}
+
+impl Drop for TessEvaluationShader {
+    fn drop(&mut self) {
+        delete_shader(self);
+    }
+}
+
+impl Shader for TessEvaluationShader {
+    fn id(&self) -> u32 {
+        self.0
+    }
+
+    fn shader_type(&self) -> ShaderType {
+        ShaderType::TessEvaluationShader
+    }
+}
\ No newline at end of file
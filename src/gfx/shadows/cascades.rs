@@ -0,0 +1,89 @@
+use vecmath::{col_mat4_mul, col_mat4_transform, mat4_inv, Matrix4};
+use crate::Camera;
+use crate::ecs::lights::DEFAULT_DIRECTION;
+use crate::math::linear_algebra::{orthographic_box, perspective};
+use crate::math::linear_algebra::matrix_wrapper::MatrixWrapper;
+use crate::math::linear_algebra::types::Vec3;
+use crate::math::linear_algebra::vector_ext::VectorExt;
+
+///View space distances at which each cascade ends. `lambda` blends between uniform (0) and logarithmic (1) splits.
+pub fn cascade_splits(near: f32, far: f32, cascades: usize, lambda: f32) -> Vec<f32> {
+    (1..=cascades).map(|i| {
+        let p = i as f32 / cascades as f32;
+        let logarithmic = near * (far / near).powf(p);
+        let uniform = near + (far - near) * p;
+        lambda * logarithmic + (1.0 - lambda) * uniform
+    }).collect()
+}
+
+///World space corners of the camera frustum between the view distances `near` and `far`.
+pub fn frustum_corners(camera: &Camera, near: f32, far: f32) -> [Vec3; 8] {
+    let projection = perspective(camera.fov(), camera.aspect_ratio(), near, far);
+    let inverse = mat4_inv(col_mat4_mul(projection, *camera.view()));
+    let mut corners = [[0.0; 3]; 8];
+    let mut i = 0;
+    for x in [-1.0, 1.0] {
+        for y in [-1.0, 1.0] {
+            for z in [-1.0, 1.0] {
+                let p = col_mat4_transform(inverse, [x, y, z, 1.0]);
+                corners[i] = [p[0] / p[3], p[1] / p[3], p[2] / p[3]];
+                i += 1;
+            }
+        }
+    }
+    corners
+}
+
+///Orthographic view-projection of a directional light shining along `direction`, enclosing `corners`.
+///The bounds are a sphere around the corners so the map keeps its size as the camera turns,
+///and they move in whole texels of a `resolution` sized map so edges do not shimmer as the camera moves.
+///`caster_distance` extends the volume towards the light to catch casters outside the camera frustum.
+pub fn fit_directional(corners: &[Vec3; 8], direction: Vec3, resolution: i32, caster_distance: f32) -> Matrix4<f32> {
+    let mut center = [0.0; 3];
+    for corner in corners {
+        for i in 0..3 {
+            center[i] += corner[i] / corners.len() as f32;
+        }
+    }
+    let mut radius: f32 = 0.0;
+    for corner in corners {
+        radius = radius.max(corner.distance(&center));
+    }
+    radius = (radius * 16.0).ceil() / 16.0;
+
+    let direction = direction.normalized_or(DEFAULT_DIRECTION);
+    let eye = [
+        center[0] - direction[0] * (radius + caster_distance),
+        center[1] - direction[1] * (radius + caster_distance),
+        center[2] - direction[2] * (radius + caster_distance),
+    ];
+    let view = MatrixWrapper::look_at(eye, center, up_for(direction)).0;
+    let projection = orthographic_box(-radius, radius, -radius, radius, 0.0, 2.0 * radius + caster_distance);
+    let mut light_space = col_mat4_mul(projection, view);
+
+    let origin = col_mat4_transform(light_space, [0.0, 0.0, 0.0, 1.0]);
+    let texels = resolution as f32 / 2.0;
+    let x = origin[0] * texels;
+    let y = origin[1] * texels;
+    light_space[3][0] += (x.round() - x) / texels;
+    light_space[3][1] += (y.round() - y) / texels;
+    light_space
+}
+
+///Perspective view-projection of a spot light covering its outer cone up to `range`.
+pub fn fit_spot(position: Vec3, direction: Vec3, cos_outer: f32, range: f32) -> Matrix4<f32> {
+    let direction = direction.normalized_or(DEFAULT_DIRECTION);
+    let fov = (cos_outer.clamp(-1.0, 1.0).acos().to_degrees() * 2.0 + 2.0).min(170.0);
+    let near = (range * 0.01).max(0.05);
+    let far = range.max(near + 0.1);
+    let target = [position[0] + direction[0], position[1] + direction[1], position[2] + direction[2]];
+    let view = MatrixWrapper::look_at(position, target, up_for(direction)).0;
+    col_mat4_mul(perspective(fov, 1.0, near, far), view)
+}
+
+fn up_for(direction: Vec3) -> Vec3 {
+    match direction[1].abs() > 0.99 {
+        true => [0.0, 0.0, 1.0],
+        false => [0.0, 1.0, 0.0]
+    }
+}
@@ -0,0 +1,284 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use vecmath::Matrix4;
+use crate::Camera;
+use crate::ecs::components_mut;
+use crate::gfx::bindings::GLConsts::DepthTest;
+use crate::gfx::bindings::graphics::{clear, depth_mask, enable, get_viewport, viewport};
+use crate::gfx::bindings::texturing::active_texture;
+use crate::gfx::lighting::{Lighting, LightType};
+use crate::gfx::program::Program;
+use crate::gfx::resource::{GlObject, Owned};
+use crate::gfx::shader::fragment_shader::FragmentShader;
+use crate::gfx::shader::preprocessor::Defines;
+use crate::gfx::shader::vertex_shader::VertexShader;
+use crate::gfx::shadows::cascades::{cascade_splits, fit_directional, fit_spot, frustum_corners};
+use crate::gfx::shadows::shadow_atlas::ShadowAtlas;
+use crate::math::linear_algebra::IDENTITY_MAT4;
+use crate::math::linear_algebra::types::Vec4;
+use crate::utils::constructor::Constructor;
+
+pub mod cascades;
+pub mod shadow_atlas;
+
+const DEPTH_VERTEX: &'static str = include_str!("../../../res/shaders/shadows/depth_vertex.glsl");
+const DEPTH_FRAGMENT: &'static str = include_str!("../../../res/shaders/shadows/depth_fragment.glsl");
+
+///Cascade split distances are uploaded as a vec4.
+pub const MAX_CASCADES: usize = 4;
+
+static mut SHADOWS: Option<Shadows> = None;
+
+#[derive(Clone)]
+pub enum ShadowError {
+    IncompleteFramebuffer(i32, i32),
+    CascadeCount(usize),
+    Program(String),
+}
+
+impl Debug for ShadowError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShadowError::IncompleteFramebuffer(width, height) => {
+                f.write_fmt(format_args!("Shadow atlas framebuffer of {}x{} is incomplete", width, height))
+            }
+            ShadowError::CascadeCount(count) => {
+                f.write_fmt(format_args!("Between 1 and {} cascades are supported, got {}", MAX_CASCADES, count))
+            }
+            ShadowError::Program(e) => {
+                f.write_fmt(format_args!("Unable to build the shadow depth program: {}", e))
+            }
+        }
+    }
+}
+
+impl Display for ShadowError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl Error for ShadowError {}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShadowSettings {
+    ///Size in texels of every shadow map.
+    pub resolution: i32,
+    ///Cascades of the directional light, 1 to `MAX_CASCADES`.
+    pub cascades: usize,
+    ///Blend between uniform (0) and logarithmic (1) cascade splits.
+    pub split_lambda: f32,
+    ///View distance the cascades cover, capped by the camera's far plane.
+    pub max_distance: f32,
+    ///How far behind the camera frustum, towards the light, casters are still rendered.
+    pub caster_distance: f32,
+    ///Spot lights beyond this count do not cast shadows.
+    pub max_spot_shadows: usize,
+    ///PCF kernel radius in texels. 0 gives hard shadows.
+    pub pcf_radius: i32,
+    pub depth_bias: f32,
+    ///World space offset along the normal, scaled by how grazing the light is.
+    pub normal_bias: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 1024,
+            cascades: 3,
+            split_lambda: 0.75,
+            max_distance: 100.0,
+            caster_distance: 50.0,
+            max_spot_shadows: 4,
+            pcf_radius: 1,
+            depth_bias: 0.002,
+            normal_bias: 0.05,
+        }
+    }
+}
+
+impl ShadowSettings {
+    pub fn tile_count(&self) -> usize {
+        self.cascades + self.max_spot_shadows
+    }
+}
+
+///A shadow map of the atlas: the light's view-projection and where it lives in the atlas.
+#[derive(Debug, Clone, Copy)]
+pub struct ShadowTile {
+    pub matrix: Matrix4<f32>,
+    pub rect: Vec4,
+}
+
+///Renders the shadow maps of every shadow casting light into a single depth atlas each frame.
+///The first directional light with `cast_shadows` gets cascaded maps fitted to the camera,
+///spot lights get one perspective map each. Point lights do not cast shadows.
+#[derive(Debug)]
+pub struct Shadows {
+    settings: ShadowSettings,
+    atlas: ShadowAtlas,
+    program: Owned<Program>,
+    tiles: Vec<ShadowTile>,
+    splits: Vec4,
+    cascades: i32,
+    camera_view: Matrix4<f32>,
+}
+
+impl Shadows {
+    pub unsafe fn init(settings: ShadowSettings) -> Result<(), ShadowError> {
+        if settings.cascades == 0 || settings.cascades > MAX_CASCADES {
+            return Err(ShadowError::CascadeCount(settings.cascades));
+        }
+        let atlas = ShadowAtlas::new(settings.resolution, settings.tile_count())?;
+        let program = match Program::new(FragmentShader::new(DEPTH_FRAGMENT), VertexShader::new(DEPTH_VERTEX)) {
+            Ok(program) => program.owned(),
+            Err(e) => return Err(ShadowError::Program(e.to_string()))
+        };
+        SHADOWS = Some(Shadows {
+            settings,
+            atlas,
+            program,
+            tiles: vec![],
+            splits: [0.0; 4],
+            cascades: 0,
+            camera_view: IDENTITY_MAT4,
+        });
+        Ok(())
+    }
+
+    ///Deletes the atlas and the depth program. Called by the app on shutdown.
+    pub unsafe fn shutdown() {
+        SHADOWS = None;
+    }
+
+    pub fn is_initialized() -> bool {
+        unsafe { SHADOWS.is_some() }
+    }
+
+    pub fn settings() -> ShadowSettings {
+        unsafe {
+            match &SHADOWS {
+                None => ShadowSettings::default(),
+                Some(shadows) => shadows.settings
+            }
+        }
+    }
+
+    ///Everything `shadows/shadows.glsl` needs defined for the shader preprocessor, including the light defines.
+    pub fn defines() -> Defines {
+        Lighting::defines().with("MAX_SHADOW_TILES", Self::settings().tile_count())
+    }
+
+    ///Renders every shadow map. `draw` is called once per map and must draw the shadow casters
+    ///with the given program, setting its `model` uniform. Components with `cast_shadows` are drawn as well.
+    ///Must run after `Lighting::update`.
+    pub fn render<F: FnMut(&Program)>(camera: &Camera, mut draw: F) {
+        unsafe {
+            if let Some(shadows) = &mut SHADOWS {
+                shadows.render_tiles(camera, &mut draw);
+            }
+        }
+    }
+
+    fn render_tiles(&mut self, camera: &Camera, draw: &mut dyn FnMut(&Program)) {
+        self.tiles.clear();
+        self.cascades = 0;
+        self.camera_view = *camera.view();
+
+        let lights = Lighting::lights();
+        let mut assigned = vec![];
+
+        let sun = lights.iter()
+            .enumerate()
+            .find(|(_, light)| light.light_type() == LightType::Directional && light.casts_shadows());
+        if let Some((index, light)) = sun {
+            let far = camera.far().min(self.settings.max_distance);
+            let splits = cascade_splits(camera.near(), far, self.settings.cascades, self.settings.split_lambda);
+            assigned.push((index, self.tiles.len()));
+            let mut near = camera.near();
+            for (cascade, split) in splits.iter().enumerate() {
+                let corners = frustum_corners(camera, near, *split);
+                self.push_tile(fit_directional(&corners, light.direction(), self.settings.resolution, self.settings.caster_distance));
+                self.splits[cascade] = *split;
+                near = *split;
+            }
+            self.cascades = splits.len() as i32;
+        }
+
+        let spots = lights.iter()
+            .enumerate()
+            .filter(|(_, light)| light.light_type() == LightType::Spot && light.casts_shadows())
+            .take(self.settings.max_spot_shadows);
+        for (index, light) in spots {
+            assigned.push((index, self.tiles.len()));
+            self.push_tile(fit_spot(light.position(), light.direction(), light.cone[1], light.range()));
+        }
+        Lighting::set_shadow_tiles(&assigned);
+
+        let previous = get_viewport();
+        self.atlas.bind();
+        depth_mask(true);
+        enable(DepthTest);
+        clear(gl::DEPTH_BUFFER_BIT);
+        self.program.enable();
+        for (index, tile) in self.tiles.iter().enumerate() {
+            self.atlas.bind_tile(index);
+            self.program.set_uniform_mat4("lightSpace", &tile.matrix);
+            draw(&self.program);
+            for component in components_mut() {
+                if *component.enabled() && component.cast_shadows() {
+                    component.render_shadow(&self.program);
+                }
+            }
+        }
+        self.atlas.unbind();
+        viewport([previous[0], previous[1]], [previous[2], previous[3]]);
+    }
+
+    fn push_tile(&mut self, matrix: Matrix4<f32>) {
+        let rect = self.atlas.tile_rect(self.tiles.len());
+        self.tiles.push(ShadowTile { matrix, rect });
+    }
+
+    ///Binds the atlas to texture unit `unit` and uploads this frame's shadow maps. The program must be enabled.
+    ///Returns the next free texture unit.
+    pub fn bind(program: &Program, unit: u32) -> u32 {
+        unsafe {
+            match &SHADOWS {
+                None => unit,
+                Some(shadows) => {
+                    active_texture(unit);
+                    shadows.atlas.texture().bind();
+                    active_texture(0);
+                    program.set_uniform_int("shadowAtlas", &(unit as i32));
+                    for (index, tile) in shadows.tiles.iter().enumerate() {
+                        program.set_uniform_mat4(format!("shadowMatrices[{}]", index), &tile.matrix);
+                        program.set_uniform_vec4(format!("shadowRects[{}]", index), &tile.rect);
+                    }
+                    program.set_uniform_vec4("cascadeSplits", &shadows.splits);
+                    program.set_uniform_int("cascadeCount", &shadows.cascades);
+                    program.set_uniform_mat4("shadowCameraView", &shadows.camera_view);
+                    program.set_uniform_int("pcfRadius", &shadows.settings.pcf_radius);
+                    program.set_uniform_float("shadowDepthBias", &shadows.settings.depth_bias);
+                    program.set_uniform_float("shadowNormalBias", &shadows.settings.normal_bias);
+                    unit + 1
+                }
+            }
+        }
+    }
+
+    ///Whether the next object drawn with `program` receives shadows. The program must be enabled.
+    pub fn bind_object(program: &Program, receive_shadows: bool) {
+        program.set_uniform_bool("receiveShadows", &receive_shadows);
+    }
+
+    ///This frame's shadow maps, cascades first.
+    pub fn tiles() -> Vec<ShadowTile> {
+        unsafe {
+            match &SHADOWS {
+                None => vec![],
+                Some(shadows) => shadows.tiles.clone()
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::gfx::bindings::{FramebufferAttachment, FramebufferTarget, TextureTarget};
+use crate::gfx::bindings::framebuffers::{bind_framebuffer, check_framebuffer_status, delete_framebuffer, disable_color_buffers, framebuffer_texture_2d, gen_framebuffers, unbind_framebuffer};
+use crate::gfx::bindings::graphics::viewport;
+use crate::gfx::shadows::ShadowError;
+use crate::gfx::resource::{GlObject, Owned};
+use crate::gfx::texture::Texture;
+use crate::math::linear_algebra::types::Vec4;
+
+///A depth only framebuffer split into a grid of square shadow map tiles. The framebuffer and texture are deleted on drop.
+#[derive(Debug)]
+pub struct ShadowAtlas {
+    fbo: u32,
+    depth: Owned<Texture>,
+    resolution: i32,
+    columns: i32,
+    rows: i32,
+}
+
+impl ShadowAtlas {
+    ///Creates an atlas holding at least `tiles` maps of `resolution` squared texels.
+    pub fn new(resolution: i32, tiles: usize) -> Result<ShadowAtlas, ShadowError> {
+        let resolution = resolution.max(1);
+        let tiles = tiles.max(1) as i32;
+        let columns = (tiles as f32).sqrt().ceil() as i32;
+        let rows = (tiles + columns - 1) / columns;
+
+        let fbo = gen_framebuffers(1);
+        bind_framebuffer(FramebufferTarget::Framebuffer, fbo);
+        let depth = Texture::depth_target(resolution * columns, resolution * rows).owned();
+        framebuffer_texture_2d(FramebufferTarget::Framebuffer, FramebufferAttachment::DepthAttachment, TextureTarget::Texture2D, depth.id(), 0);
+        disable_color_buffers();
+
+        let complete = check_framebuffer_status(FramebufferTarget::Framebuffer);
+        unbind_framebuffer(FramebufferTarget::Framebuffer);
+
+        let atlas = Self { fbo, depth, resolution, columns, rows };
+        if !complete {
+            //Dropping the unfinished atlas deletes what was created.
+            return Err(ShadowError::IncompleteFramebuffer(resolution * columns, resolution * rows));
+        }
+        Ok(atlas)
+    }
+
+    ///Binds the framebuffer and sets the viewport to cover the whole atlas.
+    pub fn bind(&self) {
+        bind_framebuffer(FramebufferTarget::Framebuffer, self.fbo);
+        viewport([0, 0], self.size());
+    }
+
+    pub fn unbind(&self) {
+        unbind_framebuffer(FramebufferTarget::Framebuffer);
+    }
+
+    ///Restricts drawing to a single tile.
+    pub fn bind_tile(&self, tile: usize) {
+        let (column, row) = self.cell(tile);
+        viewport([column * self.resolution, row * self.resolution], [self.resolution, self.resolution]);
+    }
+
+    ///Offset and size of a tile in texture coordinates.
+    pub fn tile_rect(&self, tile: usize) -> Vec4 {
+        let (column, row) = self.cell(tile);
+        let width = 1.0 / self.columns as f32;
+        let height = 1.0 / self.rows as f32;
+        [column as f32 * width, row as f32 * height, width, height]
+    }
+
+    fn cell(&self, tile: usize) -> (i32, i32) {
+        let tile = tile as i32;
+        (tile % self.columns, tile / self.columns)
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.depth
+    }
+    pub fn resolution(&self) -> i32 {
+        self.resolution
+    }
+    pub fn size(&self) -> [i32; 2] {
+        [self.resolution * self.columns, self.resolution * self.rows]
+    }
+}
+
+impl Drop for ShadowAtlas {
+    fn drop(&mut self) {
+        delete_framebuffer(self.fbo);
+    }
+}
@@ -0,0 +1,368 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use vecmath::{vec3_add, vec3_cross, vec3_dot, vec3_scale, vec3_sub};
+use crate::gfx::mesh::{Mesh, Submesh};
+use crate::math::linear_algebra::types::Vec3;
+use crate::math::linear_algebra::vector_ext::VectorExt;
+use crate::math::tangent::triangle_normal;
+
+///Weight of the planes holding open borders and material seams in place, relative to the surface planes.
+const BORDER_WEIGHT: f64 = 1000.0;
+///Collapses turning a triangle by more than about 80 degrees fold the surface over and are rejected.
+const MIN_NORMAL_DOT: f32 = 0.2;
+///Triangles outside every submesh.
+const NO_SUBMESH: usize = usize::MAX;
+
+///Sum of squared distances to a set of planes, the upper triangle of a symmetric 4x4 matrix row by row.
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn plane(normal: Vec3, point: Vec3, weight: f64) -> Quadric {
+        let [a, b, c] = normal.map(|v| v as f64);
+        let d = -(a * point[0] as f64 + b * point[1] as f64 + c * point[2] as f64);
+        Quadric([a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d].map(|v| v * weight))
+    }
+
+    fn add(&self, other: &Quadric) -> Quadric {
+        let mut sum = *self;
+        sum.0.iter_mut().zip(other.0).for_each(|(a, b)| *a += b);
+        sum
+    }
+
+    fn error(&self, point: Vec3) -> f64 {
+        let [x, y, z] = point.map(|v| v as f64);
+        let q = &self.0;
+        q[0] * x * x + 2.0 * q[1] * x * y + 2.0 * q[2] * x * z + 2.0 * q[3] * x
+            + q[4] * y * y + 2.0 * q[5] * y * z + 2.0 * q[6] * y
+            + q[7] * z * z + 2.0 * q[8] * z + q[9]
+    }
+
+    ///The point of least error, None where the planes don't pin one down, e.g. on a flat surface.
+    fn optimum(&self) -> Option<Vec3> {
+        let q = &self.0;
+        let m = [[q[0], q[1], q[2]], [q[1], q[4], q[5]], [q[2], q[5], q[7]]];
+        let det = det3(m);
+        if det.abs() <= (q[0] + q[4] + q[7]).powi(3) * 1e-9 {
+            return None;
+        }
+        //Cramer's rule on the gradient being zero.
+        let b = [-q[3], -q[6], -q[8]];
+        let solve = |column: usize| {
+            let mut m = m;
+            (0..3).for_each(|row| m[row][column] = b[row]);
+            (det3(m) / det) as f32
+        };
+        Some([solve(0), solve(1), solve(2)])
+    }
+}
+
+fn det3(m: [[f64; 3]; 3]) -> f64 {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+///An edge collapse waiting in the queue, stale once either point has changed since it was queued.
+#[derive(Debug, Clone, Copy)]
+struct Collapse {
+    cost: f64,
+    keep: usize,
+    remove: usize,
+    target: Vec3,
+    versions: (u32, u32),
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    ///Reversed so the heap pops the cheapest collapse first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+    }
+}
+
+///Garland and Heckbert's edge collapse on the mesh's positions. Vertices sharing a position form one point,
+///so uv and normal seams move together and keep their attributes.
+struct Simplifier<'a> {
+    mesh: &'a Mesh,
+    points: Vec<Vec3>,
+    point_of: Vec<usize>,
+    point_vertices: Vec<Vec<usize>>,
+    point_triangles: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric>,
+    versions: Vec<u32>,
+    removed: Vec<bool>,
+    triangles: Vec<[usize; 3]>,
+    triangle_submesh: Vec<usize>,
+    alive: Vec<bool>,
+    live: usize,
+}
+
+impl<'a> Simplifier<'a> {
+    fn new(mesh: &'a Mesh) -> Simplifier<'a> {
+        let mut keys: HashMap<[u32; 3], usize> = HashMap::new();
+        let mut points = vec![];
+        let mut point_vertices: Vec<Vec<usize>> = vec![];
+        let point_of = mesh.positions().iter().enumerate().map(|(vertex, position)| {
+            //Adding zero turns -0.0 into 0.0 so both weld.
+            let point = *keys.entry(position.map(|v| (v + 0.0).to_bits())).or_insert_with(|| {
+                points.push(*position);
+                point_vertices.push(vec![]);
+                points.len() - 1
+            });
+            point_vertices[point].push(vertex);
+            point
+        }).collect::<Vec<usize>>();
+
+        let triangles = mesh.triangles().collect::<Vec<[usize; 3]>>();
+        let mut triangle_submesh = vec![NO_SUBMESH; triangles.len()];
+        for (index, submesh) in mesh.submeshes().iter().enumerate() {
+            let end = ((submesh.start + submesh.count) / 3).min(triangles.len());
+            for tagged in triangle_submesh[(submesh.start / 3).min(end)..end].iter_mut() {
+                if *tagged == NO_SUBMESH {
+                    *tagged = index;
+                }
+            }
+        }
+
+        let mut point_triangles = vec![vec![]; points.len()];
+        for (index, triangle) in triangles.iter().enumerate() {
+            for vertex in triangle {
+                point_triangles[point_of[*vertex]].push(index);
+            }
+        }
+        let (count, triangle_count) = (points.len(), triangles.len());
+        let mut simplifier = Self {
+            mesh,
+            points,
+            point_of,
+            point_vertices,
+            point_triangles,
+            quadrics: vec![Quadric::default(); count],
+            versions: vec![0; count],
+            removed: vec![false; count],
+            triangles,
+            triangle_submesh,
+            alive: vec![true; triangle_count],
+            live: triangle_count,
+        };
+        for triangle in 0..triangle_count {
+            if !simplifier.is_proper(triangle) {
+                simplifier.alive[triangle] = false;
+                simplifier.live -= 1;
+            }
+        }
+        simplifier
+    }
+
+    fn corners(&self, triangle: usize) -> [usize; 3] {
+        self.triangles[triangle].map(|vertex| self.point_of[vertex])
+    }
+
+    ///Whether the triangle still spans three distinct points.
+    fn is_proper(&self, triangle: usize) -> bool {
+        let [a, b, c] = self.corners(triangle);
+        a != b && b != c && a != c
+    }
+
+    fn compute_quadrics(&mut self) -> Vec<(usize, usize)> {
+        let mut edges: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for triangle in (0..self.triangles.len()).filter(|t| self.alive[*t]) {
+            let corners = self.corners(triangle);
+            let positions = corners.map(|point| self.points[point]);
+            let area = vec3_cross(vec3_sub(positions[1], positions[0]), vec3_sub(positions[2], positions[0])).magnitude() as f64 * 0.5;
+            let quadric = Quadric::plane(triangle_normal(positions), positions[0], area);
+            for (i, point) in corners.iter().enumerate() {
+                self.quadrics[*point] = self.quadrics[*point].add(&quadric);
+                let next = corners[(i + 1) % 3];
+                edges.entry((*point.min(&next), *point.max(&next))).or_default().push(triangle);
+            }
+        }
+        for ((a, b), triangles) in &edges {
+            let submesh = self.triangle_submesh[triangles[0]];
+            if triangles.len() > 1 && triangles.iter().all(|t| self.triangle_submesh[*t] == submesh) {
+                continue;
+            }
+            let (pa, pb) = (self.points[*a], self.points[*b]);
+            let edge = vec3_sub(pb, pa);
+            for triangle in triangles {
+                let normal = triangle_normal(self.corners(*triangle).map(|point| self.points[point]));
+                let border = vec3_cross(edge, normal).normalized_or([0.0; 3]);
+                let quadric = Quadric::plane(border, pa, BORDER_WEIGHT * vec3_dot(edge, edge) as f64);
+                self.quadrics[*a] = self.quadrics[*a].add(&quadric);
+                self.quadrics[*b] = self.quadrics[*b].add(&quadric);
+            }
+        }
+        edges.into_keys().collect()
+    }
+
+    fn collapse_for(&self, keep: usize, remove: usize) -> Collapse {
+        let quadric = self.quadrics[keep].add(&self.quadrics[remove]);
+        let (a, b) = (self.points[keep], self.points[remove]);
+        let middle = vec3_scale(vec3_add(a, b), 0.5);
+        let reach = a.distance(&b);
+        //Nearly flat neighbourhoods can put the optimum far off along the surface.
+        let optimum = quadric.optimum().filter(|optimum| optimum.distance(&middle) <= reach);
+        let (cost, target) = optimum.into_iter().chain([a, b, middle])
+            .map(|target| (quadric.error(target), target))
+            .fold((f64::INFINITY, a), |best, candidate| if candidate.0 < best.0 { candidate } else { best });
+        Collapse { cost: cost.max(0.0), keep, remove, target, versions: (self.versions[keep], self.versions[remove]) }
+    }
+
+    ///Whether moving both points to `target` keeps every surrounding triangle from degenerating or flipping.
+    fn keeps_orientation(&self, collapse: &Collapse) -> bool {
+        for point in [collapse.keep, collapse.remove] {
+            for triangle in self.point_triangles[point].iter().filter(|t| self.alive[**t]) {
+                let corners = self.corners(*triangle);
+                if corners.contains(&collapse.keep) && corners.contains(&collapse.remove) {
+                    continue;
+                }
+                let before = triangle_normal(corners.map(|p| self.points[p]));
+                if before == [0.0; 3] {
+                    continue;
+                }
+                let after = triangle_normal(corners.map(|p| if p == point { collapse.target } else { self.points[p] }));
+                if vec3_dot(before, after) < MIN_NORMAL_DOT {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    ///Merges `remove` into `keep` and returns the points around `keep` afterwards.
+    fn collapse(&mut self, collapse: &Collapse) -> HashSet<usize> {
+        let (keep, remove) = (collapse.keep, collapse.remove);
+        self.points[keep] = collapse.target;
+        self.quadrics[keep] = self.quadrics[keep].add(&self.quadrics[remove]);
+        for vertex in std::mem::take(&mut self.point_vertices[remove]) {
+            self.point_of[vertex] = keep;
+            self.point_vertices[keep].push(vertex);
+        }
+        let moved = std::mem::take(&mut self.point_triangles[remove]);
+        self.point_triangles[keep].extend(moved);
+        self.point_triangles[keep].sort_unstable();
+        self.point_triangles[keep].dedup();
+        let mut neighbours = HashSet::new();
+        for triangle in self.point_triangles[keep].clone() {
+            if !self.alive[triangle] {
+                continue;
+            }
+            if !self.is_proper(triangle) {
+                self.alive[triangle] = false;
+                self.live -= 1;
+                continue;
+            }
+            neighbours.extend(self.corners(triangle).into_iter().filter(|point| *point != keep));
+        }
+        let alive = &self.alive;
+        self.point_triangles[keep].retain(|triangle| alive[*triangle]);
+        self.removed[remove] = true;
+        self.versions[keep] += 1;
+        self.versions[remove] += 1;
+        neighbours
+    }
+
+    fn run(mut self, target: usize) -> Mesh {
+        let mut queue = self.compute_quadrics().into_iter()
+            .map(|(a, b)| self.collapse_for(a, b))
+            .collect::<BinaryHeap<Collapse>>();
+        while self.live > target {
+            let collapse = match queue.pop() {
+                Some(collapse) => collapse,
+                None => break
+            };
+            if self.removed[collapse.keep] || self.removed[collapse.remove]
+                || collapse.versions != (self.versions[collapse.keep], self.versions[collapse.remove]) {
+                continue;
+            }
+            if !self.keeps_orientation(&collapse) {
+                continue;
+            }
+            for neighbour in self.collapse(&collapse) {
+                queue.push(self.collapse_for(collapse.keep, neighbour));
+            }
+        }
+        self.build()
+    }
+
+    ///The surviving triangles grouped by their submesh, with unused vertices dropped.
+    fn build(&self) -> Mesh {
+        let mesh = self.mesh;
+        let mut remap = vec![u32::MAX; mesh.vertex_count()];
+        let mut vertices = vec![];
+        let mut indices = vec![];
+        let mut submeshes = vec![];
+        let groups = (0..mesh.submeshes().len()).chain([NO_SUBMESH]);
+        for group in groups {
+            let start = indices.len();
+            for triangle in (0..self.triangles.len()).filter(|t| self.alive[*t] && self.triangle_submesh[*t] == group) {
+                for vertex in self.triangles[triangle] {
+                    if remap[vertex] == u32::MAX {
+                        remap[vertex] = vertices.len() as u32;
+                        vertices.push(vertex);
+                    }
+                    indices.push(remap[vertex]);
+                }
+            }
+            if let Some(submesh) = mesh.submeshes().get(group) {
+                submeshes.push(Submesh { start, count: indices.len() - start, ..submesh.clone() });
+            }
+        }
+        let positions = vertices.iter().map(|v| self.points[self.point_of[*v]]).collect();
+        let mut simplified = Mesh::new(positions, indices).with_submeshes(submeshes);
+        if !mesh.uvs().is_empty() {
+            simplified = simplified.with_uvs(vertices.iter().map(|v| mesh.uvs()[*v]).collect());
+        }
+        if !mesh.colors().is_empty() {
+            simplified = simplified.with_colors(vertices.iter().map(|v| mesh.colors()[*v]).collect());
+        }
+        if !mesh.normals().is_empty() {
+            simplified.generate_normals();
+        }
+        if !mesh.tangents().is_empty() {
+            simplified.generate_tangents();
+        }
+        simplified
+    }
+}
+
+impl Mesh {
+    ///A copy reduced by quadric error edge collapses to about `ratio` of the triangles.
+    ///Open borders and the seams between submeshes are kept in place. Normals and tangents are regenerated.
+    pub fn simplify(&self, ratio: f32) -> Mesh {
+        self.simplify_to((self.triangle_count() as f32 * ratio.clamp(0.0, 1.0)).ceil() as usize)
+    }
+
+    ///Collapses edges until at most `triangles` remain, or no collapse keeps the surface from folding over.
+    pub fn simplify_to(&self, triangles: usize) -> Mesh {
+        match triangles >= self.triangle_count() {
+            true => self.clone(),
+            false => Simplifier::new(self).run(triangles)
+        }
+    }
+
+    ///A level of detail per ratio of this mesh's triangles, each simplified from the one before.
+    pub fn generate_lods(&self, ratios: &[f32]) -> Vec<Mesh> {
+        let mut lods: Vec<Mesh> = Vec::with_capacity(ratios.len());
+        for ratio in ratios {
+            let target = (self.triangle_count() as f32 * ratio.clamp(0.0, 1.0)).ceil() as usize;
+            let lod = lods.last().unwrap_or(self).simplify_to(target);
+            lods.push(lod);
+        }
+        lods
+    }
+}
@@ -6,6 +6,7 @@ use crate::Face::Back;
 use crate::gfx::bindings::buffers::bind_vertex_array;
 use crate::gfx::bindings::graphics::polygon_mode;
 use crate::gfx::bindings::PolygonMode;
+use crate::gfx::resource::{GlObject, Owned};
 
 
 use crate::math::linear_algebra::{mat3, mat4};
@@ -89,12 +90,12 @@ void main()
 #[derive(Debug)]
 pub struct Skybox {
     skybox: VertexArrayObject,
-    program: Program,
+    program: Owned<Program>,
     layout: Option<i32>,
 }
 
 impl Skybox {
-    pub fn _new(program: Program) -> Skybox {
+    pub fn _new(program: Owned<Program>) -> Skybox {
         use crate::utils::constructor::Constructor;
         let skybox = VertexArrayObject::new(Some(VertexArrayObjectType::Arrays(36)))
             .with_buffer(VertexBufferObject::array(BufferType::ArrayBuffer, StaticDraw, &VERTICES))
@@ -108,7 +109,7 @@ impl Skybox {
     }
 
 
-    pub fn new(skybox: VertexArrayObject, program: Program) -> Skybox {
+    pub fn new(skybox: VertexArrayObject, program: Owned<Program>) -> Skybox {
         Self {
             skybox,
             program,
@@ -127,10 +128,10 @@ impl Skybox {
         self.program.disable();
     }
 
-    pub fn skybox_program() -> Result<Program, Box<dyn Error>> {
+    pub fn skybox_program() -> Result<Owned<Program>, Box<dyn Error>> {
         use crate::{FragmentShader, VertexShader};
         use crate::utils::constructor::Constructor;
-        Program::new(frag!(SKYBOX_FRAG), vert!(SKYBOX_VERT))
+        Ok(Program::new(frag!(SKYBOX_FRAG), vert!(SKYBOX_VERT))?.owned())
     }
 
     pub fn draw(&self, camera: &Camera, wireframe: bool) {
@@ -144,7 +145,7 @@ impl Skybox {
 
         bind_vertex_array(&self.skybox);
 
-        let texture = self.skybox.textures()[0];
+        let texture = &self.skybox.textures()[0];
         texture.prepare("skybox", &0, &self.program);
 
         if let Some(vao_type) = self.skybox.vao_type() {
@@ -165,8 +166,8 @@ impl Skybox {
     pub fn skybox_mut(&mut self) -> &mut VertexArrayObject {
         &mut self.skybox
     }
-    pub fn program(&self) -> Program {
-        self.program
+    pub fn program(&self) -> &Program {
+        &self.program
     }
     pub fn layout(&self) -> Option<i32> {
         self.layout
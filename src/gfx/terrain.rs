@@ -0,0 +1,312 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::path::Path;
+use image::DynamicImage;
+use vecmath::{vec3_add, vec3_scale};
+use crate::{Camera, VertexArrayObject};
+use crate::gfx::blend_map::BlendMap;
+use crate::gfx::mesh::Mesh;
+use crate::gfx::render::RenderObject;
+use crate::math::bounds::Aabb;
+use crate::math::frustum::Frustum;
+use crate::math::linear_algebra::types::{Mat4, Vec2, Vec3};
+use crate::math::linear_algebra::vector_ext::VectorExt;
+use crate::math::noise::{Fractal, Noise};
+
+///Quads along each side of a chunk.
+pub const DEFAULT_CHUNK_SIZE: usize = 32;
+
+#[derive(Clone)]
+pub enum TerrainError {
+    ///Samples along x and z, fewer than 2 along one of them.
+    TooFewSamples([usize; 2]),
+    ///Expected and found number of heights.
+    HeightCount(usize, usize),
+}
+
+impl Debug for TerrainError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TerrainError::TooFewSamples(resolution) => {
+                f.write_fmt(format_args!("Terrains need at least 2x2 samples, found {}x{}", resolution[0], resolution[1]))
+            }
+            TerrainError::HeightCount(expected, found) => {
+                f.write_fmt(format_args!("Expected {} heights, found {}", expected, found))
+            }
+        }
+    }
+}
+
+impl Display for TerrainError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl Error for TerrainError {}
+
+///A square block of the terrain's grid with its own mesh, drawn or culled as a whole.
+#[derive(Debug, Clone)]
+pub struct TerrainChunk {
+    mesh: Mesh,
+    bounds: Aabb,
+}
+
+impl TerrainChunk {
+    pub fn mesh(&self) -> &Mesh {
+        &self.mesh
+    }
+
+    pub fn bounds(&self) -> &Aabb {
+        &self.bounds
+    }
+}
+
+///A heightfield over a grid in the xz plane, centered on the origin. Samples are stored row by row,
+///rows running along x and following each other along +z, so an image's top row lies at the smallest z.
+///Uvs span the whole terrain once, matching a blend map image laid out like the heightmap.
+#[derive(Debug, Clone)]
+pub struct Terrain {
+    resolution: [usize; 2],
+    size: Vec2,
+    heights: Vec<f32>,
+    normals: Vec<Vec3>,
+    chunk_size: usize,
+    chunks: Vec<TerrainChunk>,
+}
+
+impl Terrain {
+    ///`resolution` samples along x and z, at least 2 each, covering `size` world units along x and z.
+    ///Fails with fewer samples or when `heights` doesn't hold one per sample.
+    pub fn new(resolution: [usize; 2], heights: Vec<f32>, size: Vec2) -> Result<Terrain, TerrainError> {
+        if resolution[0] < 2 || resolution[1] < 2 {
+            return Err(TerrainError::TooFewSamples(resolution));
+        }
+        if heights.len() != resolution[0] * resolution[1] {
+            return Err(TerrainError::HeightCount(resolution[0] * resolution[1], heights.len()));
+        }
+        let mut terrain = Self {
+            resolution,
+            size,
+            heights,
+            normals: vec![],
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            chunks: vec![],
+        };
+        terrain.normals = (0..resolution[1])
+            .flat_map(|z| (0..resolution[0]).map(move |x| (x, z)))
+            .map(|(x, z)| terrain.sample_normal(x, z))
+            .collect();
+        terrain.build_chunks();
+        Ok(terrain)
+    }
+
+    ///Pixel brightness scaled to `size[1]`, one sample per pixel, with `size[0]` and `size[2]` the width and depth.
+    pub fn from_image(image: &DynamicImage, size: Vec3) -> Result<Terrain, TerrainError> {
+        let luma = image.to_luma16();
+        let heights = luma.pixels().map(|pixel| pixel.0[0] as f32 / u16::MAX as f32 * size[1]).collect();
+        Self::new([luma.width() as usize, luma.height() as usize], heights, [size[0], size[2]])
+    }
+
+    pub fn from_file<P: AsRef<Path>>(file: P, size: Vec3) -> Result<Terrain, Box<dyn Error>> {
+        Ok(Self::from_image(&image::open(file)?, size)?)
+    }
+
+    ///Fractal simplex noise, `fractal.frequency` in waves per world unit; heights range from 0 to `size[1]`.
+    pub fn from_noise(noise: &Noise, fractal: &Fractal, resolution: [usize; 2], size: Vec3) -> Result<Terrain, TerrainError> {
+        let spacing = [size[0] / resolution[0].saturating_sub(1).max(1) as f32, size[2] / resolution[1].saturating_sub(1).max(1) as f32];
+        let mut heights = Vec::with_capacity(resolution[0] * resolution[1]);
+        for z in 0..resolution[1] {
+            for x in 0..resolution[0] {
+                let height = fractal.fbm([x as f32 * spacing[0], z as f32 * spacing[1]], |[x, z]| noise.simplex2(x, z));
+                heights.push((height * 0.5 + 0.5).clamp(0.0, 1.0) * size[1]);
+            }
+        }
+        Self::new(resolution, heights, [size[0], size[2]])
+    }
+
+    ///Splits the grid into chunks of `quads` by `quads` cells.
+    pub fn with_chunk_size(mut self, quads: usize) -> Terrain {
+        self.chunk_size = quads.max(1);
+        self.build_chunks();
+        self
+    }
+
+    pub fn resolution(&self) -> [usize; 2] {
+        self.resolution
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.size
+    }
+
+    ///Distance between neighbouring samples along x and z.
+    pub fn spacing(&self) -> Vec2 {
+        [self.size[0] / (self.resolution[0] - 1) as f32, self.size[1] / (self.resolution[1] - 1) as f32]
+    }
+
+    pub fn heights(&self) -> &Vec<f32> {
+        &self.heights
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn chunks(&self) -> &Vec<TerrainChunk> {
+        &self.chunks
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.chunks.iter().skip(1).fold(self.chunks[0].bounds, |bounds, chunk| bounds.union(&chunk.bounds))
+    }
+
+    fn height(&self, x: usize, z: usize) -> f32 {
+        self.heights[z * self.resolution[0] + x]
+    }
+
+    fn position(&self, x: usize, z: usize) -> Vec3 {
+        let spacing = self.spacing();
+        [x as f32 * spacing[0] - self.size[0] * 0.5, self.height(x, z), z as f32 * spacing[1] - self.size[1] * 0.5]
+    }
+
+    ///Central differences, one sided at the edges.
+    fn sample_normal(&self, x: usize, z: usize) -> Vec3 {
+        let spacing = self.spacing();
+        let (left, right) = (x.saturating_sub(1), (x + 1).min(self.resolution[0] - 1));
+        let (back, front) = (z.saturating_sub(1), (z + 1).min(self.resolution[1] - 1));
+        let dx = (self.height(right, z) - self.height(left, z)) / ((right - left) as f32 * spacing[0]);
+        let dz = (self.height(x, front) - self.height(x, back)) / ((front - back) as f32 * spacing[1]);
+        [-dx, 1.0, -dz].normalized()
+    }
+
+    fn build_chunks(&mut self) {
+        let [columns, rows] = [self.resolution[0] - 1, self.resolution[1] - 1];
+        let size = self.chunk_size;
+        let mut chunks = vec![];
+        for z0 in (0..rows).step_by(size) {
+            for x0 in (0..columns).step_by(size) {
+                let (x1, z1) = ((x0 + size).min(columns), (z0 + size).min(rows));
+                chunks.push(self.build_chunk([x0, z0], [x1, z1]));
+            }
+        }
+        self.chunks = chunks;
+    }
+
+    ///The cells between samples `start` and `end`. Cells are split along the diagonal from their +x to their +z corner.
+    fn build_chunk(&self, start: [usize; 2], end: [usize; 2]) -> TerrainChunk {
+        let width = end[0] - start[0] + 1;
+        let mut positions = vec![];
+        let mut normals = vec![];
+        let mut uvs = vec![];
+        for z in start[1]..=end[1] {
+            for x in start[0]..=end[0] {
+                positions.push(self.position(x, z));
+                normals.push(self.normals[z * self.resolution[0] + x]);
+                uvs.push([x as f32 / (self.resolution[0] - 1) as f32, z as f32 / (self.resolution[1] - 1) as f32]);
+            }
+        }
+        let mut indices = vec![];
+        let index = |x: usize, z: usize| ((z - start[1]) * width + x - start[0]) as u32;
+        for z in start[1]..end[1] {
+            for x in start[0]..end[0] {
+                indices.extend_from_slice(&[index(x, z), index(x, z + 1), index(x + 1, z)]);
+                indices.extend_from_slice(&[index(x + 1, z), index(x, z + 1), index(x + 1, z + 1)]);
+            }
+        }
+        let bounds = Aabb::from_points(&positions).unwrap();
+        TerrainChunk {
+            mesh: Mesh::new(positions, indices).with_normals(normals).with_uvs(uvs),
+            bounds,
+        }
+    }
+
+    ///The cell under a local (x, z) and the position within it from 0 to 1, None off the terrain.
+    fn cell(&self, x: f32, z: f32) -> Option<([usize; 2], Vec2)> {
+        let spacing = self.spacing();
+        let fx = (x + self.size[0] * 0.5) / spacing[0];
+        let fz = (z + self.size[1] * 0.5) / spacing[1];
+        let [columns, rows] = [(self.resolution[0] - 1) as f32, (self.resolution[1] - 1) as f32];
+        if !(0.0..=columns).contains(&fx) || !(0.0..=rows).contains(&fz) {
+            return None;
+        }
+        let (cx, cz) = ((fx.floor() as usize).min(self.resolution[0] - 2), (fz.floor() as usize).min(self.resolution[1] - 2));
+        Some(([cx, cz], [fx - cx as f32, fz - cz as f32]))
+    }
+
+    ///Height of the drawn surface at a local (x, z), following the triangles of the chunk meshes.
+    pub fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        let ([cx, cz], [tx, tz]) = self.cell(x, z)?;
+        let h00 = self.height(cx, cz);
+        let h10 = self.height(cx + 1, cz);
+        let h01 = self.height(cx, cz + 1);
+        let h11 = self.height(cx + 1, cz + 1);
+        Some(match tx + tz <= 1.0 {
+            true => h00 + (h10 - h00) * tx + (h01 - h00) * tz,
+            false => h11 + (h01 - h11) * (1.0 - tx) + (h10 - h11) * (1.0 - tz)
+        })
+    }
+
+    ///The vertex normals around a local (x, z) blended bilinearly, as the surface is shaded.
+    pub fn normal_at(&self, x: f32, z: f32) -> Option<Vec3> {
+        let ([cx, cz], [tx, tz]) = self.cell(x, z)?;
+        let normal = |x: usize, z: usize| self.normals[z * self.resolution[0] + x];
+        let weights = [
+            (normal(cx, cz), (1.0 - tx) * (1.0 - tz)),
+            (normal(cx + 1, cz), tx * (1.0 - tz)),
+            (normal(cx, cz + 1), (1.0 - tx) * tz),
+            (normal(cx + 1, cz + 1), tx * tz),
+        ];
+        let sum = weights.iter().fold([0.0; 3], |sum, (n, w)| vec3_add(sum, vec3_scale(*n, *w)));
+        Some(sum.normalized_or([0.0, 1.0, 0.0]))
+    }
+
+    ///Chunks whose bounds, placed by `model`, touch `frustum`.
+    pub fn visible_chunks(&self, frustum: &Frustum, model: &Mat4) -> Vec<usize> {
+        (0..self.chunks.len()).filter(|i| frustum.intersects_aabb(&self.chunks[*i].bounds.transformed(model))).collect()
+    }
+}
+
+///Draws a terrain's chunks with a blend map's textures, skipping chunks outside the camera's view.
+#[derive(Debug)]
+pub struct TerrainRenderer {
+    blend_map: BlendMap,
+    vaos: Vec<VertexArrayObject>,
+    bounds: Vec<Aabb>,
+}
+
+impl TerrainRenderer {
+    ///`blend_map` only needs textures, see `BlendMap::from_textures`.
+    pub fn new(terrain: &Terrain, blend_map: BlendMap) -> TerrainRenderer {
+        Self {
+            blend_map,
+            vaos: terrain.chunks.iter().map(|chunk| chunk.mesh.build_vao()).collect(),
+            bounds: terrain.chunks.iter().map(|chunk| chunk.bounds).collect(),
+        }
+    }
+
+    pub fn blend_map(&self) -> &BlendMap {
+        &self.blend_map
+    }
+
+    ///Draws the visible chunks and returns how many were drawn.
+    pub fn draw_visible(&self, camera: &Camera, model: &Mat4) -> usize {
+        let frustum = Frustum::from_camera(camera);
+        let visible = self.vaos.iter().zip(&self.bounds)
+            .filter(|(_, bounds)| frustum.intersects_aabb(&bounds.transformed(model)))
+            .map(|(vao, _)| vao)
+            .collect::<Vec<&VertexArrayObject>>();
+        self.blend_map.draw_with(camera, model, visible.iter().copied());
+        visible.len()
+    }
+}
+
+impl RenderObject for TerrainRenderer {
+    fn vao(&self) -> &VertexArrayObject {
+        self.blend_map.vao()
+    }
+
+    fn draw(&self, camera: &Camera, model: &Mat4) {
+        self.draw_visible(camera, model);
+    }
+}
@@ -1,10 +1,12 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::path::Path;
 use image::{DynamicImage, open};
-use crate::gfx::bindings::{ColorFormat, MipMaps, TextureParamName, TextureTarget, TextureWrapping};
-use crate::gfx::bindings::ColorFormat::RGB;
+use crate::gfx::bindings::{ColorFormat, DataType, MipMaps, TextureParamName, TextureTarget, TextureWrapping};
 use crate::gfx::bindings::TextureParamName::{TextureMagFilter, TextureMinFilter, TextureWrapR, TextureWrapS, TextureWrapT};
 use crate::gfx::bindings::TextureTarget::TextureCubeMap;
-use crate::gfx::bindings::texturing::{active_texture, bind_texture, bind_texture_raw, gen_textures, generate_mipmap, tex_image_2d_u_bytes, tex_image_2d_u_bytes_raw_target, tex_parameteri};
+use crate::loader::textures::CompressedImage;
+use crate::gfx::bindings::texturing::{active_texture, compressed_tex_image_2d, bind_texture, delete_texture, bind_texture_raw, gen_textures, generate_mipmap, max_anisotropy, tex_image_2d, tex_image_2d_empty, tex_image_3d, tex_parameterf, tex_parameteri, tex_parameteri_raw, tex_sub_image_3d, unpack_alignment};
 use crate::{GLConsts, Program, uniform_1i};
 use crate::gfx::bindings::uniforms::uniform_location;
 use crate::GLConsts::{ClampToEdge, Linear};
@@ -28,11 +30,28 @@ impl Default for Wrapping {
     }
 }
 
+impl Wrapping {
+    pub fn new(wrap_s: TextureWrapping, wrap_t: TextureWrapping) -> Wrapping {
+        Self { wrap_s, wrap_t, wrap_r: None }
+    }
+
+    ///The same wrapping on every axis, including R for 3D textures and cube maps.
+    pub fn all(wrapping: TextureWrapping) -> Wrapping {
+        Self { wrap_s: wrapping, wrap_t: wrapping, wrap_r: Some(wrapping) }
+    }
+
+    pub fn with_r(mut self, wrap_r: TextureWrapping) -> Wrapping {
+        self.wrap_r = Some(wrap_r);
+        self
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct MipMapping {
     min: MipMaps,
     mag: MipMaps,
     level: i32,
+    anisotropy: Option<f32>,
 }
 
 impl Default for MipMapping {
@@ -41,10 +60,65 @@ impl Default for MipMapping {
             min: MipMaps::LinearMipmapLinear,
             mag: MipMaps::Linear,
             level: 0,
+            anisotropy: None,
         }
     }
 }
 
+impl MipMapping {
+    pub fn new(min: MipMaps, mag: MipMaps) -> MipMapping {
+        Self { min, mag, level: 0, anisotropy: None }
+    }
+
+    ///Samples up to `anisotropy` texels along the steepest axis, clamped to what the driver supports.
+    pub fn with_anisotropy(mut self, anisotropy: f32) -> MipMapping {
+        self.anisotropy = Some(anisotropy);
+        self
+    }
+
+    pub fn with_level(mut self, level: i32) -> MipMapping {
+        self.level = level;
+        self
+    }
+
+    ///Whether the min filter samples mip levels, i.e. whether uploads need `generate_mipmap`.
+    pub fn uses_mipmaps(&self) -> bool {
+        match self.min {
+            MipMaps::Linear | MipMaps::Nearest => false,
+            _ => true
+        }
+    }
+}
+
+///How color textures store their data. Color maps authored by artists, like base color, are usually sRGB,
+///data such as normals, roughness or heights is linear.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorSpace {
+    Linear,
+    Srgb,
+}
+
+#[derive(Debug, Clone)]
+pub enum TextureError {
+    ///No images were given for a texture array.
+    Empty,
+    ///Layer `n` of an array differs in size or format from the first.
+    LayerMismatch(usize),
+}
+
+impl Display for TextureError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextureError::Empty => f.write_str("Texture array without layers"),
+            TextureError::LayerMismatch(layer) => {
+                f.write_fmt(format_args!("Layer {} differs in size or format from layer 0", layer))
+            }
+        }
+    }
+}
+
+impl Error for TextureError {}
+
 #[macro_export]
 macro_rules! texture {
     ($t:expr) => {
@@ -52,7 +126,9 @@ macro_rules! texture {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+///A texture name and its parameters. Not `Clone`, holders keep it in an `Owned` or `Shared`, which
+///delete it on drop.
+#[derive(Debug)]
 pub struct Texture {
     id: u32,
     target: TextureTarget,
@@ -61,66 +137,272 @@ pub struct Texture {
     color_format: ColorFormat,
     width: i32,
     height: i32,
+    ///Layers of arrays and depth of 3D textures, 1 otherwise.
+    depth: i32,
 }
 
+///Pixels in client memory. `color_format` and `data_type` describe the source data, e.g. `RGBA` bytes
+///or `RGB` floats, from which `internal_format` picks the format stored on the GPU.
 #[derive(Debug, Clone)]
 pub struct ImageBuffer {
     bytes: Vec<u8>,
     width: i32,
     height: i32,
+    depth: i32,
     color_format: ColorFormat,
+    data_type: DataType,
 }
 
 impl<'a> ImageBuffer {
     pub fn new(bytes: &'a [u8], width: i32, height: i32, color_format: ColorFormat) -> Self {
-        Self { bytes: bytes.to_vec(), width, height, color_format }
+        Self { bytes: bytes.to_vec(), width, height, depth: 1, color_format, data_type: DataType::UnsignedByte }
     }
 
+    pub fn with_data_type(mut self, data_type: DataType) -> ImageBuffer {
+        self.data_type = data_type;
+        self
+    }
+
+    ///Volume data for 3D textures, `depth` slices of `width` by `height` pixels.
+    pub fn volume(bytes: Vec<u8>, width: i32, height: i32, depth: i32, color_format: ColorFormat, data_type: DataType) -> ImageBuffer {
+        Self { bytes, width, height, depth, color_format, data_type }
+    }
+
+    ///Keeps the image's channels and bit depth: grayscale uploads as `Red`, alpha is kept,
+    ///16 bit images upload as shorts and HDR images as floats.
     pub fn from_image(image: &'a DynamicImage) -> ImageBuffer {
-        let color_format = ColorFormat::RGB;
-        let width = image.width() as i32;
-        let height = image.height() as i32;
+        let (color_format, data_type) = match image {
+            DynamicImage::ImageLuma8(_) => (ColorFormat::Red, DataType::UnsignedByte),
+            DynamicImage::ImageLumaA8(_) => (ColorFormat::RG, DataType::UnsignedByte),
+            DynamicImage::ImageRgb8(_) => (ColorFormat::RGB, DataType::UnsignedByte),
+            DynamicImage::ImageRgba8(_) => (ColorFormat::RGBA, DataType::UnsignedByte),
+            DynamicImage::ImageLuma16(_) => (ColorFormat::Red, DataType::UnsignedShort),
+            DynamicImage::ImageLumaA16(_) => (ColorFormat::RG, DataType::UnsignedShort),
+            DynamicImage::ImageRgb16(_) => (ColorFormat::RGB, DataType::UnsignedShort),
+            DynamicImage::ImageRgba16(_) => (ColorFormat::RGBA, DataType::UnsignedShort),
+            DynamicImage::ImageRgb32F(_) => (ColorFormat::RGB, DataType::Float),
+            DynamicImage::ImageRgba32F(_) => (ColorFormat::RGBA, DataType::Float),
+            _ => return Self::from_image_owned(DynamicImage::ImageRgba8(image.to_rgba8()))
+        };
         Self {
             bytes: image.as_bytes().to_vec(),
-            width,
-            height,
+            width: image.width() as i32,
+            height: image.height() as i32,
+            depth: 1,
             color_format,
+            data_type,
         }
     }
 
     pub fn from_image_owned(image: DynamicImage) -> ImageBuffer {
-        let color_format = ColorFormat::RGB;
-        let width = image.width() as i32;
-        let height = image.height() as i32;
-        Self {
-            bytes: image.as_bytes().to_vec(),
-            width,
-            height,
-            color_format,
+        Self::from_image(&image)
+    }
+
+    ///The sized GPU format matching the source data. sRGB only applies to 8 bit RGB(A),
+    ///there are no sRGB formats for fewer channels or higher precision.
+    pub fn internal_format(&self, color_space: ColorSpace) -> ColorFormat {
+        let srgb = color_space == ColorSpace::Srgb;
+        match (self.color_format.channels(), self.data_type) {
+            (1, DataType::UnsignedByte) => ColorFormat::R8,
+            (2, DataType::UnsignedByte) => ColorFormat::RG8,
+            (3, DataType::UnsignedByte) if srgb => ColorFormat::SRGB8,
+            (3, DataType::UnsignedByte) => ColorFormat::RGB8,
+            (4, DataType::UnsignedByte) if srgb => ColorFormat::SRGB8Alpha8,
+            (4, DataType::UnsignedByte) => ColorFormat::RGBA8,
+            (1, DataType::UnsignedShort) => ColorFormat::R16,
+            (2, DataType::UnsignedShort) => ColorFormat::RG16,
+            (3, DataType::UnsignedShort) => ColorFormat::RGB16,
+            (4, DataType::UnsignedShort) => ColorFormat::RGBA16,
+            (1, _) => ColorFormat::R32F,
+            (2, _) => ColorFormat::RG32F,
+            (3, _) => ColorFormat::RGB32F,
+            _ => ColorFormat::RGBA32F,
         }
     }
 
+    ///Uploads the pixels into mip level `texture.mip_mapping.level` of the bound texture.
     pub fn generate(&self, texture: &Texture) {
         let target = texture.target;
-        tex_image_2d_u_bytes(target, texture.mip_mapping.level, texture.color_format, self.width, self.height, self.color_format, &self.bytes[..]);
-        generate_mipmap(texture.target);
+        //Rows of RGB and single channel images aren't padded to 4 bytes.
+        unpack_alignment(1);
+        match target {
+            TextureTarget::Texture3D | TextureTarget::Texture2DArray => {
+                tex_image_3d(target, texture.mip_mapping.level, texture.color_format, self.width, self.height, self.depth, self.color_format, self.data_type, self.bytes.as_ptr() as *const _);
+            }
+            _ => {
+                tex_image_2d(target as u32, texture.mip_mapping.level, texture.color_format, self.width, self.height, self.color_format, self.data_type, self.bytes.as_ptr() as *const _);
+            }
+        }
+        unpack_alignment(4);
+        if texture.mip_mapping.uses_mipmaps() {
+            generate_mipmap(texture.target);
+        }
+    }
+
+    pub fn bytes(&self) -> &Vec<u8> {
+        &self.bytes
+    }
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+    pub fn depth(&self) -> i32 {
+        self.depth
+    }
+    pub fn color_format(&self) -> ColorFormat {
+        self.color_format
+    }
+    pub fn data_type(&self) -> DataType {
+        self.data_type
     }
 }
 
 impl Texture {
     pub fn new(target: TextureTarget, wrapping: Wrapping, mip_mapping: MipMapping, color_format: ColorFormat, image_buffer: &'_ ImageBuffer) -> Self {
         let id = gen_textures(1);
-        let me = Self { id, target, wrapping, mip_mapping, color_format, width: image_buffer.width, height: image_buffer.height };
+        let me = Self { id, target, wrapping, mip_mapping, color_format, width: image_buffer.width, height: image_buffer.height, depth: image_buffer.depth };
         me.generate(Some(image_buffer));
         me
     }
+
+    ///Uploads the image as linear data with repeat wrapping and trilinear filtering.
     pub fn from_image(image: &DynamicImage) -> Result<Texture, Box<dyn std::error::Error>> {
-        let target = TextureTarget::Texture2D;
-        let texture_wrapping = Wrapping::default();
-        let mip_mapping = MipMapping::default();
-        let color_format = ColorFormat::RGB;
-        let image_buffer = ImageBuffer::from_image(&image);
-        Ok(Self::new(target, texture_wrapping, mip_mapping, color_format, &image_buffer))
+        Self::from_image_with(image, ColorSpace::Linear, Wrapping::default(), MipMapping::default())
+    }
+
+    pub fn from_image_with(image: &DynamicImage, color_space: ColorSpace, wrapping: Wrapping, mip_mapping: MipMapping) -> Result<Texture, Box<dyn std::error::Error>> {
+        let image_buffer = ImageBuffer::from_image(image);
+        let color_format = image_buffer.internal_format(color_space);
+        Ok(Self::new(TextureTarget::Texture2D, wrapping, mip_mapping, color_format, &image_buffer))
+    }
+
+    ///Uploads a DDS or KTX2 image with its pre-baked mip chain. Compressed data can't have mipmaps generated,
+    ///so a mipmapped min filter falls back to linear for images with a single level.
+    pub fn from_compressed(image: &CompressedImage, wrapping: Wrapping, mut mip_mapping: MipMapping) -> Texture {
+        if image.levels.len() < 2 && mip_mapping.uses_mipmaps() {
+            mip_mapping.min = MipMaps::Linear;
+        }
+        let me = Self {
+            id: gen_textures(1),
+            target: TextureTarget::Texture2D,
+            wrapping,
+            mip_mapping,
+            color_format: image.format.color_format(image.srgb),
+            width: image.width as i32,
+            height: image.height as i32,
+            depth: 1,
+        };
+        me.generate(None);
+        tex_parameteri_raw(me.target, TextureParamName::TextureMaxLevel, image.levels.len() as i32 - 1);
+        for (level, data) in image.levels.iter().enumerate() {
+            let (width, height) = image.level_dimensions(level);
+            compressed_tex_image_2d(me.target, level as i32, me.color_format, width as i32, height as i32, data);
+        }
+        me
+    }
+
+    pub fn from_compressed_file<P: AsRef<Path>>(path: P) -> Result<Texture, Box<dyn std::error::Error>> {
+        let image = crate::loader::textures::load(path)?;
+        Ok(Self::from_compressed(&image, Wrapping::default(), MipMapping::default()))
+    }
+
+    ///A 2D texture array with one layer per image. Every layer needs the size and format of the first.
+    pub fn array_2d(layers: &[ImageBuffer], color_space: ColorSpace, wrapping: Wrapping, mip_mapping: MipMapping) -> Result<Texture, TextureError> {
+        let first = layers.first().ok_or(TextureError::Empty)?;
+        for (index, layer) in layers.iter().enumerate() {
+            if layer.width != first.width || layer.height != first.height
+                || layer.color_format as u32 != first.color_format as u32 || layer.data_type != first.data_type {
+                return Err(TextureError::LayerMismatch(index));
+            }
+        }
+        let me = Self {
+            id: gen_textures(1),
+            target: TextureTarget::Texture2DArray,
+            wrapping,
+            mip_mapping,
+            color_format: first.internal_format(color_space),
+            width: first.width,
+            height: first.height,
+            depth: layers.len() as i32,
+        };
+        me.generate(None);
+        unpack_alignment(1);
+        tex_image_3d(me.target, mip_mapping.level, me.color_format, me.width, me.height, me.depth, first.color_format, first.data_type, std::ptr::null());
+        for (index, layer) in layers.iter().enumerate() {
+            tex_sub_image_3d(me.target, mip_mapping.level, [0, 0, index as i32], [me.width, me.height, 1], layer.color_format, layer.data_type, &layer.bytes);
+        }
+        unpack_alignment(4);
+        if mip_mapping.uses_mipmaps() {
+            generate_mipmap(me.target);
+        }
+        Ok(me)
+    }
+
+    ///A 3D texture from volume data, see `ImageBuffer::volume`.
+    pub fn texture_3d(volume: &ImageBuffer, color_space: ColorSpace, wrapping: Wrapping, mip_mapping: MipMapping) -> Texture {
+        let color_format = volume.internal_format(color_space);
+        Self::new(TextureTarget::Texture3D, wrapping, mip_mapping, color_format, volume)
+    }
+
+    ///Creates an empty, non mip-mapped texture that can be attached to a framebuffer.
+    pub fn render_target(width: i32, height: i32, color_format: ColorFormat) -> Texture {
+        let id = gen_textures(1);
+        let me = Self {
+            id,
+            target: TextureTarget::Texture2D,
+            wrapping: Wrapping {
+                wrap_s: TextureWrapping::ClampToEdge,
+                wrap_t: TextureWrapping::ClampToEdge,
+                wrap_r: None,
+            },
+            mip_mapping: MipMapping::new(MipMaps::Linear, MipMaps::Linear),
+            color_format,
+            width,
+            height,
+            depth: 1,
+        };
+        me.generate(None);
+        let data_type = match color_format {
+            ColorFormat::R16F | ColorFormat::RG16F | ColorFormat::RGB16F | ColorFormat::RGBA16F
+            | ColorFormat::R32F | ColorFormat::RG32F | ColorFormat::RGB32F | ColorFormat::RGBA32F => DataType::Float,
+            _ => DataType::UnsignedByte
+        };
+        tex_image_2d_empty(me.target, 0, color_format, width, height, ColorFormat::RGBA, data_type);
+        me
+    }
+
+    ///Creates an empty 24 bit depth texture with nearest filtering, for depth only framebuffers such as shadow maps.
+    pub fn depth_target(width: i32, height: i32) -> Texture {
+        Self::depth_texture(width, height, ColorFormat::DepthComponent24)
+    }
+
+    ///Creates an empty depth or depth-stencil texture with nearest filtering. `color_format` is one of the
+    ///sized depth formats, e.g. `DepthComponent32F` or `Depth24Stencil8`.
+    pub fn depth_texture(width: i32, height: i32, color_format: ColorFormat) -> Texture {
+        let id = gen_textures(1);
+        let me = Self {
+            id,
+            target: TextureTarget::Texture2D,
+            wrapping: Wrapping {
+                wrap_s: TextureWrapping::ClampToEdge,
+                wrap_t: TextureWrapping::ClampToEdge,
+                wrap_r: None,
+            },
+            mip_mapping: MipMapping::new(MipMaps::Nearest, MipMaps::Nearest),
+            color_format,
+            width,
+            height,
+            depth: 1,
+        };
+        me.generate(None);
+        let (source, data_type) = match color_format {
+            ColorFormat::DepthStencil | ColorFormat::Depth24Stencil8 => (ColorFormat::DepthStencil, DataType::UnsignedInt248),
+            _ => (ColorFormat::DepthComponent, DataType::Float)
+        };
+        tex_image_2d_empty(me.target, 0, color_format, width, height, source, data_type);
+        me
     }
 
     pub fn cube_map(
@@ -136,16 +418,18 @@ impl Texture {
         let mut width = 0;
         let mut height = 0;
         let n = [&pos_x, &neg_x, &pos_y, &neg_y, &pos_z, &neg_z];
+        unpack_alignment(1);
         {
-            tex_image_2d_u_bytes_raw_target(GLConsts::TextureCubeMapPositiveX as u32, 0, RGB, pos_x.width, pos_x.height, RGB, &pos_x.bytes[..]);
-            tex_image_2d_u_bytes_raw_target(GLConsts::TextureCubeMapNegativeX as u32, 0, RGB, neg_x.width, neg_x.height, RGB, &neg_x.bytes[..]);
+            tex_image_2d(GLConsts::TextureCubeMapPositiveX as u32, 0, pos_x.internal_format(ColorSpace::Linear), pos_x.width, pos_x.height, pos_x.color_format, pos_x.data_type, pos_x.bytes.as_ptr() as *const _);
+            tex_image_2d(GLConsts::TextureCubeMapNegativeX as u32, 0, neg_x.internal_format(ColorSpace::Linear), neg_x.width, neg_x.height, neg_x.color_format, neg_x.data_type, neg_x.bytes.as_ptr() as *const _);
 
-            tex_image_2d_u_bytes_raw_target(GLConsts::TextureCubeMapPositiveY as u32, 0, RGB, pos_y.width, pos_y.height, RGB, &pos_y.bytes[..]);
-            tex_image_2d_u_bytes_raw_target(GLConsts::TextureCubeMapNegativeY as u32, 0, RGB, neg_y.width, neg_y.height, RGB, &neg_y.bytes[..]);
+            tex_image_2d(GLConsts::TextureCubeMapPositiveY as u32, 0, pos_y.internal_format(ColorSpace::Linear), pos_y.width, pos_y.height, pos_y.color_format, pos_y.data_type, pos_y.bytes.as_ptr() as *const _);
+            tex_image_2d(GLConsts::TextureCubeMapNegativeY as u32, 0, neg_y.internal_format(ColorSpace::Linear), neg_y.width, neg_y.height, neg_y.color_format, neg_y.data_type, neg_y.bytes.as_ptr() as *const _);
 
-            tex_image_2d_u_bytes_raw_target(GLConsts::TextureCubeMapPositiveZ as u32, 0, RGB, pos_z.width, pos_z.height, RGB, &pos_z.bytes[..]);
-            tex_image_2d_u_bytes_raw_target(GLConsts::TextureCubeMapNegativeZ as u32, 0, RGB, neg_z.width, neg_z.height, RGB, &neg_z.bytes[..]);
+            tex_image_2d(GLConsts::TextureCubeMapPositiveZ as u32, 0, pos_z.internal_format(ColorSpace::Linear), pos_z.width, pos_z.height, pos_z.color_format, pos_z.data_type, pos_z.bytes.as_ptr() as *const _);
+            tex_image_2d(GLConsts::TextureCubeMapNegativeZ as u32, 0, neg_z.internal_format(ColorSpace::Linear), neg_z.width, neg_z.height, neg_z.color_format, neg_z.data_type, neg_z.bytes.as_ptr() as *const _);
         }//POS_X
+        unpack_alignment(4);
         tex_parameteri(TextureCubeMap, TextureMinFilter, Linear);
         tex_parameteri(TextureCubeMap, TextureMagFilter, Linear);
 
@@ -161,14 +445,11 @@ impl Texture {
                 wrap_t: TextureWrapping::ClampToEdge,
                 wrap_r: Some(TextureWrapping::ClampToEdge),
             },
-            mip_mapping: MipMapping {
-                min: MipMaps::Linear,
-                mag: MipMaps::Linear,
-                level: 0,
-            },
+            mip_mapping: MipMapping::new(MipMaps::Linear, MipMaps::Linear),
             color_format: ColorFormat::RGB,
             width,
             height,
+            depth: 1,
         };
         bind_texture_raw(TextureCubeMap, &0);
         t.generate(None);
@@ -194,6 +475,11 @@ impl Texture {
         }
     }
 
+    ///Deletes the GL texture. Copies of this texture are invalid afterwards.
+    pub fn delete(&self) {
+        delete_texture(self);
+    }
+
     pub fn generate(&self, buffer: Option<&'_ ImageBuffer>) {
         self.bind();
         tex_parameteri(self.target, TextureParamName::TextureWrapS, self.wrapping.wrap_s.into());
@@ -203,6 +489,9 @@ impl Texture {
         }
         tex_parameteri(self.target, TextureParamName::TextureMinFilter, self.mip_mapping.min.into());
         tex_parameteri(self.target, TextureParamName::TextureMagFilter, self.mip_mapping.mag.into());
+        if let Some(anisotropy) = self.mip_mapping.anisotropy {
+            tex_parameterf(self.target, TextureParamName::TextureMaxAnisotropy, anisotropy.clamp(1.0, max_anisotropy()));
+        }
         if let Some(buffer) = buffer {
             buffer.generate(self);
         }
@@ -243,4 +532,7 @@ impl Texture {
     pub fn height(&self) -> i32 {
         self.height
     }
+    pub fn depth(&self) -> i32 {
+        self.depth
+    }
 }
\ No newline at end of file
@@ -1,11 +1,11 @@
 use std::ops::BitOr;
-use std::sync::{Arc, Mutex};
-use std::thread::spawn;
-use crate::{Texture, texture};
+use crate::Texture;
+use crate::gfx::resource::Shared;
+use crate::gfx::texture::ColorSpace;
+use crate::loader::assets::{AssetHandle, AssetLoader};
 use enum_iterator::{all, Sequence};
-use image::{load_from_memory};
 
-static mut TEXTURE_LIBRARY: Option<Arc<Mutex<TextureLibrary>>> = None;
+static mut TEXTURE_LIBRARY: Option<TextureLibrary> = None;
 
 #[derive(Debug, Clone, Copy, Sequence)]
 #[repr(u16)]
@@ -14,6 +14,64 @@ pub enum TextureLibraryFlag {
     Wood = 0x02,
     Water = 0x04,
     Dirt = 0x08,
+    Granite = 0x10,
+}
+
+///What a texture in a PBR set is used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Sequence)]
+pub enum TextureRole {
+    AmbientOcclusion,
+    BaseColor,
+    Height,
+    Metallic,
+    Normal,
+    Roughness,
+}
+
+///A PBR texture set, addressed by role. Sets don't need to provide every role. Textures are shared
+///with the library or loader that created them.
+#[derive(Debug, Clone, Default)]
+pub struct TextureSet {
+    textures: Vec<(TextureRole, Shared<Texture>)>,
+}
+
+impl TextureSet {
+    pub fn new() -> TextureSet {
+        Self::default()
+    }
+
+    pub fn with(mut self, role: TextureRole, texture: Shared<Texture>) -> TextureSet {
+        self.put(role, texture);
+        self
+    }
+
+    pub fn put(&mut self, role: TextureRole, texture: Shared<Texture>) {
+        self.textures.retain(|(r, _)| *r != role);
+        self.textures.push((role, texture));
+    }
+
+    pub fn get(&self, role: TextureRole) -> Option<&Shared<Texture>> {
+        self.textures.iter().find(|(r, _)| *r == role).map(|(_, texture)| texture)
+    }
+
+    pub fn contains(&self, role: TextureRole) -> bool {
+        self.get(role).is_some()
+    }
+
+    ///Textures ordered by role, i.e. in the order of `TextureRole`.
+    pub fn textures(&self) -> Vec<Shared<Texture>> {
+        let mut textures = vec![];
+        for role in all::<TextureRole>() {
+            if let Some(texture) = self.get(role) {
+                textures.push(texture.clone());
+            }
+        }
+        textures
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
 }
 
 impl Into<usize> for TextureLibraryFlag {
@@ -22,7 +80,8 @@ impl Into<usize> for TextureLibraryFlag {
             TextureLibraryFlag::Metal => 0,
             TextureLibraryFlag::Wood => 1,
             TextureLibraryFlag::Water => 2,
-            TextureLibraryFlag::Dirt => 3
+            TextureLibraryFlag::Dirt => 3,
+            TextureLibraryFlag::Granite => 4
         }
     }
 }
@@ -43,25 +102,50 @@ impl BitOr<TextureLibraryFlag> for TextureLibraryFlag {
     }
 }
 
+///The embedded PBR sets. Images are decoded by the `AssetLoader` workers and uploaded on the render thread,
+///until then `get_texture_set` returns the textures that are ready so far.
 pub struct TextureLibrary {
-    library: Vec<Vec<Texture>>,
+    library: Vec<Vec<(TextureRole, AssetHandle<Shared<Texture>>)>>,
+}
+
+///Base color maps are authored in sRGB, every other role holds linear data.
+fn color_space(role: TextureRole) -> ColorSpace {
+    match role {
+        TextureRole::BaseColor => ColorSpace::Srgb,
+        _ => ColorSpace::Linear
+    }
+}
+
+fn load(roles: &[(TextureRole, &'static [u8])]) -> Vec<(TextureRole, AssetHandle<Shared<Texture>>)> {
+    roles.iter()
+        .map(|(role, bytes)| (*role, AssetLoader::load_texture_bytes(*bytes, color_space(*role))))
+        .collect()
 }
 
 impl TextureLibrary {
-    pub fn get_textures(texture: TextureLibraryFlag) -> Vec<Texture> {
+    ///Textures of the set ordered by `TextureRole`.
+    pub fn get_textures(texture: TextureLibraryFlag) -> Vec<Shared<Texture>> {
+        Self::get_texture_set(texture).textures()
+    }
+
+    pub fn get_texture(texture: TextureLibraryFlag, role: TextureRole) -> Option<Shared<Texture>> {
+        Self::get_texture_set(texture).get(role).cloned()
+    }
+
+    ///The textures of the set uploaded so far.
+    pub fn get_texture_set(texture: TextureLibraryFlag) -> TextureSet {
         unsafe {
             let id: usize = texture.into();
             match &TEXTURE_LIBRARY {
                 None => panic!("Texture library not initialized."),
-                Some(texture_library) => {
-                    match texture_library.lock() {
-                        Ok(library) => {
-                            library.library.get(id).unwrap().clone()
-                        }
-                        Err(e) => {
-                            panic!("{}", e)
+                Some(library) => {
+                    let mut set = TextureSet::new();
+                    for (role, handle) in library.library.get(id).unwrap() {
+                        if let Some(texture) = handle.get() {
+                            set.put(*role, texture);
                         }
                     }
+                    set
                 }
             }
         }
@@ -69,50 +153,60 @@ impl TextureLibrary {
     pub fn is_loaded() -> bool {
         unsafe { TEXTURE_LIBRARY.is_some() }
     }
+    ///Drops the library's handles, textures still used by materials stay alive. Called by the app on shutdown.
+    pub unsafe fn shutdown() {
+        TEXTURE_LIBRARY = None;
+    }
+    ///Whether every requested texture has been uploaded or has failed.
+    pub fn is_ready() -> bool {
+        unsafe {
+            match &TEXTURE_LIBRARY {
+                None => false,
+                Some(library) => library.library.iter().flatten().all(|(_, handle)| !handle.is_loading())
+            }
+        }
+    }
+    ///Queues the sets in `flags` on the asset loader, which must be initialized first, see `Application::super_init`.
     pub unsafe fn init(flags: u16) {
-        TEXTURE_LIBRARY = Some(Arc::new(Mutex::new(TextureLibrary { library: vec![] })));
-
-        spawn(move || {
-            let tex_lib = TEXTURE_LIBRARY.as_mut().unwrap();
-            let libs = all::<TextureLibraryFlag>();
-            let mut library = vec![];
-
-            let mut index = 0;
-            for tex_lib in libs {
-                let mut lib = vec![];
-
-                if (flags >> index & 1) != 0 {
-                    match tex_lib {
-                        TextureLibraryFlag::Metal => {
-                            lib = vec![
-                                texture!(&load_from_memory(include_bytes!("../../../res/textures/metal/Metal_006_ambientOcclusion.jpg")).unwrap()).unwrap(),
-                                texture!(&load_from_memory(include_bytes!("../../../res/textures/metal/Metal_006_basecolor.jpg")).unwrap()).unwrap(),
-                                texture!(&load_from_memory(include_bytes!("../../../res/textures/metal/Metal_006_height.png")).unwrap()).unwrap(),
-                                texture!(&load_from_memory(include_bytes!("../../../res/textures/metal/Metal_006_metallic.jpg")).unwrap()).unwrap(),
-                                texture!(&load_from_memory(include_bytes!("../../../res/textures/metal/Metal_006_normal.jpg")).unwrap()).unwrap(),
-                                texture!(&load_from_memory(include_bytes!("../../../res/textures/metal/Metal_006_roughness.jpg")).unwrap()).unwrap(),
-                            ];
-                        }
-                        TextureLibraryFlag::Wood => {
-                            panic!("Wood not yet implemented.")
-                        }
-                        TextureLibraryFlag::Water => {
-                            //panic!("Water not yet implemented.")
-                        }
-                        TextureLibraryFlag::Dirt => {
-                            panic!("Dirt not yet implemented.")
-                        }
+        let mut library = vec![];
+        for (index, tex_lib) in all::<TextureLibraryFlag>().enumerate() {
+            let mut lib = vec![];
+
+            if (flags >> index & 1) != 0 {
+                match tex_lib {
+                    TextureLibraryFlag::Metal => {
+                        lib = load(&[
+                            (TextureRole::AmbientOcclusion, include_bytes!("../../../res/textures/metal/Metal_006_ambientOcclusion.jpg")),
+                            (TextureRole::BaseColor, include_bytes!("../../../res/textures/metal/Metal_006_basecolor.jpg")),
+                            (TextureRole::Height, include_bytes!("../../../res/textures/metal/Metal_006_height.png")),
+                            (TextureRole::Metallic, include_bytes!("../../../res/textures/metal/Metal_006_metallic.jpg")),
+                            (TextureRole::Normal, include_bytes!("../../../res/textures/metal/Metal_006_normal.jpg")),
+                            (TextureRole::Roughness, include_bytes!("../../../res/textures/metal/Metal_006_roughness.jpg")),
+                        ]);
+                    }
+                    TextureLibraryFlag::Granite => {
+                        //The granite set has no base color or height map.
+                        lib = load(&[
+                            (TextureRole::AmbientOcclusion, include_bytes!("../../../res/textures/granite/gray-granite-flecks-ao.png")),
+                            (TextureRole::Metallic, include_bytes!("../../../res/textures/granite/gray-granite-flecks-Metallic.png")),
+                            (TextureRole::Normal, include_bytes!("../../../res/textures/granite/gray-granite-flecks-Normal-dx.png")),
+                            (TextureRole::Roughness, include_bytes!("../../../res/textures/granite/gray-granite-flecks-Roughness.png")),
+                        ]);
+                    }
+                    TextureLibraryFlag::Wood => {
+                        panic!("Wood not yet implemented.")
+                    }
+                    TextureLibraryFlag::Water => {
+                        //panic!("Water not yet implemented.")
+                    }
+                    TextureLibraryFlag::Dirt => {
+                        panic!("Dirt not yet implemented.")
                     }
                 }
-
-                library.push(lib);
-                index += 1;
             }
-            let tex_l = TextureLibrary {
-                library
-            };
-            let mut lib = tex_lib.lock().unwrap();
-            *lib = tex_l;
-        });
+
+            library.push(lib);
+        }
+        TEXTURE_LIBRARY = Some(TextureLibrary { library });
     }
 }
\ No newline at end of file
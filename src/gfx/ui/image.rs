@@ -1,5 +1,6 @@
 use crate::gfx::ui::{Callbacks, ui_counter, UIElementData, UIRenderData};
 use crate::{Camera, CustomUIProperty, Layout, Texture, UI, UIElement};
+use crate::gfx::resource::Shared;
 use crate::gfx::ui::rectangle::BUFFER;
 
 #[derive(Debug)]
@@ -8,7 +9,7 @@ pub struct Image {
 }
 
 impl Image {
-    pub fn new(texture: Option<Texture>) -> Image {
+    pub fn new(texture: Option<Shared<Texture>>) -> Image {
         let mut vao = UI::generic_vao(&BUFFER);
         let (width, height) = match texture {
             None => {
@@ -3,8 +3,11 @@ use std::fmt::{Debug, Formatter};
 use downcast_rs::{Downcast, impl_downcast};
 use imgui_glfw_rs::glfw::*;
 use vecmath::{Vector4};
-use crate::{BufferDataType, BufferType, Camera, Constructor, cull_face, draw_arrays, DrawMode, DrawType, enable, Face, FragmentShader, GLConsts, Input, Layout, LocalAttribPointer, MatrixWrapper, Program, VertexArrayObject, VertexArrayObjectType, VertexBufferObject, VertexShader};
+use crate::{BufferDataType, BufferType, Camera, Constructor, cull_face, draw_arrays, DrawMode, DrawType, enable, Face, GLConsts, Input, Layout, LocalAttribPointer, MatrixWrapper, Program, VertexArrayObject, VertexArrayObjectType, VertexBufferObject};
+use crate::gfx::bindings::graphics::draw_elements;
 use crate::gfx::bindings::texturing::active_texture;
+use crate::gfx::resource::Shared;
+use crate::gfx::shader::preprocessor::Preprocessor;
 use crate::gfx::texture::Texture;
 use crate::gfx::ui::callbacks::OnDrag;
 use crate::gfx::ui::custom_ui_property::CustomUIProperty;
@@ -30,28 +33,8 @@ pub const DEFAULT_COLOR: Vector4<f32> = [0f32, 0f32, 0f32, 1f32];
 static mut COUNTER: usize = 0;
 static mut UI: Option<UI> = None;
 
-const DEFAULT_VERTEX_SHADER: &'static str = {
-    r#"
-#version 330 core
-
-uniform mat4 projection;
-uniform mat4 model;
-
-out vec2 currentPos;
-out vec2 uv;
-
-layout (location = 0) in vec2 aPos;
-layout (location = 1) in vec2 aUV;
-
-void main() {
-    gl_Position = projection * model * vec4(aPos, 0, 1);
-    currentPos = aPos;
-    uv = aUV;
-}
-
-"#
-};
-const DEFAULT_FRAGMENT_SHADER: &'static str = include_str!("../../../res/shaders/ui/frag.glsl");
+const DEFAULT_VERTEX_SHADER: &'static str = "ui/vert.glsl";
+const DEFAULT_FRAGMENT_SHADER: &'static str = "ui/frag.glsl";
 /*const DEFAULT_FRAGMENT_SHADER: &'static str = {
     r#"
 #version 330 core
@@ -93,19 +76,20 @@ void main()
 
 pub struct UI {
     elements: HashMap<usize, Box<dyn UIElement>>,
-    default_program: Program,
+    default_program: Shared<Program>,
 }
 
 impl UI {
     pub unsafe fn init() {
         UI = Some(UI {
             elements: Default::default(),
-            default_program: Program::new(
-                FragmentShader::new(DEFAULT_FRAGMENT_SHADER),
-                VertexShader::new(DEFAULT_VERTEX_SHADER),
-            ).unwrap(),
+            default_program: Preprocessor::new().program(DEFAULT_VERTEX_SHADER, DEFAULT_FRAGMENT_SHADER).unwrap(),
         })
     }
+    ///Drops every element and the default program. Called by the app on shutdown.
+    pub unsafe fn shutdown() {
+        UI = None;
+    }
     pub fn update(delta: f32) {
         unsafe {
             match &mut UI {
@@ -253,12 +237,12 @@ impl UI {
         }
     }
 
-    pub fn default_program() -> Program {
+    pub fn default_program() -> Shared<Program> {
         unsafe {
             match &UI {
                 None => panic!("Initialize the UI first."),
                 Some(ui) => {
-                    ui.default_program
+                    ui.default_program.clone()
                 }
             }
         }
@@ -344,7 +328,7 @@ impl UI {
 }
 
 #[derive(Debug)]
-pub struct UIRenderData(Program, VertexArrayObject, FillMethod);
+pub struct UIRenderData(Shared<Program>, VertexArrayObject, FillMethod);
 
 pub struct Callbacks {
     on_drag: Vec<Box<OnDrag>>,
@@ -628,7 +612,7 @@ pub trait UIElement where Self: Debug + Downcast {
     fn update(&mut self, _delta: f32) {
         self.default_update(_delta);
     }
-    fn put_texture(&mut self, texture: Texture) {
+    fn put_texture(&mut self, texture: Shared<Texture>) {
         if let Some(render_data) = &mut self.element_data_mut().render_data {
             render_data.1.put_texture::<&str>(None, texture);
         } else {
@@ -645,7 +629,7 @@ pub trait UIElement where Self: Debug + Downcast {
         if let Some(render_data) = &self.element_data().render_data {
             let projection = camera.last_orthographic();
 
-            let program = render_data.0;
+            let program = &render_data.0;
             let vao = &render_data.1;
             program.enable();
             vao.bind();
@@ -670,7 +654,7 @@ pub trait UIElement where Self: Debug + Downcast {
             program.set_uniform_bool("enableCorner", &radius_enable);
             program.set_uniform_float("cornerRadius", &self.element_data().corner_radius);
 
-            render_data.2.bind(&program, "fillStrategy");
+            render_data.2.bind(program, "fillStrategy");
 
             for i in 0..10 {
                 active_texture(i);
@@ -678,7 +662,7 @@ pub trait UIElement where Self: Debug + Downcast {
             }
 
             for i in 0..vao.textures().len() {
-                let texture = vao.textures()[i];
+                let texture = &vao.textures()[i];
                 program.set_uniform_bool(format!("texture{}", i), &true);
                 texture.active(i as u32);
                 texture.bind();
@@ -692,6 +676,9 @@ pub trait UIElement where Self: Debug + Downcast {
                     VertexArrayObjectType::ArrayStrips(tris) => {
                         draw_arrays(DrawMode::TriangleStrip, 0, *tris);
                     }
+                    VertexArrayObjectType::Elements(indices) => {
+                        draw_elements(DrawMode::Triangles, *indices);
+                    }
                 }
             }
 
@@ -1,6 +1,7 @@
 use vecmath::Matrix4;
 use crate::gfx::ui::{Callbacks, ui_counter, UIElement, UIElementData, UIRenderData};
 use crate::{CustomUIProperty, Layout, MatrixWrapper, UI};
+use crate::gfx::resource::Shared;
 use crate::gfx::texture::Texture;
 use crate::math::linear_algebra::IDENTITY_MAT4;
 
@@ -33,7 +34,7 @@ impl Rectangle {
         self
     }
 
-    pub fn with_texture(mut self, texture: Texture) -> Rectangle {
+    pub fn with_texture(mut self, texture: Shared<Texture>) -> Rectangle {
         self.put_texture(texture);
         self
     }
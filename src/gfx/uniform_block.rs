@@ -0,0 +1,260 @@
+use std::marker::PhantomData;
+use vecmath::{Matrix3, Matrix4};
+use crate::DrawType;
+use crate::gfx::bindings::BufferType::UniformBuffer as UniformBufferType;
+use crate::gfx::bindings::buffers::{bind_buffer_base, bind_buffer_raw, buffer_data_array, buffer_sub_data, delete_buffers, gen_buffers};
+use crate::gfx::bindings::uniforms::{uniform_block_binding, uniform_block_index};
+use crate::Program;
+
+///Writes values into a byte buffer following the `std140` layout rules.
+#[derive(Debug, Clone, Default)]
+pub struct Std140Writer {
+    bytes: Vec<u8>,
+}
+
+impl Std140Writer {
+    pub fn new() -> Std140Writer {
+        Self::default()
+    }
+
+    ///Pads with zeros until the length is a multiple of `alignment`.
+    pub fn align(&mut self, alignment: usize) {
+        let padding = (alignment - self.bytes.len() % alignment) % alignment;
+        self.bytes.extend(std::iter::repeat(0).take(padding));
+    }
+
+    ///Aligns to the value's base alignment and writes it.
+    pub fn write<T: Std140 + ?Sized>(&mut self, value: &T) {
+        self.align(T::ALIGN);
+        value.write_std140(self);
+    }
+
+    pub fn push_f32(&mut self, value: f32) {
+        self.bytes.extend_from_slice(&value.to_ne_bytes());
+    }
+
+    pub fn push_i32(&mut self, value: i32) {
+        self.bytes.extend_from_slice(&value.to_ne_bytes());
+    }
+
+    pub fn push_u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_ne_bytes());
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn bytes(&self) -> &Vec<u8> {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+///A type that can be a member of a `std140` uniform block.
+pub trait Std140 {
+    ///Base alignment in bytes.
+    const ALIGN: usize;
+    ///Writes the value without leading padding, `Std140Writer::write` aligns first.
+    fn write_std140(&self, writer: &mut Std140Writer);
+}
+
+impl Std140 for f32 {
+    const ALIGN: usize = 4;
+    fn write_std140(&self, writer: &mut Std140Writer) {
+        writer.push_f32(*self);
+    }
+}
+
+impl Std140 for i32 {
+    const ALIGN: usize = 4;
+    fn write_std140(&self, writer: &mut Std140Writer) {
+        writer.push_i32(*self);
+    }
+}
+
+impl Std140 for u32 {
+    const ALIGN: usize = 4;
+    fn write_std140(&self, writer: &mut Std140Writer) {
+        writer.push_u32(*self);
+    }
+}
+
+impl Std140 for bool {
+    const ALIGN: usize = 4;
+    fn write_std140(&self, writer: &mut Std140Writer) {
+        writer.push_u32(*self as u32);
+    }
+}
+
+impl Std140 for [f32; 2] {
+    const ALIGN: usize = 8;
+    fn write_std140(&self, writer: &mut Std140Writer) {
+        self.iter().for_each(|v| writer.push_f32(*v));
+    }
+}
+
+impl Std140 for [f32; 3] {
+    const ALIGN: usize = 16;
+    fn write_std140(&self, writer: &mut Std140Writer) {
+        self.iter().for_each(|v| writer.push_f32(*v));
+    }
+}
+
+impl Std140 for [f32; 4] {
+    const ALIGN: usize = 16;
+    fn write_std140(&self, writer: &mut Std140Writer) {
+        self.iter().for_each(|v| writer.push_f32(*v));
+    }
+}
+
+impl Std140 for [i32; 4] {
+    const ALIGN: usize = 16;
+    fn write_std140(&self, writer: &mut Std140Writer) {
+        self.iter().for_each(|v| writer.push_i32(*v));
+    }
+}
+
+///Column major, every column padded to a vec4.
+impl Std140 for Matrix3<f32> {
+    const ALIGN: usize = 16;
+    fn write_std140(&self, writer: &mut Std140Writer) {
+        for column in self {
+            writer.write(column);
+            writer.align(16);
+        }
+    }
+}
+
+impl Std140 for Matrix4<f32> {
+    const ALIGN: usize = 16;
+    fn write_std140(&self, writer: &mut Std140Writer) {
+        for column in self {
+            writer.write(column);
+        }
+    }
+}
+
+///Arrays have a 16 byte stride per element, whatever the element type.
+impl<T: Std140> Std140 for Vec<T> {
+    const ALIGN: usize = 16;
+    fn write_std140(&self, writer: &mut Std140Writer) {
+        for element in self {
+            writer.write(element);
+            writer.align(16);
+        }
+    }
+}
+
+///A Rust struct mirroring a GLSL `layout (std140) uniform` block. Implement it with `uniform_block!`.
+pub trait UniformBlock: Std140 {
+    ///Block name in GLSL.
+    const NAME: &'static str;
+
+    fn std140_bytes(&self) -> Vec<u8> {
+        let mut writer = Std140Writer::new();
+        writer.write(self);
+        writer.into_bytes()
+    }
+}
+
+///Declares a struct and implements `Std140` and `UniformBlock` for it, fields written in declaration order.
+///```ignore
+///uniform_block! {
+///    #[derive(Debug, Clone)]
+///    pub struct CameraMatrices("Camera") {
+///        pub projection: Matrix4<f32>,
+///        pub view: Matrix4<f32>,
+///    }
+///}
+///```
+#[macro_export]
+macro_rules! uniform_block {
+    ($(#[$meta:meta])* $vis:vis struct $name:ident($block:expr) { $($field_vis:vis $field:ident: $ty:ty),* $(,)? }) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($field_vis $field: $ty),*
+        }
+
+        impl $crate::gfx::uniform_block::Std140 for $name {
+            const ALIGN: usize = 16;
+            fn write_std140(&self, writer: &mut $crate::gfx::uniform_block::Std140Writer) {
+                $(writer.write(&self.$field);)*
+                writer.align(16);
+            }
+        }
+
+        impl $crate::gfx::uniform_block::UniformBlock for $name {
+            const NAME: &'static str = $block;
+        }
+    }
+}
+
+///A uniform buffer holding one `T`, bound to a fixed binding point.
+#[derive(Debug)]
+pub struct UniformBuffer<T: UniformBlock> {
+    ubo: u32,
+    binding: u32,
+    size: usize,
+    block: PhantomData<T>,
+}
+
+impl<T: UniformBlock> UniformBuffer<T> {
+    pub fn new(binding: u32) -> UniformBuffer<T> {
+        Self {
+            ubo: gen_buffers(1),
+            binding,
+            size: 0,
+            block: PhantomData,
+        }
+    }
+
+    ///Uploads the value, orphaning the previous storage so the driver doesn't stall on frames still reading it.
+    pub fn upload(&mut self, value: &T) {
+        let bytes = value.std140_bytes();
+        bind_buffer_raw(UniformBufferType, self.ubo);
+        buffer_data_array(UniformBufferType, bytes.len() as isize, std::ptr::null(), DrawType::DynamicDraw);
+        buffer_sub_data(UniformBufferType, 0, bytes.len() as isize, bytes.as_ptr() as *const _);
+        bind_buffer_raw(UniformBufferType, 0);
+        if self.size != bytes.len() {
+            self.size = bytes.len();
+            bind_buffer_base(UniformBufferType, self.binding, self.ubo);
+        }
+    }
+
+    ///Connects the program's block named `T::NAME` to this buffer. Returns false if the program has no such block,
+    ///and warns when the block's size differs from the Rust layout.
+    pub fn bind(&self, program: &Program) -> bool {
+        let index = uniform_block_index(program, T::NAME);
+        if index == gl::INVALID_INDEX {
+            return false;
+        }
+        if let Some(block) = program.reflection().block(T::NAME) {
+            if self.size != 0 && block.data_size as usize != self.size {
+                eprintln!("Program {}: block `{}` is {} bytes, the uploaded struct {}", program.id(), T::NAME, block.data_size, self.size);
+            }
+        }
+        uniform_block_binding(program, index, self.binding);
+        true
+    }
+
+    pub fn id(&self) -> u32 {
+        self.ubo
+    }
+    pub fn binding(&self) -> u32 {
+        self.binding
+    }
+}
+
+impl<T: UniformBlock> Drop for UniformBuffer<T> {
+    fn drop(&mut self) {
+        delete_buffers(self.ubo);
+    }
+}
@@ -1,6 +1,6 @@
 use crate::api::LuaObject;
 use crate::gfx::bindings::{BufferDataType, BufferType, ColorFlags, DrawMode, DrawType, Face, GLConsts, Ordering};
-use crate::gfx::bindings::graphics::{clear, clear_color, cull_face, depth_func, depth_mask, disable, draw_arrays, enable, front_face, shaded_wireframe, viewport};
+use crate::gfx::bindings::graphics::{clear, clear_color, cull_face, depth_func, depth_mask, disable, draw_arrays, draw_elements, enable, front_face, shaded_wireframe, viewport};
 use crate::gfx::bindings::texturing::active_texture;
 use crate::gfx::bindings::uniforms::{uniform_1i};
 use crate::gfx::mesh_builder::MeshBuilder;
@@ -50,6 +50,44 @@ mod tests {
     use crate::*;
     use crate::gfx::consts::color::Color;
     use crate::math::linear_algebra::{mat3, mat4};
+    use crate::gfx::material::TextureMapType;
+    use crate::loader::models::mtl::MTL;
+    use crate::gfx::lighting::{LightData, LightType};
+    use crate::gfx::shadows::cascades::cascade_splits;
+    use crate::gfx::shader::preprocessor::Preprocessor;
+    use crate::gfx::program::ProgramBuilder;
+    use crate::gfx::bindings::ShaderType;
+    use crate::app::AppConfigFlags;
+    use crate::gfx::uniform_block::{Std140Writer, UniformBlock};
+    use crate::math::camera::CameraMatrices;
+    use crate::math::linear_algebra::IDENTITY_MAT4;
+    use crate::gfx::texture::{ColorSpace, ImageBuffer};
+    use crate::gfx::bindings::{ColorFormat, DataType};
+    use crate::loader::textures::{CompressedFormat, CompressedImage, dds, ktx2, TextureContainerError};
+    use crate::loader::textures::bcn::bc4_block;
+    use crate::loader::assets::{AssetHandle, AssetLoader, AssetSettings, AssetState, Upload};
+    use crate::loader::asset_manager::{AssetKey, AssetManager, SharedAsset};
+    use crate::gfx::resource::{GlObjectKind, live, live_objects, track_created, track_deleted};
+    use crate::loader::models::obj::{DEFAULT_MATERIAL, DEFAULT_NAME, OBJ, Object};
+    use crate::math::triangulation::triangulate_polygon;
+    use crate::loader::models::gltf::Gltf;
+    use crate::scene::Scene;
+    use crate::gfx::animation::{AnimationClip, Channel, ChannelPath, Interpolation, NodeTransform, Skeleton};
+    use crate::math::quaternion::{from_rotation_vector, slerp, to_rotation_vector};
+    use crate::ecs::animator::Animator;
+    use crate::loader::models::ply::{PLY, PlyFormat, PlyValue};
+    use crate::loader::models::stl::STL;
+    use crate::gfx::mesh::{Mesh, Submesh, VertexAttribute};
+    use crate::gfx::mesh_builder::FACE;
+    use crate::math::tangent::triangle_normal;
+    use crate::ecs::lod_group::{LOD, LODGroup, screen_size};
+    use crate::ecs::transform::Transform;
+    use crate::gfx::primitives::Primitive;
+    use crate::math::bounds::Aabb;
+    use crate::gfx::terrain::Terrain;
+    use crate::math::frustum::Frustum;
+    use crate::math::noise::{bake, bake_float, bake_volume, Fractal, Noise, warp};
+    use crate::math::perlin;
 
     #[test]
     fn test_color() {
@@ -73,4 +111,855 @@ mod tests {
         let m4 = mat4(m3);
         println!("{:?}", m4);
     }
+
+    #[test]
+    fn mtl() {
+        let mtl = MTL::from_raw(include_str!("../res/models/sphere/sphere.mtl").to_string(), "res/models/sphere").unwrap();
+        let material = &mtl["Material.001"];
+        assert_eq!(material.shininess, 225.0);
+        assert_eq!(material.diffuse, [0.8; 3]);
+        assert_eq!(material.illumination, 2);
+        assert!(material.map(TextureMapType::Diffuse).is_some());
+        assert!(material.map(TextureMapType::Bump).unwrap().path().ends_with("gray-granite-flecks-Normal-dx.png"));
+    }
+
+    #[test]
+    fn light_culling() {
+        let point = LightData {
+            position_range: [0.0, 0.0, 0.0, 5.0],
+            direction_type: [0.0, 0.0, 0.0, LightType::Point as u8 as f32],
+            color_intensity: [1.0; 4],
+            cone: [0.0; 4],
+        };
+        assert_eq!(point.light_type(), LightType::Point);
+        assert!(point.affects([4.0, 0.0, 0.0], 0.0));
+        assert!(point.affects([7.0, 0.0, 0.0], 2.5));
+        assert!(!point.affects([7.0, 0.0, 0.0], 1.0));
+
+        let sun = LightData { direction_type: [0.0, -1.0, 0.0, LightType::Directional as u8 as f32], ..point };
+        assert!(sun.affects([1000.0, 0.0, 0.0], 0.0));
+    }
+
+    #[test]
+    fn shadow_cascades() {
+        let splits = cascade_splits(0.1, 100.0, 4, 0.75);
+        assert_eq!(splits.len(), 4);
+        assert!(splits.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!((splits[3] - 100.0).abs() < 1e-3);
+
+        let uniform = cascade_splits(0.1, 90.1, 3, 0.0);
+        assert!((uniform[0] - 30.1).abs() < 1e-3);
+    }
+
+    #[test]
+    fn shader_preprocessor() {
+        let preprocessor = Preprocessor::new()
+            .define("MAX_LIGHTS", 4)
+            .define("MAX_OBJECT_LIGHTS", 2)
+            .define("MAX_SHADOW_TILES", 3);
+        let source = "#version 330 core\n#include \"shadows/shadows.glsl\"\n#include \"lighting/lights.glsl\"\nvoid main() {}";
+        let shader = preprocessor.process("test.glsl", source).unwrap();
+        assert!(shader.source().starts_with("#version 330 core\n#define MAX_LIGHTS 4\n"));
+        assert_eq!(shader.source().matches("struct Light {").count(), 1);
+        assert_eq!(shader.map().files(), &vec!["test.glsl", "shadows/shadows.glsl", "lighting/lights.glsl"]);
+
+        assert_eq!(shader.map().map_log("0(4) : error C0000: syntax error"), "test.glsl:4 : error C0000: syntax error");
+        assert_eq!(shader.map().map_log("ERROR: 2:12: 'x' : undeclared identifier"), "ERROR: lighting/lights.glsl:12: 'x' : undeclared identifier");
+        assert!(preprocessor.process("missing.glsl", "#include \"missing/file.glsl\"").is_err());
+    }
+
+    #[test]
+    fn program_stages() {
+        assert!(ProgramBuilder::new().build().is_err());
+        assert_eq!(ShaderType::ComputeShader.required_flag().map(|flag| flag as u8), Some(AppConfigFlags::OpenGLAdvanced as u8));
+        assert!(ShaderType::GeometryShader.required_flag().is_none());
+    }
+
+    #[test]
+    fn std140_layout() {
+        let mut writer = Std140Writer::new();
+        writer.write(&1.0f32);
+        writer.write(&[2.0f32, 3.0, 4.0]);
+        writer.write(&5.0f32);
+        writer.write(&vec![6.0f32, 7.0]);
+        assert_eq!(writer.len(), 64);
+        assert_eq!(&writer.bytes()[16..20], &2.0f32.to_ne_bytes());
+        assert_eq!(&writer.bytes()[28..32], &5.0f32.to_ne_bytes());
+        assert_eq!(&writer.bytes()[48..52], &7.0f32.to_ne_bytes());
+
+        let camera = CameraMatrices { projection: IDENTITY_MAT4, view: IDENTITY_MAT4, position: [1.0, 2.0, 3.0] };
+        assert_eq!(camera.std140_bytes().len(), 144);
+    }
+
+    #[test]
+    fn image_formats() {
+        let gray = image::DynamicImage::new_luma8(3, 3);
+        let buffer = ImageBuffer::from_image(&gray);
+        assert_eq!(buffer.color_format() as u32, ColorFormat::Red as u32);
+        assert_eq!(buffer.bytes().len(), 9);
+        assert_eq!(buffer.internal_format(ColorSpace::Srgb) as u32, ColorFormat::R8 as u32);
+
+        let rgba = ImageBuffer::from_image(&image::DynamicImage::new_rgba8(2, 2));
+        assert_eq!(rgba.internal_format(ColorSpace::Srgb) as u32, ColorFormat::SRGB8Alpha8 as u32);
+        assert_eq!(rgba.internal_format(ColorSpace::Linear) as u32, ColorFormat::RGBA8 as u32);
+
+        let hdr = ImageBuffer::from_image(&image::DynamicImage::new_rgb32f(2, 2));
+        assert_eq!(hdr.data_type(), DataType::Float);
+        assert_eq!(hdr.internal_format(ColorSpace::Srgb) as u32, ColorFormat::RGB32F as u32);
+    }
+
+    #[test]
+    fn texture_containers() {
+        assert_eq!(bc4_block(&[200; 16]), [200, 200, 0, 0, 0, 0, 0, 0]);
+
+        let image = image::DynamicImage::ImageRgba8(image::RgbaImage::from_fn(6, 5, |x, y| image::Rgba([x as u8 * 40, y as u8 * 50, 90, 255])));
+        let compressed = CompressedImage::encode(&image, CompressedFormat::Bc1, true).unwrap();
+        assert_eq!(compressed.levels.len(), 3);
+        assert_eq!(compressed.levels[0].len(), 2 * 2 * 8);
+        assert_eq!(compressed.levels[2].len(), 8);
+
+        assert_eq!(dds::read(&dds::write(&compressed)).unwrap(), compressed);
+        assert_eq!(ktx2::read(&ktx2::write(&compressed)).unwrap(), compressed);
+        assert!(ktx2::read(&dds::write(&compressed)).is_err());
+
+        //A 6x5 image has 3 levels, more would shift its dimensions past 32 bits.
+        let mut dds_bytes = dds::write(&compressed);
+        dds_bytes[28..32].copy_from_slice(&40u32.to_le_bytes());
+        assert!(matches!(dds::read(&dds_bytes), Err(TextureContainerError::Header(_))));
+        let ktx2_bytes = ktx2::write(&compressed);
+        let mut too_many = ktx2_bytes.clone();
+        too_many[40..44].copy_from_slice(&40u32.to_le_bytes());
+        assert!(matches!(ktx2::read(&too_many), Err(TextureContainerError::Header(_))));
+        let mut wrong_size = ktx2_bytes.clone();
+        wrong_size[88..96].copy_from_slice(&(2 * 2 * 8 - 1u64).to_le_bytes());
+        assert!(matches!(ktx2::read(&wrong_size), Err(TextureContainerError::Header(_))));
+        let mut past_end = ktx2_bytes.clone();
+        past_end[80..88].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(matches!(ktx2::read(&past_end), Err(TextureContainerError::Truncated(0))));
+    }
+
+    struct Doubled(u32);
+
+    impl Upload for Doubled {
+        type Asset = u32;
+
+        fn upload(self) -> Result<u32, Box<dyn std::error::Error>> {
+            Ok(self.0 * 2)
+        }
+    }
+
+    #[test]
+    fn asset_loading() {
+        let handle = AssetHandle::<u32>::loading();
+        assert!(handle.is_loading() && handle.get().is_none());
+        assert_eq!(AssetHandle::ready(3).state(), AssetState::Ready(3));
+
+        unsafe {
+            AssetLoader::init(AssetSettings { workers: 2, upload_budget: std::time::Duration::from_millis(1) });
+        }
+        let ready = AssetLoader::load(|| Ok(Doubled(21)));
+        let failed = AssetLoader::load::<Doubled, _>(|| Err("missing file".into()));
+        let start = std::time::Instant::now();
+        while (ready.is_loading() || failed.is_loading()) && start.elapsed().as_secs() < 5 {
+            AssetLoader::update();
+        }
+        assert_eq!(ready.get(), Some(42));
+        assert_eq!(failed.error().as_deref(), Some("missing file"));
+    }
+
+    #[test]
+    fn asset_manager() {
+        let key = AssetKey::embedded("test/greet.lua");
+        assert!(AssetManager::lua(key.clone()).is_err());
+
+        AssetManager::embed("test/greet.lua", b"print('hello')");
+        let first = AssetManager::lua(key.clone()).unwrap();
+        let second = AssetManager::lua(key.clone()).unwrap();
+        assert!(SharedAsset::ptr_eq(&first, &second));
+        assert_eq!(first.ref_count(), 2);
+        assert_eq!(second.source(), "print('hello')");
+
+        drop(first);
+        assert!(AssetManager::is_loaded(&key));
+        drop(second);
+        assert!(!AssetManager::is_loaded(&key));
+        assert!(AssetManager::read(&AssetKey::embedded("models/debug/sphere.obj")).is_ok());
+    }
+
+    #[test]
+    fn leak_counter() {
+        let before = live(GlObjectKind::Renderbuffer);
+        track_created(GlObjectKind::Renderbuffer, 3);
+        track_created(GlObjectKind::Renderbuffer, 0);
+        if cfg!(debug_assertions) {
+            assert_eq!(live(GlObjectKind::Renderbuffer), before + 1);
+            assert!(live_objects().contains(&(GlObjectKind::Renderbuffer, before + 1)));
+        }
+        track_deleted(GlObjectKind::Renderbuffer, 3);
+        track_deleted(GlObjectKind::Renderbuffer, 0);
+        assert_eq!(live(GlObjectKind::Renderbuffer), before);
+    }
+
+    #[test]
+    fn obj_parsing() {
+        let blender = OBJ::from_raw(include_str!("../res/models/test/blender_cube.obj").to_string()).unwrap();
+        let cube = &blender["Cube"];
+        assert_eq!(cube.material_libraries(), &vec!["blender_cube.mtl".to_string()]);
+        assert_eq!((cube.vertices().len(), cube.normals().len(), cube.uvs().len()), (8, 6, 14));
+        assert_eq!(cube.groups().len(), 1);
+        assert_eq!(cube.groups()[0].faces().len(), 12);
+        assert!(!cube.groups()[0].smooth() && !cube.has_colors());
+
+        let maya = OBJ::from_raw_with_directory(include_str!("../res/models/test/maya_cube.obj").to_string(), "res/models/test").unwrap();
+        let cube = &maya[DEFAULT_NAME];
+        assert_eq!(maya.objects().len(), 1);
+        assert_eq!((cube.vertices().len(), cube.normals().len(), cube.uvs().len()), (8, 24, 14));
+        let groups = cube.groups().iter().map(|g| (g.name(), g.material(), g.faces().len(), g.smooth())).collect::<Vec<_>>();
+        assert_eq!(groups, vec![("pCube1", "initialShadingGroup", 12, false)]);
+        assert_eq!(cube.material("initialShadingGroup").unwrap().diffuse, [0.5; 3]);
+        assert_eq!(cube.tangents(&cube.groups()[0]).unwrap().len(), 12);
+
+        let mixed = OBJ::from_raw(include_str!("../res/models/test/mixed_cube.obj").to_string()).unwrap();
+        let cube = &mixed[DEFAULT_NAME];
+        assert_eq!(mixed.objects().len(), 1);
+        assert_eq!(cube.colors()[0], [1.0, 0.0, 0.0]);
+        let groups = cube.groups().iter().map(|g| (g.name(), g.faces().len(), g.smooth())).collect::<Vec<_>>();
+        assert_eq!(groups, vec![("pCube1 front", 2, false), ("pCube1 top", 2, false), ("pCube1 back", 4, true), ("pCube1 right", 4, true)]);
+        assert_eq!(cube.batch("initialShadingGroup").unwrap().faces().len(), 12);
+        //The back group starts with v/vt faces and ends with bare ones, the batch mixes all three layouts.
+        let back = &cube.groups()[2];
+        assert!(back.has_uvs() && !back.has_normals());
+        let batch = cube.batch("initialShadingGroup").unwrap();
+        assert!(batch.has_uvs() && batch.has_normals());
+        let tangents = cube.tangents(&batch).unwrap();
+        assert_eq!(tangents.len(), 12);
+        assert!(tangents.iter().flatten().all(|t| t.iter().all(|c| c.is_finite())));
+        assert_eq!(cube.group_materials(), vec!["initialShadingGroup".to_string()]);
+
+        //A library that fails to parse leaves the groups with default materials.
+        let broken = OBJ::from_raw_with_directory("mtllib broken.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n".to_string(), "res/models/test").unwrap();
+        assert!(broken[DEFAULT_NAME].materials().is_empty());
+
+        let ngon = OBJ::from_raw("v 0 0 0\nv 2 0 0\nv 2 2 0\nv 1 1 0\nv 0 2 0\nf -5 -4 -3 -2 -1\n".to_string()).unwrap();
+        let object = &ngon[DEFAULT_NAME];
+        assert_eq!(object.groups()[0].material(), DEFAULT_MATERIAL);
+        assert_eq!(object.groups()[0].faces().len(), 3);
+        assert!(OBJ::from_raw("v 0 0 0\nf 1 2 3\n".to_string()).is_err());
+
+        //Vertices no face uses stay with the object they're defined in.
+        let cloud = OBJ::from_raw("v 0 0 0\nv 1 0 0\nv 0 1 0 1 0 0\n".to_string()).unwrap();
+        assert_eq!(cloud[DEFAULT_NAME].vertices(), &vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+        assert_eq!(cloud[DEFAULT_NAME].colors()[2], [1.0, 0.0, 0.0]);
+        let split = OBJ::from_raw("o a\nv 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nf 1 2 3\no b\nv 0 0 1\nvn 0 0 1\n".to_string()).unwrap();
+        assert_eq!((split["a"].vertices().len(), split["a"].vertices()[3]), (4, [1.0, 1.0, 0.0]));
+        assert_eq!((split["b"].vertices().len(), split["b"].normals().len()), (1, 1));
+
+        //The reflex corner at (1, 1) must not be clipped.
+        let concave = [[0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [2.0, 2.0, 0.0], [1.0, 1.0, 0.0], [0.0, 2.0, 0.0]];
+        let triangles = triangulate_polygon(&concave);
+        assert_eq!(triangles.len(), 3);
+        assert!(!triangles.contains(&[2, 3, 4]));
+    }
+
+    #[test]
+    fn gltf_loading() {
+        let gltf = Gltf::from_slice(include_bytes!("../res/models/test/triangle.gltf"), None).unwrap();
+        let glb = Gltf::from_slice(include_bytes!("../res/models/test/triangle.glb"), None).unwrap();
+        for model in [&gltf, &glb] {
+            let primitive = &model.meshes()[0].primitives()[0];
+            assert_eq!(primitive.indices(), &vec![0, 1, 2]);
+            assert_eq!(primitive.positions()[1], [1.0, 0.0, 0.0]);
+            assert_eq!(primitive.uvs()[2], [0.0, 1.0]);
+            assert_eq!(primitive.colors()[1], [0.0, 1.0, 0.0, 1.0]);
+            assert_eq!(primitive.normals()[0], [0.0, 0.0, 1.0]);
+            assert_eq!(primitive.tangents()[0], [1.0, 0.0, 0.0, 1.0]);
+            assert_eq!(primitive.material(), Some(0));
+
+            let material = &model.materials()[0];
+            assert_eq!(material.name(), "Red");
+            assert_eq!(material.pbr().base_color_factor, [1.0, 0.0, 0.0, 1.0]);
+            assert_eq!((material.pbr().metallic_factor, material.pbr().roughness_factor), (0.5, 0.25));
+
+            assert_eq!(model.root_nodes(), &vec![0]);
+            assert_eq!(model.parent(1), Some(0));
+            let world = model.world_matrix(1);
+            assert!((world[3][0] - 1.0).abs() < 1e-5 && (world[3][1] - 2.0).abs() < 1e-5 && (world[3][2] - 3.0).abs() < 1e-5);
+            //The child is turned 90 degrees around y and scaled by 2, so local x maps to world -z.
+            assert!((world[0][2] + 2.0).abs() < 1e-4);
+            let rotation = model.nodes()[1].transform().rotation;
+            assert!(rotation[0].abs() < 1e-3 && (rotation[1] - 90.0).abs() < 1e-3 && rotation[2].abs() < 1e-3);
+        }
+        assert_eq!(glb.nodes()[0].translation, [1.0, 2.0, 3.0]);
+
+        let instances = gltf.instantiate();
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[1].mesh, Some(0));
+        let root = Scene::get_object(&instances[0].object).unwrap();
+        assert_eq!(root.children(), &vec![instances[1].object]);
+        assert_eq!(Scene::get_object(&instances[1].object).unwrap().parent_id(), Some(instances[0].object));
+        let model = Scene::get_object(&instances[1].object).unwrap().model_matrix();
+        let world = gltf.world_matrix(1);
+        assert!(model.iter().flatten().zip(world.iter().flatten()).all(|(a, b)| (a - b).abs() < 1e-4));
+
+        assert!(Gltf::from_slice(b"{\"asset\": {\"version\": \"1.0\"}}", None).is_err());
+    }
+
+    #[test]
+    fn skeletal_animation() {
+        let channel = |path, interpolation, times: Vec<f32>, values| Channel { node: 1, path, interpolation, times, values };
+        let linear = channel(ChannelPath::Translation, Interpolation::Linear, vec![0.0, 2.0], vec![[0.0; 4], [2.0, 4.0, 0.0, 0.0]]);
+        assert_eq!(linear.sample(1.0), Some([1.0, 2.0, 0.0, 0.0]));
+        assert_eq!(linear.sample(5.0), Some([2.0, 4.0, 0.0, 0.0]));
+        let step = channel(ChannelPath::Translation, Interpolation::Step, vec![0.0, 2.0], vec![[0.0; 4], [2.0, 4.0, 0.0, 0.0]]);
+        assert_eq!(step.sample(1.0), Some([0.0; 4]));
+        //With flat tangents the spline passes the midpoint halfway.
+        let cubic = channel(ChannelPath::Translation, Interpolation::CubicSpline, vec![0.0, 2.0], vec![[0.0; 4], [0.0; 4], [0.0; 4], [0.0; 4], [2.0, 4.0, 0.0, 0.0], [0.0; 4]]);
+        let value = cubic.sample(1.0).unwrap();
+        assert!((value[0] - 1.0).abs() < 1e-5 && (value[1] - 2.0).abs() < 1e-5);
+        assert_eq!(channel(ChannelPath::Translation, Interpolation::CubicSpline, vec![0.0, 2.0], vec![[0.0; 4]; 2]).sample(1.0), None);
+
+        let half = to_rotation_vector(slerp(from_rotation_vector([0.0; 3]), from_rotation_vector([0.0, 90.0, 0.0]), 0.5));
+        assert!(half[0].abs() < 1e-3 && (half[1] - 45.0).abs() < 1e-3 && half[2].abs() < 1e-3);
+
+        //Two joints stacked one unit apart, each bound at its rest position.
+        let node = |y| NodeTransform { translation: [0.0, y, 0.0], ..Default::default() };
+        let rest_pose = vec![node(1.0), node(1.0)];
+        let inverse_bind = |y: f32| {
+            let mut m = IDENTITY_MAT4;
+            m[3][1] = -y;
+            m
+        };
+        let skeleton = Skeleton::new(vec![0, 1], vec![inverse_bind(1.0), inverse_bind(2.0)], vec![None, Some(0)], rest_pose.clone());
+        for matrix in skeleton.joint_matrices(&rest_pose) {
+            assert_eq!(matrix, IDENTITY_MAT4);
+        }
+        let mut pose = rest_pose.clone();
+        pose[0].translation = [1.0, 1.0, 0.0];
+        let joints = skeleton.joint_matrices(&pose);
+        assert_eq!((joints[1][3][0], joints[1][3][1]), (1.0, 0.0));
+
+        let slide = AnimationClip::new("slide", vec![channel(ChannelPath::Translation, Interpolation::Linear, vec![0.0, 1.0], vec![[0.0, 1.0, 0.0, 0.0], [2.0, 1.0, 0.0, 0.0]])]);
+        let hold = AnimationClip::new("hold", vec![channel(ChannelPath::Translation, Interpolation::Step, vec![0.0, 1.0], vec![[0.0, 1.0, 0.0, 0.0]; 2])]);
+        let mut animator = Animator::new(rest_pose, Some(skeleton), vec![slide]).with_clip(hold);
+        assert!(!animator.play("missing"));
+        assert!(animator.play("slide"));
+        animator.advance(0.5);
+        assert_eq!(animator.pose()[1].translation, [1.0, 1.0, 0.0]);
+        assert!((animator.joint_matrices()[1][3][0] - 1.0).abs() < 1e-5);
+        animator.advance(0.75);
+        assert!((animator.time() - 0.25).abs() < 1e-5);
+        animator.advance(0.5);
+        assert!(animator.crossfade("hold", 1.0));
+        animator.advance(0.5);
+        //Halfway between the sliding clip, looped around to 0.25 seconds where x = 0.5, and the held x = 0.
+        assert!((animator.pose()[1].translation[0] - 0.25).abs() < 1e-5);
+        animator.advance(1.0);
+        assert_eq!(animator.pose()[1].translation[0], 0.0);
+        assert_eq!(animator.current_clip(), Some("hold"));
+
+        let gltf = Gltf::from_slice(br#"{
+            "asset": {"version": "2.0"},
+            "nodes": [{"children": [1]}, {"translation": [0, 1, 0]}],
+            "skins": [{"joints": [0, 1]}],
+            "animations": [{"name": "Raise", "channels": [{"sampler": 0, "target": {"node": 1, "path": "translation"}}],
+                "samplers": [{"input": 0, "output": 1}]}],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 2, "type": "SCALAR"},
+                {"bufferView": 0, "byteOffset": 8, "componentType": 5126, "count": 2, "type": "VEC3"}
+            ],
+            "bufferViews": [{"buffer": 0, "byteLength": 32}],
+            "buffers": [{"byteLength": 32, "uri": "data:application/octet-stream;base64,AAAAAAAAgD8AAAAAAAAAAAAAAAAAAAAAAAAAQAAAAAA="}]
+        }"#, None).unwrap();
+        assert_eq!(gltf.skins()[0].joints(), &vec![0, 1]);
+        let clip = gltf.animation("Raise").unwrap();
+        assert_eq!(clip.duration(), 1.0);
+        let mut pose = gltf.rest_pose();
+        clip.sample(0.5, &mut pose);
+        assert_eq!(pose[1].translation, [0.0, 1.0, 0.0]);
+
+        //Inverse bind matrices read from a SCALAR accessor.
+        assert!(Gltf::from_slice(br#"{
+            "asset": {"version": "2.0"},
+            "nodes": [{}],
+            "skins": [{"joints": [0], "inverseBindMatrices": 0}],
+            "accessors": [{"bufferView": 0, "componentType": 5126, "count": 2, "type": "SCALAR"}],
+            "bufferViews": [{"buffer": 0, "byteLength": 8}],
+            "buffers": [{"byteLength": 8, "uri": "data:application/octet-stream;base64,AAAAAAAAgD8="}]
+        }"#, None).is_err());
+    }
+
+    #[test]
+    fn ply_stl_exchange() {
+        let ply = PLY::from_slice(b"ply\nformat ascii 1.0\ncomment scanned\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+            property uchar red\nproperty uchar green\nproperty uchar blue\nproperty float confidence\n\
+            element face 1\nproperty list uchar int vertex_indices\nend_header\n\
+            0 0 0 255 0 0 0.5\n1 0 0 0 255 0 0.25\n1 1 0 0 0 255 1\n0 1 0 255 255 255 0\n4 0 1 2 3\n").unwrap();
+        assert_eq!(ply.comments(), &vec!["scanned".to_string()]);
+        assert_eq!(ply.element("vertex").unwrap().scalars("confidence"), Some(vec![0.5, 0.25, 1.0, 0.0]));
+        let quad = ply.to_object("quad").unwrap();
+        assert_eq!(quad.faces().count(), 2);
+        assert_eq!(quad.colors()[1], [0.0, 1.0, 0.0]);
+        for format in [PlyFormat::BinaryLittleEndian, PlyFormat::BinaryBigEndian, PlyFormat::Ascii] {
+            let read = PLY::from_slice(&ply.to_bytes(format)).unwrap();
+            assert_eq!(read.format(), format);
+            assert_eq!(read.elements(), ply.elements());
+        }
+        assert!(PLY::from_slice(b"ply\nformat ascii 1.0\nelement vertex 2\nproperty float x\nend_header\n1\n").is_err());
+        //Counts far past the data fail on the missing values instead of reserving them up front.
+        assert!(PLY::from_slice(b"ply\nformat binary_little_endian 1.0\nelement face 18446744073709551615\nproperty list uint int vertex_indices\nend_header\n\xff\xff\xff\xff").is_err());
+
+        //Corners sharing position, normal and uv are written once.
+        let obj = OBJ::from_raw(include_str!("../res/models/test/blender_cube.obj").to_string()).unwrap();
+        let cube = &obj.objects()[0];
+        let written = PLY::from_slice(&PLY::from_object(cube, PlyFormat::BinaryLittleEndian).to_bytes(PlyFormat::BinaryLittleEndian)).unwrap();
+        assert_eq!(written.element("face").unwrap().len(), cube.faces().count());
+        let read = written.to_object("cube").unwrap();
+        assert_eq!(read.faces().count(), cube.faces().count());
+        assert_eq!(read.normals().len(), read.vertices().len());
+        for (a, b) in cube.faces().zip(read.faces()) {
+            assert_eq!(cube.vertices()[a[0].0], read.vertices()[b[0].0]);
+        }
+        assert!(matches!(written.element("face").unwrap().rows()[0][0], PlyValue::List(_)));
+
+        let stl = STL::from_object(cube);
+        let ascii = STL::from_slice(stl.to_ascii().as_bytes()).unwrap();
+        let binary = STL::from_slice(&stl.to_binary()).unwrap();
+        assert_eq!(ascii, stl);
+        assert_eq!(binary.triangles(), stl.triangles());
+        assert_eq!(binary.to_binary().len(), 84 + 50 * cube.faces().count());
+        //The 8 corners of the cube are welded back together.
+        let welded = binary.to_object();
+        assert_eq!(welded.vertices().len(), 8);
+        assert_eq!(welded.normals().len(), cube.faces().count());
+        assert!(STL::from_slice(b"solid x\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nendloop\nendfacet\n").is_err());
+    }
+
+    #[test]
+    fn obj_mtl_writing() {
+        let same = |a: &Object, b: &Object| {
+            assert_eq!(a.name(), b.name());
+            assert_eq!(a.vertices(), b.vertices());
+            assert_eq!(a.colors(), b.colors());
+            assert_eq!(a.normals(), b.normals());
+            assert_eq!(a.uvs(), b.uvs());
+            assert_eq!(a.material_libraries(), b.material_libraries());
+            assert_eq!(a.groups().len(), b.groups().len());
+            for (a, b) in a.groups().iter().zip(b.groups()) {
+                assert_eq!((a.name(), a.material(), a.smooth()), (b.name(), b.material(), b.smooth()));
+                assert_eq!(a.faces(), b.faces());
+            }
+        };
+
+        //Random files: positive and negative indices, n-gons, vertex colors and group, material and smoothing switches.
+        let mut seed = 0x2545f4914f6cdd1du64;
+        let mut next = move |range: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % range as u64) as usize
+        };
+        for _ in 0..20 {
+            let mut raw = String::from("mtllib a.mtl b.mtl\n");
+            let (mut vertices, mut uvs, mut normals) = (0, 0, 0);
+            let colored = next(2) == 0;
+            for object in 0..1 + next(3) {
+                raw.push_str(&format!("o object {}\n", object));
+                for _ in 0..3 + next(6) {
+                    let mut values = (0..if colored { 6 } else { 3 }).map(|_| (next(20001) as f32 - 10000.0) / 7.0).collect::<Vec<f32>>();
+                    values.iter_mut().skip(3).for_each(|c| *c = c.abs() / 1500.0);
+                    raw.push_str(&format!("v {}\n", values.iter().map(|v| v.to_string()).collect::<Vec<String>>().join(" ")));
+                    vertices += 1;
+                }
+                for _ in 0..1 + next(4) {
+                    raw.push_str(&format!("vt {} {}\nvn {} {} {}\n", next(100) as f32 / 99.0, next(100) as f32 / 33.0, next(3), next(5), 1));
+                    uvs += 1;
+                    normals += 1;
+                }
+                for _ in 0..1 + next(8) {
+                    match next(4) {
+                        0 => raw.push_str(&format!("g part {}\n", next(3))),
+                        1 => raw.push_str(&format!("usemtl material{}\n", next(3))),
+                        2 => raw.push_str(&format!("s {}\n", ["off", "1", "2"][next(3)])),
+                        _ => {}
+                    }
+                    let layout = next(4);
+                    let index = |count: usize, next: &mut dyn FnMut(usize) -> usize| match next(2) {
+                        0 => (1 + next(count)).to_string(),
+                        _ => format!("-{}", 1 + next(count))
+                    };
+                    raw.push('f');
+                    for _ in 0..3 + next(3) {
+                        let v = index(vertices, &mut next);
+                        let corner = match layout {
+                            0 => v,
+                            1 => format!("{}/{}", v, index(uvs, &mut next)),
+                            2 => format!("{}//{}", v, index(normals, &mut next)),
+                            _ => format!("{}/{}/{}", v, index(uvs, &mut next), index(normals, &mut next))
+                        };
+                        raw.push_str(&format!(" {}", corner));
+                    }
+                    raw.push('\n');
+                }
+            }
+            let obj = OBJ::from_raw(raw).unwrap();
+            let written = OBJ::from_raw(obj.to_string()).unwrap();
+            assert_eq!(obj.objects().len(), written.objects().len());
+            for (a, b) in obj.objects().iter().zip(written.objects()) {
+                same(a, b);
+            }
+        }
+
+        let cube = OBJ::from_raw(include_str!("../res/models/test/mixed_cube.obj").to_string()).unwrap();
+        let written = OBJ::from_raw(cube.to_string()).unwrap();
+        same(&cube.objects()[0], &written.objects()[0]);
+
+        let triangle = Object::from_faces("triangle", vec![[0.0; 3], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]], vec![], vec![], vec![], vec![[(0, None, None), (1, None, None), (2, None, None)]]);
+        let generated = OBJ::new(vec![triangle.clone()]);
+        same(&triangle, &OBJ::from_raw(generated.to_string()).unwrap().objects()[0]);
+
+        let mtl = MTL::from_raw("newmtl Brick\nKa 0.1 0.2 0.3\nKd 0.5\nKs 1 1 1\nNs 96.5\nd 0.75\nillum 1\n\
+            map_Kd -s 2 2 1 -o 0.5 0 0 textures/brick.png\nbump -bm 0.3 textures/brick bump.png\n\nnewmtl Plain\n".to_string(), "").unwrap();
+        let written = MTL::from_raw(mtl.to_string(), "").unwrap();
+        assert_eq!(written.materials().len(), 2);
+        for (a, b) in mtl.materials().iter().zip(written.materials()) {
+            assert_eq!(a.name, b.name);
+            assert_eq!((a.ambient, a.diffuse, a.specular, a.emissive), (b.ambient, b.diffuse, b.specular, b.emissive));
+            assert_eq!((a.shininess, a.opacity, a.optical_density, a.illumination), (b.shininess, b.opacity, b.optical_density, b.illumination));
+            assert_eq!(a.maps().len(), b.maps().len());
+            for (a, b) in a.maps().iter().zip(b.maps()) {
+                assert_eq!((a.map_type(), a.source(), a.scale(), a.offset(), a.bump_multiplier()), (b.map_type(), b.source(), b.scale(), b.offset(), b.bump_multiplier()));
+            }
+        }
+        assert_eq!(written["Brick"].map(TextureMapType::Bump).unwrap().source(), "textures/brick bump.png");
+        assert_eq!(MTL::relative(std::path::Path::new("/models/textures/brick.png"), std::path::Path::new("/export/models")), "../../models/textures/brick.png");
+        assert_eq!(MTL::relative(std::path::Path::new("/models/brick.png"), std::path::Path::new("/models")), "brick.png");
+    }
+
+    #[test]
+    fn mesh_processing() {
+        let close = |a: &[f32], b: &[f32]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-4);
+
+        //Two triangles folded 90 degrees along the edge from the origin to +y.
+        let fold = Mesh::new(vec![[0.0; 3], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]], vec![0, 1, 2, 0, 2, 3]);
+        let mut smooth = fold.clone();
+        smooth.generate_normals();
+        assert_eq!(smooth.vertex_count(), 4);
+        assert!(close(&smooth.normals()[0], &[0.7071, 0.0, 0.7071]));
+        assert!(close(&smooth.normals()[1], &[0.0, 0.0, 1.0]));
+        let mut flat = fold.clone();
+        flat.generate_flat_normals();
+        assert_eq!(flat.vertex_count(), 6);
+        for [a, b, c] in flat.triangles() {
+            assert_eq!(flat.normals()[a], flat.normals()[b]);
+            assert_eq!(flat.normals()[b], flat.normals()[c]);
+        }
+        let mut mixed = fold.clone().with_submeshes(vec![Submesh::new("a", "m", 0, 3, true), Submesh::new("b", "m", 3, 3, false)]);
+        mixed.generate_normals();
+        assert_eq!(mixed.vertex_count(), 6);
+        let [a, _, c] = mixed.triangles().next().unwrap();
+        assert!(close(&mixed.normals()[a], &[0.0, 0.0, 1.0]) && close(&mixed.normals()[c], &[0.0, 0.0, 1.0]));
+        let [a, _, _] = mixed.triangles().nth(1).unwrap();
+        assert!(close(&mixed.normals()[a], &[1.0, 0.0, 0.0]));
+
+        let mut quad = Mesh::from_interleaved(FACE);
+        assert_eq!(quad.attributes(), vec![VertexAttribute::Position, VertexAttribute::Normal, VertexAttribute::Uv]);
+        assert_eq!(quad.interleaved(), FACE.to_vec());
+        assert_eq!(quad.weld(1e-4), 2);
+        assert_eq!((quad.vertex_count(), quad.triangle_count()), (4, 2));
+        assert!(quad.generate_tangents());
+        assert_eq!(quad.vertex_count(), 4);
+        //u runs along +y and v along +x, so the bitangent is mirrored relative to cross(normal, tangent).
+        for tangent in quad.tangents() {
+            assert!(close(tangent, &[0.0, 1.0, 0.0, -1.0]));
+        }
+        assert!(!fold.clone().generate_tangents());
+        //The uvs mirror at x = 1, so the middle vertices are split to keep both handednesses.
+        let mut strip = Mesh::new(vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0, 0.0], [2.0, 1.0, 0.0]], vec![0, 1, 4, 0, 4, 3, 1, 2, 5, 1, 5, 4])
+            .with_normals(vec![[0.0, 0.0, 1.0]; 6])
+            .with_uvs(vec![[0.0, 0.0], [1.0, 0.0], [0.0, 0.0], [0.0, 1.0], [1.0, 1.0], [0.0, 1.0]]);
+        assert!(strip.generate_tangents());
+        assert_eq!(strip.vertex_count(), 8);
+        for [a, b, c] in strip.triangles() {
+            let w = strip.tangents()[a][3];
+            assert!(strip.tangents()[b][3] == w && strip.tangents()[c][3] == w);
+            assert!(close(&strip.tangents()[a], &[w, 0.0, 0.0, w]));
+        }
+
+        let mut mirrored = quad.clone();
+        let mut mirror = IDENTITY_MAT4;
+        mirror[0][0] = -1.0;
+        mirror[3] = [2.0, 0.0, 0.0, 1.0];
+        mirrored.transform(&mirror);
+        let triangle = mirrored.triangles().next().unwrap();
+        let positions = triangle.map(|i| mirrored.positions()[i]);
+        assert!(close(&triangle_normal(positions), &mirrored.normals()[triangle[0]]));
+        assert!(close(&mirrored.tangents()[0], &[0.0, 1.0, 0.0, 1.0]));
+        let bounds = mirrored.bounds().unwrap();
+        assert!(close(&bounds.min, &[1.5, -0.5, 0.5]) && close(&bounds.max, &[2.5, 0.5, 0.5]));
+
+        let obj = OBJ::from_raw(include_str!("../res/models/test/blender_cube.obj").to_string()).unwrap();
+        let cube = Mesh::from_object(&obj.objects()[0]);
+        assert_eq!(cube.triangle_count(), obj.objects()[0].faces().count());
+        assert_eq!(cube.submeshes().len(), obj.objects()[0].groups().len());
+        assert_eq!(cube.bounds().unwrap().size(), [2.0; 3]);
+        let sphere = cube.bounding_sphere().unwrap();
+        assert!(cube.positions().iter().all(|p| sphere.contains(*p)));
+        assert!(sphere.radius <= 3.0f32.sqrt() * 1.05);
+        let mut transform = Transform::default();
+        transform.position = [1.0, 2.0, 3.0];
+        transform.scale = [1.0, 4.0, 2.0];
+        let moved = sphere.transformed(&transform.matrix());
+        assert!(cube.positions().iter().all(|p| {
+            let p = vecmath::col_mat4_transform(transform.matrix(), [p[0], p[1], p[2], 1.0]);
+            moved.contains([p[0], p[1], p[2]])
+        }));
+        assert!((moved.radius - sphere.radius * 4.0).abs() < 1e-4);
+
+        let mut merged = quad.clone();
+        merged.merge(&fold);
+        assert_eq!(merged.vertex_count(), 8);
+        assert_eq!(merged.uvs().len(), 8);
+        assert_eq!(merged.indices()[6..], [4, 5, 6, 4, 6, 7]);
+        assert_eq!(merged.submeshes()[1].start, 6);
+    }
+
+    #[test]
+    fn mesh_simplification() {
+        let size = 16;
+        let positions = (0..=size).flat_map(|x| (0..=size).map(move |z| [x as f32, 0.0, z as f32])).collect::<Vec<_>>();
+        let vertex = |x: usize, z: usize| (x * (size + 1) + z) as u32;
+        let mut indices = vec![];
+        for x in 0..size {
+            for z in 0..size {
+                indices.extend_from_slice(&[vertex(x, z), vertex(x, z + 1), vertex(x + 1, z)]);
+                indices.extend_from_slice(&[vertex(x + 1, z), vertex(x, z + 1), vertex(x + 1, z + 1)]);
+            }
+        }
+        let half = indices.len() / 2;
+        let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+        let grid = Mesh::new(positions, indices).with_normals(normals).with_submeshes(vec![
+            Submesh::new("left", "red", 0, half, true),
+            Submesh::new("right", "blue", half, half, true),
+        ]);
+
+        let simplified = grid.simplify(0.25);
+        assert!(simplified.triangle_count() <= grid.triangle_count() / 4);
+        assert!(simplified.triangle_count() >= 4);
+        assert_eq!(simplified.normals().len(), simplified.vertex_count());
+        assert!(simplified.normals().iter().all(|n| (n[1] - 1.0).abs() < 1e-5));
+        let (bounds, original) = (simplified.bounds().unwrap(), grid.bounds().unwrap());
+        for i in 0..3 {
+            assert!((bounds.min[i] - original.min[i]).abs() < 1e-4 && (bounds.max[i] - original.max[i]).abs() < 1e-4);
+        }
+        //The seam between the materials stays on x = 8 and no triangle flips.
+        let names = simplified.submeshes().iter().map(|s| s.name.as_str()).collect::<Vec<&str>>();
+        assert_eq!(names, vec!["left", "right"]);
+        for (index, submesh) in simplified.submeshes().iter().enumerate() {
+            for triangle in simplified.indices()[submesh.start..submesh.start + submesh.count].chunks(3) {
+                let corners = [0, 1, 2].map(|i| simplified.positions()[triangle[i] as usize]);
+                assert!(triangle_normal(corners)[1] > 0.99);
+                for corner in corners {
+                    assert!(corner[1].abs() < 1e-5);
+                    match index {
+                        0 => assert!(corner[0] <= 8.0 + 1e-4),
+                        _ => assert!(corner[0] >= 8.0 - 1e-4)
+                    }
+                }
+            }
+        }
+
+        let lods = grid.generate_lods(&[1.0, 0.5, 0.1]);
+        assert_eq!(lods[0], grid);
+        assert!(lods[1].triangle_count() <= 256 && lods[1].triangle_count() > lods[2].triangle_count());
+        assert!(lods[2].triangle_count() <= 52);
+
+        let mut levels = lods.into_iter().zip([0.5, 0.1, 0.02]).map(|(mesh, size)| LOD::new(mesh, size)).collect::<Vec<LOD>>();
+        levels.reverse();
+        let group = LODGroup::new(levels);
+        assert_eq!(group.levels()[0].screen_size, 0.5);
+        assert_eq!(group.levels()[0].mesh.triangle_count(), 512);
+        assert_eq!(group.level_for(2.0), Some(0));
+        assert_eq!(group.level_for(0.2), Some(1));
+        assert_eq!(group.level_for(0.05), Some(2));
+        assert_eq!(group.level_for(0.01), None);
+
+        let obj = OBJ::from_raw(include_str!("../res/models/test/blender_cube.obj").to_string()).unwrap();
+        let group = LODGroup::from_object(&obj.objects()[0], &[(1.0, 0.3), (0.5, 0.1)]);
+        assert_eq!(group.levels().len(), 2);
+        assert_eq!(group.levels()[0].mesh.triangle_count(), 12);
+        let mut level = group.levels()[1].mesh.clone();
+        level.complete_streams();
+        assert_eq!((level.normals().len(), level.uvs().len(), level.tangents().len()), (level.vertex_count(), level.vertex_count(), level.vertex_count()));
+
+        let camera = Camera::new(90.0, 100.0, 0.1, [800, 600], [0.0; 3], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]);
+        assert!((screen_size(&camera, [0.0, 0.0, -10.0], 1.0) - 0.1).abs() < 1e-5);
+        assert_eq!(screen_size(&camera, [0.0, 0.0, -0.5], 1.0), f32::INFINITY);
+    }
+
+    #[test]
+    fn procedural_primitives() {
+        use std::f32::consts::PI;
+        let shapes = [
+            (Primitive::Cube { size: [2.0, 3.0, 4.0] }, Some(24.0)),
+            (Primitive::UvSphere { radius: 1.0, segments: 64, rings: 32 }, Some(4.0 / 3.0 * PI)),
+            (Primitive::Icosphere { radius: 1.0, subdivisions: 4 }, Some(4.0 / 3.0 * PI)),
+            (Primitive::Plane { width: 2.0, depth: 3.0, subdivisions: [4, 5] }, None),
+            (Primitive::Cylinder { radius: 1.0, height: 2.0, segments: 64 }, Some(2.0 * PI)),
+            (Primitive::Cone { radius: 1.0, height: 3.0, segments: 64 }, Some(PI)),
+            (Primitive::Capsule { radius: 0.5, height: 3.0, segments: 64, rings: 16 }, Some(0.5 * PI + PI / 6.0)),
+            (Primitive::Torus { radius: 2.0, tube_radius: 0.5, segments: 64, tube_segments: 32 }, Some(PI * PI)),
+            (Primitive::Quad { width: 2.0, height: 1.0 }, None),
+        ];
+        for (shape, expected) in shapes {
+            let mesh = shape.mesh();
+            assert_eq!(mesh.normals().len(), mesh.vertex_count());
+            assert_eq!(mesh.uvs().len(), mesh.vertex_count());
+            let mut volume = 0.0;
+            for triangle in mesh.triangles() {
+                let corners = triangle.map(|i| mesh.positions()[i]);
+                let normal = triangle_normal(corners);
+                //Faces wind counter-clockwise around the outward vertex normals.
+                for i in triangle {
+                    let n = mesh.normals()[i];
+                    assert!(normal[0] * n[0] + normal[1] * n[1] + normal[2] * n[2] > 0.0, "{:?}", shape);
+                }
+                let [a, b, c] = corners;
+                volume += (a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0]) + a[2] * (b[0] * c[1] - b[1] * c[0])) / 6.0;
+            }
+            match expected {
+                Some(expected) => assert!((volume - expected).abs() < expected * 0.01, "{:?} has a volume of {}", shape, volume),
+                None => assert_eq!(volume, 0.0)
+            }
+        }
+
+        let plane = Primitive::Plane { width: 2.0, depth: 3.0, subdivisions: [4, 5] }.mesh();
+        assert_eq!((plane.vertex_count(), plane.triangle_count()), (30, 40));
+        assert_eq!(plane.bounds().unwrap(), Aabb::new([-1.0, 0.0, -1.5], [1.0, 0.0, 1.5]));
+        let cube = Primitive::Cube { size: [2.0; 3] }.mesh();
+        assert_eq!((cube.vertex_count(), cube.triangle_count()), (24, 12));
+        assert!(cube.uvs().iter().all(|uv| uv[0] >= 0.0 && uv[0] <= 1.0 && uv[1] >= 0.0 && uv[1] <= 1.0));
+        let sphere = Primitive::Icosphere { radius: 2.0, subdivisions: 2 }.mesh();
+        assert_eq!(sphere.triangle_count(), 320);
+        assert!(sphere.positions().iter().all(|p| ((p[0] * p[0] + p[1] * p[1] + p[2] * p[2]).sqrt() - 2.0).abs() < 1e-5));
+    }
+
+    #[test]
+    fn heightmap_terrain() {
+        let image = image::DynamicImage::ImageLuma8(image::GrayImage::from_fn(65, 49, |x, _| image::Luma([x as u8 * 3])));
+        let terrain = Terrain::from_image(&image, [64.0, 25.5, 48.0]).unwrap().with_chunk_size(16);
+        assert_eq!(terrain.resolution(), [65, 49]);
+        assert_eq!(terrain.spacing(), [1.0, 1.0]);
+        assert_eq!(terrain.chunks().len(), 4 * 3);
+        let bounds = terrain.bounds();
+        assert_eq!((bounds.min[0], bounds.max[0], bounds.min[2], bounds.max[2]), (-32.0, 32.0, -24.0, 24.0));
+
+        //Heights rise by 0.3 per unit along x.
+        assert!(terrain.height_at(-32.0, 0.0).unwrap().abs() < 1e-5);
+        assert!((terrain.height_at(0.25, 3.6).unwrap() - 32.25 * 0.3).abs() < 1e-3);
+        assert_eq!(terrain.height_at(32.5, 0.0), None);
+        let normal = terrain.normal_at(5.5, -2.0).unwrap();
+        let expected = [-0.3 / 1.09f32.sqrt(), 1.0 / 1.09f32.sqrt(), 0.0];
+        for i in 0..3 {
+            assert!((normal[i] - expected[i]).abs() < 1e-4);
+        }
+
+        //Chunks cover every cell once and their vertices lie on the queried surface.
+        let triangles = terrain.chunks().iter().map(|chunk| chunk.mesh().triangle_count()).sum::<usize>();
+        assert_eq!(triangles, 64 * 48 * 2);
+        for chunk in terrain.chunks() {
+            for p in chunk.mesh().positions() {
+                assert!((terrain.height_at(p[0], p[2]).unwrap() - p[1]).abs() < 1e-4);
+            }
+            for triangle in chunk.mesh().triangles() {
+                assert!(triangle_normal(triangle.map(|i| chunk.mesh().positions()[i]))[1] > 0.0);
+            }
+        }
+
+        //Looking along -z from above the center sees the far row and the middle of the center row.
+        let camera = Camera::new(90.0, 100.0, 0.1, [800, 800], [0.0, 5.0, 0.0], [0.0, 5.0, -1.0], [0.0, 1.0, 0.0]);
+        let visible = terrain.visible_chunks(&Frustum::from_camera(&camera), &IDENTITY_MAT4);
+        assert_eq!(visible, vec![0, 1, 2, 3, 5, 6]);
+
+        let strip = image::DynamicImage::ImageLuma8(image::GrayImage::new(8, 1));
+        assert!(Terrain::from_image(&strip, [1.0; 3]).is_err());
+        assert!(Terrain::new([2, 2], vec![0.0; 3], [1.0; 2]).is_err());
+    }
+
+    #[test]
+    fn noise_library() {
+        let (a, b) = (Noise::new(7), Noise::new(8));
+        assert_eq!(Noise::new(7), a);
+        assert_ne!(a, b);
+        let points = (0..2000).map(|i| {
+            let t = i as f32;
+            [(t * 0.731).sin() * 40.0 - 3.1, (t * 0.377).cos() * 40.0 + 0.6, t * 0.013 - 11.0, (t * 0.19).sin() * 7.0]
+        }).collect::<Vec<_>>();
+
+        //Reproducible per seed, with distinct seeds giving distinct fields.
+        let differs = points.iter().filter(|p| (a.simplex2(p[0], p[1]) - b.simplex2(p[0], p[1])).abs() > 1e-3).count();
+        assert!(differs > 1800);
+        assert_eq!(a.simplex3(1.3, -2.7, 5.1), Noise::new(7).simplex3(1.3, -2.7, 5.1));
+
+        //Every kind stays in about [-1, 1], perlin peaking slightly above, uses most of the range and is continuous, negative coordinates included.
+        let kinds: [fn(&Noise, &[f32; 4]) -> f32; 6] = [
+            |n, p| n.perlin(p[0], p[1], p[2]),
+            |n, p| n.value2(p[0], p[1]),
+            |n, p| n.value3(p[0], p[1], p[2]),
+            |n, p| n.simplex2(p[0], p[1]),
+            |n, p| n.simplex3(p[0], p[1], p[2]),
+            |n, p| n.simplex4(p[0], p[1], p[2], p[3]),
+        ];
+        for kind in kinds {
+            let values = points.iter().map(|p| kind(&a, p)).collect::<Vec<f32>>();
+            assert!(values.iter().all(|v| v.abs() <= 1.04));
+            assert!(values.iter().fold(0.0f32, |max, v| max.max(v.abs())) > 0.5);
+            for p in points.iter().take(200) {
+                let nudged = p.map(|v| v + 1e-3);
+                assert!((kind(&a, p) - kind(&a, &nudged)).abs() < 0.05);
+            }
+        }
+
+        //The reference table reproduces the global perlin noise.
+        perlin::init_perlin();
+        let reference = Noise::reference();
+        for p in points.iter().take(200) {
+            assert!((reference.perlin(p[0], p[1], p[2]) - perlin::noise(p[0], p[1], p[2])).abs() < 1e-6);
+        }
+        assert!(a.perlin(2.0, -3.0, 4.0).abs() < 1e-6);
+
+        //Worley distances are ordered, and the nearest cell is the same on both sides of a tiny step.
+        for p in &points {
+            let worley = a.worley2(p[0], p[1]);
+            assert!(worley.f1 <= worley.f2 && worley.f1 < 1.5);
+            let worley = a.worley3(p[0], p[1], p[2]);
+            assert!(worley.f1 <= worley.f2 && worley.f1 < 1.8);
+        }
+        let near = a.worley2(10.5, 10.5);
+        assert_eq!(near.cell, a.worley2(10.5001, 10.5).cell);
+
+        //Fractal sums stay in their ranges, and a single octave is the noise itself.
+        let fractal = Fractal::new(5).with_frequency(0.5).with_gain(0.6);
+        for p in &points {
+            let simplex = |[x, y]: [f32; 2]| a.simplex2(x, y);
+            assert!(fractal.fbm([p[0], p[1]], simplex).abs() <= 1.0);
+            assert!((0.0..=1.0).contains(&fractal.ridged([p[0], p[1]], simplex)));
+            assert!((0.0..=1.0).contains(&fractal.turbulence([p[0], p[1]], simplex)));
+            assert_eq!(Fractal::new(1).fbm([p[0], p[1]], simplex), a.simplex2(p[0], p[1]));
+        }
+        let warped = warp([1.0, 2.0, 3.0], 0.5, |[x, y, z]| a.simplex3(x, y, z));
+        assert!(warped.iter().zip([1.0, 2.0, 3.0]).all(|(w, p)| (w - p).abs() <= 0.5));
+        assert_eq!(warp([1.0, 2.0], 0.0, |[x, y]| a.simplex2(x, y)), [1.0, 2.0]);
+
+        //Baked textures hold a value per pixel in row order.
+        let image = bake(8, 4, |u, v| u * 0.5 + v);
+        assert_eq!((image.bytes().len(), image.data_type()), (32, DataType::UnsignedByte));
+        assert!(matches!(image.color_format(), ColorFormat::Red));
+        assert_eq!((image.bytes()[0], image.bytes()[31]), (40, 255));
+        let image = bake_float(3, 2, |u, v| u - v);
+        assert_eq!((image.bytes().len(), image.data_type()), (24, DataType::Float));
+        assert_eq!(f32::from_ne_bytes(image.bytes()[4..8].try_into().unwrap()), 0.5 - 0.25);
+        let volume = bake_volume(4, 4, 2, |_, _, w| w);
+        assert_eq!((volume.bytes().len(), volume.depth()), (32, 2));
+        assert_eq!((volume.bytes()[0], volume.bytes()[31]), (64, 191));
+    }
 }
\ No newline at end of file
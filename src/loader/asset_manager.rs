@@ -0,0 +1,350 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+use image::load_from_memory;
+use crate::gfx::program::Program;
+use crate::gfx::resource::{GlObject, Owned, Shared};
+use crate::gfx::shader::preprocessor::{Defines, Preprocessor};
+use crate::gfx::texture::Texture;
+use crate::loader::models::obj::OBJ;
+
+static mut ASSET_MANAGER: Option<AssetManager> = None;
+
+///Data compiled into the binary, addressed by `AssetKey::Embedded`.
+const EMBEDDED: &[(&str, &[u8])] = &[
+    ("models/debug/sphere.obj", include_bytes!("../../res/models/debug/sphere/sphere.obj")),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AssetKey {
+    Path(PathBuf),
+    ///Data compiled into the binary or registered with `AssetManager::embed`.
+    Embedded(String),
+    ///Preprocessed vertex and fragment files, see `Preprocessor::program`.
+    Program {
+        root: PathBuf,
+        vertex: String,
+        fragment: String,
+        defines: Defines,
+    },
+}
+
+impl AssetKey {
+    pub fn path<P: AsRef<Path>>(path: P) -> AssetKey {
+        AssetKey::Path(path.as_ref().to_path_buf())
+    }
+
+    pub fn embedded<T: ToString>(name: T) -> AssetKey {
+        AssetKey::Embedded(name.to_string())
+    }
+}
+
+impl Display for AssetKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssetKey::Path(path) => f.write_fmt(format_args!("{}", path.display())),
+            AssetKey::Embedded(name) => f.write_fmt(format_args!("embedded:{}", name)),
+            AssetKey::Program { vertex, fragment, .. } => f.write_fmt(format_args!("program:{}+{}", vertex, fragment)),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub enum AssetError {
+    UnknownEmbedded(String),
+    ///The key names no file data, e.g. a program key.
+    NotReadable(AssetKey),
+}
+
+impl Debug for AssetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssetError::UnknownEmbedded(name) => {
+                f.write_fmt(format_args!("No embedded asset named {}", name))
+            }
+            AssetError::NotReadable(key) => {
+                f.write_fmt(format_args!("{} can't be read as a file", key))
+            }
+        }
+    }
+}
+
+impl Display for AssetError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl Error for AssetError {}
+
+///Releases what an asset owns once its last `SharedAsset` is dropped.
+pub trait Unload {
+    fn unload(&mut self);
+}
+
+///Owned GL objects delete themselves when dropped with the entry.
+impl<T: GlObject> Unload for Owned<T> {
+    fn unload(&mut self) {}
+}
+
+impl<T: GlObject> Unload for Shared<T> {
+    fn unload(&mut self) {}
+}
+
+///Material textures are shared and deleted with the last model or material holding them.
+impl Unload for OBJ {
+    fn unload(&mut self) {}
+}
+
+///Raw font file, e.g. for `imgui::FontSource::TtfData`.
+#[derive(Debug, Clone)]
+pub struct FontData(Vec<u8>);
+
+impl FontData {
+    pub fn bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Unload for FontData {
+    fn unload(&mut self) {}
+}
+
+#[derive(Debug, Clone)]
+pub struct LuaSource(String);
+
+impl LuaSource {
+    pub fn source(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Unload for LuaSource {
+    fn unload(&mut self) {}
+}
+
+struct Entry<T: Unload> {
+    key: AssetKey,
+    asset: T,
+}
+
+impl<T: Unload> Drop for Entry<T> {
+    fn drop(&mut self) {
+        self.asset.unload();
+    }
+}
+
+///Reference counted handle to an asset of the `AssetManager`. The asset is unloaded when the last handle is dropped.
+///Handles aren't `Send`, so GL objects are always deleted on the render thread.
+pub struct SharedAsset<T: Unload> {
+    entry: Rc<Entry<T>>,
+}
+
+impl<T: Unload> Clone for SharedAsset<T> {
+    fn clone(&self) -> Self {
+        Self { entry: self.entry.clone() }
+    }
+}
+
+impl<T: Unload> Deref for SharedAsset<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.entry.asset
+    }
+}
+
+impl<T: Unload + Debug> Debug for SharedAsset<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedAsset")
+            .field("key", &self.entry.key)
+            .field("asset", &self.entry.asset)
+            .finish()
+    }
+}
+
+impl<T: Unload> SharedAsset<T> {
+    pub fn key(&self) -> &AssetKey {
+        &self.entry.key
+    }
+
+    ///Handles sharing this asset, including this one.
+    pub fn ref_count(&self) -> usize {
+        Rc::strong_count(&self.entry)
+    }
+
+    pub fn ptr_eq(a: &SharedAsset<T>, b: &SharedAsset<T>) -> bool {
+        Rc::ptr_eq(&a.entry, &b.entry)
+    }
+}
+
+fn read(embedded: &HashMap<String, &'static [u8]>, key: &AssetKey) -> Result<Vec<u8>, Box<dyn Error>> {
+    match key {
+        AssetKey::Path(path) => Ok(fs::read(path)?),
+        AssetKey::Embedded(name) => match embedded.get(name) {
+            Some(bytes) => Ok(bytes.to_vec()),
+            None => Err(Box::new(AssetError::UnknownEmbedded(name.clone())))
+        },
+        key => Err(Box::new(AssetError::NotReadable(key.clone())))
+    }
+}
+
+fn read_string(embedded: &HashMap<String, &'static [u8]>, key: &AssetKey) -> Result<String, Box<dyn Error>> {
+    Ok(String::from_utf8(read(embedded, key)?)?)
+}
+
+struct Cache<T: Unload> {
+    entries: HashMap<AssetKey, Weak<Entry<T>>>,
+}
+
+impl<T: Unload> Cache<T> {
+    fn new() -> Cache<T> {
+        Self { entries: HashMap::new() }
+    }
+
+    fn get(&self, key: &AssetKey) -> Option<SharedAsset<T>> {
+        self.entries.get(key)
+            .and_then(|entry| entry.upgrade())
+            .map(|entry| SharedAsset { entry })
+    }
+
+    fn get_or_load<F>(&mut self, key: AssetKey, load: F) -> Result<SharedAsset<T>, Box<dyn Error>>
+        where F: FnOnce(&AssetKey) -> Result<T, Box<dyn Error>> {
+        if let Some(asset) = self.get(&key) {
+            return Ok(asset);
+        }
+        self.collect();
+        let entry = Rc::new(Entry { asset: load(&key)?, key: key.clone() });
+        self.entries.insert(key, Rc::downgrade(&entry));
+        Ok(SharedAsset { entry })
+    }
+
+    ///Forgets entries whose asset has been unloaded.
+    fn collect(&mut self) {
+        self.entries.retain(|_, entry| entry.strong_count() > 0);
+    }
+
+    fn loaded(&self) -> usize {
+        self.entries.values().filter(|entry| entry.strong_count() > 0).count()
+    }
+}
+
+///Loads models, textures, programs, fonts and Lua sources once per key and hands out shared handles.
+///Must only be used on the render thread.
+pub struct AssetManager {
+    embedded: HashMap<String, &'static [u8]>,
+    models: Cache<OBJ>,
+    textures: Cache<Owned<Texture>>,
+    programs: Cache<Shared<Program>>,
+    fonts: Cache<FontData>,
+    scripts: Cache<LuaSource>,
+}
+
+impl AssetManager {
+    fn new() -> AssetManager {
+        Self {
+            embedded: EMBEDDED.iter().map(|(name, bytes)| (name.to_string(), *bytes)).collect(),
+            models: Cache::new(),
+            textures: Cache::new(),
+            programs: Cache::new(),
+            fonts: Cache::new(),
+            scripts: Cache::new(),
+        }
+    }
+
+    fn get() -> &'static mut AssetManager {
+        unsafe {
+            ASSET_MANAGER.get_or_insert_with(AssetManager::new)
+        }
+    }
+
+    ///Makes `bytes` loadable as `AssetKey::Embedded(name)`, e.g. data from `include_bytes!`.
+    pub fn embed<T: ToString>(name: T, bytes: &'static [u8]) {
+        Self::get().embedded.insert(name.to_string(), bytes);
+    }
+
+    ///Contents of a file or embedded asset.
+    pub fn read(key: &AssetKey) -> Result<Vec<u8>, Box<dyn Error>> {
+        read(&Self::get().embedded, key)
+    }
+
+    ///Parses an obj file and loads its materials' textures. Material libraries of embedded models aren't loaded.
+    pub fn obj(key: AssetKey) -> Result<SharedAsset<OBJ>, Box<dyn Error>> {
+        let manager = Self::get();
+        let embedded = &manager.embedded;
+        manager.models.get_or_load(key, |key| {
+            let mut obj = match key {
+                AssetKey::Path(path) => OBJ::from_file(path)?,
+                key => OBJ::from_raw(read_string(embedded, key)?)?
+            };
+            for object in obj.objects_mut() {
+                object.load_textures();
+            }
+            Ok(obj)
+        })
+    }
+
+    ///Loads an image, or a `.dds` or `.ktx2` container with its mip chain.
+    pub fn texture(key: AssetKey) -> Result<SharedAsset<Owned<Texture>>, Box<dyn Error>> {
+        let manager = Self::get();
+        let embedded = &manager.embedded;
+        manager.textures.get_or_load(key, |key| {
+            let bytes = read(embedded, key)?;
+            let name = match key {
+                AssetKey::Path(path) => path.to_string_lossy().to_lowercase(),
+                key => key.to_string().to_lowercase()
+            };
+            if name.ends_with(".dds") || name.ends_with(".ktx2") {
+                let image = match name.ends_with(".dds") {
+                    true => crate::loader::textures::dds::read(&bytes)?,
+                    false => crate::loader::textures::ktx2::read(&bytes)?
+                };
+                return Ok(Texture::from_compressed(&image, Default::default(), Default::default()).owned());
+            }
+            Ok(Texture::from_image(&load_from_memory(&bytes)?)?.owned())
+        })
+    }
+
+    ///Builds a program with `preprocessor`, shared with every caller using the same files, root and defines.
+    pub fn program(preprocessor: &Preprocessor, vertex: &str, fragment: &str) -> Result<SharedAsset<Shared<Program>>, Box<dyn Error>> {
+        let key = AssetKey::Program {
+            root: preprocessor.root().to_path_buf(),
+            vertex: vertex.to_string(),
+            fragment: fragment.to_string(),
+            defines: preprocessor.defines().clone(),
+        };
+        Self::get().programs.get_or_load(key, |_| preprocessor.program(vertex, fragment))
+    }
+
+    pub fn font(key: AssetKey) -> Result<SharedAsset<FontData>, Box<dyn Error>> {
+        let manager = Self::get();
+        let embedded = &manager.embedded;
+        manager.fonts.get_or_load(key, |key| Ok(FontData(read(embedded, key)?)))
+    }
+
+    pub fn lua(key: AssetKey) -> Result<SharedAsset<LuaSource>, Box<dyn Error>> {
+        let manager = Self::get();
+        let embedded = &manager.embedded;
+        manager.scripts.get_or_load(key, |key| Ok(LuaSource(read_string(embedded, key)?)))
+    }
+
+    ///Whether any handle to the asset is alive.
+    pub fn is_loaded(key: &AssetKey) -> bool {
+        let manager = Self::get();
+        manager.models.get(key).is_some()
+            || manager.textures.get(key).is_some()
+            || manager.programs.get(key).is_some()
+            || manager.fonts.get(key).is_some()
+            || manager.scripts.get(key).is_some()
+    }
+
+    ///Number of loaded assets across every type.
+    pub fn loaded() -> usize {
+        let manager = Self::get();
+        manager.models.loaded() + manager.textures.loaded() + manager.programs.loaded() + manager.fonts.loaded() + manager.scripts.loaded()
+    }
+}
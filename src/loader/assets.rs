@@ -0,0 +1,417 @@
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread::{Builder, JoinHandle};
+use std::time::{Duration, Instant};
+use image::{DynamicImage, load_from_memory};
+use crate::gfx::program::Program;
+use crate::gfx::resource::{GlObject, Shared};
+use crate::gfx::shader::preprocessor::{Preprocessor, ProcessedShader};
+use crate::gfx::texture::{ColorSpace, MipMapping, Texture, Wrapping};
+use crate::loader::models::obj::OBJ;
+use crate::loader::textures::CompressedImage;
+
+static mut ASSET_LOADER: Option<AssetLoader> = None;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+///Where an asset is in the pipeline. Assets start out `Loading`, until both the worker and the render thread are done with them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssetState<T> {
+    Loading,
+    Ready(T),
+    ///The error of the decode or upload step.
+    Failed(String),
+}
+
+///Shared view of an asset loaded by the `AssetLoader`. Clones observe the same asset.
+#[derive(Debug)]
+pub struct AssetHandle<T> {
+    state: Arc<Mutex<AssetState<T>>>,
+}
+
+impl<T> Clone for AssetHandle<T> {
+    fn clone(&self) -> Self {
+        Self { state: self.state.clone() }
+    }
+}
+
+impl<T> AssetHandle<T> {
+    pub fn loading() -> AssetHandle<T> {
+        Self { state: Arc::new(Mutex::new(AssetState::Loading)) }
+    }
+
+    ///A handle to an asset that is already available, e.g. one loaded synchronously.
+    pub fn ready(asset: T) -> AssetHandle<T> {
+        Self { state: Arc::new(Mutex::new(AssetState::Ready(asset))) }
+    }
+
+    pub fn failed<E: ToString>(error: E) -> AssetHandle<T> {
+        Self { state: Arc::new(Mutex::new(AssetState::Failed(error.to_string()))) }
+    }
+
+    fn resolve(&self, result: Result<T, String>) {
+        let mut state = self.state.lock().unwrap();
+        *state = match result {
+            Ok(asset) => AssetState::Ready(asset),
+            Err(e) => AssetState::Failed(e)
+        };
+    }
+
+    pub fn is_loading(&self) -> bool {
+        matches!(*self.state.lock().unwrap(), AssetState::Loading)
+    }
+
+    pub fn is_ready(&self) -> bool {
+        matches!(*self.state.lock().unwrap(), AssetState::Ready(_))
+    }
+
+    pub fn is_failed(&self) -> bool {
+        matches!(*self.state.lock().unwrap(), AssetState::Failed(_))
+    }
+
+    pub fn error(&self) -> Option<String> {
+        match &*self.state.lock().unwrap() {
+            AssetState::Failed(e) => Some(e.clone()),
+            _ => None
+        }
+    }
+
+    ///Calls `f` with the asset if it is ready, without cloning it.
+    pub fn with<R, F: FnOnce(&T) -> R>(&self, f: F) -> Option<R> {
+        match &*self.state.lock().unwrap() {
+            AssetState::Ready(asset) => Some(f(asset)),
+            _ => None
+        }
+    }
+}
+
+impl<T: Clone> AssetHandle<T> {
+    pub fn state(&self) -> AssetState<T> {
+        self.state.lock().unwrap().clone()
+    }
+
+    pub fn get(&self) -> Option<T> {
+        self.with(|asset| asset.clone())
+    }
+}
+
+///CPU-side result of a worker, turned into its GPU asset on the render thread.
+pub trait Upload: Send + 'static {
+    type Asset: Send + 'static;
+    ///Called on the thread owning the GL context.
+    fn upload(self) -> Result<Self::Asset, Box<dyn Error>>;
+}
+
+///A decoded image waiting to be uploaded as a 2D texture.
+pub struct DecodedTexture {
+    image: DynamicImage,
+    color_space: ColorSpace,
+    wrapping: Wrapping,
+    mip_mapping: MipMapping,
+}
+
+impl DecodedTexture {
+    pub fn new(image: DynamicImage, color_space: ColorSpace) -> DecodedTexture {
+        Self { image, color_space, wrapping: Wrapping::default(), mip_mapping: MipMapping::default() }
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P, color_space: ColorSpace) -> Result<DecodedTexture, Box<dyn Error>> {
+        Ok(Self::new(image::open(path)?, color_space))
+    }
+
+    pub fn from_memory(bytes: &[u8], color_space: ColorSpace) -> Result<DecodedTexture, Box<dyn Error>> {
+        Ok(Self::new(load_from_memory(bytes)?, color_space))
+    }
+
+    pub fn with_wrapping(mut self, wrapping: Wrapping) -> DecodedTexture {
+        self.wrapping = wrapping;
+        self
+    }
+
+    pub fn with_mip_mapping(mut self, mip_mapping: MipMapping) -> DecodedTexture {
+        self.mip_mapping = mip_mapping;
+        self
+    }
+
+    pub fn image(&self) -> &DynamicImage {
+        &self.image
+    }
+}
+
+impl Upload for DecodedTexture {
+    type Asset = Shared<Texture>;
+
+    fn upload(self) -> Result<Shared<Texture>, Box<dyn Error>> {
+        Ok(Texture::from_image_with(&self.image, self.color_space, self.wrapping, self.mip_mapping)?.shared())
+    }
+}
+
+///A DDS or KTX2 image read from disk, see `Texture::from_compressed`.
+pub struct CompressedTexture {
+    image: CompressedImage,
+    wrapping: Wrapping,
+    mip_mapping: MipMapping,
+}
+
+impl Upload for CompressedTexture {
+    type Asset = Shared<Texture>;
+
+    fn upload(self) -> Result<Shared<Texture>, Box<dyn Error>> {
+        Ok(Texture::from_compressed(&self.image, self.wrapping, self.mip_mapping).shared())
+    }
+}
+
+///A parsed model with the images of its texture maps. Images shared by several maps are decoded once.
+pub struct ParsedModel {
+    obj: OBJ,
+    images: HashMap<PathBuf, DynamicImage>,
+}
+
+impl ParsedModel {
+    ///Parses the obj and mtl files and decodes every referenced texture. Maps that fail to decode
+    ///are reported and skipped, like `Material::load_textures` does.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<ParsedModel, Box<dyn Error>> {
+        let obj = OBJ::from_file(path)?;
+        let mut images = HashMap::new();
+        for object in obj.objects() {
+            for material in object.materials().values() {
+                for map in material.maps() {
+                    if images.contains_key(map.path()) {
+                        continue;
+                    }
+                    match image::open(map.path()) {
+                        Ok(image) => {
+                            images.insert(map.path().to_path_buf(), image);
+                        }
+                        Err(e) => eprintln!("Unable to load {:?} map '{}' of material '{}': {}", map.map_type(), map.source(), material.name, e)
+                    }
+                }
+            }
+        }
+        Ok(Self { obj, images })
+    }
+
+    pub fn obj(&self) -> &OBJ {
+        &self.obj
+    }
+}
+
+impl Upload for ParsedModel {
+    type Asset = OBJ;
+
+    fn upload(mut self) -> Result<OBJ, Box<dyn Error>> {
+        let mut textures = HashMap::new();
+        for (path, image) in &self.images {
+            textures.insert(path.clone(), Texture::from_image(image)?.shared());
+        }
+        for object in self.obj.objects_mut() {
+            for material in object.materials_mut().values_mut() {
+                for map_type in material.maps().iter().map(|map| map.map_type()).collect::<Vec<_>>() {
+                    let map = material.map_mut(map_type).unwrap();
+                    if let Some(texture) = textures.get(map.path()) {
+                        map.set_texture(texture.clone());
+                    }
+                }
+            }
+        }
+        Ok(self.obj)
+    }
+}
+
+///Vertex and fragment sources run through the preprocessor, linked by `Preprocessor::link`.
+pub struct ProcessedProgram {
+    preprocessor: Preprocessor,
+    vertex: String,
+    fragment: String,
+    vertex_source: ProcessedShader,
+    fragment_source: ProcessedShader,
+}
+
+impl ProcessedProgram {
+    pub fn process(preprocessor: Preprocessor, vertex: &str, fragment: &str) -> Result<ProcessedProgram, Box<dyn Error>> {
+        Ok(Self {
+            vertex_source: preprocessor.process_file(vertex)?,
+            fragment_source: preprocessor.process_file(fragment)?,
+            vertex: vertex.to_string(),
+            fragment: fragment.to_string(),
+            preprocessor,
+        })
+    }
+}
+
+impl Upload for ProcessedProgram {
+    type Asset = Shared<Program>;
+
+    fn upload(self) -> Result<Shared<Program>, Box<dyn Error>> {
+        self.preprocessor.link(&self.vertex, &self.fragment, &self.vertex_source, &self.fragment_source)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AssetSettings {
+    ///Threads decoding assets. GL is never called on them.
+    pub workers: usize,
+    ///Time per frame the render thread spends on uploads. At least one upload runs per frame.
+    pub upload_budget: Duration,
+}
+
+impl Default for AssetSettings {
+    fn default() -> Self {
+        Self {
+            workers: std::thread::available_parallelism().map(|n| n.get().saturating_sub(1)).unwrap_or(1).clamp(1, 4),
+            upload_budget: Duration::from_millis(4),
+        }
+    }
+}
+
+///Loads assets in two steps: files are read and decoded on a pool of worker threads,
+///then the CPU-side results are queued and uploaded on the render thread by `update`.
+pub struct AssetLoader {
+    settings: AssetSettings,
+    jobs: Sender<Job>,
+    uploads: Arc<Mutex<VecDeque<Job>>>,
+    _workers: Vec<JoinHandle<()>>,
+}
+
+impl AssetLoader {
+    pub unsafe fn init(settings: AssetSettings) {
+        let (jobs, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..settings.workers.max(1))
+            .map(|i| Self::spawn_worker(i, receiver.clone()))
+            .collect();
+        ASSET_LOADER = Some(AssetLoader {
+            settings,
+            jobs,
+            uploads: Arc::new(Mutex::new(VecDeque::new())),
+            _workers: workers,
+        });
+    }
+
+    fn spawn_worker(index: usize, receiver: Arc<Mutex<Receiver<Job>>>) -> JoinHandle<()> {
+        Builder::new()
+            .name(format!("asset-worker-{}", index))
+            .spawn(move || loop {
+                let job = match receiver.lock() {
+                    Ok(receiver) => receiver.recv(),
+                    Err(_) => return
+                };
+                match job {
+                    Ok(job) => job(),
+                    //The loader was dropped.
+                    Err(_) => return
+                }
+            })
+            .expect("Unable to spawn an asset worker")
+    }
+
+    ///Stops the workers once their current job is done and drops the queued uploads. Called by the app on shutdown.
+    pub unsafe fn shutdown() {
+        ASSET_LOADER = None;
+    }
+
+    pub fn is_initialized() -> bool {
+        unsafe { ASSET_LOADER.is_some() }
+    }
+
+    fn get() -> &'static AssetLoader {
+        unsafe {
+            match &ASSET_LOADER {
+                None => panic!("Asset loader not initialized."),
+                Some(loader) => loader
+            }
+        }
+    }
+
+    ///Runs `decode` on a worker and queues its result for upload. Errors and panics of either step fail the handle.
+    pub fn load<U, F>(decode: F) -> AssetHandle<U::Asset>
+        where U: Upload, F: FnOnce() -> Result<U, Box<dyn Error>> + Send + 'static {
+        let loader = Self::get();
+        let handle = AssetHandle::loading();
+        let worker_handle = handle.clone();
+        let uploads = loader.uploads.clone();
+        let job: Job = Box::new(move || {
+            let decoded = match catch_unwind(AssertUnwindSafe(decode)) {
+                Ok(Ok(decoded)) => decoded,
+                Ok(Err(e)) => return worker_handle.resolve(Err(e.to_string())),
+                Err(_) => return worker_handle.resolve(Err("The decoder panicked".to_string()))
+            };
+            let upload: Job = Box::new(move || {
+                let result = match catch_unwind(AssertUnwindSafe(|| decoded.upload())) {
+                    Ok(result) => result.map_err(|e| e.to_string()),
+                    Err(_) => Err("The upload panicked".to_string())
+                };
+                worker_handle.resolve(result);
+            });
+            uploads.lock().unwrap().push_back(upload);
+        });
+        if loader.jobs.send(job).is_err() {
+            handle.resolve(Err("Asset workers stopped".to_string()));
+        }
+        handle
+    }
+
+    pub fn load_texture<P: AsRef<Path>>(path: P, color_space: ColorSpace, wrapping: Wrapping, mip_mapping: MipMapping) -> AssetHandle<Shared<Texture>> {
+        let path = path.as_ref().to_path_buf();
+        Self::load(move || Ok(DecodedTexture::open(path, color_space)?.with_wrapping(wrapping).with_mip_mapping(mip_mapping)))
+    }
+
+    ///Decodes an image embedded with `include_bytes!`.
+    pub fn load_texture_bytes(bytes: &'static [u8], color_space: ColorSpace) -> AssetHandle<Shared<Texture>> {
+        Self::load(move || DecodedTexture::from_memory(bytes, color_space))
+    }
+
+    pub fn load_compressed_texture<P: AsRef<Path>>(path: P, wrapping: Wrapping, mip_mapping: MipMapping) -> AssetHandle<Shared<Texture>> {
+        let path = path.as_ref().to_path_buf();
+        Self::load(move || Ok(CompressedTexture { image: crate::loader::textures::load(path)?, wrapping, mip_mapping }))
+    }
+
+    ///Loads an obj file with its materials and their textures.
+    pub fn load_obj<P: AsRef<Path>>(path: P) -> AssetHandle<OBJ> {
+        let path = path.as_ref().to_path_buf();
+        Self::load(move || ParsedModel::open(path))
+    }
+
+    pub fn load_program(preprocessor: Preprocessor, vertex: &str, fragment: &str) -> AssetHandle<Shared<Program>> {
+        let vertex = vertex.to_string();
+        let fragment = fragment.to_string();
+        Self::load(move || ProcessedProgram::process(preprocessor, &vertex, &fragment))
+    }
+
+    ///Uploads queued assets until `budget` runs out, returning how many were uploaded.
+    ///Must be called on the thread owning the GL context.
+    pub fn upload(budget: Duration) -> usize {
+        let loader = Self::get();
+        let start = Instant::now();
+        let mut uploaded = 0;
+        loop {
+            let upload = loader.uploads.lock().unwrap().pop_front();
+            match upload {
+                Some(upload) => upload(),
+                None => break
+            }
+            uploaded += 1;
+            if start.elapsed() >= budget {
+                break;
+            }
+        }
+        uploaded
+    }
+
+    ///Uploads with the budget from the settings. Called once per frame by the app.
+    pub fn update() -> usize {
+        if !Self::is_initialized() {
+            return 0;
+        }
+        Self::upload(Self::get().settings.upload_budget)
+    }
+
+    ///Decoded assets waiting for the render thread.
+    pub fn pending_uploads() -> usize {
+        Self::get().uploads.lock().unwrap().len()
+    }
+}
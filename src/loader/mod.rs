@@ -1 +1,4 @@
-pub mod models;
\ No newline at end of file
+pub mod models;
+pub mod textures;
+pub mod assets;
+pub mod asset_manager;
\ No newline at end of file
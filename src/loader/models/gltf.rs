@@ -0,0 +1,1178 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::path::{Path, PathBuf};
+use image::{DynamicImage, GrayImage, load_from_memory};
+use serde::Deserialize;
+use vecmath::{vec3_cross, vec3_sub};
+use crate::{BufferDataType, BufferType, Constructor, DrawType, LocalAttribPointer, VertexArrayObject, VertexArrayObjectType, VertexBufferObject};
+use crate::ecs::game_object::GameObject;
+use crate::ecs::transform::Transform;
+use crate::gfx::animation::{AnimationClip, Channel, ChannelPath, Interpolation, NodeTransform, Pose, Skeleton};
+use crate::gfx::objects::gpu_buffer::GpuBuffer;
+use crate::gfx::pbr::PbrMaterial;
+use crate::gfx::resource::{GlObject, Shared};
+use crate::gfx::texture::{ColorSpace, MipMapping, Texture, Wrapping};
+use crate::gfx::texture::texture_library::TextureRole;
+use crate::math::linear_algebra::types::{Mat4, Vec2, Vec3, Vec4};
+use crate::math::linear_algebra::vector_ext::VectorExt;
+use crate::math::quaternion::{compose, decompose, IDENTITY_QUAT, Quat, to_rotation_vector};
+use crate::math::tangent::{orthogonalize, triangle_tangent};
+use crate::scene::Scene;
+
+const GLB_MAGIC: u32 = 0x46546C67;
+const GLB_JSON: u32 = 0x4E4F534A;
+const GLB_BIN: u32 = 0x004E4942;
+
+///Backs accessors without a buffer view, large enough for one `MAT4` of floats.
+static ZEROS: [u8; 64] = [0; 64];
+
+///Primitive mode of indexed or plain triangle lists, the only mode this loader supports.
+const TRIANGLES: u32 = 4;
+
+#[derive(Clone)]
+pub enum GltfError {
+    InvalidGlb(String),
+    UnsupportedVersion(String),
+    ///A buffer without uri that isn't the binary chunk of a glb file.
+    MissingBuffer(usize),
+    InvalidUri(String),
+    ///Accessor index and the problem.
+    Accessor(usize, String),
+    ///Mesh name and primitive mode.
+    UnsupportedMode(String, u32),
+    ///Mesh without a `POSITION` attribute.
+    MissingPositions(String),
+    ///A node index past the node list.
+    MissingNode(usize),
+    ///Node index that is its own ancestor.
+    Cycle(usize),
+}
+
+impl Debug for GltfError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GltfError::InvalidGlb(problem) => {
+                f.write_fmt(format_args!("Invalid glb file: {}", problem))
+            }
+            GltfError::UnsupportedVersion(version) => {
+                f.write_fmt(format_args!("glTF version {} is not supported, expected 2.x", version))
+            }
+            GltfError::MissingBuffer(buffer) => {
+                f.write_fmt(format_args!("Buffer {} has no uri and there is no binary chunk", buffer))
+            }
+            GltfError::InvalidUri(uri) => {
+                f.write_fmt(format_args!("Unable to read uri {}", uri))
+            }
+            GltfError::Accessor(accessor, problem) => {
+                f.write_fmt(format_args!("Accessor {}: {}", accessor, problem))
+            }
+            GltfError::UnsupportedMode(mesh, mode) => {
+                f.write_fmt(format_args!("Mesh {} uses primitive mode {}, only triangles (4) are supported", mesh, mode))
+            }
+            GltfError::MissingPositions(mesh) => {
+                f.write_fmt(format_args!("Mesh {} has a primitive without POSITION", mesh))
+            }
+            GltfError::MissingNode(node) => {
+                f.write_fmt(format_args!("Node {} doesn't exist", node))
+            }
+            GltfError::Cycle(node) => {
+                f.write_fmt(format_args!("Node {} is its own ancestor", node))
+            }
+        }
+    }
+}
+
+impl Display for GltfError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl Error for GltfError {}
+
+fn one() -> f32 {
+    1.0
+}
+
+fn white() -> Vec4 {
+    [1.0; 4]
+}
+
+fn triangles() -> u32 {
+    TRIANGLES
+}
+
+fn linear() -> String {
+    "LINEAR".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Document {
+    asset: AssetInfo,
+    scene: Option<usize>,
+    #[serde(default)]
+    scenes: Vec<SceneInfo>,
+    #[serde(default)]
+    nodes: Vec<NodeInfo>,
+    #[serde(default)]
+    meshes: Vec<MeshInfo>,
+    #[serde(default)]
+    materials: Vec<MaterialInfo>,
+    #[serde(default)]
+    textures: Vec<TextureInfo>,
+    #[serde(default)]
+    images: Vec<ImageInfo>,
+    #[serde(default)]
+    accessors: Vec<AccessorInfo>,
+    #[serde(default)]
+    buffer_views: Vec<BufferViewInfo>,
+    #[serde(default)]
+    buffers: Vec<BufferInfo>,
+    #[serde(default)]
+    skins: Vec<SkinInfo>,
+    #[serde(default)]
+    animations: Vec<AnimationInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AssetInfo {
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SceneInfo {
+    #[serde(default)]
+    nodes: Vec<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NodeInfo {
+    name: Option<String>,
+    #[serde(default)]
+    children: Vec<usize>,
+    mesh: Option<usize>,
+    skin: Option<usize>,
+    matrix: Option<[f32; 16]>,
+    translation: Option<Vec3>,
+    rotation: Option<Quat>,
+    scale: Option<Vec3>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MeshInfo {
+    name: Option<String>,
+    primitives: Vec<PrimitiveInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PrimitiveInfo {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    #[serde(default = "triangles")]
+    mode: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MaterialInfo {
+    name: Option<String>,
+    pbr_metallic_roughness: Option<PbrInfo>,
+    normal_texture: Option<NormalTextureInfo>,
+    occlusion_texture: Option<OcclusionTextureInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PbrInfo {
+    #[serde(default = "white")]
+    base_color_factor: Vec4,
+    base_color_texture: Option<TextureReference>,
+    #[serde(default = "one")]
+    metallic_factor: f32,
+    #[serde(default = "one")]
+    roughness_factor: f32,
+    metallic_roughness_texture: Option<TextureReference>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TextureReference {
+    index: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct NormalTextureInfo {
+    index: usize,
+    #[serde(default = "one")]
+    scale: f32,
+}
+
+#[derive(Debug, Deserialize)]
+struct OcclusionTextureInfo {
+    index: usize,
+    #[serde(default = "one")]
+    strength: f32,
+}
+
+#[derive(Debug, Deserialize)]
+struct TextureInfo {
+    source: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImageInfo {
+    uri: Option<String>,
+    buffer_view: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccessorInfo {
+    buffer_view: Option<usize>,
+    #[serde(default)]
+    byte_offset: usize,
+    component_type: u32,
+    #[serde(default)]
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    sparse: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferViewInfo {
+    buffer: usize,
+    #[serde(default)]
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BufferInfo {
+    uri: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SkinInfo {
+    inverse_bind_matrices: Option<usize>,
+    joints: Vec<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnimationInfo {
+    name: Option<String>,
+    channels: Vec<ChannelInfo>,
+    samplers: Vec<SamplerInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChannelInfo {
+    sampler: usize,
+    target: TargetInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct TargetInfo {
+    node: Option<usize>,
+    path: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SamplerInfo {
+    input: usize,
+    output: usize,
+    #[serde(default = "linear")]
+    interpolation: String,
+}
+
+///Decodes standard base64 as used by data uris. Whitespace is skipped, padding is optional.
+fn decode_base64(input: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let mut bits = 0u32;
+    let mut count = 0;
+    for c in input.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            c if c.is_ascii_whitespace() => continue,
+            _ => return None
+        };
+        bits = (bits << 6) | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            out.push((bits >> count) as u8);
+        }
+    }
+    Some(out)
+}
+
+///Reads a `data:` uri or a file relative to `directory`. File uris may be percent encoded.
+fn read_uri(uri: &str, directory: Option<&Path>) -> Result<Vec<u8>, GltfError> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, payload) = data.split_once(";base64,").ok_or(GltfError::InvalidUri(uri.to_string()))?;
+        return decode_base64(payload).ok_or(GltfError::InvalidUri(uri.to_string()));
+    }
+    let directory = directory.ok_or(GltfError::InvalidUri(uri.to_string()))?;
+    std::fs::read(directory.join(percent_decode(uri))).map_err(|_| GltfError::InvalidUri(uri.to_string()))
+}
+
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Some(byte) = std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    bytes.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+///Splits a glb file into its json and optional binary chunk.
+fn split_glb(bytes: &[u8]) -> Result<(&[u8], Option<&[u8]>), GltfError> {
+    if read_u32(bytes, 0) != Some(GLB_MAGIC) {
+        return Err(GltfError::InvalidGlb("missing glTF magic".to_string()));
+    }
+    let version = read_u32(bytes, 4).unwrap_or(0);
+    if version != 2 {
+        return Err(GltfError::UnsupportedVersion(version.to_string()));
+    }
+    let length = (read_u32(bytes, 8).unwrap_or(0) as usize).min(bytes.len());
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+    while offset + 8 <= length {
+        let chunk_length = read_u32(bytes, offset).unwrap() as usize;
+        let chunk_type = read_u32(bytes, offset + 4).unwrap();
+        let data = bytes.get(offset + 8..offset + 8 + chunk_length)
+            .ok_or(GltfError::InvalidGlb(format!("chunk at {} exceeds the file", offset)))?;
+        match chunk_type {
+            GLB_JSON if json.is_none() => json = Some(data),
+            GLB_BIN if bin.is_none() => bin = Some(data),
+            _ => {}
+        }
+        offset += 8 + chunk_length;
+    }
+    Ok((json.ok_or(GltfError::InvalidGlb("missing json chunk".to_string()))?, bin))
+}
+
+///Byte layout of one accessor.
+struct AccessorData<'a> {
+    bytes: &'a [u8],
+    component_type: u32,
+    normalized: bool,
+    components: usize,
+    count: usize,
+    stride: usize,
+}
+
+impl AccessorData<'_> {
+    fn component_size(component_type: u32) -> Option<usize> {
+        match component_type {
+            5120 | 5121 => Some(1),
+            5122 | 5123 => Some(2),
+            5125 | 5126 => Some(4),
+            _ => None
+        }
+    }
+
+    fn component(&self, element: usize, component: usize) -> &[u8] {
+        let size = Self::component_size(self.component_type).unwrap();
+        let start = element * self.stride + component * size;
+        &self.bytes[start..start + size]
+    }
+
+    ///Every element as floats, normalized integers are mapped to [0, 1] or [-1, 1].
+    fn floats(&self) -> Vec<Vec<f32>> {
+        (0..self.count).map(|element| {
+            (0..self.components).map(|component| {
+                let b = self.component(element, component);
+                match (self.component_type, self.normalized) {
+                    (5120, true) => (b[0] as i8 as f32 / 127.0).max(-1.0),
+                    (5120, false) => b[0] as i8 as f32,
+                    (5121, true) => b[0] as f32 / 255.0,
+                    (5121, false) => b[0] as f32,
+                    (5122, true) => (i16::from_le_bytes([b[0], b[1]]) as f32 / 32767.0).max(-1.0),
+                    (5122, false) => i16::from_le_bytes([b[0], b[1]]) as f32,
+                    (5123, true) => u16::from_le_bytes([b[0], b[1]]) as f32 / 65535.0,
+                    (5123, false) => u16::from_le_bytes([b[0], b[1]]) as f32,
+                    (5125, _) => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32,
+                    _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]])
+                }
+            }).collect()
+        }).collect()
+    }
+
+    fn uint(&self, element: usize, component: usize) -> u32 {
+        let b = self.component(element, component);
+        match self.component_type {
+            5120 | 5121 => b[0] as u32,
+            5122 | 5123 => u16::from_le_bytes([b[0], b[1]]) as u32,
+            _ => u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+        }
+    }
+
+    ///First component of every element as an unsigned integer, for indices.
+    fn uints(&self) -> Vec<u32> {
+        (0..self.count).map(|element| self.uint(element, 0)).collect()
+    }
+
+    ///Four unsigned integers per element, for joint indices.
+    fn uint4s(&self) -> Vec<[u32; 4]> {
+        (0..self.count).map(|element| {
+            let mut values = [0; 4];
+            for (component, value) in values.iter_mut().enumerate().take(self.components) {
+                *value = self.uint(element, component);
+            }
+            values
+        }).collect()
+    }
+}
+
+///A parsed document with its buffers resolved.
+struct Source {
+    document: Document,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl Source {
+    fn parse(bytes: &[u8], directory: Option<&Path>) -> Result<Source, Box<dyn Error>> {
+        let (json, bin) = match read_u32(bytes, 0) == Some(GLB_MAGIC) {
+            true => split_glb(bytes)?,
+            false => (bytes, None)
+        };
+        let document: Document = serde_json::from_slice(json)?;
+        if !document.asset.version.starts_with("2.") {
+            return Err(Box::new(GltfError::UnsupportedVersion(document.asset.version.clone())));
+        }
+        let mut buffers = vec![];
+        for (index, buffer) in document.buffers.iter().enumerate() {
+            buffers.push(match (&buffer.uri, bin) {
+                (Some(uri), _) => read_uri(uri, directory)?,
+                (None, Some(bin)) if index == 0 => bin.to_vec(),
+                _ => return Err(Box::new(GltfError::MissingBuffer(index)))
+            });
+        }
+        Ok(Self { document, buffers })
+    }
+
+    fn buffer_view(&self, index: usize) -> Result<(&[u8], Option<usize>), GltfError> {
+        let view = self.document.buffer_views.get(index)
+            .ok_or(GltfError::InvalidGlb(format!("missing buffer view {}", index)))?;
+        let buffer = self.buffers.get(view.buffer).ok_or(GltfError::MissingBuffer(view.buffer))?;
+        let bytes = buffer.get(view.byte_offset..view.byte_offset + view.byte_length)
+            .ok_or(GltfError::InvalidGlb(format!("buffer view {} exceeds buffer {}", index, view.buffer)))?;
+        Ok((bytes, view.byte_stride))
+    }
+
+    fn accessor(&self, index: usize) -> Result<AccessorData<'_>, GltfError> {
+        let error = |problem: &str| GltfError::Accessor(index, problem.to_string());
+        let accessor = self.document.accessors.get(index).ok_or(error("missing"))?;
+        if accessor.sparse.is_some() {
+            return Err(error("sparse accessors are not supported"));
+        }
+        let components = match accessor.kind.as_str() {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" | "MAT2" => 4,
+            "MAT3" => 9,
+            "MAT4" => 16,
+            _ => return Err(error("unknown type"))
+        };
+        let size = AccessorData::component_size(accessor.component_type).ok_or(error("unknown component type"))?;
+        let element_size = components * size;
+        let (bytes, stride) = match accessor.buffer_view {
+            Some(view) => {
+                let (bytes, stride) = self.buffer_view(view)?;
+                (bytes.get(accessor.byte_offset..).ok_or(error("offset exceeds its buffer view"))?, stride.unwrap_or(element_size))
+            }
+            //Accessors without a buffer view are all zeros.
+            None => (&ZEROS[..element_size], 0)
+        };
+        if accessor.count > 0 && (accessor.count - 1) * stride + element_size > bytes.len() {
+            return Err(error("elements exceed its buffer view"));
+        }
+        Ok(AccessorData {
+            bytes,
+            component_type: accessor.component_type,
+            normalized: accessor.normalized,
+            components,
+            count: accessor.count,
+            stride,
+        })
+    }
+
+    ///Every element of a `MAT4` accessor.
+    fn matrices(&self, index: usize) -> Result<Vec<Mat4>, GltfError> {
+        if self.document.accessors.get(index).map(|accessor| accessor.kind.as_str()) != Some("MAT4") {
+            return Err(GltfError::Accessor(index, "expected MAT4 elements".to_string()));
+        }
+        Ok(self.accessor(index)?.floats().iter()
+            .filter_map(|m| <&[f32; 16]>::try_from(m.as_slice()).ok())
+            .map(to_mat4)
+            .collect())
+    }
+
+    fn image(&self, index: usize, directory: Option<&Path>) -> Result<DynamicImage, Box<dyn Error>> {
+        let image = &self.document.images[index];
+        let bytes = match (&image.uri, image.buffer_view) {
+            (Some(uri), _) => read_uri(uri, directory)?,
+            (None, Some(view)) => self.buffer_view(view)?.0.to_vec(),
+            _ => return Err(Box::new(GltfError::InvalidUri(format!("image {}", index))))
+        };
+        Ok(load_from_memory(&bytes)?)
+    }
+}
+
+fn component(values: &[f32], i: usize) -> f32 {
+    values.get(i).copied().unwrap_or(0.0)
+}
+
+fn to_vec2(values: Vec<Vec<f32>>) -> Vec<Vec2> {
+    values.into_iter().map(|v| [component(&v, 0), component(&v, 1)]).collect()
+}
+
+fn to_vec3(values: Vec<Vec<f32>>) -> Vec<Vec3> {
+    values.into_iter().map(|v| [component(&v, 0), component(&v, 1), component(&v, 2)]).collect()
+}
+
+///Elements with three components get `w` as their fourth.
+fn to_vec4(values: Vec<Vec<f32>>, w: f32) -> Vec<Vec4> {
+    values.into_iter().map(|v| [component(&v, 0), component(&v, 1), component(&v, 2), v.get(3).copied().unwrap_or(w)]).collect()
+}
+
+///Column major matrix from the 16 floats glTF stores.
+fn to_mat4(m: &[f32; 16]) -> Mat4 {
+    [
+        [m[0], m[1], m[2], m[3]],
+        [m[4], m[5], m[6], m[7]],
+        [m[8], m[9], m[10], m[11]],
+        [m[12], m[13], m[14], m[15]],
+    ]
+}
+
+///Indexed triangles sharing one material.
+#[derive(Debug, Clone)]
+pub struct GltfPrimitive {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<Vec2>,
+    tangents: Vec<Vec4>,
+    colors: Vec<Vec4>,
+    joints: Vec<[u32; 4]>,
+    weights: Vec<Vec4>,
+    indices: Vec<u32>,
+    material: Option<usize>,
+}
+
+impl GltfPrimitive {
+    fn read(source: &Source, info: &PrimitiveInfo, mesh: &str) -> Result<GltfPrimitive, GltfError> {
+        if info.mode != TRIANGLES {
+            return Err(GltfError::UnsupportedMode(mesh.to_string(), info.mode));
+        }
+        let attribute = |name: &str| -> Result<Option<Vec<Vec<f32>>>, GltfError> {
+            match info.attributes.get(name) {
+                Some(accessor) => Ok(Some(source.accessor(*accessor)?.floats())),
+                None => Ok(None)
+            }
+        };
+        let positions = to_vec3(attribute("POSITION")?.ok_or(GltfError::MissingPositions(mesh.to_string()))?);
+        let indices = match info.indices {
+            Some(accessor) => source.accessor(accessor)?.uints(),
+            None => (0..positions.len() as u32).collect()
+        };
+        if let Some(index) = indices.iter().find(|i| **i as usize >= positions.len()) {
+            return Err(GltfError::Accessor(info.indices.unwrap_or(0), format!("index {} exceeds {} vertices", index, positions.len())));
+        }
+        let mut primitive = Self {
+            normals: attribute("NORMAL")?.map(to_vec3).unwrap_or_default(),
+            uvs: attribute("TEXCOORD_0")?.map(to_vec2).unwrap_or_default(),
+            tangents: attribute("TANGENT")?.map(|t| to_vec4(t, 1.0)).unwrap_or_default(),
+            colors: attribute("COLOR_0")?.map(|c| to_vec4(c, 1.0)).unwrap_or_default(),
+            joints: match info.attributes.get("JOINTS_0") {
+                Some(accessor) => source.accessor(*accessor)?.uint4s(),
+                None => vec![]
+            },
+            weights: attribute("WEIGHTS_0")?.map(|w| to_vec4(w, 0.0)).unwrap_or_default(),
+            positions,
+            indices,
+            material: info.material,
+        };
+        if primitive.normals.len() != primitive.positions.len() {
+            primitive.generate_normals();
+        }
+        if primitive.tangents.len() != primitive.positions.len() {
+            primitive.generate_tangents();
+        }
+        if primitive.joints.len() != primitive.positions.len() || primitive.weights.len() != primitive.positions.len() {
+            primitive.joints.clear();
+            primitive.weights.clear();
+        }
+        for weights in &mut primitive.weights {
+            let sum = weights.iter().sum::<f32>();
+            if sum > f32::EPSILON {
+                weights.iter_mut().for_each(|w| *w /= sum);
+            }
+        }
+        Ok(primitive)
+    }
+
+    fn triangles(&self) -> impl Iterator<Item=[usize; 3]> + '_ {
+        self.indices.chunks_exact(3).map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+    }
+
+    ///Area weighted smooth normals, used when the file has none.
+    fn generate_normals(&mut self) {
+        let mut normals = vec![[0.0f32; 3]; self.positions.len()];
+        for [a, b, c] in self.triangles() {
+            let normal = vec3_cross(vec3_sub(self.positions[b], self.positions[a]), vec3_sub(self.positions[c], self.positions[a]));
+            for vertex in [a, b, c] {
+                for i in 0..3 {
+                    normals[vertex][i] += normal[i];
+                }
+            }
+        }
+        self.normals = normals.into_iter().map(|normal| normal.normalized_or([0.0, 1.0, 0.0])).collect();
+    }
+
+    ///Tangents accumulated over the triangles sharing a vertex, used when the file has none.
+    fn generate_tangents(&mut self) {
+        let mut accumulated = vec![([0.0f32; 3], [0.0f32; 3]); self.positions.len()];
+        if self.uvs.len() == self.positions.len() {
+            for [a, b, c] in self.triangles() {
+                let (tangent, bitangent) = triangle_tangent(
+                    [self.positions[a], self.positions[b], self.positions[c]],
+                    [self.uvs[a], self.uvs[b], self.uvs[c]],
+                );
+                for vertex in [a, b, c] {
+                    for i in 0..3 {
+                        accumulated[vertex].0[i] += tangent[i];
+                        accumulated[vertex].1[i] += bitangent[i];
+                    }
+                }
+            }
+        }
+        self.tangents = accumulated.into_iter().zip(&self.normals)
+            .map(|((tangent, bitangent), normal)| orthogonalize(*normal, tangent, bitangent))
+            .collect();
+    }
+
+    pub fn positions(&self) -> &Vec<Vec3> {
+        &self.positions
+    }
+    pub fn normals(&self) -> &Vec<Vec3> {
+        &self.normals
+    }
+    ///Empty when the primitive has no `TEXCOORD_0`.
+    pub fn uvs(&self) -> &Vec<Vec2> {
+        &self.uvs
+    }
+    pub fn tangents(&self) -> &Vec<Vec4> {
+        &self.tangents
+    }
+    ///Empty when the primitive has no `COLOR_0`.
+    pub fn colors(&self) -> &Vec<Vec4> {
+        &self.colors
+    }
+    pub fn indices(&self) -> &Vec<u32> {
+        &self.indices
+    }
+    ///Index into `Gltf::materials`.
+    pub fn material(&self) -> Option<usize> {
+        self.material
+    }
+
+    ///Four joint indices per vertex, empty for rigid primitives.
+    pub fn joints(&self) -> &Vec<[u32; 4]> {
+        &self.joints
+    }
+
+    ///Joint weights per vertex, normalized to sum to 1.
+    pub fn weights(&self) -> &Vec<Vec4> {
+        &self.weights
+    }
+
+    pub fn is_skinned(&self) -> bool {
+        !self.joints.is_empty()
+    }
+
+    ///Interleaved position, normal, uv, tangent and color, the layout `pbr_program` expects with
+    ///colors at location 4. Skinned primitives add joints and weights at locations 5 and 6, see
+    ///`skinned_pbr_program`. Missing uvs are zero and missing colors white.
+    pub fn build_vao(&self) -> VertexArrayObject {
+        let mut vertices = Vec::with_capacity(self.positions.len() * 24);
+        for i in 0..self.positions.len() {
+            vertices.extend_from_slice(&self.positions[i]);
+            vertices.extend_from_slice(&self.normals[i]);
+            vertices.extend_from_slice(&self.uvs.get(i).copied().unwrap_or([0.0; 2]));
+            vertices.extend_from_slice(&self.tangents[i]);
+            vertices.extend_from_slice(&self.colors.get(i).copied().unwrap_or([1.0; 4]));
+            if self.is_skinned() {
+                vertices.extend(self.joints[i].iter().map(|joint| *joint as f32));
+                vertices.extend_from_slice(&self.weights[i]);
+            }
+        }
+        let mut pointers = vec![
+            LocalAttribPointer::new(3, BufferDataType::Float, false),
+            LocalAttribPointer::new(3, BufferDataType::Float, false),
+            LocalAttribPointer::new(2, BufferDataType::Float, false),
+            LocalAttribPointer::new(4, BufferDataType::Float, false),
+            LocalAttribPointer::new(4, BufferDataType::Float, false),
+        ];
+        if self.is_skinned() {
+            pointers.push(LocalAttribPointer::new(4, BufferDataType::Float, false));
+            pointers.push(LocalAttribPointer::new(4, BufferDataType::Float, false));
+        }
+        VertexArrayObject::new(Some(VertexArrayObjectType::Elements(self.indices.len() as i32)))
+            .with_buffer(VertexBufferObject::array(BufferType::ArrayBuffer, DrawType::StaticDraw, &vertices))
+            .with_buffer(GpuBuffer::from_slice(BufferType::ElementArrayBuffer, DrawType::StaticDraw, &self.indices))
+            .with_local_attrib_pointers(pointers)
+            .build()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GltfMesh {
+    name: String,
+    primitives: Vec<GltfPrimitive>,
+}
+
+impl GltfMesh {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn primitives(&self) -> &Vec<GltfPrimitive> {
+        &self.primitives
+    }
+
+    pub fn build_vaos(&self) -> Vec<VertexArrayObject> {
+        self.primitives.iter().map(|primitive| primitive.build_vao()).collect()
+    }
+}
+
+///A metallic-roughness material. Its textures are set by `Gltf::load_textures`.
+#[derive(Debug, Clone)]
+pub struct GltfMaterial {
+    name: String,
+    pbr: PbrMaterial,
+    ///glTF texture index per role. The packed metallic-roughness texture is listed for both roles.
+    maps: Vec<(TextureRole, usize)>,
+}
+
+impl GltfMaterial {
+    fn read(info: &MaterialInfo, index: usize) -> GltfMaterial {
+        let mut pbr = PbrMaterial::default();
+        let mut maps = vec![];
+        if let Some(metallic_roughness) = &info.pbr_metallic_roughness {
+            pbr.base_color_factor = metallic_roughness.base_color_factor;
+            pbr.metallic_factor = metallic_roughness.metallic_factor;
+            pbr.roughness_factor = metallic_roughness.roughness_factor;
+            if let Some(texture) = &metallic_roughness.base_color_texture {
+                maps.push((TextureRole::BaseColor, texture.index));
+            }
+            if let Some(texture) = &metallic_roughness.metallic_roughness_texture {
+                maps.push((TextureRole::Metallic, texture.index));
+                maps.push((TextureRole::Roughness, texture.index));
+            }
+        }
+        if let Some(normal) = &info.normal_texture {
+            pbr.normal_scale = normal.scale;
+            maps.push((TextureRole::Normal, normal.index));
+        }
+        if let Some(occlusion) = &info.occlusion_texture {
+            pbr.occlusion_strength = occlusion.strength;
+            maps.push((TextureRole::AmbientOcclusion, occlusion.index));
+        }
+        pbr.height_scale = 0.0;
+        Self {
+            name: info.name.clone().unwrap_or(format!("material_{}", index)),
+            pbr,
+            maps,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn pbr(&self) -> &PbrMaterial {
+        &self.pbr
+    }
+
+    pub fn pbr_mut(&mut self) -> &mut PbrMaterial {
+        &mut self.pbr
+    }
+}
+
+///A node of the scene graph. Transforms are relative to the parent node.
+#[derive(Debug, Clone)]
+pub struct GltfNode {
+    name: String,
+    children: Vec<usize>,
+    mesh: Option<usize>,
+    skin: Option<usize>,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl GltfNode {
+    fn read(info: &NodeInfo, index: usize) -> GltfNode {
+        let (translation, rotation, scale) = match info.matrix {
+            Some(m) => decompose(&to_mat4(&m)),
+            None => (
+                info.translation.unwrap_or([0.0; 3]),
+                info.rotation.unwrap_or(IDENTITY_QUAT),
+                info.scale.unwrap_or([1.0; 3]),
+            )
+        };
+        Self {
+            name: info.name.clone().unwrap_or(format!("node_{}", index)),
+            children: info.children.clone(),
+            mesh: info.mesh,
+            skin: info.skin,
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn children(&self) -> &Vec<usize> {
+        &self.children
+    }
+
+    ///Index into `Gltf::meshes`.
+    pub fn mesh(&self) -> Option<usize> {
+        self.mesh
+    }
+
+    ///Index into `Gltf::skins`, the skeleton deforming this node's mesh.
+    pub fn skin(&self) -> Option<usize> {
+        self.skin
+    }
+
+    pub fn local_matrix(&self) -> Mat4 {
+        compose(self.translation, self.rotation, self.scale)
+    }
+
+    pub fn node_transform(&self) -> NodeTransform {
+        NodeTransform {
+            translation: self.translation,
+            rotation: self.rotation,
+            scale: self.scale,
+        }
+    }
+
+    ///A `Transform` with this node's translation, rotation and scale.
+    pub fn transform(&self) -> Transform {
+        let mut transform = Transform::default();
+        transform.position = self.translation;
+        transform.rotation = to_rotation_vector(self.rotation);
+        transform.scale = self.scale;
+        transform
+    }
+}
+
+///A node turned into a `GameObject` by `Gltf::instantiate`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GltfInstance {
+    pub node: usize,
+    pub object: usize,
+    pub mesh: Option<usize>,
+}
+
+///A glTF 2.0 model, from a `.gltf` file with external or embedded buffers or from a `.glb` file.
+///Parsing only decodes data, textures are uploaded by `load_textures` and vertex arrays built per primitive.
+///See: <a href="https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html">glTF 2.0</a>
+#[derive(Debug, Clone)]
+pub struct Gltf {
+    meshes: Vec<GltfMesh>,
+    materials: Vec<GltfMaterial>,
+    nodes: Vec<GltfNode>,
+    ///Root nodes per scene.
+    scenes: Vec<Vec<usize>>,
+    scene: usize,
+    skins: Vec<Skeleton>,
+    animations: Vec<AnimationClip>,
+    ///Image index per glTF texture.
+    textures: Vec<Option<usize>>,
+    images: Vec<Option<DynamicImage>>,
+    directory: Option<PathBuf>,
+}
+
+impl Gltf {
+    pub fn from_file<P: AsRef<Path>>(file: P) -> Result<Gltf, Box<dyn Error>> {
+        let file = file.as_ref();
+        let bytes = std::fs::read(file)?;
+        Self::from_slice(&bytes, file.parent())
+    }
+
+    ///Parses `.gltf` json or `.glb` bytes. External buffers and images are resolved against `directory`.
+    pub fn from_slice(bytes: &[u8], directory: Option<&Path>) -> Result<Gltf, Box<dyn Error>> {
+        let source = Source::parse(bytes, directory)?;
+        let document = &source.document;
+
+        let mut meshes = vec![];
+        for (index, mesh) in document.meshes.iter().enumerate() {
+            let name = mesh.name.clone().unwrap_or(format!("mesh_{}", index));
+            let mut primitives = vec![];
+            for primitive in &mesh.primitives {
+                primitives.push(GltfPrimitive::read(&source, primitive, &name)?);
+            }
+            meshes.push(GltfMesh { name, primitives });
+        }
+        let materials = document.materials.iter().enumerate().map(|(i, m)| GltfMaterial::read(m, i)).collect();
+        let nodes = document.nodes.iter().enumerate().map(|(i, n)| GltfNode::read(n, i)).collect::<Vec<GltfNode>>();
+
+        let mut images = vec![];
+        for index in 0..document.images.len() {
+            match source.image(index, directory) {
+                Ok(image) => images.push(Some(image)),
+                Err(e) => {
+                    eprintln!("Unable to load glTF image {}: {}", index, e);
+                    images.push(None);
+                }
+            }
+        }
+
+        let mut scenes = document.scenes.iter().map(|scene| scene.nodes.clone()).collect::<Vec<Vec<usize>>>();
+        if scenes.is_empty() {
+            let children = nodes.iter().flat_map(|node| node.children.clone()).collect::<Vec<usize>>();
+            scenes.push((0..nodes.len()).filter(|node| !children.contains(node)).collect());
+        }
+        let parents = (0..nodes.len())
+            .map(|node| nodes.iter().position(|n| n.children.contains(&node)))
+            .collect::<Vec<Option<usize>>>();
+        let rest_pose = nodes.iter().map(|node| node.node_transform()).collect::<Pose>();
+        let mut skins = vec![];
+        for skin in &document.skins {
+            let inverse_bind_matrices = match skin.inverse_bind_matrices {
+                Some(accessor) => {
+                    let matrices = source.matrices(accessor)?;
+                    if matrices.len() < skin.joints.len() {
+                        return Err(Box::new(GltfError::Accessor(accessor, format!("{} inverse bind matrices for {} joints", matrices.len(), skin.joints.len()))));
+                    }
+                    matrices
+                }
+                None => vec![]
+            };
+            skins.push(Skeleton::new(skin.joints.clone(), inverse_bind_matrices, parents.clone(), rest_pose.clone()));
+        }
+        let mut animations = vec![];
+        for (index, animation) in document.animations.iter().enumerate() {
+            animations.push(Self::read_animation(&source, animation, index)?);
+        }
+
+        let gltf = Self {
+            meshes,
+            materials,
+            nodes,
+            skins,
+            animations,
+            scene: document.scene.unwrap_or(0).min(scenes.len() - 1),
+            scenes,
+            textures: document.textures.iter().map(|texture| texture.source).collect(),
+            images,
+            directory: directory.map(|d| d.to_path_buf()),
+        };
+        for root in gltf.scenes.iter().flatten() {
+            if *root >= gltf.nodes.len() {
+                return Err(Box::new(GltfError::MissingNode(*root)));
+            }
+        }
+        for node in 0..gltf.nodes.len() {
+            gltf.check_cycles(node, &mut vec![])?;
+        }
+        Ok(gltf)
+    }
+
+    fn read_animation(source: &Source, animation: &AnimationInfo, index: usize) -> Result<AnimationClip, GltfError> {
+        let mut channels = vec![];
+        for channel in &animation.channels {
+            let path = match channel.target.path.as_str() {
+                "translation" => ChannelPath::Translation,
+                "rotation" => ChannelPath::Rotation,
+                "scale" => ChannelPath::Scale,
+                //Morph target weights aren't supported.
+                _ => continue
+            };
+            let (node, sampler) = match (channel.target.node, animation.samplers.get(channel.sampler)) {
+                (Some(node), Some(sampler)) => (node, sampler),
+                _ => continue
+            };
+            channels.push(Channel {
+                node,
+                path,
+                interpolation: Interpolation::from_name(&sampler.interpolation).unwrap_or(Interpolation::Linear),
+                times: source.accessor(sampler.input)?.floats().into_iter().map(|t| t[0]).collect(),
+                values: to_vec4(source.accessor(sampler.output)?.floats(), 0.0),
+            });
+        }
+        Ok(AnimationClip::new(animation.name.clone().unwrap_or(format!("animation_{}", index)), channels))
+    }
+
+    fn check_cycles(&self, node: usize, ancestors: &mut Vec<usize>) -> Result<(), GltfError> {
+        if node >= self.nodes.len() {
+            return Err(GltfError::MissingNode(node));
+        }
+        if ancestors.contains(&node) {
+            return Err(GltfError::Cycle(node));
+        }
+        ancestors.push(node);
+        for child in &self.nodes[node].children {
+            self.check_cycles(*child, ancestors)?;
+        }
+        ancestors.pop();
+        Ok(())
+    }
+
+    pub fn meshes(&self) -> &Vec<GltfMesh> {
+        &self.meshes
+    }
+
+    pub fn materials(&self) -> &Vec<GltfMaterial> {
+        &self.materials
+    }
+
+    pub fn nodes(&self) -> &Vec<GltfNode> {
+        &self.nodes
+    }
+
+    ///Root nodes of the default scene.
+    pub fn root_nodes(&self) -> &Vec<usize> {
+        &self.scenes[self.scene]
+    }
+
+    pub fn scenes(&self) -> &Vec<Vec<usize>> {
+        &self.scenes
+    }
+
+    ///Skeletons referenced by the nodes' `skin`.
+    pub fn skins(&self) -> &Vec<Skeleton> {
+        &self.skins
+    }
+
+    pub fn animations(&self) -> &Vec<AnimationClip> {
+        &self.animations
+    }
+
+    pub fn animation(&self, name: &str) -> Option<&AnimationClip> {
+        self.animations.iter().find(|clip| clip.name() == name)
+    }
+
+    ///Every node's transform as exported, the starting point for sampling animations.
+    pub fn rest_pose(&self) -> Pose {
+        self.nodes.iter().map(|node| node.node_transform()).collect()
+    }
+
+    pub fn directory(&self) -> Option<&Path> {
+        self.directory.as_deref()
+    }
+
+    pub fn parent(&self, node: usize) -> Option<usize> {
+        self.nodes.iter().position(|n| n.children.contains(&node))
+    }
+
+    ///The node's transform relative to the scene root.
+    pub fn world_matrix(&self, node: usize) -> Mat4 {
+        let local = self.nodes[node].local_matrix();
+        match self.parent(node) {
+            Some(parent) => vecmath::col_mat4_mul(self.world_matrix(parent), local),
+            None => local
+        }
+    }
+
+    ///Uploads the images the materials use and sets them on the materials. Requires a current GL context.
+    ///Base color maps are sRGB, the packed metallic-roughness texture is split into its blue and green channels.
+    pub fn load_textures(&mut self) {
+        let mut uploaded: HashMap<(usize, TextureRole), Shared<Texture>> = HashMap::new();
+        for material in &mut self.materials {
+            for (role, texture) in material.maps.clone() {
+                let image = match self.textures.get(texture).copied().flatten().and_then(|image| self.images.get(image)) {
+                    Some(Some(image)) => image,
+                    _ => continue
+                };
+                let key = (texture, role);
+                if !uploaded.contains_key(&key) {
+                    let result = match role {
+                        TextureRole::BaseColor => Texture::from_image_with(image, ColorSpace::Srgb, Wrapping::default(), MipMapping::default()),
+                        TextureRole::Metallic => Texture::from_image(&channel(image, 2)),
+                        TextureRole::Roughness => Texture::from_image(&channel(image, 1)),
+                        _ => Texture::from_image(image)
+                    };
+                    match result {
+                        Ok(uploaded_texture) => {
+                            uploaded.insert(key, uploaded_texture.shared());
+                        }
+                        Err(e) => {
+                            eprintln!("Unable to upload {:?} texture of material '{}': {}", role, material.name, e);
+                            continue;
+                        }
+                    }
+                }
+                material.pbr.textures_mut().put(role, uploaded[&key].clone());
+            }
+        }
+    }
+
+    ///Creates a `GameObject` with a `Transform` for every node of the default scene and adds it to the `Scene`,
+    ///children parented like their nodes. Returns the objects in depth first order.
+    pub fn instantiate(&self) -> Vec<GltfInstance> {
+        let mut instances = vec![];
+        for root in self.root_nodes() {
+            self.instantiate_node(*root, None, &mut instances);
+        }
+        instances
+    }
+
+    fn instantiate_node(&self, index: usize, parent: Option<&mut GameObject>, instances: &mut Vec<GltfInstance>) {
+        let node = &self.nodes[index];
+        let mut object = GameObject::named(&node.name);
+        object.attach_component(node.transform());
+        if let Some(parent) = parent {
+            parent.add_child(&mut object);
+        }
+        let id = Scene::add_object(object);
+        instances.push(GltfInstance { node: index, object: id, mesh: node.mesh });
+        for child in &node.children {
+            let parent = Scene::get_object(&id);
+            self.instantiate_node(*child, parent, instances);
+        }
+    }
+}
+
+///One channel of an image as a grayscale image.
+fn channel(image: &DynamicImage, channel: usize) -> DynamicImage {
+    let rgba = image.to_rgba8();
+    DynamicImage::ImageLuma8(GrayImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        image::Luma([rgba.get_pixel(x, y)[channel]])
+    }))
+}
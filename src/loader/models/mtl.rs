@@ -0,0 +1,350 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::Index;
+use std::path::{Component, Path, PathBuf};
+use crate::gfx::material::{Material, TextureMap, TextureMapType};
+use crate::math::linear_algebra::types::Vec3;
+
+#[derive(Clone)]
+pub enum MTLError {
+    MaterialNotCreated(String, usize),
+    MaterialName(String, usize),
+    ColorSize(String, usize),
+    ValueCount(String, usize),
+    MissingTexturePath(String, usize),
+    TextureOption(String, usize),
+}
+
+impl Debug for MTLError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MTLError::MaterialNotCreated(e, ln) => {
+                f.write_fmt(format_args!("Unable to perform mtl command because no material had been declared with newmtl: {} on line {}", e, ln))
+            }
+            MTLError::MaterialName(e, ln) => {
+                f.write_fmt(format_args!("newmtl requires exactly one name: {} on line {}", e, ln))
+            }
+            MTLError::ColorSize(e, ln) => {
+                f.write_fmt(format_args!("Color must be given as 'r [g b]': {} on line {}", e, ln))
+            }
+            MTLError::ValueCount(e, ln) => {
+                f.write_fmt(format_args!("Expected a single value: {} on line {}", e, ln))
+            }
+            MTLError::MissingTexturePath(e, ln) => {
+                f.write_fmt(format_args!("Texture map is missing a file name: {} on line {}", e, ln))
+            }
+            MTLError::TextureOption(e, ln) => {
+                f.write_fmt(format_args!("Invalid texture map option: {} on line {}", e, ln))
+            }
+        }
+    }
+}
+
+impl Display for MTLError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl Error for MTLError {}
+
+///A parsed wavefront material library.
+#[derive(Debug, Clone)]
+pub struct MTL {
+    materials: Vec<Material>,
+}
+
+impl MTL {
+    pub fn new(materials: Vec<Material>) -> MTL {
+        Self { materials }
+    }
+
+    ///Parses a material library. Texture paths are resolved relative to the library's directory.
+    pub fn from_file<P: AsRef<Path>>(file: P) -> Result<MTL, Box<dyn Error>> {
+        let file = file.as_ref();
+        let directory = file.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+        match std::fs::read_to_string(file) {
+            Ok(contents) => {
+                Self::from_raw(contents, directory)
+            }
+            Err(e) => {
+                Err(Box::new(e))
+            }
+        }
+    }
+
+    pub fn from_raw<P: AsRef<Path>>(raw: String, directory: P) -> Result<MTL, Box<dyn Error>> {
+        let directory = directory.as_ref();
+        let mut materials: Vec<Material> = vec![];
+
+        let mut line_number = 0;
+        for line in raw.split("\n") {
+            line_number += 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("#") {
+                continue;
+            }
+            let contents = line.split_ascii_whitespace().collect::<Vec<&str>>();
+            let command = contents[0];
+            let input = &contents[1..];
+
+            if command == "newmtl" {
+                if input.len() != 1 {
+                    return Err(Box::new(MTLError::MaterialName(line.to_string(), line_number)));
+                }
+                materials.push(Material::new(input[0]));
+                continue;
+            }
+
+            let material = match materials.last_mut() {
+                None => {
+                    return Err(Box::new(MTLError::MaterialNotCreated(line.to_string(), line_number)));
+                }
+                Some(material) => material
+            };
+
+            match command {
+                "Ka" => material.ambient = Self::color(line, input, line_number)?,
+                "Kd" => material.diffuse = Self::color(line, input, line_number)?,
+                "Ks" => material.specular = Self::color(line, input, line_number)?,
+                "Ke" => material.emissive = Self::color(line, input, line_number)?,
+                "Ns" => material.shininess = Self::value(line, input, line_number)?,
+                "Ni" => material.optical_density = Self::value(line, input, line_number)?,
+                "d" => {
+                    //'d -halo 0.5' is accepted, the halo factor is ignored.
+                    let input = match input.first() {
+                        Some(&"-halo") => &input[1..],
+                        _ => input
+                    };
+                    material.opacity = Self::value(line, input, line_number)?;
+                }
+                "Tr" => material.opacity = 1.0 - Self::value(line, input, line_number)?,
+                "illum" => material.illumination = Self::value(line, input, line_number)? as u8,
+                _ => {
+                    let map_type = match command {
+                        "map_Ka" => TextureMapType::Ambient,
+                        "map_Kd" => TextureMapType::Diffuse,
+                        "map_Ks" => TextureMapType::Specular,
+                        "map_Ns" => TextureMapType::SpecularExponent,
+                        "map_d" => TextureMapType::Dissolve,
+                        "map_Bump" | "map_bump" | "bump" => TextureMapType::Bump,
+                        "norm" | "map_Kn" => TextureMapType::Normal,
+                        "disp" => TextureMapType::Displacement,
+                        "map_Ke" => TextureMapType::Emissive,
+                        _ => {
+                            println!("Command {} has been ignored.", command);
+                            continue;
+                        }
+                    };
+                    let map = Self::texture_map(map_type, line, input, line_number, directory)?;
+                    material.put_map(map);
+                }
+            }
+        }
+        Ok(MTL {
+            materials
+        })
+    }
+
+    fn value(line: &str, input: &[&str], line_number: usize) -> Result<f32, Box<dyn Error>> {
+        if input.len() != 1 {
+            return Err(Box::new(MTLError::ValueCount(line.to_string(), line_number)));
+        }
+        Ok(input[0].parse::<f32>()?)
+    }
+
+    fn color(line: &str, input: &[&str], line_number: usize) -> Result<Vec3, Box<dyn Error>> {
+        //Spectral ('spectral file.rfl') and CIE XYZ ('xyz x y z') colors are not supported.
+        match input.len() {
+            1 => {
+                let r = input[0].parse::<f32>()?;
+                Ok([r; 3])
+            }
+            3 => {
+                let r = input[0].parse::<f32>()?;
+                let g = input[1].parse::<f32>()?;
+                let b = input[2].parse::<f32>()?;
+                Ok([r, g, b])
+            }
+            _ => {
+                Err(Box::new(MTLError::ColorSize(line.to_string(), line_number)))
+            }
+        }
+    }
+
+    ///Parses 'map_X [-option values...] file'. The file name may contain spaces.
+    fn texture_map(map_type: TextureMapType, line: &str, input: &[&str], line_number: usize, directory: &Path) -> Result<TextureMap, Box<dyn Error>> {
+        let mut scale = [1.0; 3];
+        let mut offset = [0.0; 3];
+        let mut bump_multiplier = 1.0;
+
+        let mut index = 0;
+        while index < input.len() && input[index].starts_with("-") {
+            let option = input[index];
+            index += 1;
+            let arguments = match option {
+                "-blendu" | "-blendv" | "-cc" | "-clamp" | "-imfchan" | "-type" => 1,
+                "-bm" | "-boost" | "-texres" => 1,
+                "-mm" => 2,
+                "-o" | "-s" | "-t" => 3,
+                _ => {
+                    return Err(Box::new(MTLError::TextureOption(line.to_string(), line_number)));
+                }
+            };
+            let mut values = vec![];
+            //-o, -s and -t take 1 to 3 numbers.
+            while values.len() < arguments && index < input.len() {
+                let value = input[index];
+                if arguments == 3 && value.parse::<f32>().is_err() {
+                    break;
+                }
+                values.push(value);
+                index += 1;
+            }
+            if values.is_empty() {
+                return Err(Box::new(MTLError::TextureOption(line.to_string(), line_number)));
+            }
+            match option {
+                "-s" => {
+                    for i in 0..values.len() {
+                        scale[i] = values[i].parse::<f32>()?;
+                    }
+                }
+                "-o" => {
+                    for i in 0..values.len() {
+                        offset[i] = values[i].parse::<f32>()?;
+                    }
+                }
+                "-bm" => {
+                    bump_multiplier = values[0].parse::<f32>()?;
+                }
+                _ => {}
+            }
+        }
+
+        if index >= input.len() {
+            return Err(Box::new(MTLError::MissingTexturePath(line.to_string(), line_number)));
+        }
+        let source = input[index..].join(" ");
+        let mut map = TextureMap::new(map_type, &source, Self::resolve(&source, directory));
+        *map.scale_mut() = scale;
+        *map.offset_mut() = offset;
+        *map.bump_multiplier_mut() = bump_multiplier;
+        Ok(map)
+    }
+
+    ///Resolves a texture path against the library directory. Exporters often write absolute paths
+    ///from the artist's machine, so when that path does not exist the bare file name is tried next to the library.
+    pub fn resolve(source: &str, directory: &Path) -> PathBuf {
+        let normalized = source.replace("\\\\", "/").replace("\\", "/");
+        let path = directory.join(&normalized);
+        if path.exists() {
+            return path;
+        }
+        match Path::new(&normalized).file_name() {
+            Some(file_name) => {
+                let local = directory.join(file_name);
+                match local.exists() {
+                    true => local,
+                    false => path
+                }
+            }
+            None => path
+        }
+    }
+
+    ///`path` as seen from `directory`, with `/` separators. Relative paths are taken from the working
+    ///directory. Paths on another root, e.g. another drive, are returned absolute.
+    pub fn relative(path: &Path, directory: &Path) -> String {
+        let absolute = |path: &Path| std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let (path, directory) = (absolute(path), absolute(directory));
+        let path = path.components().collect::<Vec<Component>>();
+        let directory = directory.components().collect::<Vec<Component>>();
+        let common = path.iter().zip(&directory).take_while(|(a, b)| a == b).count();
+        if common == 0 {
+            return path.iter().collect::<PathBuf>().to_string_lossy().to_string();
+        }
+        let mut parts = vec!["..".to_string(); directory.len() - common];
+        parts.extend(path[common..].iter().map(|c| c.as_os_str().to_string_lossy().to_string()));
+        parts.join("/")
+    }
+
+    pub fn materials(&self) -> &Vec<Material> {
+        &self.materials
+    }
+
+    pub fn into_materials(self) -> Vec<Material> {
+        self.materials
+    }
+
+    ///Writes the library to `file`, with texture paths rewritten relative to its directory so they
+    ///still resolve when it is written somewhere other than where it was loaded from.
+    pub fn write_file<P: AsRef<Path>>(&self, file: P) -> Result<(), Box<dyn Error>> {
+        let file = file.as_ref();
+        let directory = file.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+        let mut library = self.clone();
+        for map in library.materials.iter_mut().flat_map(|material| material.maps_mut().iter_mut()) {
+            if !map.path().as_os_str().is_empty() {
+                *map.source_mut() = Self::relative(map.path(), &directory);
+            }
+        }
+        std::fs::write(file, library.to_string())?;
+        Ok(())
+    }
+
+    pub fn get<T: ToString>(&self, name: T) -> Option<&Material> {
+        let name = name.to_string();
+        self.materials.iter().find(|material| material.name == name)
+    }
+}
+
+impl Display for MTL {
+    ///Writes every material with the statements `from_raw` reads. Texture options are written when they differ from their defaults.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (index, material) in self.materials.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "newmtl {}", material.name)?;
+            let [r, g, b] = material.ambient;
+            writeln!(f, "Ka {} {} {}", r, g, b)?;
+            let [r, g, b] = material.diffuse;
+            writeln!(f, "Kd {} {} {}", r, g, b)?;
+            let [r, g, b] = material.specular;
+            writeln!(f, "Ks {} {} {}", r, g, b)?;
+            let [r, g, b] = material.emissive;
+            writeln!(f, "Ke {} {} {}", r, g, b)?;
+            writeln!(f, "Ns {}", material.shininess)?;
+            writeln!(f, "Ni {}", material.optical_density)?;
+            writeln!(f, "d {}", material.opacity)?;
+            writeln!(f, "illum {}", material.illumination)?;
+            for map in material.maps() {
+                write!(f, "{}", map.map_type().keyword())?;
+                if map.scale() != [1.0; 3] {
+                    let [u, v, w] = map.scale();
+                    write!(f, " -s {} {} {}", u, v, w)?;
+                }
+                if map.offset() != [0.0; 3] {
+                    let [u, v, w] = map.offset();
+                    write!(f, " -o {} {} {}", u, v, w)?;
+                }
+                if map.bump_multiplier() != 1.0 {
+                    write!(f, " -bm {}", map.bump_multiplier())?;
+                }
+                writeln!(f, " {}", map.source())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: ToString> Index<T> for MTL {
+    type Output = Material;
+
+    fn index(&self, index: T) -> &Self::Output {
+        match self.get(index) {
+            Some(material) => material,
+            None => panic!("Could not find material")
+        }
+    }
+}
@@ -1,65 +1,73 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use std::fmt::{Debug, Display, Formatter};
-use std::num::ParseIntError;
+use std::fmt::{Debug, Display, Formatter, Write};
 use std::ops::Index;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use enum_iterator::{all, Sequence};
 use maplit::hashmap;
-use crate::math::linear_algebra::types::{Vec2, Vec3};
+use crate::math::linear_algebra::types::{Vec2, Vec3, Vec4};
+use crate::math::tangent::{orthogonalize, triangle_normal, triangle_tangent};
+use crate::math::triangulation::triangulate_polygon;
 use crate::utils::data_structure::DataStructure;
-use crate::{BufferDataType, BufferType, Constructor, DrawType, LocalAttribPointer, VertexArrayObject, VertexArrayObjectType, VertexBufferObject};
+use crate::{BufferDataType, BufferType, Constructor, DrawType, LocalAttribPointer, Texture, VertexArrayObject, VertexArrayObjectType, VertexBufferObject};
+use crate::gfx::material::Material;
+use crate::gfx::resource::Shared;
+use crate::loader::models::mtl::MTL;
 
+///Indices of a face corner into its object's vertices, uvs and normals.
 pub type Vertex = (usize, Option<usize>, Option<usize>);
 pub type Face = [Vertex; 3];
 
+///Name of the object created for geometry before the first `o`, and of the group before the first `g`.
+pub const DEFAULT_NAME: &'static str = "default";
+///Material of faces before the first `usemtl`.
+pub const DEFAULT_MATERIAL: &'static str = "default";
+
 #[derive(Clone)]
 pub enum OBJError {
     MaterialLibraryCount(String, usize),
     VertexSize(String, usize),
     VertexTextureSize(String, usize),
     VertexNormalSize(String, usize),
-    VertexRect(String, usize),
-    ObjectNotCreated(String, usize),
     MaterialUsage(String, usize),
     FaceLength(String, usize),
-    NoGroupFound(String, usize),
     ShadingOption(String, usize),
+    ///An index of 0, or one that isn't a number.
+    InvalidIndex(String, usize),
+    ///An index pointing past the elements defined so far.
+    IndexOutOfRange(String, usize),
 }
 
 impl Debug for OBJError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             OBJError::MaterialLibraryCount(error, ln) => {
-                f.write_fmt(format_args!("Material libraries can only have one path: {} on line {}", error, ln))
+                f.write_fmt(format_args!("Material libraries need at least one path: {} on line {}", error, ln))
             }
             OBJError::VertexSize(vertex, ln) => {
-                f.write_fmt(format_args!("Vertex size is out of bounds. Must be 3/4: {} on line {}", vertex, ln))
-            }
-            OBJError::VertexRect(rect, ln) => {
-                f.write_fmt(format_args!("Rects are not supported: {} on line {}", rect, ln))
-            }
-            OBJError::ObjectNotCreated(e, ln) => {
-                f.write_fmt(format_args!("Unable to perform obj command because no object had been selected: {} on line {}", e, ln))
+                f.write_fmt(format_args!("Vertex size is out of bounds. Must be 3/4 or 6/7 with colors: {} on line {}", vertex, ln))
             }
             OBJError::VertexTextureSize(tex_coord, ln) => {
-                f.write_fmt(format_args!("Vertex texture coord size is out of bounds. Must be 2/3: {} on line {}", tex_coord, ln))
+                f.write_fmt(format_args!("Vertex texture coord size is out of bounds. Must be 1 to 3: {} on line {}", tex_coord, ln))
             }
             OBJError::VertexNormalSize(normal_size, ln) => {
-                f.write_fmt(format_args!("Vertex normal size is out of bounds. Must be 3/4: {} on line {}", normal_size, ln))
+                f.write_fmt(format_args!("Vertex normal size is out of bounds. Must be 3: {} on line {}", normal_size, ln))
             }
             OBJError::MaterialUsage(usage, ln) => {
-                f.write_fmt(format_args!("Too many materials defined. Only one can be defined: {} on line {}", usage, ln))
+                f.write_fmt(format_args!("usemtl needs a material name: {} on line {}", usage, ln))
             }
             OBJError::FaceLength(face, ln) => {
-                f.write_fmt(format_args!("Must supply face in groups of 3. {} on line {}", face, ln))
-            }
-            OBJError::NoGroupFound(group, ln) => {
-                f.write_fmt(format_args!("Unable to insert face data in null group. {} on line {}", group, ln))
+                f.write_fmt(format_args!("Faces need at least 3 vertices. {} on line {}", face, ln))
             }
             OBJError::ShadingOption(shading, ln) => {
                 f.write_fmt(format_args!("Shading option not supported: {} on line {}", shading, ln))
             }
+            OBJError::InvalidIndex(index, ln) => {
+                f.write_fmt(format_args!("Invalid index, indices start at 1 or count back from -1: {} on line {}", index, ln))
+            }
+            OBJError::IndexOutOfRange(index, ln) => {
+                f.write_fmt(format_args!("Index refers to an element that hasn't been defined: {} on line {}", index, ln))
+            }
         }
     }
 }
@@ -78,6 +86,7 @@ pub enum OBJCommand {
     ///# this is a comment
     Comment,
     ///List of geometric vertices, with (x, y, z, [w]) coordinates, w is optional and defaults to 1.0.
+    ///Some exporters append an (r, g, b) vertex color.
     Vertex,
 
     ///Texture coordinates, in (u, [v, w]) coordinates, these will vary between 0 and 1. v, w are optional and default to 0.
@@ -89,10 +98,11 @@ pub enum OBJCommand {
     ///Parameter space vertices in (u, [v, w]) form; free form geometry statement (see below); ignored
     ParameterSpaceVertex,
 
-    ///Polygonal face data. 1-indexed. Vertex/Vertex Texture Coordinate/Vertex Normal
+    ///Polygonal face data. Vertex/Vertex Texture Coordinate/Vertex Normal, 1-indexed into every element
+    ///defined so far in the file, or negative to count back from the latest one. Polygons are triangulated.
     Face,
 
-    ///Line element. 1-indexed. Example: 'l 5 8 1 2 4 9'
+    ///Line element. 1-indexed. Example: 'l 5 8 1 2 4 9'; ignored
     Line,
 
     UseMaterial,
@@ -101,190 +111,307 @@ pub enum OBJCommand {
 
     Object,
 
+    ///g name, starts a new group within the current object.
+    Group,
+
     Smooth,
 }
 
-impl OBJCommand {
-    fn vertex_from_raw(raw: &str) -> Result<Vertex, Box<ParseIntError>> {
-        let data = raw.split("/").collect::<Vec<&str>>();
+///Parser state. Elements are indexed file wide, faces copy the ones they use into their object,
+///the rest are copied into the object they're defined in when it's closed.
+struct ParseState {
+    positions: Vec<Vec3>,
+    colors: Vec<Option<Vec3>>,
+    uvs: Vec<Vec2>,
+    normals: Vec<Vec3>,
+    material_libraries: Vec<String>,
+    objects: Vec<Object>,
+    current: Option<Object>,
+    ///File wide index to object index, per element type.
+    local: [HashMap<usize, usize>; 3],
+    ///Element counts when the last object was closed, elements after them belong to the current object.
+    start: [usize; 3],
+    group: String,
+    material: String,
+    smooth: bool,
+}
 
-        let v = data[0].parse::<usize>()? - 1;
-        let vt = match data.get(1) {
-            None => {
-                None
+impl ParseState {
+    fn new() -> ParseState {
+        Self {
+            positions: vec![],
+            colors: vec![],
+            uvs: vec![],
+            normals: vec![],
+            material_libraries: vec![],
+            objects: vec![],
+            current: None,
+            local: [HashMap::new(), HashMap::new(), HashMap::new()],
+            start: [0; 3],
+            group: DEFAULT_NAME.to_string(),
+            material: DEFAULT_MATERIAL.to_string(),
+            smooth: false,
+        }
+    }
+
+    fn start_object(&mut self, name: String) {
+        self.close_object();
+        self.current = Some(Object::new(name));
+    }
+
+    ///Copies the elements no face used into the current object, puts its elements back into file order
+    ///and stores it. Elements are copied on first use, so without this, writing an object and reading it
+    ///back could reorder them.
+    fn close_object(&mut self) {
+        if self.current.is_none() {
+            return;
+        }
+        let counts = [self.positions.len(), self.uvs.len(), self.normals.len()];
+        for element in 0..3 {
+            for index in self.start[element]..counts[element] {
+                self.localize(element, index);
             }
-            Some(vt) => {
-                Some(vt.parse::<usize>()? - 1)
+        }
+        self.start = counts;
+        let local = std::mem::replace(&mut self.local, [HashMap::new(), HashMap::new(), HashMap::new()]);
+        let mut object = self.current.take().unwrap();
+        let remap = local.map(|local| {
+            let mut order = local.into_iter().collect::<Vec<(usize, usize)>>();
+            order.sort_unstable();
+            let mut remap = vec![0; order.len()];
+            for (rank, (_, index)) in order.into_iter().enumerate() {
+                remap[index] = rank;
+            }
+            remap
+        });
+        fn reorder<T: Copy>(elements: &mut Vec<T>, remap: &[usize]) {
+            if elements.is_empty() {
+                return;
             }
+            let old = elements.clone();
+            for (index, element) in old.into_iter().enumerate() {
+                elements[remap[index]] = element;
+            }
+        }
+        reorder(&mut object.vertices, &remap[0]);
+        reorder(&mut object.colors, &remap[0]);
+        reorder(&mut object.uvs, &remap[1]);
+        reorder(&mut object.normals, &remap[2]);
+        for group in &mut object.groups {
+            for face in &mut group.faces {
+                for (v, vt, vn) in face {
+                    *v = remap[0][*v];
+                    *vt = vt.map(|vt| remap[1][vt]);
+                    *vn = vn.map(|vn| remap[2][vn]);
+                }
+            }
+        }
+        self.objects.push(object);
+    }
+
+    ///The current object, created on demand for files without `o` lines.
+    fn object(&mut self) -> &mut Object {
+        if self.current.is_none() {
+            self.start_object(DEFAULT_NAME.to_string());
+        }
+        self.current.as_mut().unwrap()
+    }
+
+    ///The group faces are added to, started anew whenever `g`, `usemtl` or `s` changed.
+    fn group(&mut self) -> &mut Group {
+        let (name, material, smooth) = (self.group.clone(), self.material.clone(), self.smooth);
+        let object = self.object();
+        let matches = object.groups.last()
+            .map(|g| g.name == name && g.material == material && g.shading == smooth)
+            .unwrap_or(false);
+        if !matches {
+            object.groups.push(Group { name, material, faces: vec![], shading: smooth });
+        }
+        object.groups.last_mut().unwrap()
+    }
+
+    fn finish(mut self) -> Vec<Object> {
+        //Vertices without faces, like point clouds, still make an object.
+        if self.start[0] < self.positions.len() {
+            self.object();
+        }
+        self.close_object();
+        for object in &mut self.objects {
+            object.material_libraries = self.material_libraries.clone();
+        }
+        self.objects
+    }
+
+    ///Resolves a 1-based or negative index against the `count` elements defined so far.
+    fn resolve(raw: &str, count: usize, line_number: usize) -> Result<usize, Box<dyn Error>> {
+        let index = raw.parse::<i64>().map_err(|_| OBJError::InvalidIndex(raw.to_string(), line_number))?;
+        let resolved = match index {
+            0 => return Err(Box::new(OBJError::InvalidIndex(raw.to_string(), line_number))),
+            i if i > 0 => i - 1,
+            i => count as i64 + i
         };
-        let vn = match data.get(2) {
-            None => {
-                None
+        if resolved < 0 || resolved >= count as i64 {
+            return Err(Box::new(OBJError::IndexOutOfRange(raw.to_string(), line_number)));
+        }
+        Ok(resolved as usize)
+    }
+
+    ///Copies the element at file wide `index` into the current object, once.
+    fn localize(&mut self, element: usize, index: usize) -> usize {
+        if let Some(local) = self.local[element].get(&index) {
+            return *local;
+        }
+        let (position, color, uv, normal) = match element {
+            0 => (Some(self.positions[index]), self.colors[index], None, None),
+            1 => (None, None, Some(self.uvs[index]), None),
+            _ => (None, None, None, Some(self.normals[index]))
+        };
+        let object = self.object();
+        let local = match element {
+            0 => {
+                if color.is_some() || !object.colors.is_empty() {
+                    object.colors.resize(object.vertices.len(), [1.0; 3]);
+                    object.colors.push(color.unwrap_or([1.0; 3]));
+                }
+                object.vertices.push(position.unwrap());
+                object.vertices.len() - 1
+            }
+            1 => {
+                object.uvs.push(uv.unwrap());
+                object.uvs.len() - 1
             }
-            Some(vn) => {
-                Some(vn.parse::<usize>()? - 1)
+            _ => {
+                object.normals.push(normal.unwrap());
+                object.normals.len() - 1
             }
         };
-        Ok((v, vt, vn))
+        self.local[element].insert(index, local);
+        local
     }
-    pub fn process_input(&self, line_number: usize, command: &str, input: &[&str], material_libraries: &mut Vec<String>, current_object: &mut Option<Object>, object_buffer: &mut Vec<Object>) -> Result<(), Box<dyn Error>> {
+
+    ///Parses `v`, `v/vt`, `v//vn` or `v/vt/vn` into object local indices.
+    fn vertex(&mut self, raw: &str, line_number: usize) -> Result<Vertex, Box<dyn Error>> {
+        let data = raw.split('/').collect::<Vec<&str>>();
+        if data.len() > 3 {
+            return Err(Box::new(OBJError::InvalidIndex(raw.to_string(), line_number)));
+        }
+        let v = Self::resolve(data[0], self.positions.len(), line_number)?;
+        let vt = match data.get(1) {
+            Some(vt) if !vt.is_empty() => Some(Self::resolve(vt, self.uvs.len(), line_number)?),
+            _ => None
+        };
+        let vn = match data.get(2) {
+            Some(vn) if !vn.is_empty() => Some(Self::resolve(vn, self.normals.len(), line_number)?),
+            _ => None
+        };
+        Ok((
+            self.localize(0, v),
+            vt.map(|vt| self.localize(1, vt)),
+            vn.map(|vn| self.localize(2, vn)),
+        ))
+    }
+}
+
+fn parse_floats(input: &[&str]) -> Result<Vec<f32>, Box<dyn Error>> {
+    let mut values = vec![];
+    for value in input {
+        values.push(value.parse::<f32>()?);
+    }
+    Ok(values)
+}
+
+impl OBJCommand {
+    ///The command for a line's keyword, None for keywords this parser doesn't know.
+    pub fn from_keyword(keyword: &str) -> Option<OBJCommand> {
+        all::<OBJCommand>().find(|command| command == &keyword)
+    }
+
+    fn process_input(&self, line_number: usize, input: &[&str], state: &mut ParseState) -> Result<(), Box<dyn Error>> {
         match self {
             OBJCommand::Vertex => {
-                if input.len() == 4 {
-                    return Err(Box::new(OBJError::VertexSize(input.join(" "), line_number)));
-                }
-                match input.len() == 3 {
-                    true => {
-                        match current_object {
-                            None => {
-                                return Err(Box::new(OBJError::ObjectNotCreated(format!("v {}", input.join(" ")), line_number)));
-                            }
-                            Some(obj) => {
-                                let x = input[0].parse::<f32>()?;
-                                let y = input[1].parse::<f32>()?;
-                                let z = input[2].parse::<f32>()?;
-                                obj.vertices.push([x, y, z]);
-                            }
-                        }
-                    }
-                    false => {
-                        return Err(Box::new(OBJError::VertexSize(input.join(" "), line_number)));
-                    }
-                }
+                let values = parse_floats(input)?;
+                let color = match values.len() {
+                    3 | 4 => None,
+                    6 => Some([values[3], values[4], values[5]]),
+                    7 => Some([values[4], values[5], values[6]]),
+                    _ => return Err(Box::new(OBJError::VertexSize(input.join(" "), line_number)))
+                };
+                state.positions.push([values[0], values[1], values[2]]);
+                state.colors.push(color);
             }
             OBJCommand::VertexTextureCoordinate => {
-                if input.len() > 2 {
+                let values = parse_floats(input)?;
+                if values.is_empty() || values.len() > 3 {
                     return Err(Box::new(OBJError::VertexTextureSize(input.join(" "), line_number)));
                 }
-                match input.len() == 2 {
-                    true => {
-                        match current_object {
-                            None => {
-                                return Err(Box::new(OBJError::ObjectNotCreated(format!("vt {}", input.join(" ")), line_number)));
-                            }
-                            Some(obj) => {
-                                let u = input[0].parse::<f32>()?;
-                                let v = input[1].parse::<f32>()?;
-                                obj.uvs.push([u, v]);
-                            }
-                        }
-                    }
-                    false => {
-                        return Err(Box::new(OBJError::VertexSize(input.join(" "), line_number)));
-                    }
-                }
+                state.uvs.push([values[0], values.get(1).copied().unwrap_or(0.0)]);
             }
             OBJCommand::VertexNormal => {
-                match input.len() == 3 {
-                    true => {
-                        match current_object {
-                            None => {
-                                return Err(Box::new(OBJError::ObjectNotCreated(format!("vn {}", input.join(" ")), line_number)));
-                            }
-                            Some(obj) => {
-                                let x = input[0].parse::<f32>()?;
-                                let y = input[1].parse::<f32>()?;
-                                let z = input[2].parse::<f32>()?;
-                                obj.normals.push([x, y, z]);
-                            }
-                        }
-                    }
-                    false => {
-                        return Err(Box::new(OBJError::VertexNormalSize(input.join(" "), line_number)));
-                    }
+                let values = parse_floats(input)?;
+                if values.len() != 3 {
+                    return Err(Box::new(OBJError::VertexNormalSize(input.join(" "), line_number)));
                 }
+                state.normals.push([values[0], values[1], values[2]]);
             }
             OBJCommand::Face => {
-                if input.len() == 3 {
-                    if let Some(current_object) = current_object {
-                        if let Some(group) = current_object.groups.last_mut() {
-                            let faces = &mut group.faces;
-
-                            let a = Self::vertex_from_raw(input[0])?;
-                            let b = Self::vertex_from_raw(input[1])?;
-                            let c = Self::vertex_from_raw(input[2])?;
-                            let face = [a, b, c];
-                            faces.push(face);
-                        } else {
-                            return Err(Box::new(OBJError::NoGroupFound(input.join(" "), line_number)));
-                        }
-                    } else {
-                        return Err(Box::new(OBJError::ObjectNotCreated(input.join(" "), line_number)));
-                    }
-                } else {
+                if input.len() < 3 {
                     return Err(Box::new(OBJError::FaceLength(input.join(" "), line_number)));
                 }
+                let mut corners = vec![];
+                for raw in input {
+                    corners.push(state.vertex(raw, line_number)?);
+                }
+                let triangles = {
+                    let vertices = &state.object().vertices;
+                    triangulate_polygon(&corners.iter().map(|corner| vertices[corner.0]).collect::<Vec<Vec3>>())
+                };
+                let group = state.group();
+                for [a, b, c] in triangles {
+                    group.faces.push([corners[a], corners[b], corners[c]]);
+                }
             }
             OBJCommand::UseMaterial => {
-                if input.len() > 1 {
+                if input.is_empty() {
                     return Err(Box::new(OBJError::MaterialUsage(input.join(" "), line_number)));
                 }
-                //Create new group.
-                match current_object {
-                    None => {
-                        return Err(Box::new(OBJError::ObjectNotCreated("Cannot use a material if no object is currently selected.".to_string(), line_number)));
-                    }
-                    Some(current_object) => {
-                        current_object.groups.push(Group {
-                            material: input[0].to_string(),
-                            faces: vec![],
-                            shading: false,
-                        });
-                    }
-                }
+                state.material = input.join(" ");
             }
             OBJCommand::MaterialLibrary => {
                 //Example: mtllib cube.mtl
-                match input.len() == 1 {
-                    true => {
-                        material_libraries.push(input[0].to_string());
-                    }
-                    false => {
-                        return Err(Box::new(OBJError::MaterialLibraryCount(input.join(" "), line_number)));
+                if input.is_empty() {
+                    return Err(Box::new(OBJError::MaterialLibraryCount(input.join(" "), line_number)));
+                }
+                for library in input {
+                    if !state.material_libraries.contains(&library.to_string()) {
+                        state.material_libraries.push(library.to_string());
                     }
                 }
             }
             OBJCommand::Object => {
-                match input.len() == 1 {
-                    true => {
-                        if let Some(current_object) = current_object {
-                            object_buffer.push(current_object.clone());
-                        }
-                        *current_object = Some(Object {
-                            name: input[0].to_string(),
-                            vertices: vec![],
-                            normals: vec![],
-                            uvs: vec![],
-                            groups: vec![],
-                            material_libraries: material_libraries.clone(),
-                        });
-                        material_libraries.clear();
-                    }
-                    false => {
-                        return Err(Box::new(OBJError::MaterialLibraryCount(input.join(" "), line_number)));
-                    }
-                }
+                let name = match input.is_empty() {
+                    true => DEFAULT_NAME.to_string(),
+                    false => input.join(" ")
+                };
+                state.start_object(name);
             }
-            OBJCommand::Smooth => {
-                if let Some(current_object) = current_object {
-                    if let Some(group) = current_object.groups.last_mut() {
-                        if input[0] == "on" {
-                            group.shading = true;
-                        } else if input[0] == "off" {
-                            group.shading = false;
-                        } else {
-                            return Err(Box::new(OBJError::ShadingOption(input.join(" "), line_number)));
-                        }
-                    } else {
-                        return Err(Box::new(OBJError::NoGroupFound(input.join(" "), line_number)));
-                    }
-                } else {
-                    return Err(Box::new(OBJError::ObjectNotCreated(input.join(" "), line_number)));
-                }
+            OBJCommand::Group => {
+                state.group = match input.is_empty() {
+                    true => DEFAULT_NAME.to_string(),
+                    false => input.join(" ")
+                };
             }
-            _ => {
-                println!("Command {} has been ignored.", command);
+            OBJCommand::Smooth => {
+                state.smooth = match input.first() {
+                    Some(&"off") => false,
+                    Some(&"on") => true,
+                    Some(group) => match group.parse::<u32>() {
+                        Ok(group) => group != 0,
+                        Err(_) => return Err(Box::new(OBJError::ShadingOption(input.join(" "), line_number)))
+                    },
+                    None => return Err(Box::new(OBJError::ShadingOption(input.join(" "), line_number)))
+                };
             }
+            OBJCommand::Comment | OBJCommand::ParameterSpaceVertex | OBJCommand::Line => {}
         }
         Ok(())
     }
@@ -338,6 +465,11 @@ impl PartialEq<&str> for OBJCommand {
                     return true;
                 }
             }
+            OBJCommand::Group => {
+                if *other == "g" {
+                    return true;
+                }
+            }
             OBJCommand::Smooth => {
                 if *other == "s" {
                     return true;
@@ -357,10 +489,13 @@ impl PartialEq<&str> for OBJCommand {
 pub struct Object {
     name: String,
     vertices: Vec<Vec3>,
+    ///Per vertex colors, empty if the file has none for this object's vertices.
+    colors: Vec<Vec3>,
     normals: Vec<Vec3>,
     uvs: Vec<Vec2>,
     groups: Vec<Group>,
     material_libraries: Vec<String>,
+    materials: HashMap<String, Material>,
 }
 
 impl<T: ToString> Index<T> for Object {
@@ -378,12 +513,48 @@ impl<T: ToString> Index<T> for Object {
 }
 
 impl Object {
+    fn new(name: String) -> Object {
+        Self {
+            name,
+            vertices: vec![],
+            colors: vec![],
+            normals: vec![],
+            uvs: vec![],
+            groups: vec![],
+            material_libraries: vec![],
+            materials: hashmap! {},
+        }
+    }
+
+    ///An object with a single group of `faces` using the default material, for formats without groups or materials.
+    ///`colors` is empty or holds one color per vertex.
+    pub fn from_faces<T: ToString>(name: T, vertices: Vec<Vec3>, colors: Vec<Vec3>, normals: Vec<Vec3>, uvs: Vec<Vec2>, faces: Vec<Face>) -> Object {
+        let mut object = Self::new(name.to_string());
+        object.vertices = vertices;
+        object.colors = colors;
+        object.normals = normals;
+        object.uvs = uvs;
+        object.groups.push(Group {
+            name: DEFAULT_NAME.to_string(),
+            material: DEFAULT_MATERIAL.to_string(),
+            faces,
+            shading: false,
+        });
+        object
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
     pub fn vertices(&self) -> &Vec<Vec3> {
         &self.vertices
     }
+    pub fn colors(&self) -> &Vec<Vec3> {
+        &self.colors
+    }
+    pub fn has_colors(&self) -> bool {
+        !self.colors.is_empty()
+    }
     pub fn normals(&self) -> &Vec<Vec3> {
         &self.normals
     }
@@ -393,104 +564,270 @@ impl Object {
     pub fn groups(&self) -> &Vec<Group> {
         &self.groups
     }
+    ///Every face of every group.
+    pub fn faces(&self) -> impl Iterator<Item=&Face> {
+        self.groups.iter().flat_map(|group| group.faces.iter())
+    }
     pub fn material_libraries(&self) -> &Vec<String> {
         &self.material_libraries
     }
+    pub fn materials(&self) -> &HashMap<String, Material> {
+        &self.materials
+    }
+    pub fn materials_mut(&mut self) -> &mut HashMap<String, Material> {
+        &mut self.materials
+    }
+    pub fn material<T: ToString>(&self, name: T) -> Option<&Material> {
+        self.materials.get(&name.to_string())
+    }
+
+    ///Parses every material library of this object, resolving paths against `directory`.
+    ///Libraries that cannot be found or parsed are reported and skipped, their groups use default materials.
+    pub fn load_material_libraries<P: AsRef<Path>>(&mut self, directory: P) {
+        let directory = directory.as_ref();
+        for library in &self.material_libraries {
+            let path = directory.join(library);
+            if !path.exists() {
+                eprintln!("Material library '{}' of object '{}' could not be found.", path.display(), self.name);
+                continue;
+            }
+            match MTL::from_file(&path) {
+                Ok(mtl) => {
+                    for material in mtl.into_materials() {
+                        self.materials.insert(material.name.clone(), material);
+                    }
+                }
+                Err(e) => eprintln!("Material library '{}' of object '{}' could not be parsed: {}", path.display(), self.name, e)
+            }
+        }
+    }
+
+    ///Loads the textures of every material so that later VAOs reuse them. Requires a current GL context.
+    pub fn load_textures(&self) {
+        for material in self.materials.values() {
+            material.load_textures();
+        }
+    }
+
+    ///The material's textures by uniform name, loaded once per map and shared by every VAO built with them.
+    fn material_textures(&self, material: &str) -> Vec<(String, Shared<Texture>)> {
+        match self.materials.get(material) {
+            Some(material) => material.load_textures().into_iter()
+                .map(|(map_type, texture)| (map_type.uniform().to_string(), texture))
+                .collect(),
+            None => vec![]
+        }
+    }
+
+    ///The corner's normal, or the face's flat normal if the corner has none.
+    fn corner_normal(&self, face: &Face, corner: usize) -> Vec3 {
+        match face[corner].2 {
+            Some(normal) => self.normals[normal],
+            None => triangle_normal([self.vertices[face[0].0], self.vertices[face[1].0], self.vertices[face[2].0]])
+        }
+    }
+
+    ///The corner's uv, or the origin if the corner has none.
+    fn corner_uv(&self, face: &Face, corner: usize) -> Vec2 {
+        face[corner].1.map(|uv| self.uvs[uv]).unwrap_or([0.0; 2])
+    }
+
+    ///Writes a corner's position and, if the layout has them, its normal and uv. Missing ones are padded
+    ///so every corner has the same stride, see `corner_normal` and `corner_uv`.
+    fn put_corner(&self, out_positions: &mut Vec<f32>, face: &Face, corner: usize, normals: bool, uvs: bool) {
+        out_positions.push_array(&self.vertices[face[corner].0]);
+        if normals {
+            out_positions.push_array(&self.corner_normal(face, corner));
+        }
+        if uvs {
+            out_positions.push_array(&self.corner_uv(face, corner));
+        }
+    }
 
-    fn put_positions(out_positions: &mut Vec<f32>, vertex: &Vertex, vertices: &Vec<Vec3>, uvs: &&Vec<Vec2>, normals: &&Vec<Vec3>) {
-        let position = vertices[vertex.0];
-        out_positions.push_array(&position);
-        if let Some(normal) = vertex.2 {
-            let normal = normals[normal];
-            out_positions.push_array(&normal);
+    ///Every group using `material` merged into one, as `g` and `s` lines split a material's faces.
+    pub fn batch<T: ToString>(&self, material: T) -> Option<Group> {
+        let material = material.to_string();
+        let mut groups = self.groups.iter().filter(|g| g.material == material);
+        let mut batch = groups.next()?.clone();
+        for group in groups {
+            batch.faces.extend_from_slice(&group.faces);
         }
-        if let Some(uv) = vertex.1 {
-            let uv = uvs[uv];
-            out_positions.push_array(&uv);
+        Some(batch)
+    }
+
+    ///Materials used by the object's groups, in order of first use.
+    pub fn group_materials(&self) -> Vec<String> {
+        let mut materials: Vec<String> = vec![];
+        for group in &self.groups {
+            if !materials.contains(&group.material) {
+                materials.push(group.material.clone());
+            }
         }
+        materials
     }
 
     pub fn build_vaos(&self) -> HashMap<String, Option<VertexArrayObject>> {
         let mut vaos = hashmap! {};
-        for g in &self.groups {
-            vaos.insert(g.material.clone(), self.build_vao(g.material.clone()));
+        for material in self.group_materials() {
+            let vao = self.build_vao(&material);
+            vaos.insert(material, vao);
         }
         vaos
     }
 
     pub fn build_vao<T: ToString>(&self, material: T) -> Option<VertexArrayObject> {
-        let group = material.to_string();
-        for g in &self.groups {
-            if g.material.eq(&group) {
-                let mut positions = vec![];
-                let vertices = &self.vertices;
-                let uvs = &self.uvs;
-                let normals = &self.normals;
-
-                for face in &g.faces {
-                    for vertex in face {
-                        Self::put_positions(&mut positions, vertex, vertices, &uvs, &normals);
-                    }
-                }
+        let g = self.batch(material)?;
+        let (has_normals, has_uvs) = (g.has_normals(), g.has_uvs());
+        let mut positions = vec![];
+        for face in &g.faces {
+            for corner in 0..3 {
+                self.put_corner(&mut positions, face, corner, has_normals, has_uvs);
+            }
+        }
 
-                let mut pointers = vec![
-                    LocalAttribPointer::new(3, BufferDataType::Float, false)
-                ];
-                if g.has_normals() {
-                    pointers.push(
-                        LocalAttribPointer::new(3, BufferDataType::Float, false)
-                    );
-                }
-                if g.has_uvs() {
-                    pointers.push(
-                        LocalAttribPointer::new(2, BufferDataType::Float, false)
-                    );
+        let mut pointers = vec![
+            LocalAttribPointer::new(3, BufferDataType::Float, false)
+        ];
+        if has_normals {
+            pointers.push(
+                LocalAttribPointer::new(3, BufferDataType::Float, false)
+            );
+        }
+        if has_uvs {
+            pointers.push(
+                LocalAttribPointer::new(2, BufferDataType::Float, false)
+            );
+        }
+        let mut vao = VertexArrayObject::new(Some(VertexArrayObjectType::Arrays((g.faces.len() * 3) as i32)))
+            .with_buffer(VertexBufferObject::array(BufferType::ArrayBuffer, DrawType::StaticDraw, &positions))
+            .with_local_attrib_pointers(pointers)
+            .build();
+        for (name, texture) in self.material_textures(&g.material) {
+            vao.put_texture(Some(name), texture);
+        }
+        Some(vao)
+    }
+
+    ///Per corner tangents of a group, indexed like `faces`. Tangents are accumulated over every face
+    ///sharing a (position, uv, normal) vertex, so smooth surfaces get continuous tangents.
+    ///None if no face has normals or uvs, corners missing them are padded like in `build_vao`.
+    pub fn tangents(&self, group: &Group) -> Option<Vec<[Vec4; 3]>> {
+        if !group.has_normals() || !group.has_uvs() {
+            return None;
+        }
+        let mut accumulated: HashMap<Vertex, (Vec3, Vec3)> = hashmap! {};
+        for face in &group.faces {
+            let positions = [self.vertices[face[0].0], self.vertices[face[1].0], self.vertices[face[2].0]];
+            let uvs = [self.corner_uv(face, 0), self.corner_uv(face, 1), self.corner_uv(face, 2)];
+            let (tangent, bitangent) = triangle_tangent(positions, uvs);
+            for vertex in face {
+                let entry = accumulated.entry(*vertex).or_insert(([0.0; 3], [0.0; 3]));
+                for i in 0..3 {
+                    entry.0[i] += tangent[i];
+                    entry.1[i] += bitangent[i];
                 }
-                return Some(
-                    VertexArrayObject::new(Some(VertexArrayObjectType::Arrays((g.faces.len() * 3) as i32)))
-                        .with_buffer(VertexBufferObject::array(BufferType::ArrayBuffer, DrawType::StaticDraw, &positions))
-                        .with_local_attrib_pointers(pointers)
-                        .build()
-                )
             }
         }
-        None
+        let mut tangents = vec![];
+        for face in &group.faces {
+            let mut corners = [[0.0; 4]; 3];
+            for (corner, vertex) in face.iter().enumerate() {
+                let normal = self.corner_normal(face, corner);
+                let (tangent, bitangent) = accumulated[vertex];
+                corners[corner] = orthogonalize(normal, tangent, bitangent);
+            }
+            tangents.push(corners);
+        }
+        Some(tangents)
+    }
+
+    ///Builds a VAO laid out as position (0), normal (1), uv (2), tangent (3) for normal mapped shading.
+    ///Returns None if the group doesn't exist or none of its faces have normals or uvs.
+    pub fn build_tangent_vao<T: ToString>(&self, material: T) -> Option<VertexArrayObject> {
+        let group = self.batch(material)?;
+        let tangents = self.tangents(&group)?;
+
+        let mut positions = vec![];
+        for (face, tangents) in group.faces.iter().zip(tangents.iter()) {
+            for (corner, tangent) in tangents.iter().enumerate() {
+                self.put_corner(&mut positions, face, corner, true, true);
+                positions.push_array(tangent);
+            }
+        }
+
+        let mut vao = VertexArrayObject::new(Some(VertexArrayObjectType::Arrays((group.faces.len() * 3) as i32)))
+            .with_buffer(VertexBufferObject::array(BufferType::ArrayBuffer, DrawType::StaticDraw, &positions))
+            .with_local_attrib_pointers(vec![
+                LocalAttribPointer::new(3, BufferDataType::Float, false),
+                LocalAttribPointer::new(3, BufferDataType::Float, false),
+                LocalAttribPointer::new(2, BufferDataType::Float, false),
+                LocalAttribPointer::new(4, BufferDataType::Float, false),
+            ])
+            .build();
+        for (name, texture) in self.material_textures(&group.material) {
+            vao.put_texture(Some(name), texture);
+        }
+        Some(vao)
     }
 }
 
+///Faces sharing a `g` name, material and smoothing flag.
 #[derive(Debug, Clone)]
 pub struct Group {
+    name: String,
     material: String,
     faces: Vec<Face>,
     shading: bool,
 }
 
 impl Group {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn material(&self) -> &str {
+        &self.material
+    }
+
+    pub fn faces(&self) -> &Vec<Face> {
+        &self.faces
+    }
+
+    ///Whether `s` smoothing was on for these faces.
+    pub fn smooth(&self) -> bool {
+        self.shading
+    }
+
+    ///Whether any corner has a normal. Files may mix `v/vt/vn` and `v` faces within a group.
     pub fn has_normals(&self) -> bool {
-        if self.faces.len() == 0 {
-            return false;
-        }
-        self.faces[0][0].2.is_some()
+        self.faces.iter().flatten().any(|vertex| vertex.2.is_some())
     }
 
+    ///Whether any corner has a uv.
     pub fn has_uvs(&self) -> bool {
-        if self.faces.len() == 0 {
-            return false;
-        }
-        self.faces[0][0].1.is_some()
+        self.faces.iter().flatten().any(|vertex| vertex.1.is_some())
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct OBJ {
     objects: Vec<Object>,
+    directory: Option<PathBuf>,
 }
 
 impl OBJ {
+    ///Objects to write with `to_string` or `write_file`, e.g. built with `Object::from_faces` or `MeshBuilder::to_object`.
+    pub fn new(objects: Vec<Object>) -> OBJ {
+        Self { objects, directory: None }
+    }
+
+    ///Parses the obj file along with its material libraries, which are resolved relative to the file.
     pub fn from_file<P: AsRef<Path>>(file: P) -> Result<OBJ, Box<dyn Error>> {
+        let file = file.as_ref();
         match std::fs::read_to_string(file) {
             Ok(contents) => {
-                Self::from_raw(contents)
+                let directory = file.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+                Self::from_raw_with_directory(contents, directory)
             }
             Err(e) => {
                 Err(Box::new(e))
@@ -498,50 +835,178 @@ impl OBJ {
         }
     }
 
-    fn all_commands() -> Vec<OBJCommand> {
-        let mut commands = vec![];
-        let cmds = all::<OBJCommand>();
-        for cmd in cmds {
-            commands.push(cmd);
+    ///Same as `from_raw`, but loads material libraries from `directory`.
+    pub fn from_raw_with_directory<P: AsRef<Path>>(raw: String, directory: P) -> Result<OBJ, Box<dyn Error>> {
+        let mut obj = Self::from_raw(raw)?;
+        let directory = directory.as_ref().to_path_buf();
+        for object in &mut obj.objects {
+            object.load_material_libraries(&directory);
         }
-        commands
+        obj.directory = Some(directory);
+        Ok(obj)
     }
 
-    pub fn from_raw(_raw: String) -> Result<OBJ, Box<dyn Error>> {
-        let commands = Self::all_commands();
-        let mut material_libraries: Vec<String> = vec![];
-        let mut object_buffer = vec![];
-        let mut current_object = None;
+    ///Parses obj source. Lines ending in `\` continue on the next line, `#` starts a comment and
+    ///unknown keywords are skipped with a warning.
+    pub fn from_raw(raw: String) -> Result<OBJ, Box<dyn Error>> {
+        let mut state = ParseState::new();
+        let mut unknown = HashSet::new();
 
-        let mut line_number = 1;
-        for line in _raw.split("\n") {
-            let line = line.trim();
+        let mut line = String::new();
+        let mut start_line = 1;
+        for (index, raw_line) in raw.lines().enumerate() {
             if line.is_empty() {
-                line_number += 1;
+                start_line = index + 1;
+            }
+            let raw_line = raw_line.trim_end();
+            if let Some(continued) = raw_line.strip_suffix('\\') {
+                line.push_str(continued);
+                line.push(' ');
                 continue;
             }
-            let contents = line.split_ascii_whitespace().collect::<Vec<&str>>();
-            let command = &contents[0];
-            let contents = &contents[1..];
-
-            let cmd = commands.iter().find(|cmd| *cmd == command).expect(format!("{} is not a valid obj command.", command).as_str());
-
-            if let Err(e) = cmd.process_input(line_number, command, contents, &mut material_libraries, &mut current_object, &mut object_buffer) {
-                return Err(e);
+            line.push_str(raw_line);
+            let code = line.split('#').next().unwrap_or("");
+            let contents = code.split_ascii_whitespace().collect::<Vec<&str>>();
+            if let Some((keyword, contents)) = contents.split_first() {
+                match OBJCommand::from_keyword(keyword) {
+                    Some(cmd) => cmd.process_input(start_line, contents, &mut state)?,
+                    None => {
+                        if unknown.insert(keyword.to_string()) {
+                            eprintln!("Skipping unsupported obj command {} on line {}", keyword, start_line);
+                        }
+                    }
+                }
             }
-            line_number += 1;
-        }
-        if let Some(current_object) = current_object {
-            object_buffer.push(current_object.clone());
+            line.clear();
         }
         Ok(OBJ {
-            objects: object_buffer
+            objects: state.finish(),
+            directory: None,
         })
     }
 
     pub fn objects(&self) -> &Vec<Object> {
         &self.objects
     }
+
+    pub fn objects_mut(&mut self) -> &mut Vec<Object> {
+        &mut self.objects
+    }
+
+    ///Material libraries of every object, in order of first reference.
+    pub fn material_libraries(&self) -> Vec<String> {
+        let mut libraries: Vec<String> = vec![];
+        for library in self.objects.iter().flat_map(|object| object.material_libraries.iter()) {
+            if !libraries.contains(library) {
+                libraries.push(library.clone());
+            }
+        }
+        libraries
+    }
+
+    ///Every loaded material of every object, those used by groups first in order of use.
+    pub fn material_library(&self) -> MTL {
+        let mut materials: Vec<Material> = vec![];
+        for object in &self.objects {
+            let mut names = object.group_materials();
+            let mut unused = object.materials.keys().filter(|name| !names.contains(name)).cloned().collect::<Vec<String>>();
+            unused.sort();
+            names.append(&mut unused);
+            for name in names {
+                if let Some(material) = object.materials.get(&name) {
+                    if !materials.iter().any(|m| m.name == name) {
+                        materials.push(material.clone());
+                    }
+                }
+            }
+        }
+        MTL::new(materials)
+    }
+
+    ///Writes the objects to `file`. If they have materials, these are written next to it with the `mtl`
+    ///extension and texture paths relative to it, and that library is referenced instead of the ones
+    ///the objects were loaded with.
+    pub fn write_file<P: AsRef<Path>>(&self, file: P) -> Result<(), Box<dyn Error>> {
+        let file = file.as_ref();
+        let library = self.material_library();
+        let mut libraries = self.material_libraries();
+        if !library.materials().is_empty() {
+            let path = file.with_extension("mtl");
+            library.write_file(&path)?;
+            libraries = path.file_name().map(|name| vec![name.to_string_lossy().to_string()]).unwrap_or_default();
+        }
+        let mut out = String::new();
+        self.write(&mut out, &libraries)?;
+        std::fs::write(file, out)?;
+        Ok(())
+    }
+
+    ///Writes every object with global 1-based indices. Every object restates its first group's
+    ///`g`, `usemtl` and `s`, later groups only what changed.
+    fn write(&self, f: &mut dyn Write, libraries: &[String]) -> std::fmt::Result {
+        if !libraries.is_empty() {
+            writeln!(f, "mtllib {}", libraries.join(" "))?;
+        }
+        let (mut vertex_offset, mut uv_offset, mut normal_offset) = (1, 1, 1);
+        for object in &self.objects {
+            writeln!(f, "o {}", object.name)?;
+            for (index, [x, y, z]) in object.vertices.iter().enumerate() {
+                match object.colors.get(index) {
+                    Some([r, g, b]) => writeln!(f, "v {} {} {} {} {} {}", x, y, z, r, g, b)?,
+                    None => writeln!(f, "v {} {} {}", x, y, z)?
+                }
+            }
+            for [u, v] in &object.uvs {
+                writeln!(f, "vt {} {}", u, v)?;
+            }
+            for [x, y, z] in &object.normals {
+                writeln!(f, "vn {} {} {}", x, y, z)?;
+            }
+            let mut last: Option<&Group> = None;
+            for group in &object.groups {
+                if last.map(|last| last.name != group.name).unwrap_or(true) {
+                    writeln!(f, "g {}", group.name)?;
+                }
+                if last.map(|last| last.material != group.material).unwrap_or(true) {
+                    writeln!(f, "usemtl {}", group.material)?;
+                }
+                if last.map(|last| last.shading != group.shading).unwrap_or(true) {
+                    writeln!(f, "s {}", if group.shading { "1" } else { "off" })?;
+                }
+                for face in &group.faces {
+                    write!(f, "f")?;
+                    for (v, vt, vn) in face {
+                        write!(f, " {}", v + vertex_offset)?;
+                        match (vt, vn) {
+                            (Some(vt), Some(vn)) => write!(f, "/{}/{}", vt + uv_offset, vn + normal_offset)?,
+                            (Some(vt), None) => write!(f, "/{}", vt + uv_offset)?,
+                            (None, Some(vn)) => write!(f, "//{}", vn + normal_offset)?,
+                            (None, None) => {}
+                        }
+                    }
+                    writeln!(f)?;
+                }
+                last = Some(group);
+            }
+            vertex_offset += object.vertices.len();
+            uv_offset += object.uvs.len();
+            normal_offset += object.normals.len();
+        }
+        Ok(())
+    }
+
+    ///The directory material libraries were resolved against, if loaded from disk.
+    pub fn directory(&self) -> Option<&Path> {
+        self.directory.as_deref()
+    }
+}
+
+impl Display for OBJ {
+    ///The objects as obj source, referencing the objects' material libraries. `from_raw` reads it back
+    ///into the same objects, except for vertices no face uses, which the parser drops.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.write(f, &self.material_libraries())
+    }
 }
 
 impl<T: ToString> Index<T> for OBJ {
@@ -0,0 +1,684 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::path::Path;
+use crate::math::linear_algebra::types::{Vec2, Vec3};
+use crate::math::triangulation::triangulate_polygon;
+use crate::loader::models::obj::{Face, Object, Vertex};
+
+pub const VERTEX_ELEMENT: &'static str = "vertex";
+pub const FACE_ELEMENT: &'static str = "face";
+
+#[derive(Clone)]
+pub enum PlyError {
+    ///The file doesn't start with `ply` or has no `end_header`.
+    InvalidHeader(String),
+    ///A header line that couldn't be parsed and its line number.
+    HeaderLine(String, usize),
+    UnknownType(String),
+    UnsupportedFormat(String),
+    ///The body ended before every row of the element was read.
+    UnexpectedEnd(String),
+    ///An ascii value that isn't a number.
+    InvalidValue(String),
+    MissingElement(String),
+    ///Element and property name.
+    MissingProperty(String, String),
+    ///A face index past the vertex list.
+    IndexOutOfRange(usize),
+}
+
+impl Debug for PlyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlyError::InvalidHeader(problem) => {
+                f.write_fmt(format_args!("Invalid ply header: {}", problem))
+            }
+            PlyError::HeaderLine(line, ln) => {
+                f.write_fmt(format_args!("Unable to parse ply header line: {} on line {}", line, ln))
+            }
+            PlyError::UnknownType(name) => {
+                f.write_fmt(format_args!("Unknown ply property type {}", name))
+            }
+            PlyError::UnsupportedFormat(format) => {
+                f.write_fmt(format_args!("Ply format {} is not supported, expected ascii, binary_little_endian or binary_big_endian 1.0", format))
+            }
+            PlyError::UnexpectedEnd(element) => {
+                f.write_fmt(format_args!("Ply data ended while reading element {}", element))
+            }
+            PlyError::InvalidValue(value) => {
+                f.write_fmt(format_args!("Ply value is not a number: {}", value))
+            }
+            PlyError::MissingElement(element) => {
+                f.write_fmt(format_args!("Ply file has no {} element", element))
+            }
+            PlyError::MissingProperty(element, property) => {
+                f.write_fmt(format_args!("Ply element {} has no property {}", element, property))
+            }
+            PlyError::IndexOutOfRange(index) => {
+                f.write_fmt(format_args!("Ply face refers to vertex {}, which doesn't exist", index))
+            }
+        }
+    }
+}
+
+impl Display for PlyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl Error for PlyError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+impl PlyFormat {
+    pub fn from_name(name: &str) -> Option<PlyFormat> {
+        match name {
+            "ascii" => Some(PlyFormat::Ascii),
+            "binary_little_endian" => Some(PlyFormat::BinaryLittleEndian),
+            "binary_big_endian" => Some(PlyFormat::BinaryBigEndian),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlyFormat::Ascii => "ascii",
+            PlyFormat::BinaryLittleEndian => "binary_little_endian",
+            PlyFormat::BinaryBigEndian => "binary_big_endian",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlyType {
+    Char,
+    UChar,
+    Short,
+    UShort,
+    Int,
+    UInt,
+    Float,
+    Double,
+}
+
+impl PlyType {
+    ///Accepts both the original names and the sized ones, e.g. `uchar` and `uint8`.
+    pub fn from_name(name: &str) -> Option<PlyType> {
+        match name {
+            "char" | "int8" => Some(PlyType::Char),
+            "uchar" | "uint8" => Some(PlyType::UChar),
+            "short" | "int16" => Some(PlyType::Short),
+            "ushort" | "uint16" => Some(PlyType::UShort),
+            "int" | "int32" => Some(PlyType::Int),
+            "uint" | "uint32" => Some(PlyType::UInt),
+            "float" | "float32" => Some(PlyType::Float),
+            "double" | "float64" => Some(PlyType::Double),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlyType::Char => "char",
+            PlyType::UChar => "uchar",
+            PlyType::Short => "short",
+            PlyType::UShort => "ushort",
+            PlyType::Int => "int",
+            PlyType::UInt => "uint",
+            PlyType::Float => "float",
+            PlyType::Double => "double",
+        }
+    }
+
+    ///Size in bytes in binary files.
+    pub fn size(&self) -> usize {
+        match self {
+            PlyType::Char | PlyType::UChar => 1,
+            PlyType::Short | PlyType::UShort => 2,
+            PlyType::Int | PlyType::UInt | PlyType::Float => 4,
+            PlyType::Double => 8,
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        !matches!(self, PlyType::Float | PlyType::Double)
+    }
+
+    ///Largest value of integer types, which colors are normalized by.
+    pub fn max(&self) -> Option<f64> {
+        match self {
+            PlyType::Char => Some(i8::MAX as f64),
+            PlyType::UChar => Some(u8::MAX as f64),
+            PlyType::Short => Some(i16::MAX as f64),
+            PlyType::UShort => Some(u16::MAX as f64),
+            PlyType::Int => Some(i32::MAX as f64),
+            PlyType::UInt => Some(u32::MAX as f64),
+            PlyType::Float | PlyType::Double => None,
+        }
+    }
+
+    fn read(&self, bytes: &[u8], big_endian: bool) -> f64 {
+        macro_rules! read {
+            ($t:ty) => {{
+                let mut raw = [0u8; std::mem::size_of::<$t>()];
+                raw.copy_from_slice(&bytes[..std::mem::size_of::<$t>()]);
+                match big_endian {
+                    true => <$t>::from_be_bytes(raw) as f64,
+                    false => <$t>::from_le_bytes(raw) as f64
+                }
+            }};
+        }
+        match self {
+            PlyType::Char => read!(i8),
+            PlyType::UChar => read!(u8),
+            PlyType::Short => read!(i16),
+            PlyType::UShort => read!(u16),
+            PlyType::Int => read!(i32),
+            PlyType::UInt => read!(u32),
+            PlyType::Float => read!(f32),
+            PlyType::Double => read!(f64),
+        }
+    }
+
+    fn write(&self, value: f64, big_endian: bool, out: &mut Vec<u8>) {
+        macro_rules! write {
+            ($t:ty) => {{
+                let value = value as $t;
+                match big_endian {
+                    true => out.extend_from_slice(&value.to_be_bytes()),
+                    false => out.extend_from_slice(&value.to_le_bytes())
+                }
+            }};
+        }
+        match self {
+            PlyType::Char => write!(i8),
+            PlyType::UChar => write!(u8),
+            PlyType::Short => write!(i16),
+            PlyType::UShort => write!(u16),
+            PlyType::Int => write!(i32),
+            PlyType::UInt => write!(u32),
+            PlyType::Float => write!(f32),
+            PlyType::Double => write!(f64),
+        }
+    }
+
+    fn format(&self, value: f64) -> String {
+        match self {
+            PlyType::Float => format!("{}", value as f32),
+            PlyType::Double => format!("{}", value),
+            _ => format!("{}", value.round() as i64)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlyProperty {
+    Scalar { name: String, kind: PlyType },
+    ///A variable length list, stored as its length followed by the items.
+    List { name: String, count: PlyType, item: PlyType },
+}
+
+impl PlyProperty {
+    pub fn scalar<T: ToString>(name: T, kind: PlyType) -> PlyProperty {
+        PlyProperty::Scalar { name: name.to_string(), kind }
+    }
+
+    pub fn list<T: ToString>(name: T, count: PlyType, item: PlyType) -> PlyProperty {
+        PlyProperty::List { name: name.to_string(), count, item }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            PlyProperty::Scalar { name, .. } | PlyProperty::List { name, .. } => name
+        }
+    }
+
+    ///Type of the values, the item type for lists.
+    pub fn kind(&self) -> PlyType {
+        match self {
+            PlyProperty::Scalar { kind, .. } => *kind,
+            PlyProperty::List { item, .. } => *item,
+        }
+    }
+}
+
+///Values are kept as f64, which holds every ply type exactly.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlyValue {
+    Scalar(f64),
+    List(Vec<f64>),
+}
+
+impl PlyValue {
+    pub fn scalar(&self) -> Option<f64> {
+        match self {
+            PlyValue::Scalar(value) => Some(*value),
+            PlyValue::List(_) => None
+        }
+    }
+
+    pub fn list(&self) -> Option<&Vec<f64>> {
+        match self {
+            PlyValue::Scalar(_) => None,
+            PlyValue::List(values) => Some(values)
+        }
+    }
+}
+
+///A named table, e.g. `vertex` or `face`, with one value per property and row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlyElement {
+    name: String,
+    properties: Vec<PlyProperty>,
+    rows: Vec<Vec<PlyValue>>,
+}
+
+impl PlyElement {
+    pub fn new<T: ToString>(name: T, properties: Vec<PlyProperty>) -> PlyElement {
+        Self { name: name.to_string(), properties, rows: vec![] }
+    }
+
+    ///Appends a row, which must hold one value per property in order.
+    pub fn push_row(&mut self, row: Vec<PlyValue>) {
+        debug_assert_eq!(row.len(), self.properties.len());
+        self.rows.push(row);
+    }
+
+    pub fn with_row(mut self, row: Vec<PlyValue>) -> PlyElement {
+        self.push_row(row);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn properties(&self) -> &Vec<PlyProperty> {
+        &self.properties
+    }
+
+    pub fn rows(&self) -> &Vec<Vec<PlyValue>> {
+        &self.rows
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn property(&self, name: &str) -> Option<&PlyProperty> {
+        self.properties.iter().find(|property| property.name() == name)
+    }
+
+    fn property_index(&self, name: &str) -> Option<usize> {
+        self.properties.iter().position(|property| property.name() == name)
+    }
+
+    ///Every row's value of a scalar property.
+    pub fn scalars(&self, name: &str) -> Option<Vec<f64>> {
+        let index = self.property_index(name)?;
+        self.rows.iter().map(|row| row[index].scalar()).collect()
+    }
+
+    ///Every row's value of a list property.
+    pub fn lists(&self, name: &str) -> Option<Vec<&Vec<f64>>> {
+        let index = self.property_index(name)?;
+        self.rows.iter().map(|row| row[index].list()).collect()
+    }
+
+    ///The first of `names` this element has as scalar property.
+    fn first_scalars(&self, names: &[&str]) -> Option<(Vec<f64>, PlyType)> {
+        names.iter().find_map(|name| Some((self.scalars(name)?, self.property(name)?.kind())))
+    }
+}
+
+///Reads values from the body of a ply file.
+enum Body<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary { bytes: &'a [u8], offset: usize, big_endian: bool },
+}
+
+impl<'a> Body<'a> {
+    fn next(&mut self, kind: PlyType, element: &str) -> Result<f64, PlyError> {
+        match self {
+            Body::Ascii(tokens) => {
+                let token = tokens.next().ok_or(PlyError::UnexpectedEnd(element.to_string()))?;
+                token.parse::<f64>().map_err(|_| PlyError::InvalidValue(token.to_string()))
+            }
+            Body::Binary { bytes, offset, big_endian } => {
+                let end = *offset + kind.size();
+                if end > bytes.len() {
+                    return Err(PlyError::UnexpectedEnd(element.to_string()));
+                }
+                let value = kind.read(&bytes[*offset..end], *big_endian);
+                *offset = end;
+                Ok(value)
+            }
+        }
+    }
+}
+
+///A Stanford polygon file. Every element and property of the file is kept, `to_object` interprets
+///the standard `vertex` and `face` elements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PLY {
+    format: PlyFormat,
+    comments: Vec<String>,
+    elements: Vec<PlyElement>,
+}
+
+impl PLY {
+    pub fn new(format: PlyFormat) -> PLY {
+        Self { format, comments: vec![], elements: vec![] }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(file: P) -> Result<PLY, Box<dyn Error>> {
+        let bytes = std::fs::read(file)?;
+        Ok(Self::from_slice(&bytes)?)
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<PLY, PlyError> {
+        let (header, body) = Self::split_header(bytes)?;
+        let mut lines = header.lines().enumerate().map(|(index, line)| (index + 1, line.trim()));
+        match lines.next() {
+            Some((_, "ply")) => {}
+            _ => return Err(PlyError::InvalidHeader("missing 'ply' magic".to_string()))
+        }
+
+        let mut ply = Self::new(PlyFormat::Ascii);
+        let mut counts = vec![];
+        for (ln, line) in lines {
+            let words = line.split_ascii_whitespace().collect::<Vec<&str>>();
+            let invalid = || PlyError::HeaderLine(line.to_string(), ln);
+            match words.as_slice() {
+                [] => {}
+                ["format", format, version] => {
+                    ply.format = PlyFormat::from_name(format).ok_or(PlyError::UnsupportedFormat(format.to_string()))?;
+                    if !version.starts_with('1') {
+                        return Err(PlyError::UnsupportedFormat(format!("{} {}", format, version)));
+                    }
+                }
+                ["comment", ..] | ["obj_info", ..] => {
+                    ply.comments.push(line.splitn(2, ' ').nth(1).unwrap_or("").trim().to_string());
+                }
+                ["element", name, count] => {
+                    counts.push(count.parse::<usize>().map_err(|_| invalid())?);
+                    ply.elements.push(PlyElement::new(name, vec![]));
+                }
+                ["property", "list", count, item, name] => {
+                    let property = PlyProperty::list(name, Self::parse_type(count)?, Self::parse_type(item)?);
+                    ply.elements.last_mut().ok_or_else(invalid)?.properties.push(property);
+                }
+                ["property", kind, name] => {
+                    let property = PlyProperty::scalar(name, Self::parse_type(kind)?);
+                    ply.elements.last_mut().ok_or_else(invalid)?.properties.push(property);
+                }
+                _ => return Err(invalid())
+            }
+        }
+
+        //Every value takes at least a byte, which bounds what the untrusted counts may reserve.
+        let limit = body.len();
+        let mut body = match ply.format {
+            PlyFormat::Ascii => Body::Ascii(std::str::from_utf8(body)
+                .map_err(|_| PlyError::InvalidValue("non utf-8 ascii data".to_string()))?
+                .split_ascii_whitespace()),
+            PlyFormat::BinaryLittleEndian => Body::Binary { bytes: body, offset: 0, big_endian: false },
+            PlyFormat::BinaryBigEndian => Body::Binary { bytes: body, offset: 0, big_endian: true },
+        };
+        for (element, count) in ply.elements.iter_mut().zip(counts) {
+            element.rows.reserve(count.min(limit));
+            for _ in 0..count {
+                let mut row = Vec::with_capacity(element.properties.len());
+                for property in &element.properties {
+                    row.push(match property {
+                        PlyProperty::Scalar { kind, .. } => PlyValue::Scalar(body.next(*kind, &element.name)?),
+                        PlyProperty::List { count, item, .. } => {
+                            let length = body.next(*count, &element.name)?;
+                            if length < 0.0 {
+                                return Err(PlyError::InvalidValue(length.to_string()));
+                            }
+                            let mut values = Vec::with_capacity((length as usize).min(limit));
+                            for _ in 0..length as usize {
+                                values.push(body.next(*item, &element.name)?);
+                            }
+                            PlyValue::List(values)
+                        }
+                    });
+                }
+                element.rows.push(row);
+            }
+        }
+        Ok(ply)
+    }
+
+    fn parse_type(name: &str) -> Result<PlyType, PlyError> {
+        PlyType::from_name(name).ok_or(PlyError::UnknownType(name.to_string()))
+    }
+
+    ///Splits after the newline ending `end_header`.
+    fn split_header(bytes: &[u8]) -> Result<(&str, &[u8]), PlyError> {
+        let marker = b"end_header";
+        let start = bytes.windows(marker.len())
+            .position(|window| window == marker)
+            .ok_or(PlyError::InvalidHeader("missing end_header".to_string()))?;
+        let mut end = start + marker.len();
+        while end < bytes.len() && bytes[end] != b'\n' {
+            end += 1;
+        }
+        let header = std::str::from_utf8(&bytes[..start])
+            .map_err(|_| PlyError::InvalidHeader("header is not ascii".to_string()))?;
+        Ok((header, &bytes[(end + 1).min(bytes.len())..]))
+    }
+
+    pub fn format(&self) -> PlyFormat {
+        self.format
+    }
+
+    pub fn comments(&self) -> &Vec<String> {
+        &self.comments
+    }
+
+    pub fn elements(&self) -> &Vec<PlyElement> {
+        &self.elements
+    }
+
+    pub fn element(&self, name: &str) -> Option<&PlyElement> {
+        self.elements.iter().find(|element| element.name == name)
+    }
+
+    pub fn with_comment<T: ToString>(mut self, comment: T) -> PLY {
+        self.comments.push(comment.to_string());
+        self
+    }
+
+    pub fn with_element(mut self, element: PlyElement) -> PLY {
+        self.elements.push(element);
+        self
+    }
+
+    ///Serializes every element in `format`.
+    pub fn to_bytes(&self, format: PlyFormat) -> Vec<u8> {
+        let mut header = format!("ply\nformat {} 1.0\n", format.name());
+        for comment in &self.comments {
+            header.push_str(&format!("comment {}\n", comment));
+        }
+        for element in &self.elements {
+            header.push_str(&format!("element {} {}\n", element.name, element.rows.len()));
+            for property in &element.properties {
+                match property {
+                    PlyProperty::Scalar { name, kind } => header.push_str(&format!("property {} {}\n", kind.name(), name)),
+                    PlyProperty::List { name, count, item } => header.push_str(&format!("property list {} {} {}\n", count.name(), item.name(), name)),
+                }
+            }
+        }
+        header.push_str("end_header\n");
+
+        let mut out = header.into_bytes();
+        for element in &self.elements {
+            for row in &element.rows {
+                match format {
+                    PlyFormat::Ascii => {
+                        let mut values = vec![];
+                        for (property, value) in element.properties.iter().zip(row) {
+                            match (property, value) {
+                                (PlyProperty::Scalar { kind, .. }, PlyValue::Scalar(value)) => values.push(kind.format(*value)),
+                                (PlyProperty::List { item, .. }, PlyValue::List(items)) => {
+                                    values.push(items.len().to_string());
+                                    values.extend(items.iter().map(|value| item.format(*value)));
+                                }
+                                (PlyProperty::Scalar { kind, .. }, PlyValue::List(_)) => values.push(kind.format(0.0)),
+                                (PlyProperty::List { .. }, PlyValue::Scalar(_)) => values.push("0".to_string()),
+                            }
+                        }
+                        out.extend_from_slice(values.join(" ").as_bytes());
+                        out.push(b'\n');
+                    }
+                    PlyFormat::BinaryLittleEndian | PlyFormat::BinaryBigEndian => {
+                        let big_endian = format == PlyFormat::BinaryBigEndian;
+                        for (property, value) in element.properties.iter().zip(row) {
+                            match (property, value) {
+                                (PlyProperty::Scalar { kind, .. }, PlyValue::Scalar(value)) => kind.write(*value, big_endian, &mut out),
+                                (PlyProperty::List { count, item, .. }, PlyValue::List(items)) => {
+                                    count.write(items.len() as f64, big_endian, &mut out);
+                                    for value in items {
+                                        item.write(*value, big_endian, &mut out);
+                                    }
+                                }
+                                (PlyProperty::Scalar { kind, .. }, PlyValue::List(_)) => kind.write(0.0, big_endian, &mut out),
+                                (PlyProperty::List { count, .. }, PlyValue::Scalar(_)) => count.write(0.0, big_endian, &mut out),
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    ///Writes the file in the format it was read in, or created with.
+    pub fn write_file<P: AsRef<Path>>(&self, file: P) -> Result<(), Box<dyn Error>> {
+        std::fs::write(file, self.to_bytes(self.format))?;
+        Ok(())
+    }
+
+    ///A `vertex` element with positions, and normals, colors and uvs when every face has them,
+    ///plus a `face` element. Corners sharing a position, normal and uv become one vertex.
+    pub fn from_object(object: &Object, format: PlyFormat) -> PLY {
+        let has_normals = object.faces().all(|face| face.iter().all(|v| v.2.is_some()));
+        let has_uvs = object.faces().all(|face| face.iter().all(|v| v.1.is_some()));
+        let has_colors = object.has_colors();
+
+        let mut properties = vec![
+            PlyProperty::scalar("x", PlyType::Float),
+            PlyProperty::scalar("y", PlyType::Float),
+            PlyProperty::scalar("z", PlyType::Float),
+        ];
+        if has_normals {
+            for name in ["nx", "ny", "nz"] {
+                properties.push(PlyProperty::scalar(name, PlyType::Float));
+            }
+        }
+        if has_colors {
+            for name in ["red", "green", "blue"] {
+                properties.push(PlyProperty::scalar(name, PlyType::UChar));
+            }
+        }
+        if has_uvs {
+            for name in ["s", "t"] {
+                properties.push(PlyProperty::scalar(name, PlyType::Float));
+            }
+        }
+        let mut vertices = PlyElement::new(VERTEX_ELEMENT, properties);
+        let mut faces = PlyElement::new(FACE_ELEMENT, vec![PlyProperty::list("vertex_indices", PlyType::UChar, PlyType::Int)]);
+
+        let mut indices: HashMap<Vertex, usize> = HashMap::new();
+        for face in object.faces() {
+            let mut corners = vec![];
+            for vertex in face {
+                let key = (vertex.0, vertex.1.filter(|_| has_uvs), vertex.2.filter(|_| has_normals));
+                let index = match indices.get(&key) {
+                    Some(index) => *index,
+                    None => {
+                        let mut row = object.vertices()[vertex.0].iter().map(|v| PlyValue::Scalar(*v as f64)).collect::<Vec<PlyValue>>();
+                        if let Some(normal) = key.2 {
+                            row.extend(object.normals()[normal].iter().map(|v| PlyValue::Scalar(*v as f64)));
+                        }
+                        if has_colors {
+                            let color = object.colors().get(vertex.0).copied().unwrap_or([1.0; 3]);
+                            row.extend(color.iter().map(|c| PlyValue::Scalar((c.clamp(0.0, 1.0) * 255.0).round() as f64)));
+                        }
+                        if let Some(uv) = key.1 {
+                            row.extend(object.uvs()[uv].iter().map(|v| PlyValue::Scalar(*v as f64)));
+                        }
+                        vertices.push_row(row);
+                        indices.insert(key, vertices.len() - 1);
+                        vertices.len() - 1
+                    }
+                };
+                corners.push(index as f64);
+            }
+            faces.push_row(vec![PlyValue::List(corners)]);
+        }
+        Self::new(format).with_element(vertices).with_element(faces)
+    }
+
+    ///Reads the `vertex` element's `x`, `y`, `z`, and when present `nx`, `ny`, `nz`, `red`, `green`, `blue`
+    ///and `s`, `t` (or `u`, `v`) into an object. Polygons of the `face` element are triangulated, a file
+    ///without faces is a point cloud and yields an object without faces.
+    pub fn to_object<T: ToString>(&self, name: T) -> Result<Object, PlyError> {
+        let vertex = self.element(VERTEX_ELEMENT).ok_or(PlyError::MissingElement(VERTEX_ELEMENT.to_string()))?;
+        let column = |names: &[&str]| vertex.first_scalars(names);
+        let required = |property: &str| column(&[property]).map(|(values, _)| values)
+            .ok_or(PlyError::MissingProperty(VERTEX_ELEMENT.to_string(), property.to_string()));
+        let (x, y, z) = (required("x")?, required("y")?, required("z")?);
+        let positions = (0..vertex.len()).map(|i| [x[i] as f32, y[i] as f32, z[i] as f32]).collect::<Vec<Vec3>>();
+
+        let normals = match (column(&["nx"]), column(&["ny"]), column(&["nz"])) {
+            (Some((x, _)), Some((y, _)), Some((z, _))) => (0..vertex.len()).map(|i| [x[i] as f32, y[i] as f32, z[i] as f32]).collect(),
+            _ => vec![]
+        };
+        let colors = match (column(&["red", "r", "diffuse_red"]), column(&["green", "g", "diffuse_green"]), column(&["blue", "b", "diffuse_blue"])) {
+            (Some((r, kind)), Some((g, _)), Some((b, _))) => {
+                let scale = kind.max().unwrap_or(1.0);
+                (0..vertex.len()).map(|i| [(r[i] / scale) as f32, (g[i] / scale) as f32, (b[i] / scale) as f32]).collect()
+            }
+            _ => vec![]
+        };
+        let uvs: Vec<Vec2> = match (column(&["s", "u", "texture_u", "texture_s"]), column(&["t", "v", "texture_v", "texture_t"])) {
+            (Some((s, _)), Some((t, _))) => (0..vertex.len()).map(|i| [s[i] as f32, t[i] as f32]).collect(),
+            _ => vec![]
+        };
+
+        let mut faces: Vec<Face> = vec![];
+        if let Some(face) = self.element(FACE_ELEMENT) {
+            let polygons = face.lists("vertex_indices").or_else(|| face.lists("vertex_index"))
+                .ok_or(PlyError::MissingProperty(FACE_ELEMENT.to_string(), "vertex_indices".to_string()))?;
+            for polygon in polygons {
+                let polygon = polygon.iter().map(|i| *i as usize).collect::<Vec<usize>>();
+                if let Some(index) = polygon.iter().find(|i| **i >= positions.len()) {
+                    return Err(PlyError::IndexOutOfRange(*index));
+                }
+                let points = polygon.iter().map(|i| positions[*i]).collect::<Vec<Vec3>>();
+                for triangle in triangulate_polygon(&points) {
+                    let corner = |i: usize| -> Vertex {
+                        let index = polygon[triangle[i]];
+                        (index, (!uvs.is_empty()).then_some(index), (!normals.is_empty()).then_some(index))
+                    };
+                    faces.push([corner(0), corner(1), corner(2)]);
+                }
+            }
+        }
+        Ok(Object::from_faces(name, positions, colors, normals, uvs, faces))
+    }
+}
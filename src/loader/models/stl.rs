@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::path::Path;
+use crate::math::linear_algebra::types::Vec3;
+use crate::math::tangent::triangle_normal;
+use crate::loader::models::obj::{Face, Object};
+
+const HEADER_SIZE: usize = 80;
+const TRIANGLE_SIZE: usize = 50;
+
+#[derive(Clone)]
+pub enum StlError {
+    ///A binary file whose size doesn't match its triangle count, or that is too short for a header.
+    Truncated(usize),
+    ///An ascii line that couldn't be parsed and its line number.
+    Syntax(String, usize),
+    ///An ascii facet without three vertices, with the line number of its `endfacet`.
+    FacetSize(usize, usize),
+}
+
+impl Debug for StlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StlError::Truncated(size) => {
+                f.write_fmt(format_args!("Binary stl of {} bytes is truncated or not an stl file", size))
+            }
+            StlError::Syntax(line, ln) => {
+                f.write_fmt(format_args!("Unable to parse stl line: {} on line {}", line, ln))
+            }
+            StlError::FacetSize(count, ln) => {
+                f.write_fmt(format_args!("Facets need 3 vertices, found {} on line {}", count, ln))
+            }
+        }
+    }
+}
+
+impl Display for StlError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl Error for StlError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StlTriangle {
+    pub normal: Vec3,
+    pub vertices: [Vec3; 3],
+}
+
+///A stereolithography file, a list of triangles with facet normals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct STL {
+    name: String,
+    triangles: Vec<StlTriangle>,
+}
+
+impl STL {
+    pub fn new<T: ToString>(name: T, triangles: Vec<StlTriangle>) -> STL {
+        Self { name: name.to_string(), triangles }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(file: P) -> Result<STL, Box<dyn Error>> {
+        let bytes = std::fs::read(file)?;
+        Ok(Self::from_slice(&bytes)?)
+    }
+
+    ///Detects the format by size, as binary files may also start with `solid`.
+    pub fn from_slice(bytes: &[u8]) -> Result<STL, StlError> {
+        if let Some(count) = Self::binary_count(bytes) {
+            if HEADER_SIZE + 4 + count * TRIANGLE_SIZE == bytes.len() {
+                return Self::from_binary(bytes);
+            }
+        }
+        let start = bytes.iter().position(|b| !b.is_ascii_whitespace()).unwrap_or(bytes.len());
+        if bytes[start..].starts_with(b"solid") {
+            return Self::from_ascii(&String::from_utf8_lossy(bytes));
+        }
+        Err(StlError::Truncated(bytes.len()))
+    }
+
+    fn binary_count(bytes: &[u8]) -> Option<usize> {
+        let count = bytes.get(HEADER_SIZE..HEADER_SIZE + 4)?;
+        Some(u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize)
+    }
+
+    pub fn from_binary(bytes: &[u8]) -> Result<STL, StlError> {
+        let count = Self::binary_count(bytes).ok_or(StlError::Truncated(bytes.len()))?;
+        let data = &bytes[HEADER_SIZE + 4..];
+        if data.len() < count * TRIANGLE_SIZE {
+            return Err(StlError::Truncated(bytes.len()));
+        }
+        let float = |offset: usize| f32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]);
+        let vec3 = |offset: usize| [float(offset), float(offset + 4), float(offset + 8)];
+        let triangles = (0..count).map(|i| {
+            let offset = i * TRIANGLE_SIZE;
+            StlTriangle {
+                normal: vec3(offset),
+                vertices: [vec3(offset + 12), vec3(offset + 24), vec3(offset + 36)],
+            }
+        }).collect();
+        let name = String::from_utf8_lossy(&bytes[..HEADER_SIZE]);
+        let name = name.trim_end_matches('\0').trim();
+        Ok(Self::new(name.strip_prefix("solid").unwrap_or(name).trim(), triangles))
+    }
+
+    pub fn from_ascii(source: &str) -> Result<STL, StlError> {
+        let mut name = String::new();
+        let mut triangles = vec![];
+        let mut normal = [0.0; 3];
+        let mut vertices: Vec<Vec3> = vec![];
+        for (index, line) in source.lines().enumerate() {
+            let ln = index + 1;
+            let words = line.split_ascii_whitespace().collect::<Vec<&str>>();
+            let vector = |values: &[&str]| -> Result<Vec3, StlError> {
+                let parse = |value: &str| value.parse::<f32>().map_err(|_| StlError::Syntax(line.to_string(), ln));
+                match values {
+                    [x, y, z] => Ok([parse(*x)?, parse(*y)?, parse(*z)?]),
+                    _ => Err(StlError::Syntax(line.to_string(), ln))
+                }
+            };
+            match words.as_slice() {
+                [] => {}
+                ["solid", rest @ ..] => name = rest.join(" "),
+                ["facet", "normal", values @ ..] => {
+                    normal = vector(values)?;
+                    vertices.clear();
+                }
+                ["vertex", values @ ..] => vertices.push(vector(values)?),
+                ["endfacet"] => {
+                    if vertices.len() != 3 {
+                        return Err(StlError::FacetSize(vertices.len(), ln));
+                    }
+                    triangles.push(StlTriangle { normal, vertices: [vertices[0], vertices[1], vertices[2]] });
+                }
+                ["outer", "loop"] | ["endloop"] | ["endsolid", ..] => {}
+                _ => return Err(StlError::Syntax(line.to_string(), ln))
+            }
+        }
+        Ok(Self::new(name, triangles))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn triangles(&self) -> &Vec<StlTriangle> {
+        &self.triangles
+    }
+
+    ///Every face of the object with its geometric normal.
+    pub fn from_object(object: &Object) -> STL {
+        let triangles = object.faces().map(|face| {
+            let vertices = [object.vertices()[face[0].0], object.vertices()[face[1].0], object.vertices()[face[2].0]];
+            StlTriangle { normal: triangle_normal(vertices), vertices }
+        }).collect();
+        Self::new(object.name(), triangles)
+    }
+
+    ///Welds corners with identical positions into shared vertices. Each face gets its facet normal,
+    ///computed from the winding where the file's normal is zero.
+    pub fn to_object(&self) -> Object {
+        let mut vertices = vec![];
+        let mut indices: HashMap<[u32; 3], usize> = HashMap::new();
+        let mut normals = Vec::with_capacity(self.triangles.len());
+        let mut faces: Vec<Face> = Vec::with_capacity(self.triangles.len());
+        for triangle in &self.triangles {
+            let normal = match triangle.normal == [0.0; 3] {
+                true => triangle_normal(triangle.vertices),
+                false => triangle.normal
+            };
+            normals.push(normal);
+            let mut face = [(0, None, Some(normals.len() - 1)); 3];
+            for (corner, position) in face.iter_mut().zip(triangle.vertices) {
+                //Adding zero turns -0.0 into 0.0 so both weld.
+                let key = position.map(|v| (v + 0.0).to_bits());
+                corner.0 = *indices.entry(key).or_insert_with(|| {
+                    vertices.push(position);
+                    vertices.len() - 1
+                });
+            }
+            faces.push(face);
+        }
+        Object::from_faces(&self.name, vertices, vec![], normals, vec![], faces)
+    }
+
+    pub fn to_ascii(&self) -> String {
+        let mut out = format!("solid {}\n", self.name);
+        for triangle in &self.triangles {
+            let [x, y, z] = triangle.normal;
+            out.push_str(&format!("  facet normal {} {} {}\n    outer loop\n", x, y, z));
+            for [x, y, z] in triangle.vertices {
+                out.push_str(&format!("      vertex {} {} {}\n", x, y, z));
+            }
+            out.push_str("    endloop\n  endfacet\n");
+        }
+        out.push_str(&format!("endsolid {}\n", self.name));
+        out
+    }
+
+    ///The header holds the name, which must not start with `solid` as some readers take such files for ascii.
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HEADER_SIZE + 4 + self.triangles.len() * TRIANGLE_SIZE);
+        let mut header = [0u8; HEADER_SIZE];
+        let name = self.name.as_bytes();
+        let length = name.len().min(HEADER_SIZE);
+        header[..length].copy_from_slice(&name[..length]);
+        if header.starts_with(b"solid") {
+            header[..5].copy_from_slice(b"SOLID");
+        }
+        out.extend_from_slice(&header);
+        out.extend_from_slice(&(self.triangles.len() as u32).to_le_bytes());
+        for triangle in &self.triangles {
+            for vector in [triangle.normal].iter().chain(triangle.vertices.iter()) {
+                for value in vector {
+                    out.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            //Attribute byte count, unused.
+            out.extend_from_slice(&[0, 0]);
+        }
+        out
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, file: P, binary: bool) -> Result<(), Box<dyn Error>> {
+        match binary {
+            true => std::fs::write(file, self.to_binary())?,
+            false => std::fs::write(file, self.to_ascii())?
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use image::RgbaImage;
+use crate::loader::textures::{CompressedFormat, TextureContainerError};
+
+///Compresses one mip level. Only the formats the asset tool produces can be encoded:
+///BC1, BC3, BC4 and BC5. Partial blocks at the edges repeat the last row and column.
+pub fn encode(image: &RgbaImage, format: CompressedFormat) -> Result<Vec<u8>, TextureContainerError> {
+    let mut out = Vec::with_capacity(format.level_size(image.width(), image.height()));
+    let blocks_x = (image.width() + 3) / 4;
+    let blocks_y = (image.height() + 3) / 4;
+    for by in 0..blocks_y {
+        for bx in 0..blocks_x {
+            let block = read_block(image, bx * 4, by * 4);
+            match format {
+                CompressedFormat::Bc1 => out.extend_from_slice(&bc1_block(&block)),
+                CompressedFormat::Bc3 => {
+                    out.extend_from_slice(&bc4_block(&channel(&block, 3)));
+                    out.extend_from_slice(&bc1_block(&block));
+                }
+                CompressedFormat::Bc4 => out.extend_from_slice(&bc4_block(&channel(&block, 0))),
+                CompressedFormat::Bc5 => {
+                    out.extend_from_slice(&bc4_block(&channel(&block, 0)));
+                    out.extend_from_slice(&bc4_block(&channel(&block, 1)));
+                }
+                _ => return Err(TextureContainerError::UnsupportedFormat(format!("encoding {:?}", format)))
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn read_block(image: &RgbaImage, x: u32, y: u32) -> [[u8; 4]; 16] {
+    let mut block = [[0; 4]; 16];
+    for i in 0..16 {
+        let px = (x + i as u32 % 4).min(image.width() - 1);
+        let py = (y + i as u32 / 4).min(image.height() - 1);
+        block[i] = image.get_pixel(px, py).0;
+    }
+    block
+}
+
+fn channel(block: &[[u8; 4]; 16], channel: usize) -> [u8; 16] {
+    let mut values = [0; 16];
+    for i in 0..16 {
+        values[i] = block[i][channel];
+    }
+    values
+}
+
+fn to_565(color: [f32; 3]) -> u16 {
+    let r = (color[0].clamp(0.0, 255.0) * 31.0 / 255.0).round() as u16;
+    let g = (color[1].clamp(0.0, 255.0) * 63.0 / 255.0).round() as u16;
+    let b = (color[2].clamp(0.0, 255.0) * 31.0 / 255.0).round() as u16;
+    (r << 11) | (g << 5) | b
+}
+
+fn from_565(color: u16) -> [f32; 3] {
+    let r = ((color >> 11) & 31) as f32 * 255.0 / 31.0;
+    let g = ((color >> 5) & 63) as f32 * 255.0 / 63.0;
+    let b = (color & 31) as f32 * 255.0 / 31.0;
+    [r, g, b]
+}
+
+fn distance_squared(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (0..3).map(|i| (a[i] - b[i]) * (a[i] - b[i])).sum()
+}
+
+///Four color BC1 block. Endpoints are the colors furthest apart along the block's bounding box diagonal.
+pub fn bc1_block(block: &[[u8; 4]; 16]) -> [u8; 8] {
+    let colors = block.map(|p| [p[0] as f32, p[1] as f32, p[2] as f32]);
+    let mut min = [255.0f32; 3];
+    let mut max = [0.0f32; 3];
+    for color in &colors {
+        for c in 0..3 {
+            min[c] = min[c].min(color[c]);
+            max[c] = max[c].max(color[c]);
+        }
+    }
+    let axis = [max[0] - min[0], max[1] - min[1], max[2] - min[2]];
+    let project = |color: &[f32; 3]| color[0] * axis[0] + color[1] * axis[1] + color[2] * axis[2];
+    let mut low = colors[0];
+    let mut high = colors[0];
+    for color in &colors {
+        if project(color) < project(&low) {
+            low = *color;
+        }
+        if project(color) > project(&high) {
+            high = *color;
+        }
+    }
+
+    let mut color0 = to_565(high);
+    let mut color1 = to_565(low);
+    if color0 < color1 {
+        std::mem::swap(&mut color0, &mut color1);
+    }
+    let mut indices = 0u32;
+    if color0 != color1 {
+        let c0 = from_565(color0);
+        let c1 = from_565(color1);
+        let palette = [
+            c0,
+            c1,
+            [(2.0 * c0[0] + c1[0]) / 3.0, (2.0 * c0[1] + c1[1]) / 3.0, (2.0 * c0[2] + c1[2]) / 3.0],
+            [(c0[0] + 2.0 * c1[0]) / 3.0, (c0[1] + 2.0 * c1[1]) / 3.0, (c0[2] + 2.0 * c1[2]) / 3.0],
+        ];
+        for (i, color) in colors.iter().enumerate() {
+            let index = (0..4).min_by(|a, b| distance_squared(palette[*a], *color).total_cmp(&distance_squared(palette[*b], *color))).unwrap();
+            indices |= (index as u32) << (2 * i);
+        }
+    }
+
+    let mut out = [0; 8];
+    out[0..2].copy_from_slice(&color0.to_le_bytes());
+    out[2..4].copy_from_slice(&color1.to_le_bytes());
+    out[4..8].copy_from_slice(&indices.to_le_bytes());
+    out
+}
+
+///Eight value BC4 block spanning the block's minimum and maximum.
+pub fn bc4_block(values: &[u8; 16]) -> [u8; 8] {
+    let max = *values.iter().max().unwrap();
+    let min = *values.iter().min().unwrap();
+    let mut out = [0; 8];
+    out[0] = max;
+    out[1] = min;
+    if max == min {
+        return out;
+    }
+    let palette = (0..8).map(|i| match i {
+        0 => max as f32,
+        1 => min as f32,
+        i => ((8 - i) as f32 * max as f32 + (i - 1) as f32 * min as f32) / 7.0
+    }).collect::<Vec<f32>>();
+    let mut indices = 0u64;
+    for (i, value) in values.iter().enumerate() {
+        let value = *value as f32;
+        let index = (0..8).min_by(|a, b| (palette[*a] - value).abs().total_cmp(&(palette[*b] - value).abs())).unwrap();
+        indices |= (index as u64) << (3 * i);
+    }
+    out[2..8].copy_from_slice(&indices.to_le_bytes()[0..6]);
+    out
+}
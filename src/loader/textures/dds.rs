@@ -0,0 +1,151 @@
+use crate::loader::textures::{check_level_count, CompressedFormat, CompressedImage, TextureContainerError};
+
+const MAGIC: &[u8; 4] = b"DDS ";
+const HEADER_SIZE: usize = 124;
+const DX10_HEADER_SIZE: usize = 20;
+
+const DDSD_CAPS: u32 = 0x1;
+const DDSD_HEIGHT: u32 = 0x2;
+const DDSD_WIDTH: u32 = 0x4;
+const DDSD_PIXELFORMAT: u32 = 0x1000;
+const DDSD_MIPMAPCOUNT: u32 = 0x20000;
+const DDSD_LINEARSIZE: u32 = 0x80000;
+const DDPF_FOURCC: u32 = 0x4;
+const DDSCAPS_COMPLEX: u32 = 0x8;
+const DDSCAPS_TEXTURE: u32 = 0x1000;
+const DDSCAPS_MIPMAP: u32 = 0x400000;
+const DIMENSION_TEXTURE2D: u32 = 3;
+
+fn u32_at(bytes: &[u8], offset: usize) -> Result<u32, TextureContainerError> {
+    bytes.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(TextureContainerError::Header("file ends inside the header".to_string()))
+}
+
+///Maps a DXGI format to a compressed format and whether it is sRGB.
+fn from_dxgi(format: u32) -> Option<(CompressedFormat, bool)> {
+    match format {
+        71 => Some((CompressedFormat::Bc1, false)),
+        72 => Some((CompressedFormat::Bc1, true)),
+        77 => Some((CompressedFormat::Bc3, false)),
+        78 => Some((CompressedFormat::Bc3, true)),
+        80 => Some((CompressedFormat::Bc4, false)),
+        83 => Some((CompressedFormat::Bc5, false)),
+        95 => Some((CompressedFormat::Bc6h, false)),
+        98 => Some((CompressedFormat::Bc7, false)),
+        99 => Some((CompressedFormat::Bc7, true)),
+        _ => None
+    }
+}
+
+fn to_dxgi(format: CompressedFormat, srgb: bool) -> Option<u32> {
+    let srgb = srgb && format.supports_srgb();
+    match (format, srgb) {
+        (CompressedFormat::Bc1, false) => Some(71),
+        (CompressedFormat::Bc1, true) => Some(72),
+        (CompressedFormat::Bc3, false) => Some(77),
+        (CompressedFormat::Bc3, true) => Some(78),
+        (CompressedFormat::Bc4, _) => Some(80),
+        (CompressedFormat::Bc5, _) => Some(83),
+        (CompressedFormat::Bc6h, _) => Some(95),
+        (CompressedFormat::Bc7, false) => Some(98),
+        (CompressedFormat::Bc7, true) => Some(99),
+        _ => None
+    }
+}
+
+///Reads a 2D DDS texture with a BCn payload, using either a legacy FourCC or a DX10 header.
+pub fn read(bytes: &[u8]) -> Result<CompressedImage, TextureContainerError> {
+    if bytes.len() < 4 || &bytes[0..4] != MAGIC {
+        return Err(TextureContainerError::Magic("DDS".to_string()));
+    }
+    if u32_at(bytes, 4)? as usize != HEADER_SIZE {
+        return Err(TextureContainerError::Header("header size isn't 124".to_string()));
+    }
+    let height = u32_at(bytes, 12)?;
+    let width = u32_at(bytes, 16)?;
+    let level_count = u32_at(bytes, 28)?.max(1) as usize;
+    check_level_count(width, height, level_count)?;
+    let pixel_flags = u32_at(bytes, 80)?;
+    let four_cc = bytes.get(84..88).ok_or(TextureContainerError::Header("missing pixel format".to_string()))?;
+    if pixel_flags & DDPF_FOURCC == 0 {
+        return Err(TextureContainerError::UnsupportedFormat("uncompressed DDS".to_string()));
+    }
+
+    let mut offset = 4 + HEADER_SIZE;
+    let (format, srgb) = match four_cc {
+        b"DXT1" => (CompressedFormat::Bc1, false),
+        b"DXT5" => (CompressedFormat::Bc3, false),
+        b"ATI1" | b"BC4U" => (CompressedFormat::Bc4, false),
+        b"ATI2" | b"BC5U" => (CompressedFormat::Bc5, false),
+        b"DX10" => {
+            let dxgi = u32_at(bytes, offset)?;
+            let dimension = u32_at(bytes, offset + 4)?;
+            let array_size = u32_at(bytes, offset + 12)?;
+            if dimension != DIMENSION_TEXTURE2D || array_size > 1 {
+                return Err(TextureContainerError::UnsupportedFormat("only single 2D textures are supported".to_string()));
+            }
+            offset += DX10_HEADER_SIZE;
+            from_dxgi(dxgi).ok_or(TextureContainerError::UnsupportedFormat(format!("DXGI format {}", dxgi)))?
+        }
+        other => return Err(TextureContainerError::UnsupportedFormat(String::from_utf8_lossy(other).to_string()))
+    };
+
+    let mut image = CompressedImage { format, srgb, width, height, levels: vec![] };
+    for level in 0..level_count {
+        let (w, h) = image.level_dimensions(level);
+        let size = format.level_size(w, h);
+        let data = bytes.get(offset..offset + size).ok_or(TextureContainerError::Truncated(level))?;
+        image.levels.push(data.to_vec());
+        offset += size;
+    }
+    Ok(image)
+}
+
+///Writes the image with a DX10 header, which can express sRGB and every BCn format.
+///ETC2 has no DXGI format and can't be stored in DDS, use KTX2 for it.
+pub fn write(image: &CompressedImage) -> Vec<u8> {
+    let dxgi = to_dxgi(image.format, image.srgb).expect("ETC2 textures can't be stored as DDS");
+    let mut out = Vec::with_capacity(4 + HEADER_SIZE + DX10_HEADER_SIZE + image.levels.iter().map(|l| l.len()).sum::<usize>());
+    let push = |value: u32, out: &mut Vec<u8>| out.extend_from_slice(&value.to_le_bytes());
+
+    out.extend_from_slice(MAGIC);
+    push(HEADER_SIZE as u32, &mut out);
+    push(DDSD_CAPS | DDSD_HEIGHT | DDSD_WIDTH | DDSD_PIXELFORMAT | DDSD_MIPMAPCOUNT | DDSD_LINEARSIZE, &mut out);
+    push(image.height, &mut out);
+    push(image.width, &mut out);
+    push(image.levels.first().map(|l| l.len()).unwrap_or(0) as u32, &mut out);
+    //Depth
+    push(0, &mut out);
+    push(image.levels.len() as u32, &mut out);
+    for _ in 0..11 {
+        push(0, &mut out);
+    }
+    //Pixel format
+    push(32, &mut out);
+    push(DDPF_FOURCC, &mut out);
+    out.extend_from_slice(b"DX10");
+    for _ in 0..5 {
+        push(0, &mut out);
+    }
+    let mut caps = DDSCAPS_TEXTURE;
+    if image.levels.len() > 1 {
+        caps |= DDSCAPS_COMPLEX | DDSCAPS_MIPMAP;
+    }
+    push(caps, &mut out);
+    for _ in 0..4 {
+        push(0, &mut out);
+    }
+
+    push(dxgi, &mut out);
+    push(DIMENSION_TEXTURE2D, &mut out);
+    //Misc flags, array size, alpha mode
+    push(0, &mut out);
+    push(1, &mut out);
+    push(0, &mut out);
+
+    for level in &image.levels {
+        out.extend_from_slice(level);
+    }
+    out
+}
@@ -0,0 +1,197 @@
+use crate::loader::textures::{check_level_count, CompressedFormat, CompressedImage, TextureContainerError};
+
+const IDENTIFIER: [u8; 12] = [0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+///Identifier, nine header fields and the index.
+const HEADER_SIZE: usize = 12 + 9 * 4 + 4 * 4 + 2 * 8;
+const LEVEL_INDEX_ENTRY: usize = 3 * 8;
+
+const KHR_DF_TRANSFER_LINEAR: u8 = 1;
+const KHR_DF_TRANSFER_SRGB: u8 = 2;
+const KHR_DF_PRIMARIES_BT709: u8 = 1;
+
+fn u32_at(bytes: &[u8], offset: usize) -> Result<u32, TextureContainerError> {
+    bytes.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(TextureContainerError::Header("file ends inside the header".to_string()))
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> Result<u64, TextureContainerError> {
+    bytes.get(offset..offset + 8)
+        .map(|b| u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+        .ok_or(TextureContainerError::Header("file ends inside the level index".to_string()))
+}
+
+fn from_vk_format(format: u32) -> Option<(CompressedFormat, bool)> {
+    match format {
+        131 | 133 => Some((CompressedFormat::Bc1, false)),
+        132 | 134 => Some((CompressedFormat::Bc1, true)),
+        137 => Some((CompressedFormat::Bc3, false)),
+        138 => Some((CompressedFormat::Bc3, true)),
+        139 => Some((CompressedFormat::Bc4, false)),
+        141 => Some((CompressedFormat::Bc5, false)),
+        143 => Some((CompressedFormat::Bc6h, false)),
+        145 => Some((CompressedFormat::Bc7, false)),
+        146 => Some((CompressedFormat::Bc7, true)),
+        147 => Some((CompressedFormat::Etc2Rgb, false)),
+        148 => Some((CompressedFormat::Etc2Rgb, true)),
+        151 => Some((CompressedFormat::Etc2Rgba, false)),
+        152 => Some((CompressedFormat::Etc2Rgba, true)),
+        _ => None
+    }
+}
+
+fn to_vk_format(format: CompressedFormat, srgb: bool) -> u32 {
+    let srgb = srgb && format.supports_srgb();
+    match (format, srgb) {
+        (CompressedFormat::Bc1, false) => 133,
+        (CompressedFormat::Bc1, true) => 134,
+        (CompressedFormat::Bc3, false) => 137,
+        (CompressedFormat::Bc3, true) => 138,
+        (CompressedFormat::Bc4, _) => 139,
+        (CompressedFormat::Bc5, _) => 141,
+        (CompressedFormat::Bc6h, _) => 143,
+        (CompressedFormat::Bc7, false) => 145,
+        (CompressedFormat::Bc7, true) => 146,
+        (CompressedFormat::Etc2Rgb, false) => 147,
+        (CompressedFormat::Etc2Rgb, true) => 148,
+        (CompressedFormat::Etc2Rgba, false) => 151,
+        (CompressedFormat::Etc2Rgba, true) => 152,
+    }
+}
+
+///Reads a single 2D KTX2 texture. Supercompressed (Basis Universal, zstd) files aren't supported.
+pub fn read(bytes: &[u8]) -> Result<CompressedImage, TextureContainerError> {
+    if bytes.len() < HEADER_SIZE || bytes[0..12] != IDENTIFIER {
+        return Err(TextureContainerError::Magic("KTX2".to_string()));
+    }
+    let vk_format = u32_at(bytes, 12)?;
+    let width = u32_at(bytes, 20)?;
+    let height = u32_at(bytes, 24)?;
+    let depth = u32_at(bytes, 28)?;
+    let layers = u32_at(bytes, 32)?;
+    let faces = u32_at(bytes, 36)?;
+    let level_count = u32_at(bytes, 40)?.max(1) as usize;
+    check_level_count(width, height, level_count)?;
+    let supercompression = u32_at(bytes, 44)?;
+    if supercompression != 0 {
+        return Err(TextureContainerError::UnsupportedFormat(format!("supercompression scheme {}", supercompression)));
+    }
+    if depth > 1 || layers > 1 || faces != 1 {
+        return Err(TextureContainerError::UnsupportedFormat("only single 2D textures are supported".to_string()));
+    }
+    let (format, srgb) = from_vk_format(vk_format)
+        .ok_or(TextureContainerError::UnsupportedFormat(format!("VkFormat {}", vk_format)))?;
+
+    let mut image = CompressedImage { format, srgb, width, height, levels: vec![] };
+    for level in 0..level_count {
+        let entry = HEADER_SIZE + level * LEVEL_INDEX_ENTRY;
+        let offset = u64_at(bytes, entry)? as usize;
+        let length = u64_at(bytes, entry + 8)? as usize;
+        let (w, h) = image.level_dimensions(level);
+        if length != format.level_size(w, h) {
+            return Err(TextureContainerError::Header(format!("mip level {} is {} bytes, a {}x{} level needs {}", level, length, w, h, format.level_size(w, h))));
+        }
+        let end = offset.checked_add(length).ok_or(TextureContainerError::Truncated(level))?;
+        let data = bytes.get(offset..end).ok_or(TextureContainerError::Truncated(level))?;
+        image.levels.push(data.to_vec());
+    }
+    Ok(image)
+}
+
+///Basic data format descriptor for a block compressed format, as required by the spec.
+fn data_format_descriptor(format: CompressedFormat, srgb: bool) -> Vec<u8> {
+    let (model, samples): (u8, Vec<(u8, u8)>) = match format {
+        //(channel id, bit length - 1) of each sample.
+        CompressedFormat::Bc1 => (128, vec![(0, 63)]),
+        CompressedFormat::Bc3 => (130, vec![(15, 63), (0, 63)]),
+        CompressedFormat::Bc4 => (131, vec![(0, 63)]),
+        CompressedFormat::Bc5 => (132, vec![(0, 63), (1, 63)]),
+        CompressedFormat::Bc6h => (133, vec![(0, 127)]),
+        CompressedFormat::Bc7 => (134, vec![(0, 127)]),
+        CompressedFormat::Etc2Rgb => (161, vec![(2, 63)]),
+        CompressedFormat::Etc2Rgba => (161, vec![(15, 63), (2, 63)]),
+    };
+    let transfer = match srgb && format.supports_srgb() {
+        true => KHR_DF_TRANSFER_SRGB,
+        false => KHR_DF_TRANSFER_LINEAR
+    };
+    let block_size = 24 + 16 * samples.len();
+
+    let mut dfd = vec![];
+    dfd.extend_from_slice(&((4 + block_size) as u32).to_le_bytes());
+    //Vendor 0 (Khronos), descriptor type 0 (basic)
+    dfd.extend_from_slice(&0u32.to_le_bytes());
+    //Version 2, block size
+    dfd.extend_from_slice(&2u16.to_le_bytes());
+    dfd.extend_from_slice(&(block_size as u16).to_le_bytes());
+    dfd.extend_from_slice(&[model, KHR_DF_PRIMARIES_BT709, transfer, 0]);
+    //4x4x1 texel blocks, stored as dimension - 1
+    dfd.extend_from_slice(&[3, 3, 0, 0]);
+    dfd.extend_from_slice(&[format.block_bytes() as u8, 0, 0, 0, 0, 0, 0, 0]);
+
+    let mut bit_offset = 0u16;
+    for (channel, bits) in samples {
+        dfd.extend_from_slice(&bit_offset.to_le_bytes());
+        dfd.push(bits);
+        dfd.push(channel);
+        //Sample positions
+        dfd.extend_from_slice(&[0, 0, 0, 0]);
+        //Lower and upper
+        dfd.extend_from_slice(&0u32.to_le_bytes());
+        dfd.extend_from_slice(&u32::MAX.to_le_bytes());
+        bit_offset += bits as u16 + 1;
+    }
+    dfd
+}
+
+fn align(out: &mut Vec<u8>, alignment: usize) {
+    while out.len() % alignment != 0 {
+        out.push(0);
+    }
+}
+
+///Writes the image with its mip chain. Level data is stored smallest level first, as the spec requires.
+pub fn write(image: &CompressedImage) -> Vec<u8> {
+    let dfd = data_format_descriptor(image.format, image.srgb);
+    let level_index_size = image.levels.len() * LEVEL_INDEX_ENTRY;
+    let dfd_offset = HEADER_SIZE + level_index_size;
+
+    let mut out = vec![];
+    out.extend_from_slice(&IDENTIFIER);
+    for value in [
+        to_vk_format(image.format, image.srgb),
+        //Type size is 1 for block compressed formats.
+        1,
+        image.width,
+        image.height,
+        0,
+        0,
+        1,
+        image.levels.len() as u32,
+        0,
+    ] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out.extend_from_slice(&(dfd_offset as u32).to_le_bytes());
+    out.extend_from_slice(&(dfd.len() as u32).to_le_bytes());
+    //No key/value data, no supercompression global data
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&0u64.to_le_bytes());
+    out.extend_from_slice(&0u64.to_le_bytes());
+
+    let index_start = out.len();
+    out.resize(index_start + level_index_size, 0);
+    out.extend_from_slice(&dfd);
+
+    for (level, data) in image.levels.iter().enumerate().rev() {
+        align(&mut out, image.format.block_bytes());
+        let entry = index_start + level * LEVEL_INDEX_ENTRY;
+        let offset = out.len() as u64;
+        out[entry..entry + 8].copy_from_slice(&offset.to_le_bytes());
+        out[entry + 8..entry + 16].copy_from_slice(&(data.len() as u64).to_le_bytes());
+        out[entry + 16..entry + 24].copy_from_slice(&(data.len() as u64).to_le_bytes());
+        out.extend_from_slice(data);
+    }
+    out
+}
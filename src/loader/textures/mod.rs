@@ -0,0 +1,273 @@
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use image::DynamicImage;
+use image::imageops::FilterType;
+use crate::gfx::bindings::ColorFormat;
+
+pub mod dds;
+pub mod ktx2;
+pub mod bcn;
+
+#[derive(Clone)]
+pub enum TextureContainerError {
+    ///The file doesn't start with the container's magic bytes.
+    Magic(String),
+    Header(String),
+    UnsupportedFormat(String),
+    ///Level data runs past the end of the file.
+    Truncated(usize),
+    UnknownContainer(String),
+}
+
+impl Debug for TextureContainerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextureContainerError::Magic(container) => {
+                f.write_fmt(format_args!("Not a {} file", container))
+            }
+            TextureContainerError::Header(e) => {
+                f.write_fmt(format_args!("Invalid header: {}", e))
+            }
+            TextureContainerError::UnsupportedFormat(format) => {
+                f.write_fmt(format_args!("Unsupported pixel format: {}", format))
+            }
+            TextureContainerError::Truncated(level) => {
+                f.write_fmt(format_args!("Mip level {} runs past the end of the file", level))
+            }
+            TextureContainerError::UnknownContainer(path) => {
+                f.write_fmt(format_args!("Expected a .dds or .ktx2 file: {}", path))
+            }
+        }
+    }
+}
+
+impl Display for TextureContainerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:?}", self))
+    }
+}
+
+impl Error for TextureContainerError {}
+
+///Block compressed pixel formats. Every format stores 4x4 texel blocks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompressedFormat {
+    ///RGB with 1 bit alpha, 8 bytes per block.
+    Bc1,
+    ///RGBA, BC1 color plus a BC4 alpha block.
+    Bc3,
+    ///Single channel.
+    Bc4,
+    ///Two channels, e.g. the XY of normal maps.
+    Bc5,
+    ///Unsigned HDR RGB.
+    Bc6h,
+    Bc7,
+    Etc2Rgb,
+    Etc2Rgba,
+}
+
+impl CompressedFormat {
+    pub fn block_bytes(&self) -> usize {
+        match self {
+            CompressedFormat::Bc1 | CompressedFormat::Bc4 | CompressedFormat::Etc2Rgb => 8,
+            _ => 16
+        }
+    }
+
+    ///Bytes of a `width` by `height` level, partial blocks at the edges count as whole blocks.
+    pub fn level_size(&self, width: u32, height: u32) -> usize {
+        let blocks_x = ((width + 3) / 4).max(1) as usize;
+        let blocks_y = ((height + 3) / 4).max(1) as usize;
+        blocks_x * blocks_y * self.block_bytes()
+    }
+
+    ///Whether the format has an sRGB variant.
+    pub fn supports_srgb(&self) -> bool {
+        match self {
+            CompressedFormat::Bc4 | CompressedFormat::Bc5 | CompressedFormat::Bc6h => false,
+            _ => true
+        }
+    }
+
+    pub fn color_format(&self, srgb: bool) -> ColorFormat {
+        let srgb = srgb && self.supports_srgb();
+        match (self, srgb) {
+            (CompressedFormat::Bc1, false) => ColorFormat::CompressedRgbaS3tcDxt1,
+            (CompressedFormat::Bc1, true) => ColorFormat::CompressedSrgbAlphaS3tcDxt1,
+            (CompressedFormat::Bc3, false) => ColorFormat::CompressedRgbaS3tcDxt5,
+            (CompressedFormat::Bc3, true) => ColorFormat::CompressedSrgbAlphaS3tcDxt5,
+            (CompressedFormat::Bc4, _) => ColorFormat::CompressedRedRgtc1,
+            (CompressedFormat::Bc5, _) => ColorFormat::CompressedRgRgtc2,
+            (CompressedFormat::Bc6h, _) => ColorFormat::CompressedRgbBptcUnsignedFloat,
+            (CompressedFormat::Bc7, false) => ColorFormat::CompressedRgbaBptcUnorm,
+            (CompressedFormat::Bc7, true) => ColorFormat::CompressedSrgbAlphaBptcUnorm,
+            (CompressedFormat::Etc2Rgb, false) => ColorFormat::CompressedRgb8Etc2,
+            (CompressedFormat::Etc2Rgb, true) => ColorFormat::CompressedSrgb8Etc2,
+            (CompressedFormat::Etc2Rgba, false) => ColorFormat::CompressedRgba8Etc2Eac,
+            (CompressedFormat::Etc2Rgba, true) => ColorFormat::CompressedSrgb8Alpha8Etc2Eac,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<CompressedFormat> {
+        match name.to_lowercase().as_str() {
+            "bc1" | "dxt1" => Some(CompressedFormat::Bc1),
+            "bc3" | "dxt5" => Some(CompressedFormat::Bc3),
+            "bc4" => Some(CompressedFormat::Bc4),
+            "bc5" => Some(CompressedFormat::Bc5),
+            "bc6h" => Some(CompressedFormat::Bc6h),
+            "bc7" => Some(CompressedFormat::Bc7),
+            "etc2" => Some(CompressedFormat::Etc2Rgb),
+            "etc2a" => Some(CompressedFormat::Etc2Rgba),
+            _ => None
+        }
+    }
+}
+
+///A compressed 2D image with its mip chain, level 0 first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressedImage {
+    pub format: CompressedFormat,
+    pub srgb: bool,
+    pub width: u32,
+    pub height: u32,
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl CompressedImage {
+    ///Size of mip `level`, at least 1x1.
+    pub fn level_dimensions(&self, level: usize) -> (u32, u32) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+
+    ///Compresses `image` and every level of its mip chain down to 1x1.
+    pub fn encode(image: &DynamicImage, format: CompressedFormat, srgb: bool) -> Result<CompressedImage, TextureContainerError> {
+        let mut levels = vec![];
+        let mut level = image.to_rgba8();
+        loop {
+            levels.push(bcn::encode(&level, format)?);
+            if level.width() == 1 && level.height() == 1 {
+                break;
+            }
+            let width = (level.width() / 2).max(1);
+            let height = (level.height() / 2).max(1);
+            level = image::imageops::resize(&level, width, height, FilterType::Triangle);
+        }
+        Ok(CompressedImage {
+            format,
+            srgb,
+            width: image.width(),
+            height: image.height(),
+            levels,
+        })
+    }
+}
+
+///Rejects more levels than the chain down to 1x1 has, which `level_dimensions` can't size.
+fn check_level_count(width: u32, height: u32, level_count: usize) -> Result<(), TextureContainerError> {
+    let max = (32 - width.max(height).leading_zeros()) as usize;
+    if level_count > max {
+        return Err(TextureContainerError::Header(format!("{} mip levels for a {}x{} image, at most {}", level_count, width, height, max)));
+    }
+    Ok(())
+}
+
+///Reads a `.dds` or `.ktx2` file.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<CompressedImage, Box<dyn Error>> {
+    let path = path.as_ref();
+    let bytes = fs::read(path)?;
+    match extension(path).as_str() {
+        "dds" => Ok(dds::read(&bytes)?),
+        "ktx2" => Ok(ktx2::read(&bytes)?),
+        _ => Err(Box::new(TextureContainerError::UnknownContainer(path.display().to_string())))
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Container {
+    Dds,
+    Ktx2,
+}
+
+impl Container {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Container::Dds => "dds",
+            Container::Ktx2 => "ktx2",
+        }
+    }
+
+    pub fn write(&self, image: &CompressedImage) -> Vec<u8> {
+        match self {
+            Container::Dds => dds::write(image),
+            Container::Ktx2 => ktx2::write(image),
+        }
+    }
+}
+
+///Settings of `convert_folder`.
+#[derive(Debug, Clone)]
+pub struct ConvertOptions {
+    pub container: Container,
+    ///Forces a format, otherwise it is picked per image by `pick_format`.
+    pub format: Option<CompressedFormat>,
+    ///Where the containers are written, next to the sources if `None`.
+    pub output: Option<PathBuf>,
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            container: Container::Ktx2,
+            format: None,
+            output: None,
+        }
+    }
+}
+
+///Chooses a format from the image's channels and its file name: single channel maps like roughness
+///become BC4, images with transparency BC3, everything else BC1. Base color maps are flagged sRGB.
+pub fn pick_format(image: &DynamicImage, name: &str) -> (CompressedFormat, bool) {
+    let name = name.to_lowercase();
+    let srgb = ["basecolor", "albedo", "diffuse", "color"].iter().any(|role| name.contains(role));
+    let format = if !image.color().has_color() && !image.color().has_alpha() {
+        CompressedFormat::Bc4
+    } else if image.color().has_alpha() && image.to_rgba8().pixels().any(|pixel| pixel[3] < 255) {
+        CompressedFormat::Bc3
+    } else {
+        CompressedFormat::Bc1
+    };
+    (format, srgb)
+}
+
+///Compresses every png, jpg, tga and bmp image in `input`, returning the written files.
+pub fn convert_folder<P: AsRef<Path>>(input: P, options: &ConvertOptions) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let input = input.as_ref();
+    let output = options.output.clone().unwrap_or(input.to_path_buf());
+    fs::create_dir_all(&output)?;
+
+    let mut entries = fs::read_dir(input)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| ["png", "jpg", "jpeg", "tga", "bmp"].contains(&extension(path).as_str()))
+        .collect::<Vec<PathBuf>>();
+    entries.sort();
+
+    let mut written = vec![];
+    for path in entries {
+        let image = image::open(&path)?;
+        let name = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let (picked, srgb) = pick_format(&image, &name);
+        let format = options.format.unwrap_or(picked);
+        let compressed = CompressedImage::encode(&image, format, srgb)?;
+        let target = output.join(format!("{}.{}", name, options.container.extension()));
+        fs::write(&target, options.container.write(&compressed))?;
+        written.push(target);
+    }
+    Ok(written)
+}
@@ -0,0 +1,112 @@
+use vecmath::{col_mat4_transform, vec3_add, vec3_scale, vec3_sub};
+use crate::math::linear_algebra::types::{Mat4, Vec3};
+use crate::math::linear_algebra::vector_ext::VectorExt;
+
+///Axis aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Aabb {
+        Self { min, max }
+    }
+
+    ///None for an empty point set.
+    pub fn from_points(points: &[Vec3]) -> Option<Aabb> {
+        let first = *points.first()?;
+        Some(points.iter().fold(Self::new(first, first), |aabb, point| aabb.with_point(*point)))
+    }
+
+    ///The box grown to contain `point`.
+    pub fn with_point(self, point: Vec3) -> Aabb {
+        Self {
+            min: [self.min[0].min(point[0]), self.min[1].min(point[1]), self.min[2].min(point[2])],
+            max: [self.max[0].max(point[0]), self.max[1].max(point[1]), self.max[2].max(point[2])],
+        }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        self.with_point(other.min).with_point(other.max)
+    }
+
+    pub fn center(&self) -> Vec3 {
+        [(self.min[0] + self.max[0]) * 0.5, (self.min[1] + self.max[1]) * 0.5, (self.min[2] + self.max[2]) * 0.5]
+    }
+
+    pub fn size(&self) -> Vec3 {
+        [self.max[0] - self.min[0], self.max[1] - self.min[1], self.max[2] - self.min[2]]
+    }
+
+    ///Half the size.
+    pub fn extents(&self) -> Vec3 {
+        let size = self.size();
+        [size[0] * 0.5, size[1] * 0.5, size[2] * 0.5]
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        (0..3).all(|i| point[i] >= self.min[i] && point[i] <= self.max[i])
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            [min[0], min[1], min[2]], [max[0], min[1], min[2]], [min[0], max[1], min[2]], [max[0], max[1], min[2]],
+            [min[0], min[1], max[2]], [max[0], min[1], max[2]], [min[0], max[1], max[2]], [max[0], max[1], max[2]],
+        ]
+    }
+
+    ///The box around this box's corners transformed by a column major matrix.
+    pub fn transformed(&self, matrix: &Mat4) -> Aabb {
+        let corners = self.corners().map(|[x, y, z]| {
+            let p = col_mat4_transform(*matrix, [x, y, z, 1.0]);
+            [p[0], p[1], p[2]]
+        });
+        Self::from_points(&corners).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn new(center: Vec3, radius: f32) -> BoundingSphere {
+        Self { center, radius }
+    }
+
+    ///Ritter's approximation, at most a few percent larger than the minimal sphere. None for an empty point set.
+    pub fn from_points(points: &[Vec3]) -> Option<BoundingSphere> {
+        let first = *points.first()?;
+        let farthest = |from: Vec3| points.iter().copied()
+            .fold(from, |best, p| if from.distance(&p) > from.distance(&best) { p } else { best });
+        let a = farthest(first);
+        let b = farthest(a);
+        let mut sphere = Self::new(vec3_scale(vec3_add(a, b), 0.5), a.distance(&b) * 0.5);
+        for point in points {
+            let d = sphere.center.distance(point);
+            if d > sphere.radius {
+                let radius = (sphere.radius + d) * 0.5;
+                sphere.center = vec3_add(sphere.center, vec3_scale(vec3_sub(*point, sphere.center), (radius - sphere.radius) / d));
+                sphere.radius = radius;
+            }
+        }
+        Some(sphere)
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        self.center.distance(&point) <= self.radius * (1.0 + 1e-5)
+    }
+
+    ///The sphere around this sphere transformed by a column major matrix, scaled by its largest axis.
+    pub fn transformed(&self, matrix: &Mat4) -> BoundingSphere {
+        let [x, y, z] = self.center;
+        let center = col_mat4_transform(*matrix, [x, y, z, 1.0]);
+        let scale = matrix[..3].iter().fold(0.0f32, |max, axis| max.max([axis[0], axis[1], axis[2]].magnitude()));
+        Self::new([center[0], center[1], center[2]], self.radius * scale)
+    }
+}
@@ -2,7 +2,7 @@ use rlua::{UserData, UserDataMethods};
 use vecmath::Matrix4;
 use crate::math::linear_algebra::{orthographic, perspective};
 use crate::math::linear_algebra::types::Vec3;
-use crate::{MatrixWrapper, Program, v3};
+use crate::{MatrixWrapper, Program, uniform_block, v3};
 use crate::math::linear_algebra::vector_ext::VectorExt;
 
 #[derive(Debug, Clone)]
@@ -27,6 +27,17 @@ pub struct Camera {
     last_orthographic: Matrix4<f32>,
 }
 
+uniform_block! {
+    ///The camera as a `std140` block, for shaders declaring
+    ///`layout (std140) uniform Camera { mat4 projection; mat4 view; vec3 cameraPosition; };`.
+    #[derive(Debug, Clone)]
+    pub struct CameraMatrices("Camera") {
+        pub projection: Matrix4<f32>,
+        pub view: Matrix4<f32>,
+        pub position: Vec3,
+    }
+}
+
 impl UserData for Camera {
     fn add_methods<'lua, T: UserDataMethods<'lua, Self>>(_methods: &mut T) {
         _methods.add_method_mut("set_position", |_, me, pos: [f32; 3]| {
@@ -132,6 +143,15 @@ impl Camera {
         self.position
     }
 
+    ///The matrices `prepare_render` sets, to upload once per frame through a `UniformBuffer<CameraMatrices>`.
+    pub fn matrices(&self) -> CameraMatrices {
+        CameraMatrices {
+            projection: self.last_perspective,
+            view: self.view,
+            position: self.position,
+        }
+    }
+
     pub fn prepare_render(&self, program: &Program) {
         program.set_uniform_mat4("projection", &self.last_perspective);
         program.set_uniform_mat4("view", &self.view);
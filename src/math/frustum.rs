@@ -0,0 +1,57 @@
+use vecmath::col_mat4_mul;
+use crate::Camera;
+use crate::math::bounds::{Aabb, BoundingSphere};
+use crate::math::linear_algebra::types::{Mat4, Vec3, Vec4};
+
+///The six planes bounding what a projection sees, as `[a, b, c, d]` with unit normals pointing inwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    ///Extracts the planes of a column major view-projection matrix, in world space for a camera's.
+    ///The order is left, right, bottom, top, near, far.
+    pub fn from_matrix(view_projection: &Mat4) -> Frustum {
+        let m = view_projection;
+        let row = |i: usize| [m[0][i], m[1][i], m[2][i], m[3][i]];
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        let add = |a: Vec4, b: Vec4, sign: f32| [a[0] + b[0] * sign, a[1] + b[1] * sign, a[2] + b[2] * sign, a[3] + b[3] * sign];
+        let planes = [add(w, x, 1.0), add(w, x, -1.0), add(w, y, 1.0), add(w, y, -1.0), add(w, z, 1.0), add(w, z, -1.0)]
+            .map(|plane| {
+                let length = (plane[0] * plane[0] + plane[1] * plane[1] + plane[2] * plane[2]).sqrt().max(f32::EPSILON);
+                plane.map(|v| v / length)
+            });
+        Self { planes }
+    }
+
+    ///What the camera's last perspective projection sees.
+    pub fn from_camera(camera: &Camera) -> Frustum {
+        Self::from_matrix(&col_mat4_mul(*camera.last_perspective(), *camera.view()))
+    }
+
+    pub fn planes(&self) -> &[Vec4; 6] {
+        &self.planes
+    }
+
+    fn distance(plane: &Vec4, point: Vec3) -> f32 {
+        plane[0] * point[0] + plane[1] * point[1] + plane[2] * point[2] + plane[3]
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        self.planes.iter().all(|plane| Self::distance(plane, point) >= 0.0)
+    }
+
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes.iter().all(|plane| Self::distance(plane, sphere.center) >= -sphere.radius)
+    }
+
+    ///Conservative: boxes near a corner of the frustum may pass without touching it.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            //The corner furthest along the plane's normal.
+            let corner = [0, 1, 2].map(|i| if plane[i] >= 0.0 { aabb.max[i] } else { aabb.min[i] });
+            Self::distance(plane, corner) >= 0.0
+        })
+    }
+}
@@ -43,6 +43,23 @@ pub fn orthographic(
     ]
 }
 
+///Orthographic projection of an arbitrary box, looking down -z.
+pub fn orthographic_box(
+    left: f32,
+    right: f32,
+    bottom: f32,
+    top: f32,
+    near: f32,
+    far: f32,
+) -> Matrix4<f32> {
+    [
+        [2.0 / (right - left), 0f32, 0f32, 0f32],
+        [0f32, 2.0 / (top - bottom), 0f32, 0f32],
+        [0f32, 0f32, -2.0 / (far - near), 0f32],
+        [-(right + left) / (right - left), -(top + bottom) / (top - bottom), -(far + near) / (far - near), 1f32]
+    ]
+}
+
 pub fn perspective(
     fov: f32,
     aspect_ratio: f32,
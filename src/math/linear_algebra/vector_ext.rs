@@ -14,4 +14,16 @@ pub trait VectorExt where Self: Index<usize, Output=f32> {
     }
 
     fn normalized(&self) -> Self;
+
+    ///`fallback` for vectors too short to normalize.
+    fn normalized_or(&self, fallback: Self) -> Self where Self: Sized {
+        match self.magnitude() <= f32::EPSILON {
+            true => fallback,
+            false => self.normalized()
+        }
+    }
+
+    fn distance(&self, other: &Self) -> f32 {
+        ((self[0] - other[0]).powf(2.0) + (self[1] - other[1]).powf(2.0) + (self[2] - other[2]).powf(2.0)).sqrt()
+    }
 }
\ No newline at end of file
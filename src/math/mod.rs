@@ -2,6 +2,12 @@ pub mod color;
 pub mod linear_algebra;
 pub mod camera;
 pub mod perlin;
+pub mod tangent;
+pub mod triangulation;
+pub mod quaternion;
+pub mod bounds;
+pub mod frustum;
+pub mod noise;
 
 pub fn m_max<T: PartialOrd + Copy>(values: &[T]) -> Option<T> {
     if values.len() == 0 {
@@ -0,0 +1,390 @@
+use crate::gfx::bindings::{ColorFormat, DataType};
+use crate::gfx::texture::ImageBuffer;
+use crate::math::linear_algebra::types::Vec4;
+use crate::math::perlin::{fade, grad, lerp, PERMUTATIONS};
+
+const GRAD3: [[f32; 3]; 12] = [
+    [1.0, 1.0, 0.0], [-1.0, 1.0, 0.0], [1.0, -1.0, 0.0], [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0], [-1.0, 0.0, 1.0], [1.0, 0.0, -1.0], [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0], [0.0, -1.0, 1.0], [0.0, 1.0, -1.0], [0.0, -1.0, -1.0],
+];
+
+const GRAD4: [[f32; 4]; 32] = [
+    [0.0, 1.0, 1.0, 1.0], [0.0, 1.0, 1.0, -1.0], [0.0, 1.0, -1.0, 1.0], [0.0, 1.0, -1.0, -1.0],
+    [0.0, -1.0, 1.0, 1.0], [0.0, -1.0, 1.0, -1.0], [0.0, -1.0, -1.0, 1.0], [0.0, -1.0, -1.0, -1.0],
+    [1.0, 0.0, 1.0, 1.0], [1.0, 0.0, 1.0, -1.0], [1.0, 0.0, -1.0, 1.0], [1.0, 0.0, -1.0, -1.0],
+    [-1.0, 0.0, 1.0, 1.0], [-1.0, 0.0, 1.0, -1.0], [-1.0, 0.0, -1.0, 1.0], [-1.0, 0.0, -1.0, -1.0],
+    [1.0, 1.0, 0.0, 1.0], [1.0, 1.0, 0.0, -1.0], [1.0, -1.0, 0.0, 1.0], [1.0, -1.0, 0.0, -1.0],
+    [-1.0, 1.0, 0.0, 1.0], [-1.0, 1.0, 0.0, -1.0], [-1.0, -1.0, 0.0, 1.0], [-1.0, -1.0, 0.0, -1.0],
+    [1.0, 1.0, 1.0, 0.0], [1.0, 1.0, -1.0, 0.0], [1.0, -1.0, 1.0, 0.0], [1.0, -1.0, -1.0, 0.0],
+    [-1.0, 1.0, 1.0, 0.0], [-1.0, 1.0, -1.0, 0.0], [-1.0, -1.0, 1.0, 0.0], [-1.0, -1.0, -1.0, 0.0],
+];
+
+///Distances from a point to the nearest and second nearest feature point of cellular noise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Worley {
+    pub f1: f32,
+    pub f2: f32,
+    ///Random id of the nearest feature point's cell, the same for every point closest to it.
+    pub cell: u32,
+}
+
+///Gradient and cellular noise over a permutation table of its own, so differently seeded generators
+///can be used side by side. Every function is defined for negative coordinates too.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Noise {
+    seed: u64,
+    permutations: [u8; 512],
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Noise {
+    ///Shuffles the table with a splitmix64 sequence starting at `seed`.
+    pub fn new(seed: u64) -> Noise {
+        let mut table = [0u8; 256];
+        table.iter_mut().enumerate().for_each(|(i, value)| *value = i as u8);
+        let mut state = seed;
+        for i in (1..256).rev() {
+            let j = (splitmix64(&mut state) % (i as u64 + 1)) as usize;
+            table.swap(i, j);
+        }
+        Self::from_table(seed, &table)
+    }
+
+    ///Ken Perlin's reference permutation, the table of `perlin::noise`.
+    pub fn reference() -> Noise {
+        let table = PERMUTATIONS.iter().map(|p| *p as u8).collect::<Vec<u8>>();
+        Self::from_table(0, &table)
+    }
+
+    fn from_table(seed: u64, table: &[u8]) -> Noise {
+        let mut permutations = [0u8; 512];
+        permutations.iter_mut().enumerate().for_each(|(i, value)| *value = table[i & 255]);
+        Self { seed, permutations }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn hash(&self, i: i32) -> usize {
+        self.permutations[(i & 255) as usize] as usize
+    }
+
+    fn hash2(&self, i: i32, j: i32) -> usize {
+        self.permutations[self.hash(i) + (j & 255) as usize] as usize
+    }
+
+    fn hash3(&self, i: i32, j: i32, k: i32) -> usize {
+        self.permutations[self.hash2(i, j) + (k & 255) as usize] as usize
+    }
+
+    fn hash4(&self, i: i32, j: i32, k: i32, l: i32) -> usize {
+        self.permutations[self.hash3(i, j, k) + (l & 255) as usize] as usize
+    }
+
+    ///Improved Perlin noise, roughly in [-1, 1] and zero on integer coordinates.
+    pub fn perlin(&self, x: f32, y: f32, z: f32) -> f32 {
+        let (xi, yi, zi) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+        let (x, y, z) = (x - x.floor(), y - y.floor(), z - z.floor());
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+        let corner = |dx: i32, dy: i32, dz: i32| {
+            grad(self.hash3(xi + dx, yi + dy, zi + dz) as i32, x - dx as f32, y - dy as f32, z - dz as f32)
+        };
+        lerp(w,
+             lerp(v, lerp(u, corner(0, 0, 0), corner(1, 0, 0)), lerp(u, corner(0, 1, 0), corner(1, 1, 0))),
+             lerp(v, lerp(u, corner(0, 0, 1), corner(1, 0, 1)), lerp(u, corner(0, 1, 1), corner(1, 1, 1))))
+    }
+
+    ///Random values in [-1, 1] on integer coordinates, smoothly interpolated between them.
+    pub fn value2(&self, x: f32, y: f32) -> f32 {
+        let (xi, yi) = (x.floor() as i32, y.floor() as i32);
+        let (u, v) = (fade(x - x.floor()), fade(y - y.floor()));
+        let corner = |dx: i32, dy: i32| lattice(self.hash2(xi + dx, yi + dy));
+        lerp(v, lerp(u, corner(0, 0), corner(1, 0)), lerp(u, corner(0, 1), corner(1, 1)))
+    }
+
+    pub fn value3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let (xi, yi, zi) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+        let (u, v, w) = (fade(x - x.floor()), fade(y - y.floor()), fade(z - z.floor()));
+        let corner = |dx: i32, dy: i32, dz: i32| lattice(self.hash3(xi + dx, yi + dy, zi + dz));
+        lerp(w,
+             lerp(v, lerp(u, corner(0, 0, 0), corner(1, 0, 0)), lerp(u, corner(0, 1, 0), corner(1, 1, 0))),
+             lerp(v, lerp(u, corner(0, 0, 1), corner(1, 0, 1)), lerp(u, corner(0, 1, 1), corner(1, 1, 1))))
+    }
+
+    ///Simplex noise in [-1, 1], after Stefan Gustavson's reference implementation.
+    pub fn simplex2(&self, x: f32, y: f32) -> f32 {
+        let f2 = 0.5 * (3.0f32.sqrt() - 1.0);
+        let g2 = (3.0 - 3.0f32.sqrt()) / 6.0;
+        let s = (x + y) * f2;
+        let (i, j) = ((x + s).floor() as i32, (y + s).floor() as i32);
+        let t = (i + j) as f32 * g2;
+        let (x0, y0) = (x - (i as f32 - t), y - (j as f32 - t));
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+        let corner = |dx: f32, dy: f32, hash: usize| {
+            let t = 0.5 - dx * dx - dy * dy;
+            if t < 0.0 {
+                return 0.0;
+            }
+            let g = GRAD3[hash % 12];
+            t.powi(4) * (g[0] * dx + g[1] * dy)
+        };
+        70.0 * (corner(x0, y0, self.hash2(i, j))
+            + corner(x0 - i1 as f32 + g2, y0 - j1 as f32 + g2, self.hash2(i + i1, j + j1))
+            + corner(x0 - 1.0 + 2.0 * g2, y0 - 1.0 + 2.0 * g2, self.hash2(i + 1, j + 1)))
+    }
+
+    pub fn simplex3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let (f3, g3) = (1.0 / 3.0, 1.0 / 6.0);
+        let s = (x + y + z) * f3;
+        let (i, j, k) = ((x + s).floor() as i32, (y + s).floor() as i32, (z + s).floor() as i32);
+        let t = (i + j + k) as f32 * g3;
+        let d0 = [x - (i as f32 - t), y - (j as f32 - t), z - (k as f32 - t)];
+        //The second and third corners, walking the simplex along the largest offsets first.
+        let ([i1, j1, k1], [i2, j2, k2]) = match (d0[0] >= d0[1], d0[1] >= d0[2], d0[0] >= d0[2]) {
+            (true, true, _) => ([1, 0, 0], [1, 1, 0]),
+            (true, false, true) => ([1, 0, 0], [1, 0, 1]),
+            (true, false, false) => ([0, 0, 1], [1, 0, 1]),
+            (false, false, _) => ([0, 0, 1], [0, 1, 1]),
+            (false, true, false) => ([0, 1, 0], [0, 1, 1]),
+            (false, true, true) => ([0, 1, 0], [1, 1, 0]),
+        };
+        let corner = |offset: [i32; 3], n: f32| {
+            let d = [0, 1, 2].map(|a| d0[a] - offset[a] as f32 + n * g3);
+            let t = 0.6 - d[0] * d[0] - d[1] * d[1] - d[2] * d[2];
+            if t < 0.0 {
+                return 0.0;
+            }
+            let g = GRAD3[self.hash3(i + offset[0], j + offset[1], k + offset[2]) % 12];
+            t.powi(4) * (g[0] * d[0] + g[1] * d[1] + g[2] * d[2])
+        };
+        32.0 * (corner([0, 0, 0], 0.0) + corner([i1, j1, k1], 1.0) + corner([i2, j2, k2], 2.0) + corner([1, 1, 1], 3.0))
+    }
+
+    pub fn simplex4(&self, x: f32, y: f32, z: f32, w: f32) -> f32 {
+        let f4 = (5.0f32.sqrt() - 1.0) / 4.0;
+        let g4 = (5.0 - 5.0f32.sqrt()) / 20.0;
+        let s = (x + y + z + w) * f4;
+        let cell = [x, y, z, w].map(|v| (v + s).floor() as i32);
+        let t = cell.iter().sum::<i32>() as f32 * g4;
+        let d0 = [x, y, z, w];
+        let d0 = [0, 1, 2, 3].map(|a| d0[a] - (cell[a] as f32 - t));
+        //Ranks the offsets by magnitude to find the order the simplex is walked in.
+        let mut rank = [0; 4];
+        for a in 0..4 {
+            for b in a + 1..4 {
+                match d0[a] > d0[b] {
+                    true => rank[a] += 1,
+                    false => rank[b] += 1
+                }
+            }
+        }
+        let corner = |n: i32| {
+            let offset = rank.map(|r| if r >= 4 - n { 1 } else { 0 });
+            let d = [0, 1, 2, 3].map(|a| d0[a] - offset[a] as f32 + n as f32 * g4);
+            let t = 0.6 - d.iter().map(|v| v * v).sum::<f32>();
+            if t < 0.0 {
+                return 0.0;
+            }
+            let g = GRAD4[self.hash4(cell[0] + offset[0], cell[1] + offset[1], cell[2] + offset[2], cell[3] + offset[3]) % 32];
+            t.powi(4) * (g[0] * d[0] + g[1] * d[1] + g[2] * d[2] + g[3] * d[3])
+        };
+        27.0 * (0..=4).map(corner).sum::<f32>()
+    }
+
+    ///Cellular noise with one randomly placed feature point per unit square.
+    pub fn worley2(&self, x: f32, y: f32) -> Worley {
+        let (xi, yi) = (x.floor() as i32, y.floor() as i32);
+        let mut worley = Worley { f1: f32::MAX, f2: f32::MAX, cell: 0 };
+        for cy in yi - 1..=yi + 1 {
+            for cx in xi - 1..=xi + 1 {
+                let feature = [cx as f32 + lattice01(self.hash3(cx, cy, 0)), cy as f32 + lattice01(self.hash3(cx, cy, 1))];
+                let distance = ((feature[0] - x).powi(2) + (feature[1] - y).powi(2)).sqrt();
+                worley.insert(distance, (self.hash3(cx, cy, 2) << 8 | self.hash3(cx, cy, 3)) as u32);
+            }
+        }
+        worley
+    }
+
+    ///Cellular noise with one randomly placed feature point per unit cube.
+    pub fn worley3(&self, x: f32, y: f32, z: f32) -> Worley {
+        let (xi, yi, zi) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+        let mut worley = Worley { f1: f32::MAX, f2: f32::MAX, cell: 0 };
+        for cz in zi - 1..=zi + 1 {
+            for cy in yi - 1..=yi + 1 {
+                for cx in xi - 1..=xi + 1 {
+                    let feature = [
+                        cx as f32 + lattice01(self.hash4(cx, cy, cz, 0)),
+                        cy as f32 + lattice01(self.hash4(cx, cy, cz, 1)),
+                        cz as f32 + lattice01(self.hash4(cx, cy, cz, 2)),
+                    ];
+                    let distance = ((feature[0] - x).powi(2) + (feature[1] - y).powi(2) + (feature[2] - z).powi(2)).sqrt();
+                    worley.insert(distance, (self.hash4(cx, cy, cz, 3) << 8 | self.hash4(cx, cy, cz, 4)) as u32);
+                }
+            }
+        }
+        worley
+    }
+}
+
+impl Worley {
+    fn insert(&mut self, distance: f32, cell: u32) {
+        if distance < self.f1 {
+            self.f2 = self.f1;
+            self.f1 = distance;
+            self.cell = cell;
+        } else if distance < self.f2 {
+            self.f2 = distance;
+        }
+    }
+}
+
+///Sums octaves of a noise function, each `lacunarity` times the frequency and `gain` times the amplitude of the last.
+///Works on points of any dimension, e.g. `fractal.fbm([x, y], |[x, y]| noise.simplex2(x, y))`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fractal {
+    pub octaves: u32,
+    ///Frequency of the first octave.
+    pub frequency: f32,
+    pub lacunarity: f32,
+    pub gain: f32,
+}
+
+impl Default for Fractal {
+    fn default() -> Self {
+        Self {
+            octaves: 4,
+            frequency: 1.0,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+}
+
+impl Fractal {
+    pub fn new(octaves: u32) -> Fractal {
+        Self { octaves, ..Default::default() }
+    }
+
+    pub fn with_frequency(mut self, frequency: f32) -> Fractal {
+        self.frequency = frequency;
+        self
+    }
+
+    pub fn with_lacunarity(mut self, lacunarity: f32) -> Fractal {
+        self.lacunarity = lacunarity;
+        self
+    }
+
+    pub fn with_gain(mut self, gain: f32) -> Fractal {
+        self.gain = gain;
+        self
+    }
+
+    ///Weighted average of `shape` applied to every octave.
+    fn sum<const N: usize, F: Fn([f32; N]) -> f32, S: Fn(f32) -> f32>(&self, point: [f32; N], noise: F, shape: S) -> f32 {
+        let (mut frequency, mut amplitude) = (self.frequency, 1.0);
+        let (mut sum, mut total) = (0.0, 0.0);
+        for _ in 0..self.octaves.max(1) {
+            sum += shape(noise(point.map(|v| v * frequency))) * amplitude;
+            total += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+        sum / total
+    }
+
+    ///Fractional Brownian motion, in the range of `noise`.
+    pub fn fbm<const N: usize, F: Fn([f32; N]) -> f32>(&self, point: [f32; N], noise: F) -> f32 {
+        self.sum(point, noise, |n| n)
+    }
+
+    ///Absolute values of a signed noise, creased where it crosses zero. In [0, 1] for noise in [-1, 1].
+    pub fn turbulence<const N: usize, F: Fn([f32; N]) -> f32>(&self, point: [f32; N], noise: F) -> f32 {
+        self.sum(point, noise, f32::abs)
+    }
+
+    ///Musgrave's ridged multifractal: sharp ridges along the zero crossings of a signed noise,
+    ///with detail weighted towards the ridges. In [0, 1] for noise in [-1, 1].
+    pub fn ridged<const N: usize, F: Fn([f32; N]) -> f32>(&self, point: [f32; N], noise: F) -> f32 {
+        let (mut frequency, mut amplitude, mut weight) = (self.frequency, 1.0, 1.0);
+        let (mut sum, mut total) = (0.0, 0.0);
+        for _ in 0..self.octaves.max(1) {
+            let signal = (1.0 - noise(point.map(|v| v * frequency)).abs().min(1.0)).powi(2) * weight;
+            weight = (signal * 2.0).clamp(0.0, 1.0);
+            sum += signal * amplitude;
+            total += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+        sum / total
+    }
+}
+
+///Domain warping: moves `point` along each axis by `strength` times `noise`, sampled at an offset per axis
+///so the axes move independently. Sampling a noise at the warped point gives swirling, folded patterns.
+pub fn warp<const N: usize, F: Fn([f32; N]) -> f32>(point: [f32; N], strength: f32, noise: F) -> [f32; N] {
+    let mut warped = point;
+    for (axis, value) in warped.iter_mut().enumerate() {
+        *value += strength * noise(point.map(|v| v + axis as f32 * 17.3));
+    }
+    warped
+}
+
+///Pixel centers as uvs from 0 to 1, the first row at v = 0 like the rows of an `ImageBuffer`.
+fn pixels(width: i32, height: i32) -> impl Iterator<Item=(f32, f32)> {
+    (0..height).flat_map(move |y| (0..width).map(move |x| ((x as f32 + 0.5) / width as f32, (y as f32 + 0.5) / height as f32)))
+}
+
+///A single channel 8 bit image of `sample` at every pixel's uv, clamped to [0, 1].
+pub fn bake<F: Fn(f32, f32) -> f32>(width: i32, height: i32, sample: F) -> ImageBuffer {
+    let bytes = pixels(width, height).map(|(u, v)| unorm8(sample(u, v))).collect::<Vec<u8>>();
+    ImageBuffer::new(&bytes, width, height, ColorFormat::Red)
+}
+
+///A single channel float image, unclamped, e.g. for heights or displacement.
+pub fn bake_float<F: Fn(f32, f32) -> f32>(width: i32, height: i32, sample: F) -> ImageBuffer {
+    let bytes = pixels(width, height).flat_map(|(u, v)| sample(u, v).to_ne_bytes()).collect::<Vec<u8>>();
+    ImageBuffer::new(&bytes, width, height, ColorFormat::Red).with_data_type(DataType::Float)
+}
+
+///An 8 bit RGBA image, each channel clamped to [0, 1].
+pub fn bake_rgba<F: Fn(f32, f32) -> Vec4>(width: i32, height: i32, sample: F) -> ImageBuffer {
+    let bytes = pixels(width, height).flat_map(|(u, v)| sample(u, v).map(unorm8)).collect::<Vec<u8>>();
+    ImageBuffer::new(&bytes, width, height, ColorFormat::RGBA)
+}
+
+///A single channel 8 bit volume for 3D textures, sampled at every voxel center with coordinates from 0 to 1.
+pub fn bake_volume<F: Fn(f32, f32, f32) -> f32>(width: i32, height: i32, depth: i32, sample: F) -> ImageBuffer {
+    let bytes = (0..depth).flat_map(|z| {
+        let w = (z as f32 + 0.5) / depth as f32;
+        pixels(width, height).map(|(u, v)| unorm8(sample(u, v, w))).collect::<Vec<u8>>()
+    }).collect::<Vec<u8>>();
+    ImageBuffer::volume(bytes, width, height, depth, ColorFormat::Red, DataType::UnsignedByte)
+}
+
+fn unorm8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn lattice(hash: usize) -> f32 {
+    hash as f32 / 255.0 * 2.0 - 1.0
+}
+
+fn lattice01(hash: usize) -> f32 {
+    hash as f32 / 255.0
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
@@ -1,5 +1,5 @@
 static mut P: [usize; 512] = [0; 512];
-const PERMUTATIONS: &[usize] = &[
+pub(crate) const PERMUTATIONS: &[usize] = &[
     151, 160, 137, 91, 90, 15,
     131, 13, 201, 95, 96, 53, 194, 233, 7, 225, 140, 36, 103, 30, 69, 142, 8, 99, 37, 240, 21, 10, 23,
     190, 6, 148, 247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219, 203, 117, 35, 11, 32, 57, 177, 33,
@@ -15,16 +15,21 @@ const PERMUTATIONS: &[usize] = &[
     138, 236, 205, 93, 222, 114, 67, 29, 24, 72, 243, 141, 128, 195, 78, 66, 215, 61, 156, 180
 ];
 
+///Fills the permutation table, twice over so lookups past 255 wrap.
 pub fn init_perlin() {
     for i in 0..256 {
-        unsafe { P[i] = PERMUTATIONS[i]; }
+        unsafe {
+            P[i] = PERMUTATIONS[i];
+            P[i + 256] = PERMUTATIONS[i];
+        }
     }
 }
 
+///Perlin noise over the global table, see `noise::Noise` for seeded generators and more kinds of noise.
 pub fn noise(x: f32, y: f32, z: f32) -> f32 {
-    let mut X = x.floor() as usize & 255;
-    let mut Y = y.floor() as usize & 255;
-    let mut Z = z.floor() as usize & 255;
+    let X = (x.floor() as i32 & 255) as usize;
+    let Y = (y.floor() as i32 & 255) as usize;
+    let Z = (z.floor() as i32 & 255) as usize;
 
     let x = x - x.floor();
     let y = y - y.floor();
@@ -34,13 +39,13 @@ pub fn noise(x: f32, y: f32, z: f32) -> f32 {
     let v = fade(y);
     let w = fade(z);
     let (A, AA, AB) = unsafe {
-        let a = P[X];
+        let a = P[X] + Y;
         let aa = P[a as usize] + Z;
         let ab = P[a as usize + 1] + Z;
         (a as usize, aa as usize, ab as usize)
     };
     let (B, BA, BB) = unsafe {
-        let b = P[X + 1];
+        let b = P[X + 1] + Y;
         let ba = P[b as usize] + Z;
         let bb = P[b as usize + 1] + Z;
         (b as usize, ba as usize, bb as usize)
@@ -0,0 +1,144 @@
+use crate::math::linear_algebra::types::{Mat4, Vec3, Vec4};
+
+///Rotation quaternion stored as (x, y, z, w), the layout glTF uses.
+pub type Quat = Vec4;
+
+pub const IDENTITY_QUAT: Quat = [0.0, 0.0, 0.0, 1.0];
+
+pub fn normalize(q: Quat) -> Quat {
+    let length = (q[0] * q[0] + q[1] * q[1] + q[2] * q[2] + q[3] * q[3]).sqrt();
+    if length <= f32::EPSILON {
+        return IDENTITY_QUAT;
+    }
+    [q[0] / length, q[1] / length, q[2] / length, q[3] / length]
+}
+
+///The rotation `a` applied after `b`.
+pub fn multiply(a: Quat, b: Quat) -> Quat {
+    [
+        a[3] * b[0] + a[0] * b[3] + a[1] * b[2] - a[2] * b[1],
+        a[3] * b[1] - a[0] * b[2] + a[1] * b[3] + a[2] * b[0],
+        a[3] * b[2] + a[0] * b[1] - a[1] * b[0] + a[2] * b[3],
+        a[3] * b[3] - a[0] * b[0] - a[1] * b[1] - a[2] * b[2],
+    ]
+}
+
+///The axis scaled by the angle in degrees, the rotation format of `Transform` and `MatrixExt::rotation`.
+pub fn to_rotation_vector(q: Quat) -> Vec3 {
+    let mut q = normalize(q);
+    if q[3] < 0.0 {
+        q = [-q[0], -q[1], -q[2], -q[3]];
+    }
+    let sin = (q[0] * q[0] + q[1] * q[1] + q[2] * q[2]).sqrt();
+    if sin <= f32::EPSILON {
+        return [0.0; 3];
+    }
+    let angle = (2.0 * sin.atan2(q[3])).to_degrees();
+    [q[0] / sin * angle, q[1] / sin * angle, q[2] / sin * angle]
+}
+
+///Inverse of `to_rotation_vector`.
+pub fn from_rotation_vector(rotation: Vec3) -> Quat {
+    let angle = (rotation[0] * rotation[0] + rotation[1] * rotation[1] + rotation[2] * rotation[2]).sqrt();
+    if angle <= f32::EPSILON {
+        return IDENTITY_QUAT;
+    }
+    let half = angle.to_radians() * 0.5;
+    let s = half.sin() / angle;
+    [rotation[0] * s, rotation[1] * s, rotation[2] * s, half.cos()]
+}
+
+///Column major rotation matrix.
+pub fn to_matrix(q: Quat) -> Mat4 {
+    let [x, y, z, w] = normalize(q);
+    [
+        [1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y + z * w), 2.0 * (x * z - y * w), 0.0],
+        [2.0 * (x * y - z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z + x * w), 0.0],
+        [2.0 * (x * z + y * w), 2.0 * (y * z - x * w), 1.0 - 2.0 * (x * x + y * y), 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]
+}
+
+///Rotation of a column major matrix whose columns are unit length.
+pub fn from_matrix(m: &Mat4) -> Quat {
+    let trace = m[0][0] + m[1][1] + m[2][2];
+    let q = if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        [(m[1][2] - m[2][1]) / s, (m[2][0] - m[0][2]) / s, (m[0][1] - m[1][0]) / s, 0.25 * s]
+    } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+        let s = (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt() * 2.0;
+        [0.25 * s, (m[1][0] + m[0][1]) / s, (m[2][0] + m[0][2]) / s, (m[1][2] - m[2][1]) / s]
+    } else if m[1][1] > m[2][2] {
+        let s = (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt() * 2.0;
+        [(m[1][0] + m[0][1]) / s, 0.25 * s, (m[2][1] + m[1][2]) / s, (m[2][0] - m[0][2]) / s]
+    } else {
+        let s = (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt() * 2.0;
+        [(m[2][0] + m[0][2]) / s, (m[2][1] + m[1][2]) / s, 0.25 * s, (m[0][1] - m[1][0]) / s]
+    };
+    normalize(q)
+}
+
+///Column major translation * rotation * scale.
+pub fn compose(translation: Vec3, rotation: Quat, scale: Vec3) -> Mat4 {
+    let mut m = to_matrix(rotation);
+    for column in 0..3 {
+        for row in 0..3 {
+            m[column][row] *= scale[column];
+        }
+    }
+    m[3] = [translation[0], translation[1], translation[2], 1.0];
+    m
+}
+
+///Splits a column major matrix without shear into translation, rotation and scale.
+pub fn decompose(m: &Mat4) -> (Vec3, Quat, Vec3) {
+    let translation = [m[3][0], m[3][1], m[3][2]];
+    let mut scale = [0.0; 3];
+    let mut rotation = *m;
+    for column in 0..3 {
+        scale[column] = (m[column][0] * m[column][0] + m[column][1] * m[column][1] + m[column][2] * m[column][2]).sqrt();
+        if scale[column] > f32::EPSILON {
+            for row in 0..3 {
+                rotation[column][row] /= scale[column];
+            }
+        }
+    }
+    let determinant = rotation[0][0] * (rotation[1][1] * rotation[2][2] - rotation[2][1] * rotation[1][2])
+        - rotation[1][0] * (rotation[0][1] * rotation[2][2] - rotation[2][1] * rotation[0][2])
+        + rotation[2][0] * (rotation[0][1] * rotation[1][2] - rotation[1][1] * rotation[0][2]);
+    if determinant < 0.0 {
+        scale[0] = -scale[0];
+        for row in 0..3 {
+            rotation[0][row] = -rotation[0][row];
+        }
+    }
+    (translation, from_matrix(&rotation), scale)
+}
+
+///Spherical interpolation along the shortest arc, falling back to a normalized lerp for nearly equal rotations.
+pub fn slerp(a: Quat, b: Quat, t: f32) -> Quat {
+    let mut b = b;
+    let mut cos = a[0] * b[0] + a[1] * b[1] + a[2] * b[2] + a[3] * b[3];
+    if cos < 0.0 {
+        b = [-b[0], -b[1], -b[2], -b[3]];
+        cos = -cos;
+    }
+    if cos > 0.9995 {
+        return normalize([
+            a[0] + (b[0] - a[0]) * t,
+            a[1] + (b[1] - a[1]) * t,
+            a[2] + (b[2] - a[2]) * t,
+            a[3] + (b[3] - a[3]) * t,
+        ]);
+    }
+    let theta = cos.acos();
+    let sin = theta.sin();
+    let wa = ((1.0 - t) * theta).sin() / sin;
+    let wb = (t * theta).sin() / sin;
+    [
+        a[0] * wa + b[0] * wb,
+        a[1] * wa + b[1] * wb,
+        a[2] * wa + b[2] * wb,
+        a[3] * wa + b[3] * wb,
+    ]
+}
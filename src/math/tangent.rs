@@ -0,0 +1,48 @@
+use vecmath::{vec3_cross, vec3_dot, vec3_scale, vec3_sub};
+use crate::math::linear_algebra::types::{Vec2, Vec3, Vec4};
+use crate::math::linear_algebra::vector_ext::VectorExt;
+
+///Tangent and bitangent of a triangle from its positions and texture coordinates.
+///Degenerate uv mappings yield zero vectors, which leave accumulated tangents untouched.
+pub fn triangle_tangent(positions: [Vec3; 3], uvs: [Vec2; 3]) -> (Vec3, Vec3) {
+    let e1 = vec3_sub(positions[1], positions[0]);
+    let e2 = vec3_sub(positions[2], positions[0]);
+    let du1 = uvs[1][0] - uvs[0][0];
+    let dv1 = uvs[1][1] - uvs[0][1];
+    let du2 = uvs[2][0] - uvs[0][0];
+    let dv2 = uvs[2][1] - uvs[0][1];
+
+    let determinant = du1 * dv2 - du2 * dv1;
+    if determinant.abs() < f32::EPSILON {
+        return ([0.0; 3], [0.0; 3]);
+    }
+    let r = 1.0 / determinant;
+    let tangent = vec3_scale(vec3_sub(vec3_scale(e1, dv2), vec3_scale(e2, dv1)), r);
+    let bitangent = vec3_scale(vec3_sub(vec3_scale(e2, du1), vec3_scale(e1, du2)), r);
+    (tangent, bitangent)
+}
+
+///Unit normal of a counter-clockwise triangle, zero if the triangle is degenerate.
+pub fn triangle_normal(positions: [Vec3; 3]) -> Vec3 {
+    vec3_cross(vec3_sub(positions[1], positions[0]), vec3_sub(positions[2], positions[0])).normalized_or([0.0; 3])
+}
+
+///Gram-Schmidt orthogonalizes an accumulated tangent against the normal.
+///The w component is the handedness of the bitangent: `bitangent = cross(normal, tangent) * w`.
+pub fn orthogonalize(normal: Vec3, tangent: Vec3, bitangent: Vec3) -> Vec4 {
+    let t = vec3_sub(tangent, vec3_scale(normal, vec3_dot(normal, tangent)))
+        .normalized_or(any_perpendicular(normal));
+    let w = match vec3_dot(vec3_cross(normal, t), bitangent) < 0.0 {
+        true => -1.0,
+        false => 1.0
+    };
+    [t[0], t[1], t[2], w]
+}
+
+fn any_perpendicular(normal: Vec3) -> Vec3 {
+    let axis = match normal[0].abs() < 0.9 {
+        true => [1.0, 0.0, 0.0],
+        false => [0.0, 1.0, 0.0]
+    };
+    vec3_cross(axis, normal).normalized_or(axis)
+}
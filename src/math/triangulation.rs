@@ -0,0 +1,86 @@
+use crate::math::linear_algebra::types::{Vec2, Vec3};
+
+///Triangulates a simple polygon, convex or concave, by ear clipping. Returns indices into `points`
+///that keep the polygon's winding. Degenerate polygons where no ear can be found are fanned.
+pub fn triangulate_polygon(points: &[Vec3]) -> Vec<[usize; 3]> {
+    if points.len() < 3 {
+        return vec![];
+    }
+    if points.len() == 3 {
+        return vec![[0, 1, 2]];
+    }
+    let projected = project(points);
+    let orientation = signed_area(&projected).signum();
+
+    let mut remaining = (0..points.len()).collect::<Vec<usize>>();
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|&i| {
+            let prev = remaining[(i + count - 1) % count];
+            let current = remaining[i];
+            let next = remaining[(i + 1) % count];
+            is_ear(&projected, &remaining, [prev, current, next], orientation)
+        });
+        match ear {
+            Some(i) => {
+                triangles.push([remaining[(i + count - 1) % count], remaining[i], remaining[(i + 1) % count]]);
+                remaining.remove(i);
+            }
+            None => break
+        }
+    }
+    for i in 1..remaining.len() - 1 {
+        triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+    }
+    triangles
+}
+
+///Drops the axis the polygon's Newell normal points along the most.
+fn project(points: &[Vec3]) -> Vec<Vec2> {
+    let mut normal = [0.0f32; 3];
+    for i in 0..points.len() {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        normal[0] += (a[1] - b[1]) * (a[2] + b[2]);
+        normal[1] += (a[2] - b[2]) * (a[0] + b[0]);
+        normal[2] += (a[0] - b[0]) * (a[1] + b[1]);
+    }
+    let (u, v) = if normal[0].abs() >= normal[1].abs() && normal[0].abs() >= normal[2].abs() {
+        (1, 2)
+    } else if normal[1].abs() >= normal[2].abs() {
+        (2, 0)
+    } else {
+        (0, 1)
+    };
+    points.iter().map(|p| [p[u], p[v]]).collect()
+}
+
+fn signed_area(points: &[Vec2]) -> f32 {
+    let mut area = 0.0;
+    for i in 0..points.len() {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        area += a[0] * b[1] - b[0] * a[1];
+    }
+    area * 0.5
+}
+
+fn cross(o: Vec2, a: Vec2, b: Vec2) -> f32 {
+    (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+}
+
+fn is_ear(points: &[Vec2], remaining: &[usize], [prev, current, next]: [usize; 3], orientation: f32) -> bool {
+    let (a, b, c) = (points[prev], points[current], points[next]);
+    if cross(a, b, c) * orientation <= f32::EPSILON {
+        return false;
+    }
+    !remaining.iter()
+        .filter(|&&i| i != prev && i != current && i != next)
+        .any(|&i| {
+            let p = points[i];
+            cross(a, b, p) * orientation >= 0.0
+                && cross(b, c, p) * orientation >= 0.0
+                && cross(c, a, p) * orientation >= 0.0
+        })
+}
@@ -34,6 +34,24 @@ impl Scene {
         unsafe { SCENE = Some(scene); }
     }
 
+    ///Drops the scene with its objects and components.
+    pub fn unload_scene() {
+        unsafe {
+            SCENE = None;
+            OBJECTS = None;
+            COMPONENTS = None;
+        }
+    }
+
+    ///Adds an object to the loaded objects and returns its id.
+    pub fn add_object(object: GameObject) -> usize {
+        let id = object.id;
+        unsafe {
+            OBJECTS.get_or_insert_with(HashMap::new).insert(id, object);
+        }
+        id
+    }
+
     pub fn get_object(id: &usize) -> Option<&'static mut GameObject> {
         unsafe {
             if let Some(objects) = &mut OBJECTS {
@@ -22,7 +22,9 @@ impl IGameObject {
             tag: self.tag.clone(),
             components: vec![
 
-            ]
+            ],
+            parent: None,
+            children: vec![],
         }
     }
 }
\ No newline at end of file